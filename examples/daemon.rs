@@ -0,0 +1,187 @@
+//! Reference daemon for Linux gateways.
+//!
+//! Runs the full lifecycle recommended by the datasheet:
+//!
+//! - Initialize the sensor and restore the baseline saved by a previous run,
+//!   if it is not older than one week.
+//! - Measure once per second, feeding the humidity compensation from a
+//!   configurable source every minute.
+//! - Save the baseline every hour, starting 12 hours after initialization
+//!   if no baseline could be restored.
+//!
+//! Usage:
+//!
+//! ```text
+//! cargo run --example daemon --features linux -- \
+//!     [--device /dev/i2c-1] [--baseline sgp30-baseline] [--humidity SOURCE]
+//! ```
+//!
+//! The humidity source is one of:
+//!
+//! - `none`: use the default of the sensor (11.57 g/m³)
+//! - `fixed:<g/m³>`: a fixed absolute humidity, e.g. `fixed:8.5`
+//! - `file:<path>`: a file containing the temperature (°C) and relative
+//!   humidity (%), separated by whitespace, e.g. written by a companion
+//!   sensor daemon. It is re-read every minute.
+
+extern crate sgp30;
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use sgp30::humidity::{self, RelativeHumidity, Temperature};
+use sgp30::storage::{FileStore, RestoreOutcome};
+use sgp30::{Humidity, Sgp30, DEFAULT_AUTOSAVE_INTERVAL_SECS, INITIAL_BASELINE_SECS};
+
+/// Interval between two humidity updates, in measurements.
+const HUMIDITY_INTERVAL: u32 = 60;
+
+enum HumiditySource {
+    None,
+    Fixed(Humidity),
+    File(PathBuf),
+}
+
+impl HumiditySource {
+    fn parse(arg: &str) -> Result<Self, String> {
+        if arg == "none" {
+            Ok(HumiditySource::None)
+        } else if let Some(value) = arg.strip_prefix("fixed:") {
+            let value: f32 = value.parse().map_err(|e| format!("Invalid humidity {:?}: {}", value, e))?;
+            Humidity::from_f32(value)
+                .map(HumiditySource::Fixed)
+                .map_err(|e| format!("Invalid humidity {}: {}", value, e))
+        } else if let Some(path) = arg.strip_prefix("file:") {
+            Ok(HumiditySource::File(PathBuf::from(path)))
+        } else {
+            Err(format!("Invalid humidity source {:?}", arg))
+        }
+    }
+
+    fn read(&self) -> Result<Option<Humidity>, String> {
+        match *self {
+            HumiditySource::None => Ok(None),
+            HumiditySource::Fixed(humidity) => Ok(Some(humidity)),
+            HumiditySource::File(ref path) => {
+                let contents = fs::read_to_string(path)
+                    .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+                let mut values = contents.split_whitespace().map(str::parse::<f32>);
+                match (values.next(), values.next()) {
+                    (Some(Ok(t)), Some(Ok(rh))) => {
+                        humidity::absolute_humidity(Temperature::from_celsius(t), RelativeHumidity::from_percent(rh))
+                            .map(Some)
+                            .map_err(|e| format!("Invalid humidity for {} °C / {} %RH: {}", t, rh, e))
+                    }
+                    _ => Err(format!("Expected temperature and relative humidity in {}", path.display())),
+                }
+            }
+        }
+    }
+}
+
+struct Args {
+    device: String,
+    baseline: PathBuf,
+    humidity: HumiditySource,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        device: "/dev/i2c-1".into(),
+        baseline: PathBuf::from("sgp30-baseline"),
+        humidity: HumiditySource::None,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let value = iter.next().ok_or_else(|| format!("Missing value for {}", arg))?;
+        match arg.as_str() {
+            "--device" => args.device = value,
+            "--baseline" => args.baseline = PathBuf::from(value),
+            "--humidity" => args.humidity = HumiditySource::parse(&value)?,
+            _ => return Err(format!("Unknown argument {}", arg)),
+        }
+    }
+    Ok(args)
+}
+
+/// Return the current time as UNIX timestamp, which is stored with the
+/// baseline to detect stale baselines after a restart.
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let args = parse_args().unwrap_or_else(|e| fail(e));
+    let mut store = FileStore::new(&args.baseline);
+
+    let mut sgp = Sgp30::open(&args.device)
+        .unwrap_or_else(|e| fail(format!("Could not open {}: {}", args.device, e)));
+    sgp.init().unwrap_or_else(|e| fail(format!("Could not initialize sensor: {}", e)));
+
+    // Until the sensor has determined its own baseline, the baseline it
+    // reports must not be saved. With a restored baseline, it is valid
+    // right away.
+    let mut save_in = match sgp.restore_baseline_from(&mut store, Some(unix_time())) {
+        Ok(RestoreOutcome::Restored) => {
+            println!("Restored baseline from {}", args.baseline.display());
+            DEFAULT_AUTOSAVE_INTERVAL_SECS
+        }
+        Ok(outcome) => {
+            println!("No baseline restored ({:?}), first save in 12 hours", outcome);
+            INITIAL_BASELINE_SECS
+        }
+        Err(e) => {
+            eprintln!("Could not restore baseline: {}", e);
+            INITIAL_BASELINE_SECS
+        }
+    };
+
+    let interval = Duration::from_secs(1);
+    let mut next = Instant::now();
+    let mut count: u32 = 0;
+    loop {
+        let now = Instant::now();
+        if next > now {
+            thread::sleep(next - now);
+        }
+        next += interval;
+
+        if count.is_multiple_of(HUMIDITY_INTERVAL) {
+            match args.humidity.read() {
+                Ok(humidity) => {
+                    if let Err(e) = sgp.set_humidity(humidity.as_ref()) {
+                        eprintln!("Could not set humidity: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        count = count.wrapping_add(1);
+
+        match sgp.measure() {
+            Ok(measurement) => println!("{}", measurement),
+            Err(e) => eprintln!("Measurement failed: {}", e),
+        }
+
+        save_in -= 1;
+        if save_in == 0 {
+            match sgp.save_baseline(&mut store, unix_time()) {
+                Ok(record) => println!("Saved baseline {}", record.baseline),
+                Err(e) => eprintln!("Could not save baseline: {}", e),
+            }
+            save_in = DEFAULT_AUTOSAVE_INTERVAL_SECS;
+        }
+    }
+}
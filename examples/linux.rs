@@ -4,7 +4,7 @@ extern crate sgp30;
 
 use embedded_hal::blocking::delay::DelayMs;
 use linux_embedded_hal::{I2cdev, Delay};
-use sgp30::Sgp30;
+use sgp30::{I2cAddress, Sgp30};
 
 
 fn measure_loop(sgp: &mut Sgp30<I2cdev, Delay>) -> ! {
@@ -27,8 +27,7 @@ fn measure_loop(sgp: &mut Sgp30<I2cdev, Delay>) -> ! {
 
 fn main() {
     let dev = I2cdev::new("/dev/i2c-1").unwrap();
-    let address = 0x58;
-    let mut sgp = Sgp30::new(dev, address, Delay);
+    let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, Delay);
 
     println!("Starting SGP30 tests.");
     println!();
@@ -0,0 +1,22 @@
+//! Decoding a compressed series never panics, and the decoded measurements
+//! survive a round trip.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sgp30::compress::{Decoder, Encoder};
+
+fuzz_target!(|data: &[u8]| {
+    // The iteration stops after an error, so the error can only be last
+    let decoded: Vec<_> = Decoder::new(data).filter_map(Result::ok).collect();
+
+    // Varints may have redundant continuation bytes, so only the decoded
+    // values are compared
+    let mut buf = vec![0; decoded.len() * 6];
+    let mut encoder = Encoder::new(&mut buf);
+    for measurement in &decoded {
+        encoder.push(*measurement).unwrap();
+    }
+    let reencoded: Vec<_> = Decoder::new(encoder.finish()).map(Result::unwrap).collect();
+    assert_eq!(decoded, reencoded);
+});
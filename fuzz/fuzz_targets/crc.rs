@@ -0,0 +1,36 @@
+//! The CRC validator agrees with a bitwise reference implementation.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sgp30::crc::SoftwareCrc;
+use sgp30::protocol::{self, Command};
+use sgp30::Error;
+
+fn reference_crc8(data: &[u8]) -> u8 {
+    let mut crc: u8 = 0xff;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 > 0 { (crc << 1) ^ 0x31 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+fuzz_target!(|data: &[u8]| {
+    assert_eq!(protocol::crc8(data), reference_crc8(data));
+
+    let valid = data
+        .chunks_exact(3)
+        .all(|chunk| reference_crc8(&chunk[0..2]) == chunk[2]);
+    match protocol::validate_crc::<(), _>(Command::MeasureAirQuality, data, &mut SoftwareCrc) {
+        Ok(()) => assert!(valid),
+        Err(Error::Crc { word, received, computed, .. }) => {
+            assert!(!valid);
+            assert_ne!(received, computed);
+            assert_eq!(reference_crc8(&word.to_be_bytes()), computed);
+        }
+        Err(e) => panic!("unexpected error: {:?}", e),
+    }
+});
@@ -0,0 +1,74 @@
+//! The driver never panics on responses from a misbehaving sensor.
+
+#![no_main]
+
+use embedded_hal::blocking::i2c::{Read, Write, WriteRead};
+use libfuzzer_sys::fuzz_target;
+use sgp30::{Baseline, I2cAddress, NoDelay, Sgp30};
+
+/// A bus answering every read with the next bytes of the fuzzer input, and
+/// failing once the input is exhausted.
+struct HostileBus<'a>(&'a [u8]);
+
+impl<'a> HostileBus<'a> {
+    fn respond(&mut self, buffer: &mut [u8]) -> Result<(), ()> {
+        if buffer.len() > self.0.len() {
+            self.0 = &[];
+            return Err(());
+        }
+        let (response, rest) = self.0.split_at(buffer.len());
+        buffer.copy_from_slice(response);
+        self.0 = rest;
+        Ok(())
+    }
+}
+
+impl<'a> Write for HostileBus<'a> {
+    type Error = ();
+
+    fn write(&mut self, _address: u8, _bytes: &[u8]) -> Result<(), ()> {
+        Ok(())
+    }
+}
+
+impl<'a> Read for HostileBus<'a> {
+    type Error = ();
+
+    fn read(&mut self, _address: u8, buffer: &mut [u8]) -> Result<(), ()> {
+        self.respond(buffer)
+    }
+}
+
+impl<'a> WriteRead for HostileBus<'a> {
+    type Error = ();
+
+    fn write_read(&mut self, _address: u8, _bytes: &[u8], buffer: &mut [u8]) -> Result<(), ()> {
+        self.respond(buffer)
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&script, responses)) = data.split_first() else {
+        return;
+    };
+    let mut sgp = Sgp30::new(HostileBus(responses), I2cAddress::DEFAULT, NoDelay);
+    // Every bit of the first byte selects a command, in a fixed order
+    let _ = sgp.force_init();
+    if script & 0x01 != 0 {
+        let _ = sgp.serial();
+    }
+    if script & 0x02 != 0 {
+        let _ = sgp.selftest();
+    }
+    if script & 0x04 != 0 {
+        let _ = sgp.get_feature_set();
+    }
+    if script & 0x08 != 0 {
+        let _ = sgp.set_baseline(&Baseline { co2eq: 0x8F00, tvoc: 0x9000 });
+    }
+    for _ in 0..script >> 4 {
+        let _ = sgp.measure();
+        let _ = sgp.measure_raw_signals();
+        let _ = sgp.get_baseline();
+    }
+});
@@ -0,0 +1,83 @@
+//! Loading from flash filled with arbitrary bytes never panics, and a store
+//! recovers from whatever it found by saving a new record.
+
+#![no_main]
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
+use libfuzzer_sys::fuzz_target;
+use sgp30::storage::{BaselineRecord, BaselineStore, DualFlashStore, FlashStore, JournalStore};
+use sgp30::Baseline;
+
+const PAGE_SIZE: usize = 64;
+const PAGES: usize = 4;
+
+/// A RAM-backed NOR flash.
+struct RamFlash([u8; PAGE_SIZE * PAGES]);
+
+impl RamFlash {
+    fn new(data: &[u8]) -> Self {
+        let mut flash = RamFlash([0xFF; PAGE_SIZE * PAGES]);
+        let len = data.len().min(flash.0.len());
+        flash.0[..len].copy_from_slice(&data[..len]);
+        flash
+    }
+}
+
+impl ErrorType for RamFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for RamFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let data = self.0.get(offset as usize..offset as usize + bytes.len());
+        bytes.copy_from_slice(data.ok_or(NorFlashErrorKind::OutOfBounds)?);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl NorFlash for RamFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = PAGE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let data = self.0.get_mut(from as usize..to as usize);
+        data.ok_or(NorFlashErrorKind::OutOfBounds)?.fill(0xFF);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let data = self.0.get_mut(offset as usize..offset as usize + bytes.len());
+        for (cell, byte) in data.ok_or(NorFlashErrorKind::OutOfBounds)?.iter_mut().zip(bytes) {
+            // NOR flash can only clear bits
+            *cell &= byte;
+        }
+        Ok(())
+    }
+}
+
+fn check<S: BaselineStore>(mut store: S)
+where
+    S::Error: core::fmt::Debug,
+{
+    // Whatever was found, the next save must be loaded back
+    let _ = store.load();
+    let record = BaselineRecord {
+        serial: [0, 0, 1, 2, 3, 4],
+        baseline: Baseline { co2eq: 0x1234, tvoc: 0x5678 },
+        timestamp: 42,
+    };
+    store.save(&record).unwrap();
+    assert_eq!(store.load().unwrap(), Some(record));
+}
+
+fuzz_target!(|data: &[u8]| {
+    check(FlashStore::new(RamFlash::new(data), 0));
+    check(DualFlashStore::new(RamFlash::new(data), 0, PAGE_SIZE as u32));
+    check(JournalStore::new(RamFlash::new(data), 0, PAGES as u32));
+});
@@ -0,0 +1,19 @@
+//! Decoding baseline records and telemetry frames never panics, and decoded
+//! values survive a round trip.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sgp30::format::TelemetryFrame;
+use sgp30::storage::BaselineRecord;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(record) = BaselineRecord::decode(data) {
+        // Every byte of a record is significant
+        assert_eq!(record.encode()[..], data[..BaselineRecord::ENCODED_LEN]);
+    }
+
+    if let Ok(frame) = TelemetryFrame::decode(data) {
+        assert_eq!(TelemetryFrame::decode(&frame.encode()), Ok(frame));
+    }
+});
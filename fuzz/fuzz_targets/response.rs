@@ -0,0 +1,39 @@
+//! Decoding a response never panics, and succeeds exactly for plausible
+//! responses with valid checksums.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sgp30::protocol::{self, Command, ResponseError};
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() >= 2 {
+        if let Some(command) = Command::from_bytes([data[0], data[1]]) {
+            let mut frame = [0; protocol::MAX_FRAME_LEN];
+            assert_eq!(protocol::encode_frame(command, &[], &mut frame), 2);
+            assert_eq!(frame[0..2], data[0..2]);
+        }
+    }
+
+    // Try every word count a command can return, and a wrong one
+    for count in 0..=4 {
+        let mut words = [0; 4];
+        let words = &mut words[..count];
+        match protocol::decode_response(data, words) {
+            Ok(()) => {
+                assert!(protocol::is_plausible_response(data));
+                for (index, word) in words.iter().enumerate() {
+                    assert_eq!(*word, protocol::decode_word(data, index));
+                }
+            }
+            Err(ResponseError::Invalid) => {
+                assert!(data.len() != count * 3 || !protocol::is_plausible_response(data));
+            }
+            Err(ResponseError::Crc { index, word, received, computed }) => {
+                assert_eq!(word, protocol::decode_word(data, index));
+                assert_eq!(received, data[index * 3 + 2]);
+                assert_ne!(received, computed);
+            }
+        }
+    }
+});
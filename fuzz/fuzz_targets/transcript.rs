@@ -0,0 +1,24 @@
+//! Iterating over a transcript never panics, and every entry lies within
+//! the transcript.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use sgp30::transcript;
+
+fuzz_target!(|data: &[u8]| {
+    let mut entries = transcript::entries(data);
+    let mut previous = 0;
+    while let Some(result) = entries.next() {
+        match result {
+            Ok(entry) => {
+                assert!(entries.offset() > previous && entries.offset() <= data.len());
+                if let Some(read) = entry.read {
+                    assert_eq!(read.len(), entry.read_len);
+                }
+            }
+            Err(e) => assert_eq!(e.offset, previous),
+        }
+        previous = entries.offset();
+    }
+});
@@ -0,0 +1,94 @@
+use core::convert::TryFrom;
+use core::fmt;
+
+/// The I²C address of an SGP30.
+///
+/// The SGP30 has the fixed address 0x58, which is the only one accepted by
+/// [`new()`](#method.new). A wrong address would otherwise only show up as
+/// NACKed transfers. Behind an address translator such as the LTC4316, the
+/// translated address can be passed to
+/// [`new_unchecked()`](#method.new_unchecked).
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct I2cAddress(u8);
+
+/// Error when creating an [`I2cAddress`](struct.I2cAddress.html) that is not
+/// the address of an SGP30. Contains the rejected address.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InvalidAddress(pub u8);
+
+impl fmt::Display for InvalidAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid SGP30 I²C address 0x{:02X}, expected 0x58", self.0)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for InvalidAddress {}
+
+impl I2cAddress {
+    /// The address of the SGP30 (0x58).
+    pub const DEFAULT: I2cAddress = I2cAddress(0x58);
+
+    /// Create an address, which must be 0x58.
+    pub const fn new(address: u8) -> Result<Self, InvalidAddress> {
+        if address == I2cAddress::DEFAULT.0 {
+            Ok(I2cAddress(address))
+        } else {
+            Err(InvalidAddress(address))
+        }
+    }
+
+    /// Create an address without checking it, e.g. for a sensor behind an
+    /// address translator.
+    pub const fn new_unchecked(address: u8) -> Self {
+        I2cAddress(address)
+    }
+
+    /// Return the 7-bit address.
+    pub const fn as_u8(self) -> u8 {
+        self.0
+    }
+}
+
+impl Default for I2cAddress {
+    fn default() -> Self {
+        I2cAddress::DEFAULT
+    }
+}
+
+impl TryFrom<u8> for I2cAddress {
+    type Error = InvalidAddress;
+
+    fn try_from(address: u8) -> Result<Self, InvalidAddress> {
+        I2cAddress::new(address)
+    }
+}
+
+impl From<I2cAddress> for u8 {
+    fn from(address: I2cAddress) -> u8 {
+        address.0
+    }
+}
+
+impl fmt::Display for I2cAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:02X}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new() {
+        assert_eq!(I2cAddress::new(0x58).unwrap(), I2cAddress::DEFAULT);
+        assert_eq!(I2cAddress::new(0x59), Err(InvalidAddress(0x59)));
+        assert_eq!(I2cAddress::try_from(0x85), Err(InvalidAddress(0x85)));
+        assert_eq!(I2cAddress::new_unchecked(0x59).as_u8(), 0x59);
+        assert_eq!(u8::from(I2cAddress::default()), 0x58);
+    }
+}
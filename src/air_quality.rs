@@ -0,0 +1,128 @@
+use core::fmt;
+
+use types::Measurement;
+
+/// Upper TVOC limits (exclusive, in ppb) of the first four classes.
+///
+/// These correspond to the TVOC levels 1-4 defined by the German Federal
+/// Environment Agency (UBA) of 0.3, 1, 3 and 10 mg/m³, converted using the
+/// Sensirion recommended ethanol-equivalent factor.
+const TVOC_LIMITS_PPB: [u16; 4] = [65, 220, 660, 2200];
+
+/// Upper CO₂eq limits (exclusive, in ppm) of the first four classes.
+///
+/// The UBA considers concentrations below 1000 ppm harmless and above
+/// 2000 ppm unacceptable.
+const CO2EQ_LIMITS_PPM: [u16; 4] = [600, 1000, 1500, 2000];
+
+/// An indoor air quality classification, from best to worst.
+///
+/// The classification can be obtained from a measurement using
+/// [`Measurement::air_quality()`](struct.Measurement.html#method.air_quality),
+/// which uses the worse of the TVOC and CO₂eq classes.
+///
+/// | Class     | TVOC (ppb) | CO₂eq (ppm) |
+/// |-----------|------------|-------------|
+/// | Excellent | < 65       | < 600       |
+/// | Good      | < 220      | < 1000      |
+/// | Moderate  | < 660      | < 1500      |
+/// | Poor      | < 2200     | < 2000      |
+/// | Unhealthy | ≥ 2200     | ≥ 2000      |
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AirQuality {
+    /// Clean air, no objections
+    Excellent,
+    /// No relevant objections
+    Good,
+    /// Some objections, ventilation recommended
+    Moderate,
+    /// Major objections, intensified ventilation necessary
+    Poor,
+    /// Situation not acceptable, ventilate immediately
+    Unhealthy,
+}
+
+impl AirQuality {
+    /// Classify a TVOC concentration.
+    pub fn from_tvoc_ppb(tvoc_ppb: u16) -> Self {
+        AirQuality::classify(tvoc_ppb, &TVOC_LIMITS_PPB)
+    }
+
+    /// Classify a CO₂eq concentration.
+    pub fn from_co2eq_ppm(co2eq_ppm: u16) -> Self {
+        AirQuality::classify(co2eq_ppm, &CO2EQ_LIMITS_PPM)
+    }
+
+    fn classify(value: u16, limits: &[u16; 4]) -> Self {
+        match limits.iter().position(|&limit| value < limit) {
+            Some(0) => AirQuality::Excellent,
+            Some(1) => AirQuality::Good,
+            Some(2) => AirQuality::Moderate,
+            Some(_) => AirQuality::Poor,
+            None => AirQuality::Unhealthy,
+        }
+    }
+}
+
+impl fmt::Display for AirQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            AirQuality::Excellent => "excellent",
+            AirQuality::Good => "good",
+            AirQuality::Moderate => "moderate",
+            AirQuality::Poor => "poor",
+            AirQuality::Unhealthy => "unhealthy",
+        })
+    }
+}
+
+impl Measurement {
+    /// Classify the measurement, using the worse of the TVOC and CO₂eq
+    /// classes (see [`AirQuality`](enum.AirQuality.html)).
+    pub fn air_quality(&self) -> AirQuality {
+        let tvoc = AirQuality::from_tvoc_ppb(self.tvoc_ppb);
+        let co2eq = AirQuality::from_co2eq_ppm(self.co2eq_ppm);
+        if tvoc > co2eq { tvoc } else { co2eq }
+    }
+}
+
+impl From<Measurement> for AirQuality {
+    fn from(measurement: Measurement) -> Self {
+        measurement.air_quality()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tvoc_classes() {
+        assert_eq!(AirQuality::from_tvoc_ppb(0), AirQuality::Excellent);
+        assert_eq!(AirQuality::from_tvoc_ppb(64), AirQuality::Excellent);
+        assert_eq!(AirQuality::from_tvoc_ppb(65), AirQuality::Good);
+        assert_eq!(AirQuality::from_tvoc_ppb(659), AirQuality::Moderate);
+        assert_eq!(AirQuality::from_tvoc_ppb(2199), AirQuality::Poor);
+        assert_eq!(AirQuality::from_tvoc_ppb(2200), AirQuality::Unhealthy);
+        assert_eq!(AirQuality::from_tvoc_ppb(60_000), AirQuality::Unhealthy);
+    }
+
+    #[test]
+    fn co2eq_classes() {
+        assert_eq!(AirQuality::from_co2eq_ppm(400), AirQuality::Excellent);
+        assert_eq!(AirQuality::from_co2eq_ppm(999), AirQuality::Good);
+        assert_eq!(AirQuality::from_co2eq_ppm(1000), AirQuality::Moderate);
+        assert_eq!(AirQuality::from_co2eq_ppm(1999), AirQuality::Poor);
+        assert_eq!(AirQuality::from_co2eq_ppm(2000), AirQuality::Unhealthy);
+    }
+
+    #[test]
+    fn measurement_uses_worse_class() {
+        let measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 300 };
+        assert_eq!(measurement.air_quality(), AirQuality::Moderate);
+        let measurement = Measurement { co2eq_ppm: 2500, tvoc_ppb: 0 };
+        assert_eq!(AirQuality::from(measurement), AirQuality::Unhealthy);
+    }
+}
@@ -0,0 +1,252 @@
+//! Threshold alerting with hysteresis and minimum dwell time.
+//!
+//! A naive `value > limit` check toggles constantly when the signal hovers
+//! around the limit. [`Alerts`](struct.Alerts.html) only raises an alert once
+//! the value stayed above the raise threshold for a number of consecutive
+//! measurements, and only clears it once the value stayed below the (lower)
+//! clear threshold for the same number of measurements.
+//!
+//! ```
+//! use sgp30::Measurement;
+//! use sgp30::alerts::{AlertEvent, Alerts, Channel, Threshold};
+//!
+//! let mut alerts = Alerts::new().with_tvoc(Threshold::new(660, 500, 2));
+//! let high = Measurement { co2eq_ppm: 400, tvoc_ppb: 700 };
+//! assert!(alerts.update(&high).is_empty());
+//! assert_eq!(
+//!     alerts.update(&high).as_slice(),
+//!     &[AlertEvent::AlertRaised { channel: Channel::Tvoc, value: 700 }],
+//! );
+//! assert!(alerts.is_active(Channel::Tvoc));
+//! ```
+
+use heapless::Vec;
+
+use types::Measurement;
+
+/// The measurement signal an alert refers to.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Channel {
+    /// CO₂ equivalent (ppm).
+    Co2eq,
+    /// Total volatile organic compounds (ppb).
+    Tvoc,
+}
+
+/// An event emitted by [`Alerts::update`](struct.Alerts.html#method.update).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AlertEvent {
+    /// The value stayed above the raise threshold for the dwell time.
+    AlertRaised {
+        /// The affected signal.
+        channel: Channel,
+        /// The value that triggered the event.
+        value: u16,
+    },
+    /// The value stayed below the clear threshold for the dwell time.
+    AlertCleared {
+        /// The affected signal.
+        channel: Channel,
+        /// The value that triggered the event.
+        value: u16,
+    },
+}
+
+/// Alert thresholds for a single signal.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Threshold {
+    /// An alert is raised when the value is at or above this threshold.
+    pub raise: u16,
+    /// An active alert is cleared when the value is below this threshold.
+    pub clear: u16,
+    /// Number of consecutive measurements the condition must hold before the
+    /// alert state changes. Values of 0 and 1 switch immediately.
+    pub min_dwell: u32,
+}
+
+impl Threshold {
+    /// Create a new threshold.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `clear` is larger than `raise`.
+    pub fn new(raise: u16, clear: u16, min_dwell: u32) -> Self {
+        assert!(clear <= raise, "clear threshold must not exceed raise threshold");
+        Threshold { raise, clear, min_dwell }
+    }
+}
+
+/// Alert state of a single signal.
+#[derive(Debug, Clone)]
+struct ChannelState {
+    threshold: Threshold,
+    active: bool,
+    /// Number of consecutive measurements that would change the state.
+    pending: u32,
+}
+
+impl ChannelState {
+    fn new(threshold: Threshold) -> Self {
+        ChannelState {
+            threshold,
+            active: false,
+            pending: 0,
+        }
+    }
+
+    fn update(&mut self, channel: Channel, value: u16) -> Option<AlertEvent> {
+        let transition = if self.active {
+            value < self.threshold.clear
+        } else {
+            value >= self.threshold.raise
+        };
+        if !transition {
+            self.pending = 0;
+            return None;
+        }
+        self.pending += 1;
+        if self.pending < self.threshold.min_dwell {
+            return None;
+        }
+        self.pending = 0;
+        self.active = !self.active;
+        Some(if self.active {
+            AlertEvent::AlertRaised { channel, value }
+        } else {
+            AlertEvent::AlertCleared { channel, value }
+        })
+    }
+}
+
+/// Threshold alerting over CO₂eq and TVOC measurements.
+#[derive(Debug, Clone, Default)]
+pub struct Alerts {
+    co2eq: Option<ChannelState>,
+    tvoc: Option<ChannelState>,
+}
+
+impl Alerts {
+    /// Create a new instance without any thresholds configured.
+    pub fn new() -> Self {
+        Alerts::default()
+    }
+
+    /// Configure the CO₂eq threshold (in ppm).
+    pub fn with_co2eq(mut self, threshold: Threshold) -> Self {
+        self.co2eq = Some(ChannelState::new(threshold));
+        self
+    }
+
+    /// Configure the TVOC threshold (in ppb).
+    pub fn with_tvoc(mut self, threshold: Threshold) -> Self {
+        self.tvoc = Some(ChannelState::new(threshold));
+        self
+    }
+
+    /// Feed a new measurement and return the resulting alert events.
+    pub fn update(&mut self, measurement: &Measurement) -> Vec<AlertEvent, 2> {
+        let mut events = Vec::new();
+        if let Some(ref mut state) = self.co2eq {
+            if let Some(event) = state.update(Channel::Co2eq, measurement.co2eq_ppm) {
+                let _ = events.push(event);
+            }
+        }
+        if let Some(ref mut state) = self.tvoc {
+            if let Some(event) = state.update(Channel::Tvoc, measurement.tvoc_ppb) {
+                let _ = events.push(event);
+            }
+        }
+        events
+    }
+
+    /// Whether an alert is currently active for the given signal.
+    pub fn is_active(&self, channel: Channel) -> bool {
+        let state = match channel {
+            Channel::Co2eq => &self.co2eq,
+            Channel::Tvoc => &self.tvoc,
+        };
+        state.as_ref().is_some_and(|s| s.active)
+    }
+
+    /// Clear all active alerts without emitting events, e.g. after the sensor
+    /// was re-initialized.
+    pub fn reset(&mut self) {
+        for state in self.co2eq.iter_mut().chain(self.tvoc.iter_mut()) {
+            state.active = false;
+            state.pending = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn co2eq(value: u16) -> Measurement {
+        Measurement { co2eq_ppm: value, tvoc_ppb: 0 }
+    }
+
+    #[test]
+    fn hysteresis() {
+        let mut alerts = Alerts::new().with_co2eq(Threshold::new(1000, 800, 0));
+        assert!(alerts.update(&co2eq(999)).is_empty());
+        assert_eq!(
+            alerts.update(&co2eq(1000)).as_slice(),
+            &[AlertEvent::AlertRaised { channel: Channel::Co2eq, value: 1000 }],
+        );
+        // Between the thresholds, nothing happens
+        assert!(alerts.update(&co2eq(900)).is_empty());
+        assert!(alerts.is_active(Channel::Co2eq));
+        assert_eq!(
+            alerts.update(&co2eq(799)).as_slice(),
+            &[AlertEvent::AlertCleared { channel: Channel::Co2eq, value: 799 }],
+        );
+        assert!(!alerts.is_active(Channel::Co2eq));
+    }
+
+    #[test]
+    fn dwell_time() {
+        let mut alerts = Alerts::new().with_co2eq(Threshold::new(1000, 800, 3));
+        assert!(alerts.update(&co2eq(1200)).is_empty());
+        assert!(alerts.update(&co2eq(1200)).is_empty());
+        // An interruption restarts the dwell time
+        assert!(alerts.update(&co2eq(500)).is_empty());
+        assert!(alerts.update(&co2eq(1200)).is_empty());
+        assert!(alerts.update(&co2eq(1200)).is_empty());
+        assert_eq!(alerts.update(&co2eq(1200)).len(), 1);
+        assert!(alerts.is_active(Channel::Co2eq));
+    }
+
+    #[test]
+    fn both_channels() {
+        let mut alerts = Alerts::new()
+            .with_co2eq(Threshold::new(1000, 800, 1))
+            .with_tvoc(Threshold::new(660, 500, 1));
+        let events = alerts.update(&Measurement { co2eq_ppm: 1500, tvoc_ppb: 1000 });
+        assert_eq!(events.len(), 2);
+        assert!(alerts.is_active(Channel::Co2eq));
+        assert!(alerts.is_active(Channel::Tvoc));
+        alerts.reset();
+        assert!(!alerts.is_active(Channel::Co2eq));
+        assert!(!alerts.is_active(Channel::Tvoc));
+    }
+
+    #[test]
+    fn unconfigured_channel() {
+        let mut alerts = Alerts::new();
+        assert!(alerts.update(&co2eq(60000)).is_empty());
+        assert!(!alerts.is_active(Channel::Co2eq));
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_threshold() {
+        Threshold::new(800, 1000, 0);
+    }
+}
@@ -0,0 +1,188 @@
+//! Composite air quality index.
+//!
+//! Many products show a single "air score" instead of two separate
+//! concentrations. This module maps CO₂eq and TVOC to sub-indices on a 0–500
+//! scale (similar to the US EPA AQI) and combines them using a selectable
+//! [`Weighting`](trait.Weighting.html) scheme.
+//!
+//! The breakpoints up to an index of 200 match the class limits of
+//! [`AirQuality`](../enum.AirQuality.html), so an index of 0–50 corresponds to
+//! "excellent", 51–100 to "good" and so on.
+//!
+//! ```
+//! use sgp30::Measurement;
+//! use sgp30::aqi::{self, Weighted, Worst};
+//!
+//! let measurement = Measurement { co2eq_ppm: 800, tvoc_ppb: 65 };
+//! assert_eq!(aqi::co2eq_sub_index(800), 75);
+//! assert_eq!(aqi::tvoc_sub_index(65), 50);
+//! assert_eq!(aqi::index(&measurement, &Worst), 75);
+//! assert_eq!(aqi::index(&measurement, &Weighted::new(1, 1)), 63);
+//! ```
+
+use types::Measurement;
+
+/// Upper end of the index scale.
+pub const MAX_INDEX: u16 = 500;
+
+/// Index values at the breakpoints.
+const INDEX_BREAKPOINTS: [u16; 7] = [0, 50, 100, 150, 200, 300, MAX_INDEX];
+
+/// CO₂eq concentrations (ppm) at the index breakpoints.
+const CO2EQ_BREAKPOINTS_PPM: [u16; 7] = [400, 600, 1000, 1500, 2000, 5000, 10_000];
+
+/// TVOC concentrations (ppb) at the index breakpoints.
+const TVOC_BREAKPOINTS_PPB: [u16; 7] = [0, 65, 220, 660, 2200, 5500, 11_000];
+
+/// A scheme for combining the CO₂eq and TVOC sub-indices into one index.
+pub trait Weighting {
+    /// Combine the two sub-indices (each 0–500) into one index (0–500).
+    fn combine(&self, co2eq_index: u16, tvoc_index: u16) -> u16;
+}
+
+/// Use the worse of the two sub-indices.
+///
+/// This is the approach of the US EPA AQI and makes sure that a single
+/// pollutant at a bad level is never hidden by a good other one.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct Worst;
+
+impl Weighting for Worst {
+    fn combine(&self, co2eq_index: u16, tvoc_index: u16) -> u16 {
+        co2eq_index.max(tvoc_index)
+    }
+}
+
+/// Use the weighted mean of the two sub-indices (rounded).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Weighted {
+    co2eq: u8,
+    tvoc: u8,
+}
+
+impl Weighted {
+    /// Create a new weighted mean scheme.
+    ///
+    /// # Panics
+    ///
+    /// Panics if both weights are zero.
+    pub fn new(co2eq: u8, tvoc: u8) -> Self {
+        assert!(co2eq > 0 || tvoc > 0, "at least one weight must be non-zero");
+        Weighted { co2eq, tvoc }
+    }
+}
+
+impl Weighting for Weighted {
+    fn combine(&self, co2eq_index: u16, tvoc_index: u16) -> u16 {
+        let total = u32::from(self.co2eq) + u32::from(self.tvoc);
+        let sum = u32::from(co2eq_index) * u32::from(self.co2eq)
+            + u32::from(tvoc_index) * u32::from(self.tvoc);
+        ((sum + total / 2) / total) as u16
+    }
+}
+
+impl<F> Weighting for F
+where
+    F: Fn(u16, u16) -> u16,
+{
+    fn combine(&self, co2eq_index: u16, tvoc_index: u16) -> u16 {
+        self(co2eq_index, tvoc_index).min(MAX_INDEX)
+    }
+}
+
+/// Return the CO₂eq sub-index (0–500) of a concentration in ppm.
+pub fn co2eq_sub_index(co2eq_ppm: u16) -> u16 {
+    interpolate(co2eq_ppm, &CO2EQ_BREAKPOINTS_PPM)
+}
+
+/// Return the TVOC sub-index (0–500) of a concentration in ppb.
+pub fn tvoc_sub_index(tvoc_ppb: u16) -> u16 {
+    interpolate(tvoc_ppb, &TVOC_BREAKPOINTS_PPB)
+}
+
+/// Return the composite index (0–500) of a measurement.
+pub fn index<W: Weighting>(measurement: &Measurement, weighting: &W) -> u16 {
+    weighting.combine(
+        co2eq_sub_index(measurement.co2eq_ppm),
+        tvoc_sub_index(measurement.tvoc_ppb),
+    )
+}
+
+/// Linearly interpolate between the breakpoints, rounding to the nearest
+/// integer and clamping to the index range.
+fn interpolate(value: u16, breakpoints: &[u16; 7]) -> u16 {
+    if value <= breakpoints[0] {
+        return INDEX_BREAKPOINTS[0];
+    }
+    for i in 1..breakpoints.len() {
+        if value <= breakpoints[i] {
+            let (c_lo, c_hi) = (u32::from(breakpoints[i - 1]), u32::from(breakpoints[i]));
+            let (i_lo, i_hi) = (u32::from(INDEX_BREAKPOINTS[i - 1]), u32::from(INDEX_BREAKPOINTS[i]));
+            let span = c_hi - c_lo;
+            let offset = ((u32::from(value) - c_lo) * (i_hi - i_lo) + span / 2) / span;
+            return (i_lo + offset) as u16;
+        }
+    }
+    MAX_INDEX
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use air_quality::AirQuality;
+
+    #[test]
+    fn sub_index_breakpoints() {
+        assert_eq!(co2eq_sub_index(0), 0);
+        assert_eq!(co2eq_sub_index(400), 0);
+        assert_eq!(co2eq_sub_index(600), 50);
+        assert_eq!(co2eq_sub_index(1000), 100);
+        assert_eq!(co2eq_sub_index(10_000), 500);
+        assert_eq!(co2eq_sub_index(57_330), 500);
+        assert_eq!(tvoc_sub_index(0), 0);
+        assert_eq!(tvoc_sub_index(660), 150);
+        assert_eq!(tvoc_sub_index(60_000), 500);
+    }
+
+    #[test]
+    fn interpolation() {
+        assert_eq!(co2eq_sub_index(500), 25);
+        assert_eq!(tvoc_sub_index(440), 125);
+    }
+
+    /// The sub-index bands must agree with the classification.
+    #[test]
+    fn matches_air_quality_classes() {
+        for &ppb in &[0, 64, 65, 219, 220, 659, 660, 2199, 2200] {
+            let class = AirQuality::from_tvoc_ppb(ppb);
+            let index = tvoc_sub_index(ppb);
+            let expected = match index {
+                0..=50 => AirQuality::Excellent,
+                51..=100 => AirQuality::Good,
+                101..=150 => AirQuality::Moderate,
+                151..=200 => AirQuality::Poor,
+                _ => AirQuality::Unhealthy,
+            };
+            // Values exactly at a limit belong to the next class but map
+            // onto the upper end of the lower band.
+            assert!(class == expected || TVOC_BREAKPOINTS_PPB.contains(&ppb), "{}", ppb);
+        }
+    }
+
+    #[test]
+    fn weighting_schemes() {
+        let m = Measurement { co2eq_ppm: 1000, tvoc_ppb: 2200 };
+        assert_eq!(index(&m, &Worst), 200);
+        assert_eq!(index(&m, &Weighted::new(1, 1)), 150);
+        assert_eq!(index(&m, &Weighted::new(1, 0)), 100);
+        assert_eq!(index(&m, &Weighted::new(1, 3)), 175);
+        assert_eq!(index(&m, &|a: u16, b: u16| a + b), 300);
+        assert_eq!(index(&m, &|_: u16, _: u16| 1000), MAX_INDEX);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_weights() {
+        Weighted::new(0, 0);
+    }
+}
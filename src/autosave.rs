@@ -0,0 +1,63 @@
+/// Default interval between two automatic baseline saves (one hour), as
+/// recommended by the datasheet.
+pub const DEFAULT_AUTOSAVE_INTERVAL_SECS: u32 = 60 * 60;
+
+/// Time after initialization without a restored baseline until the baseline
+/// is valid for the first time (12 hours), according to the datasheet.
+pub const INITIAL_BASELINE_SECS: u32 = 12 * 60 * 60;
+
+/// Time until a failed baseline save is retried (one minute), unless the
+/// interval is shorter.
+pub const AUTOSAVE_RETRY_SECS: u32 = 60;
+
+/// State of the automatic baseline saving.
+///
+/// The driver has no notion of time, so the state counts measurements,
+/// assuming the recommended measurement interval of 1 s.
+#[derive(Debug, Clone)]
+pub(crate) struct Autosave {
+    /// The number of measurements between two saves.
+    interval: u32,
+    /// The number of measurements until the next save.
+    remaining: u32,
+}
+
+impl Autosave {
+    pub fn new(interval: u32) -> Self {
+        Autosave {
+            interval,
+            remaining: INITIAL_BASELINE_SECS,
+        }
+    }
+
+    /// Change the interval, keeping the current schedule.
+    pub fn configure(&mut self, interval: u32) {
+        self.interval = interval;
+        self.remaining = self.remaining.min(interval.max(1));
+    }
+
+    /// The sensor was (re-)initialized without a baseline.
+    pub fn on_init(&mut self) {
+        self.remaining = INITIAL_BASELINE_SECS;
+    }
+
+    /// A valid baseline was written to the sensor or saved.
+    pub fn on_baseline_valid(&mut self) {
+        self.remaining = self.interval;
+    }
+
+    /// Count a measurement.
+    pub fn tick(&mut self) {
+        self.remaining = self.remaining.saturating_sub(1);
+    }
+
+    /// Whether the baseline should be saved.
+    pub fn is_due(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Saving the baseline failed, retry later.
+    pub fn on_failure(&mut self) {
+        self.remaining = AUTOSAVE_RETRY_SECS.min(self.interval.max(1));
+    }
+}
@@ -0,0 +1,179 @@
+//! Batched logging of measurement windows.
+//!
+//! Logging every measurement over RTT quickly saturates the link in high-rate
+//! debugging sessions. [`BatchLogger`](struct.BatchLogger.html) accumulates
+//! `N` measurements and then emits a single record with the minimum, maximum
+//! and mean of each signal. With the `defmt` feature, the record is logged
+//! with `defmt::debug!`; it is also returned, e.g. for other log sinks.
+//!
+//! ```
+//! use sgp30::Measurement;
+//! use sgp30::batch::BatchLogger;
+//!
+//! let mut logger: BatchLogger<2> = BatchLogger::new();
+//! assert!(logger.push(&Measurement { co2eq_ppm: 400, tvoc_ppb: 10 }).is_none());
+//! let batch = logger.push(&Measurement { co2eq_ppm: 600, tvoc_ppb: 31 }).unwrap();
+//! assert_eq!((batch.co2eq.min, batch.co2eq.max, batch.co2eq.mean), (400, 600, 500));
+//! assert_eq!(batch.tvoc.mean, 21);
+//! ```
+
+use types::Measurement;
+
+/// Minimum, maximum and mean of a signal over a batch.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BatchChannel {
+    /// Smallest value
+    pub min: u16,
+    /// Largest value
+    pub max: u16,
+    /// Mean, rounded to the nearest integer
+    pub mean: u16,
+}
+
+/// Summary of a batch of measurements.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Batch {
+    /// CO₂eq (ppm)
+    pub co2eq: BatchChannel,
+    /// TVOC (ppb)
+    pub tvoc: BatchChannel,
+    /// Number of measurements in the batch
+    pub count: u32,
+}
+
+/// Running minimum, maximum and sum of a signal.
+#[derive(Debug, Copy, Clone)]
+struct Accumulator {
+    min: u16,
+    max: u16,
+    sum: u32,
+}
+
+impl Accumulator {
+    const EMPTY: Accumulator = Accumulator { min: u16::MAX, max: 0, sum: 0 };
+
+    fn add(&mut self, value: u16) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.sum += u32::from(value);
+    }
+
+    fn finish(&self, count: u32) -> BatchChannel {
+        BatchChannel {
+            min: self.min,
+            max: self.max,
+            mean: ((self.sum + count / 2) / count) as u16,
+        }
+    }
+}
+
+/// Accumulates `N` measurements into a single [`Batch`](struct.Batch.html).
+///
+/// Only running sums are kept, so the logger needs a few bytes regardless
+/// of `N`. `N` must be between 1 and 65536.
+#[derive(Debug, Clone)]
+pub struct BatchLogger<const N: usize> {
+    co2eq: Accumulator,
+    tvoc: Accumulator,
+    count: u32,
+}
+
+impl<const N: usize> BatchLogger<N> {
+    /// Create a new logger.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is 0 or larger than 65536, which could overflow the
+    /// running sums.
+    pub fn new() -> Self {
+        assert!(N > 0 && N <= 1 << 16, "batch size must be between 1 and 65536");
+        BatchLogger {
+            co2eq: Accumulator::EMPTY,
+            tvoc: Accumulator::EMPTY,
+            count: 0,
+        }
+    }
+
+    /// Return the number of measurements in the current batch.
+    pub fn len(&self) -> usize {
+        self.count as usize
+    }
+
+    /// Whether the current batch is empty.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Add a measurement. When the batch is complete, log and return its
+    /// summary and start a new batch.
+    pub fn push(&mut self, measurement: &Measurement) -> Option<Batch> {
+        self.co2eq.add(measurement.co2eq_ppm);
+        self.tvoc.add(measurement.tvoc_ppb);
+        self.count += 1;
+        if self.len() < N {
+            return None;
+        }
+        let batch = self.flush();
+        #[cfg(feature = "defmt")]
+        {
+            if let Some(ref b) = batch {
+                defmt::debug!(
+                    "SGP30 x{=u32}: CO2eq {=u16}..{=u16} ~{=u16} ppm, TVOC {=u16}..{=u16} ~{=u16} ppb",
+                    b.count, b.co2eq.min, b.co2eq.max, b.co2eq.mean, b.tvoc.min, b.tvoc.max, b.tvoc.mean,
+                );
+            }
+        }
+        batch
+    }
+
+    /// Return the summary of the current, possibly incomplete batch and
+    /// start a new one, or `None` if it is empty. The summary is not logged.
+    pub fn flush(&mut self) -> Option<Batch> {
+        if self.count == 0 {
+            return None;
+        }
+        let batch = Batch {
+            co2eq: self.co2eq.finish(self.count),
+            tvoc: self.tvoc.finish(self.count),
+            count: self.count,
+        };
+        self.co2eq = Accumulator::EMPTY;
+        self.tvoc = Accumulator::EMPTY;
+        self.count = 0;
+        Some(batch)
+    }
+}
+
+impl<const N: usize> Default for BatchLogger<N> {
+    fn default() -> Self {
+        BatchLogger::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn batches() {
+        let mut logger: BatchLogger<3> = BatchLogger::new();
+        for (co2eq_ppm, tvoc_ppb) in [(500, 1), (400, 2)] {
+            assert!(logger.push(&Measurement { co2eq_ppm, tvoc_ppb }).is_none());
+        }
+        let batch = logger.push(&Measurement { co2eq_ppm: 450, tvoc_ppb: 2 }).unwrap();
+        assert_eq!(batch, Batch {
+            co2eq: BatchChannel { min: 400, max: 500, mean: 450 },
+            tvoc: BatchChannel { min: 1, max: 2, mean: 2 },
+            count: 3,
+        });
+        assert!(logger.is_empty());
+
+        assert!(logger.flush().is_none());
+        logger.push(&Measurement { co2eq_ppm: 60_000, tvoc_ppb: 60_000 });
+        assert_eq!(logger.flush().unwrap().co2eq.mean, 60_000);
+    }
+}
@@ -0,0 +1,100 @@
+#[allow(unused_imports)] // Required for no_std
+use num_traits::float::FloatCore;
+
+use types::Measurement;
+
+/// A per-sensor linear calibration profile.
+///
+/// SGP30 sensors show some unit-to-unit spread. If the sensors are
+/// characterized against a reference during production, the resulting gain
+/// and offset can be stored per unit and applied to every measurement:
+///
+/// `corrected = raw · gain + offset`
+///
+/// The result is rounded and clamped to the `u16` range.
+///
+/// A calibration can be attached to the driver with
+/// [`Sgp30::set_calibration()`](struct.Sgp30.html#method.set_calibration).
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Calibration {
+    /// Offset added to the CO₂eq value (in ppm) after applying the gain.
+    pub co2eq_offset: i16,
+    /// Gain applied to the CO₂eq value.
+    pub co2eq_gain: f32,
+    /// Offset added to the TVOC value (in ppb) after applying the gain.
+    pub tvoc_offset: i16,
+    /// Gain applied to the TVOC value.
+    pub tvoc_gain: f32,
+}
+
+impl Calibration {
+    /// The identity calibration, which does not change measurements.
+    pub const IDENTITY: Calibration = Calibration {
+        co2eq_offset: 0,
+        co2eq_gain: 1.0,
+        tvoc_offset: 0,
+        tvoc_gain: 1.0,
+    };
+
+    /// Apply the calibration to a measurement.
+    pub fn apply(&self, measurement: Measurement) -> Measurement {
+        Measurement {
+            co2eq_ppm: Calibration::correct(measurement.co2eq_ppm, self.co2eq_gain, self.co2eq_offset),
+            tvoc_ppb: Calibration::correct(measurement.tvoc_ppb, self.tvoc_gain, self.tvoc_offset),
+        }
+    }
+
+    fn correct(value: u16, gain: f32, offset: i16) -> u16 {
+        let corrected = (f32::from(value) * gain + f32::from(offset)).round();
+        if corrected <= 0.0 {
+            0
+        } else if corrected >= f32::from(u16::MAX) {
+            u16::MAX
+        } else {
+            corrected as u16
+        }
+    }
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration::IDENTITY
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn identity() {
+        let m = Measurement { co2eq_ppm: 612, tvoc_ppb: 87 };
+        assert_eq!(Calibration::default().apply(m), m);
+    }
+
+    #[test]
+    fn gain_and_offset() {
+        let calibration = Calibration {
+            co2eq_offset: -20,
+            co2eq_gain: 1.1,
+            tvoc_offset: 5,
+            tvoc_gain: 0.9,
+        };
+        let m = calibration.apply(Measurement { co2eq_ppm: 1000, tvoc_ppb: 100 });
+        assert_eq!(m, Measurement { co2eq_ppm: 1080, tvoc_ppb: 95 });
+    }
+
+    #[test]
+    fn clamping() {
+        let calibration = Calibration {
+            co2eq_offset: -500,
+            co2eq_gain: 1.0,
+            tvoc_offset: 0,
+            tvoc_gain: 2.0,
+        };
+        let m = calibration.apply(Measurement { co2eq_ppm: 400, tvoc_ppb: 60_000 });
+        assert_eq!(m, Measurement { co2eq_ppm: 0, tvoc_ppb: u16::MAX });
+    }
+}
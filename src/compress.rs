@@ -0,0 +1,285 @@
+//! Delta and varint compression of measurement series.
+//!
+//! Low bandwidth links such as LoRaWAN or NB-IoT can only transmit small
+//! frames. Consecutive measurements are usually very similar, so storing the
+//! differences between them as variable length integers reduces an hour of
+//! 1 Hz samples to a fraction of its raw size.
+//!
+//! The format is:
+//!
+//! - The first measurement: CO₂eq and TVOC as unsigned LEB128 varints
+//! - Every following measurement: The CO₂eq and TVOC differences to the
+//!   previous measurement, zigzag encoded as unsigned LEB128 varints
+//!
+//! ```
+//! use sgp30::Measurement;
+//! use sgp30::compress::{Decoder, Encoder};
+//!
+//! let mut buf = [0; 64];
+//! let mut encoder = Encoder::new(&mut buf);
+//! encoder.push(Measurement { co2eq_ppm: 612, tvoc_ppb: 87 }).unwrap();
+//! encoder.push(Measurement { co2eq_ppm: 615, tvoc_ppb: 85 }).unwrap();
+//! let frame = encoder.finish();
+//! assert_eq!(frame.len(), 5);
+//!
+//! let mut decoder = Decoder::new(frame);
+//! assert_eq!(decoder.next(), Some(Ok(Measurement { co2eq_ppm: 612, tvoc_ppb: 87 })));
+//! assert_eq!(decoder.next(), Some(Ok(Measurement { co2eq_ppm: 615, tvoc_ppb: 85 })));
+//! assert_eq!(decoder.next(), None);
+//! ```
+
+use core::fmt;
+
+use types::Measurement;
+
+/// Maximum length of a varint encoding a `u16` or a zigzag encoded `u16`
+/// difference (17 bits).
+const MAX_VARINT_LEN: usize = 3;
+
+/// Errors when encoding or decoding a compressed series.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CompressError {
+    /// The output buffer is too small for another measurement.
+    BufferFull,
+    /// The input ended in the middle of a measurement.
+    Truncated,
+    /// The input contains a value that does not fit into a measurement.
+    Invalid,
+}
+
+impl fmt::Display for CompressError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            CompressError::BufferFull => "Output buffer is full",
+            CompressError::Truncated => "Compressed data is truncated",
+            CompressError::Invalid => "Compressed data is invalid",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for CompressError {}
+
+/// Compresses a series of measurements into a byte buffer.
+#[derive(Debug)]
+pub struct Encoder<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+    count: usize,
+    last: Option<Measurement>,
+}
+
+impl<'a> Encoder<'a> {
+    /// Create a new encoder writing into `buf`.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Encoder {
+            buf,
+            len: 0,
+            count: 0,
+            last: None,
+        }
+    }
+
+    /// Append a measurement.
+    ///
+    /// If the buffer is too small, `CompressError::BufferFull` is returned and
+    /// the buffer is left unchanged.
+    pub fn push(&mut self, measurement: Measurement) -> Result<(), CompressError> {
+        let (co2eq, tvoc) = match self.last {
+            Some(last) => (
+                zigzag(i32::from(measurement.co2eq_ppm) - i32::from(last.co2eq_ppm)),
+                zigzag(i32::from(measurement.tvoc_ppb) - i32::from(last.tvoc_ppb)),
+            ),
+            None => (u32::from(measurement.co2eq_ppm), u32::from(measurement.tvoc_ppb)),
+        };
+        let mut tmp = [0; 2 * MAX_VARINT_LEN];
+        let mut n = write_varint(co2eq, &mut tmp);
+        n += write_varint(tvoc, &mut tmp[n..]);
+        if self.len + n > self.buf.len() {
+            return Err(CompressError::BufferFull);
+        }
+        self.buf[self.len..self.len + n].copy_from_slice(&tmp[..n]);
+        self.len += n;
+        self.count += 1;
+        self.last = Some(measurement);
+        Ok(())
+    }
+
+    /// Return the number of encoded measurements.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Return the number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no measurement was encoded yet.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Finish encoding and return the encoded bytes.
+    pub fn finish(self) -> &'a [u8] {
+        &self.buf[..self.len]
+    }
+}
+
+/// Decodes a compressed series of measurements.
+///
+/// The decoder is an iterator over the measurements. After an error, the
+/// iteration stops.
+#[derive(Debug, Clone)]
+pub struct Decoder<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    last: Option<Measurement>,
+}
+
+impl<'a> Decoder<'a> {
+    /// Create a new decoder reading from `buf`.
+    pub fn new(buf: &'a [u8]) -> Self {
+        Decoder { buf, pos: 0, last: None }
+    }
+
+    fn read_varint(&mut self) -> Result<u32, CompressError> {
+        let mut value: u32 = 0;
+        for i in 0..MAX_VARINT_LEN {
+            let byte = *self.buf.get(self.pos).ok_or(CompressError::Truncated)?;
+            self.pos += 1;
+            value |= u32::from(byte & 0x7F) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(CompressError::Invalid)
+    }
+
+    fn decode(&mut self) -> Result<Measurement, CompressError> {
+        let co2eq = self.read_varint()?;
+        let tvoc = self.read_varint()?;
+        let (co2eq, tvoc) = match self.last {
+            Some(last) => (
+                i32::from(last.co2eq_ppm) + unzigzag(co2eq),
+                i32::from(last.tvoc_ppb) + unzigzag(tvoc),
+            ),
+            None => (co2eq as i32, tvoc as i32),
+        };
+        if !(0..=0xFFFF).contains(&co2eq) || !(0..=0xFFFF).contains(&tvoc) {
+            return Err(CompressError::Invalid);
+        }
+        Ok(Measurement {
+            co2eq_ppm: co2eq as u16,
+            tvoc_ppb: tvoc as u16,
+        })
+    }
+}
+
+impl<'a> Iterator for Decoder<'a> {
+    type Item = Result<Measurement, CompressError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.buf.len() {
+            return None;
+        }
+        let result = self.decode();
+        match result {
+            Ok(measurement) => self.last = Some(measurement),
+            // Stop iterating after an error
+            Err(_) => self.pos = self.buf.len(),
+        }
+        Some(result)
+    }
+}
+
+fn zigzag(value: i32) -> u32 {
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+fn unzigzag(value: u32) -> i32 {
+    ((value >> 1) as i32) ^ -((value & 1) as i32)
+}
+
+/// Write a LEB128 varint, return the number of bytes written.
+fn write_varint(mut value: u32, buf: &mut [u8]) -> usize {
+    let mut i = 0;
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[i] = byte;
+            return i + 1;
+        }
+        buf[i] = byte | 0x80;
+        i += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(co2eq_ppm: u16, tvoc_ppb: u16) -> Measurement {
+        Measurement { co2eq_ppm, tvoc_ppb }
+    }
+
+    #[test]
+    fn zigzag_roundtrip() {
+        for &v in &[0, 1, -1, 2, -2, 65535, -65535] {
+            assert_eq!(unzigzag(zigzag(v)), v);
+        }
+        assert_eq!(zigzag(-1), 1);
+        assert_eq!(zigzag(1), 2);
+    }
+
+    #[test]
+    fn roundtrip() {
+        let series = [m(400, 0), m(400, 0), m(57_330, 60_000), m(0, 65_535), m(612, 87)];
+        let mut buf = [0; 64];
+        let mut encoder = Encoder::new(&mut buf);
+        for &measurement in &series {
+            encoder.push(measurement).unwrap();
+        }
+        assert_eq!(encoder.count(), series.len());
+        let frame = encoder.finish();
+        let decoded: Result<::heapless::Vec<Measurement, 8>, _> = Decoder::new(frame).collect();
+        assert_eq!(decoded.unwrap().as_slice(), &series);
+    }
+
+    #[test]
+    fn compression() {
+        let mut buf = [0; 8192];
+        let mut encoder = Encoder::new(&mut buf);
+        for i in 0..3600u16 {
+            encoder.push(m(400 + i / 60, 100 + (i % 3))).unwrap();
+        }
+        // Small differences take one byte per value
+        assert_eq!(encoder.len(), 3 + 3599 * 2);
+    }
+
+    #[test]
+    fn buffer_full() {
+        let mut buf = [0; 4];
+        let mut encoder = Encoder::new(&mut buf);
+        encoder.push(m(400, 0)).unwrap();
+        assert_eq!(encoder.len(), 3);
+        assert_eq!(encoder.push(m(1000, 0)), Err(CompressError::BufferFull));
+        assert_eq!(encoder.len(), 3);
+        assert_eq!(encoder.count(), 1);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let mut decoder = Decoder::new(&[0x90, 0x03, 0x80]);
+        assert_eq!(decoder.next(), Some(Err(CompressError::Truncated)));
+        assert_eq!(decoder.next(), None);
+        let mut decoder = Decoder::new(&[0xFF, 0xFF, 0xFF, 0x01]);
+        assert_eq!(decoder.next(), Some(Err(CompressError::Invalid)));
+        // Difference leads below zero
+        let mut decoder = Decoder::new(&[0x00, 0x00, 0x01, 0x00]);
+        assert_eq!(decoder.next(), Some(Ok(m(0, 0))));
+        assert_eq!(decoder.next(), Some(Err(CompressError::Invalid)));
+    }
+}
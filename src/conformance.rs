@@ -0,0 +1,362 @@
+//! Conformance test of a sensor against the datasheet.
+//!
+//! [`run()`](fn.run.html) takes a bus and a delay, runs the checks below on
+//! the SGP30 at address 0x58 and returns a [`Report`](struct.Report.html),
+//! e.g. for incoming inspection of sensors:
+//!
+//! 1. The serial number can be read, and reads the same twice.
+//! 2. The feature set reports an SGP30.
+//! 3. The on-chip self-test returns the success pattern. The datasheet does
+//!    not allow it after the measurement was initialized, so it runs first.
+//! 4. The sensor accepts the initialization with the maximum execution time
+//!    of the datasheet, and returns 400 ppm CO₂eq and 0 ppb TVOC during the
+//!    15 s initialization phase.
+//! 5. After the initialization phase, measurements at the 1 Hz cadence
+//!    succeed and stay within the output range.
+//! 6. A baseline written to the sensor reads back unchanged. The original
+//!    baseline is restored afterwards.
+//!
+//! The driver waits for the maximum execution times and does not retry, so a
+//! slow or flaky sensor fails the test. With the default of 5 measurements
+//! after the initialization phase, the test takes about 21 s.
+//!
+//! ```
+//! use sgp30::conformance::{self, Check};
+//! use sgp30::simulator::{SimulatedSgp30, Trajectory};
+//! use sgp30::{Measurement, NoDelay};
+//!
+//! let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement::default()));
+//! let (report, _sensor, _delay) = conformance::run(sensor, NoDelay);
+//! assert!(report.passed());
+//! assert!(matches!(report.self_test, Check::Passed));
+//! ```
+
+use hal::blocking::delay::DelayUs;
+use hal::blocking::i2c::{Read, Write, WriteRead};
+
+use protocol::WARMUP_SAMPLES;
+use types::{Baseline, FeatureSet, Measurement, ProductType};
+use {Error, I2cAddress, Sgp30};
+
+/// Number of measurements after the initialization phase.
+pub const DEFAULT_MEASUREMENTS: u32 = 5;
+
+/// Largest CO₂eq and TVOC value of the output range.
+const MAX_OUTPUT: u16 = 60_000;
+
+/// The baseline written for the round-trip check.
+const TEST_BASELINE: Baseline = Baseline { co2eq: 0x8F3C, tvoc: 0x91A5 };
+
+/// Why a check failed.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Failure {
+    /// The serial number read differently the second time.
+    SerialMismatch([u8; 6], [u8; 6]),
+    /// The feature set reports a different product.
+    ProductType(ProductType),
+    /// The self-test did not return the success pattern.
+    SelfTest,
+    /// A measurement during the initialization phase returned a different
+    /// value than 400 ppm CO₂eq and 0 ppb TVOC.
+    InitializationPhase {
+        /// Index of the measurement after the initialization
+        sample: u32,
+        /// The measurement
+        measurement: Measurement,
+    },
+    /// A measurement was outside the output range.
+    OutOfRange {
+        /// Index of the measurement after the initialization
+        sample: u32,
+        /// The measurement
+        measurement: Measurement,
+    },
+    /// The baseline read back differently than written.
+    BaselineMismatch {
+        /// The written baseline
+        expected: Baseline,
+        /// The baseline read back
+        actual: Baseline,
+    },
+}
+
+/// The result of a check.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Check<E> {
+    /// The sensor behaved as specified.
+    Passed,
+    /// The sensor responded, but not as specified.
+    Failed(Failure),
+    /// Communication with the sensor failed.
+    Error(Error<E>),
+    /// The check was not run because a check it depends on did not pass.
+    Skipped,
+}
+
+impl<E> Check<E> {
+    /// Whether the check passed.
+    pub fn passed(&self) -> bool {
+        matches!(*self, Check::Passed)
+    }
+}
+
+impl<E> From<Error<E>> for Check<E> {
+    fn from(error: Error<E>) -> Self {
+        Check::Error(error)
+    }
+}
+
+/// The results of a conformance test.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Report<E> {
+    /// The serial number, if it could be read
+    pub serial: Option<[u8; 6]>,
+    /// The feature set, if it could be read
+    pub feature_set: Option<FeatureSet>,
+    /// Reading the serial number
+    pub serial_read: Check<E>,
+    /// The product type of the feature set
+    pub product_type: Check<E>,
+    /// The on-chip self-test
+    pub self_test: Check<E>,
+    /// Initialization and the initialization phase
+    pub init: Check<E>,
+    /// Measurements at 1 Hz after the initialization phase
+    pub cadence: Check<E>,
+    /// Writing and reading back the baseline
+    pub baseline_round_trip: Check<E>,
+}
+
+impl<E> Report<E> {
+    /// Whether all checks passed.
+    pub fn passed(&self) -> bool {
+        self.serial_read.passed()
+            && self.product_type.passed()
+            && self.self_test.passed()
+            && self.init.passed()
+            && self.cadence.passed()
+            && self.baseline_round_trip.passed()
+    }
+}
+
+/// Run the conformance test with
+/// [`DEFAULT_MEASUREMENTS`](constant.DEFAULT_MEASUREMENTS.html) measurements
+/// after the initialization phase and return the report together with the
+/// bus and the delay.
+pub fn run<I2C, D, E>(i2c: I2C, delay: D) -> (Report<E>, I2C, D)
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+{
+    run_with_measurements(i2c, delay, DEFAULT_MEASUREMENTS)
+}
+
+/// Run the conformance test with the given number of measurements after the
+/// initialization phase.
+pub fn run_with_measurements<I2C, D, E>(i2c: I2C, delay: D, measurements: u32) -> (Report<E>, I2C, D)
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+{
+    let mut sgp = Sgp30::new(i2c, I2cAddress::DEFAULT, delay);
+    let mut report = Report {
+        serial: None,
+        feature_set: None,
+        serial_read: Check::Skipped,
+        product_type: Check::Skipped,
+        self_test: Check::Skipped,
+        init: Check::Skipped,
+        cadence: Check::Skipped,
+        baseline_round_trip: Check::Skipped,
+    };
+
+    report.serial_read = match (sgp.serial(), sgp.serial()) {
+        (Ok(first), Ok(second)) => {
+            report.serial = Some(first);
+            if first == second {
+                Check::Passed
+            } else {
+                Check::Failed(Failure::SerialMismatch(first, second))
+            }
+        }
+        (Err(e), _) | (_, Err(e)) => e.into(),
+    };
+
+    report.product_type = match sgp.get_feature_set() {
+        Ok(feature_set) => {
+            report.feature_set = Some(feature_set);
+            match feature_set.product_type {
+                ProductType::Sgp30 => Check::Passed,
+                other => Check::Failed(Failure::ProductType(other)),
+            }
+        }
+        Err(e) => e.into(),
+    };
+
+    report.self_test = match sgp.selftest() {
+        Ok(true) => Check::Passed,
+        Ok(false) => Check::Failed(Failure::SelfTest),
+        Err(e) => e.into(),
+    };
+
+    report.init = check_init(&mut sgp);
+    if report.init.passed() {
+        report.cadence = check_cadence(&mut sgp, measurements);
+        report.baseline_round_trip = check_baseline_round_trip(&mut sgp);
+    }
+
+    let Sgp30 { i2c, delay, .. } = sgp;
+    (report, i2c, delay)
+}
+
+/// Wait for the rest of the second after a measurement.
+fn wait_for_next_second<I2C, D, E>(sgp: &mut Sgp30<I2C, D>)
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+{
+    let remaining_us = 1_000_000u32.saturating_sub(sgp.timings.measure_air_quality_us);
+    sgp.wait(remaining_us);
+}
+
+fn check_init<I2C, D, E>(sgp: &mut Sgp30<I2C, D>) -> Check<E>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+{
+    if let Err(e) = sgp.force_init() {
+        return e.into();
+    }
+    for sample in 0..WARMUP_SAMPLES {
+        wait_for_next_second(sgp);
+        match sgp.measure() {
+            Ok(Measurement { co2eq_ppm: 400, tvoc_ppb: 0 }) => {}
+            Ok(measurement) => return Check::Failed(Failure::InitializationPhase { sample, measurement }),
+            Err(e) => return e.into(),
+        }
+    }
+    Check::Passed
+}
+
+fn check_cadence<I2C, D, E>(sgp: &mut Sgp30<I2C, D>, measurements: u32) -> Check<E>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+{
+    for sample in WARMUP_SAMPLES..WARMUP_SAMPLES + measurements {
+        wait_for_next_second(sgp);
+        match sgp.measure() {
+            Ok(measurement) => {
+                if measurement.co2eq_ppm < 400 || measurement.co2eq_ppm > MAX_OUTPUT || measurement.tvoc_ppb > MAX_OUTPUT {
+                    return Check::Failed(Failure::OutOfRange { sample, measurement });
+                }
+            }
+            Err(e) => return e.into(),
+        }
+    }
+    Check::Passed
+}
+
+fn check_baseline_round_trip<I2C, D, E>(sgp: &mut Sgp30<I2C, D>) -> Check<E>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+{
+    let original = match sgp.get_baseline() {
+        Ok(baseline) => baseline,
+        Err(e) => return e.into(),
+    };
+    if let Err(e) = sgp.set_baseline(&TEST_BASELINE) {
+        return e.into();
+    }
+    let actual = sgp.get_baseline();
+    // Restore the original baseline even if the read-back failed
+    let restored = sgp.set_baseline(&original);
+    match actual {
+        Ok(actual) if actual != TEST_BASELINE => {
+            Check::Failed(Failure::BaselineMismatch { expected: TEST_BASELINE, actual })
+        }
+        Ok(_) => restored.map_or_else(Check::from, |()| Check::Passed),
+        Err(e) => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as hal;
+
+    use super::*;
+    use simulator::{Fault, SimulatedSgp30, SimulatorError, Trajectory};
+    use NoDelay;
+
+    const CLEAN_AIR: Measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 0 };
+
+    #[test]
+    fn passed() {
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(CLEAN_AIR)).with_serial([1, 2, 3, 4, 5, 6]);
+        let (report, sensor, _) = run(sensor, NoDelay);
+        assert!(report.passed(), "{:?}", report);
+        assert_eq!(report.serial, Some([1, 2, 3, 4, 5, 6]));
+        assert_eq!(report.feature_set.unwrap().product_type, ProductType::Sgp30);
+        assert_eq!(sensor.time_secs(), WARMUP_SAMPLES + DEFAULT_MEASUREMENTS);
+        // The original baseline was restored
+        assert_ne!(sensor.baseline(), TEST_BASELINE);
+    }
+
+    #[test]
+    fn failures() {
+        // Transfers 0 to 5 read the serial number and feature set, 6 and 7
+        // run the self-test, 8 initializes
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(CLEAN_AIR))
+            .with_fault(1, Fault::CorruptCrc)
+            .with_fault(9, Fault::Nack);
+        let (report, _, _) = run(sensor, NoDelay);
+        assert!(!report.passed());
+        assert!(matches!(report.serial_read, Check::Error(Error::Crc { .. })));
+        assert_eq!(report.serial, None);
+        assert!(report.product_type.passed());
+        assert!(report.self_test.passed());
+        assert!(matches!(report.init, Check::Error(Error::I2c { source: SimulatorError::Nack, .. })));
+        assert!(matches!(report.cadence, Check::Skipped));
+        assert!(matches!(report.baseline_round_trip, Check::Skipped));
+
+        // A reset before reading back the baseline (transfers 9 to 38 are
+        // the initialization phase, 39 and 40 the measurement, 41 to 43 get
+        // and set the baseline)
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(CLEAN_AIR)).with_fault(44, Fault::Reset);
+        let (report, _, _) = run_with_measurements(sensor, NoDelay, 1);
+        assert!(report.cadence.passed());
+        match report.baseline_round_trip {
+            Check::Failed(Failure::BaselineMismatch { expected, actual }) => {
+                assert_eq!(expected, TEST_BASELINE);
+                assert_eq!(actual, ::simulator::INITIAL_BASELINE);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn initialization_phase() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[
+            0, 0, 129, 0, 100, 254, 204, 130, 135,
+            0, 0, 129, 0, 100, 254, 204, 130, 135,
+            0x00, 0x22, 0x65,
+            0xD4, 0x00, 0xC6,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+        ]);
+        let (report, _, _) = run(dev, hal::DelayMockNoop);
+        assert!(report.serial_read.passed());
+        assert!(report.product_type.passed());
+        assert!(report.self_test.passed());
+        match report.init {
+            Check::Failed(Failure::InitializationPhase { sample: 0, measurement }) => {
+                assert_eq!(measurement, Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 });
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+}
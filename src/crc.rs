@@ -0,0 +1,48 @@
+//! Pluggable CRC-8 calculation.
+//!
+//! Every data word exchanged with the SGP30 is protected by a CRC-8 checksum
+//! (polynomial 0x31, initialization 0xFF). By default, the driver calculates
+//! it in software using [`SoftwareCrc`](struct.SoftwareCrc.html). Platforms
+//! with a CRC peripheral, or applications already using a CRC library, can
+//! provide their own implementation of [`Crc8`](trait.Crc8.html) with
+//! [`Sgp30::with_crc()`](../struct.Sgp30.html#method.with_crc):
+//!
+//! ```ignore
+//! let sgp = Sgp30::new(i2c, I2cAddress::DEFAULT, delay).with_crc(HardwareCrc::new(crc_peripheral));
+//! ```
+//!
+//! Closures implement the trait as well.
+
+use protocol;
+
+/// A CRC-8 implementation with polynomial 0x31 and initialization 0xFF.
+pub trait Crc8 {
+    /// Calculate the checksum of `data`.
+    fn checksum(&mut self, data: &[u8]) -> u8;
+}
+
+/// The built-in software CRC-8 (table-driven with the `fast-crc` feature).
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct SoftwareCrc;
+
+impl Crc8 for SoftwareCrc {
+    fn checksum(&mut self, data: &[u8]) -> u8 {
+        protocol::crc8(data)
+    }
+}
+
+impl<F: FnMut(&[u8]) -> u8> Crc8 for F {
+    fn checksum(&mut self, data: &[u8]) -> u8 {
+        self(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn software_crc() {
+        assert_eq!(SoftwareCrc.checksum(&[0xBE, 0xEF]), 0x92);
+    }
+}
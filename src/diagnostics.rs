@@ -0,0 +1,86 @@
+use health::Health;
+use observer::TransactionResult;
+use protocol::Command;
+use types::{Baseline, FeatureSet};
+
+/// Diagnostic counters of the driver.
+///
+/// The counters are maintained by the driver (and by the
+/// [`Sgp30Manager`](manager/struct.Sgp30Manager.html) for retries and the
+/// measurement cadence) and can be read with
+/// [`Sgp30::stats()`](struct.Sgp30.html#method.stats), e.g. to report the
+/// health of the sensor and the bus. All counters saturate at `u32::MAX`.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DriverStats {
+    /// Number of failed I²C transfers, including retried ones
+    pub i2c_errors: u32,
+    /// Number of responses with an invalid CRC checksum
+    pub crc_errors: u32,
+    /// Number of retried transfers, re-read responses and retried
+    /// measurements
+    pub retries: u32,
+    /// Number of successful air quality measurements
+    pub measurements: u32,
+    /// Number of measurements that were taken too late to keep the cadence
+    pub cadence_violations: u32,
+    /// Number of failed automatic baseline saves
+    pub autosave_failures: u32,
+}
+
+/// The last failed transaction, see
+/// [`DriverState`](struct.DriverState.html).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LastError {
+    /// The command that was being executed, if any
+    pub command: Option<Command>,
+    /// How the transaction failed
+    pub result: TransactionResult,
+}
+
+/// A snapshot of the driver state for field diagnostics.
+///
+/// Returned by [`Sgp30::dump_state()`](struct.Sgp30.html#method.dump_state)
+/// without touching the bus, so it can be printed or sent over telemetry
+/// even when the sensor does not respond anymore.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DriverState {
+    /// Whether the air quality measurement is initialized
+    pub initialized: bool,
+    /// Number of air quality measurements since the initialization
+    pub samples_since_init: u32,
+    /// The last failed transaction, if any
+    pub last_error: Option<LastError>,
+    /// The feature set read last, if any
+    pub feature_set: Option<FeatureSet>,
+    /// The baseline read from or written to the sensor last, if any
+    pub baseline: Option<Baseline>,
+    /// The health of the sensor
+    pub health: Health,
+    /// The diagnostic counters
+    pub stats: DriverStats,
+}
+
+/// Increment a counter.
+pub(crate) fn count(counter: &mut u32) {
+    *counter = counter.saturating_add(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_saturates() {
+        let mut counter = u32::MAX - 1;
+        count(&mut counter);
+        assert_eq!(counter, u32::MAX);
+        count(&mut counter);
+        assert_eq!(counter, u32::MAX);
+    }
+}
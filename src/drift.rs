@@ -0,0 +1,178 @@
+//! Baseline drift monitoring.
+//!
+//! The baseline of the SGP30 changes slowly over the lifetime of a sensor.
+//! A sudden or unusually large change can indicate aging or contamination.
+//! [`DriftMonitor`](struct.DriftMonitor.html) tracks successive
+//! [`get_baseline()`](../struct.Sgp30.html#method.get_baseline) values
+//! against a reference and flags when the drift exceeds a configurable bound.
+//!
+//! ```
+//! use sgp30::Baseline;
+//! use sgp30::drift::DriftMonitor;
+//!
+//! let mut monitor: DriftMonitor<u32> = DriftMonitor::new(0x0400);
+//! monitor.update(Baseline { co2eq: 0x8F00, tvoc: 0x9000 }, 0);
+//! let exceeded = monitor.update(Baseline { co2eq: 0x8F80, tvoc: 0x9500 }, 86_400);
+//! assert!(exceeded);
+//! assert_eq!(monitor.drift().unwrap().tvoc, 0x0500);
+//! assert_eq!(monitor.rate_per_day().unwrap().co2eq, 128.0);
+//! ```
+
+use snapshot::{BaselineSnapshot, Timestamp};
+use types::Baseline;
+
+/// Seconds per day, used for drift rates.
+const SECS_PER_DAY: f32 = 86_400.0;
+
+/// The change of the baseline compared to the reference.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Drift {
+    /// Change of the CO₂eq baseline
+    pub co2eq: i32,
+    /// Change of the TVOC baseline
+    pub tvoc: i32,
+}
+
+impl Drift {
+    /// Return the larger absolute change of both signals.
+    pub fn magnitude(&self) -> u32 {
+        self.co2eq.unsigned_abs().max(self.tvoc.unsigned_abs())
+    }
+}
+
+/// The rate of change of the baseline, in baseline units per day.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DriftRate {
+    /// Rate of the CO₂eq baseline
+    pub co2eq: f32,
+    /// Rate of the TVOC baseline
+    pub tvoc: f32,
+}
+
+/// A monitor tracking the drift of the baseline against a reference.
+///
+/// The first baseline passed to [`update()`](#method.update) becomes the
+/// reference, until [`reset()`](#method.reset) is called.
+#[derive(Debug, Clone)]
+pub struct DriftMonitor<T> {
+    /// The maximum tolerated drift per signal.
+    max_drift: u16,
+    /// The reference baseline.
+    reference: Option<BaselineSnapshot<T>>,
+    /// The most recent baseline.
+    latest: Option<BaselineSnapshot<T>>,
+}
+
+impl<T: Timestamp> DriftMonitor<T> {
+    /// Create a new monitor flagging drifts larger than `max_drift`.
+    pub fn new(max_drift: u16) -> Self {
+        DriftMonitor {
+            max_drift,
+            reference: None,
+            latest: None,
+        }
+    }
+
+    /// Add a baseline read at time `now` and return whether the drift exceeds
+    /// the bound.
+    pub fn update(&mut self, baseline: Baseline, now: T) -> bool {
+        let snapshot = BaselineSnapshot::new(baseline, now);
+        if self.reference.is_none() {
+            self.reference = Some(snapshot);
+        } else {
+            self.latest = Some(snapshot);
+        }
+        self.is_exceeded()
+    }
+
+    /// Return the reference snapshot.
+    pub fn reference(&self) -> Option<&BaselineSnapshot<T>> {
+        self.reference.as_ref()
+    }
+
+    /// Return the drift of the most recent baseline compared to the
+    /// reference, or `None` if less than two baselines were added.
+    pub fn drift(&self) -> Option<Drift> {
+        match (&self.reference, &self.latest) {
+            (Some(reference), Some(latest)) => Some(Drift {
+                co2eq: i32::from(latest.baseline.co2eq) - i32::from(reference.baseline.co2eq),
+                tvoc: i32::from(latest.baseline.tvoc) - i32::from(reference.baseline.tvoc),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Return the average drift rate since the reference, or `None` if less
+    /// than two baselines were added or no time elapsed in between.
+    pub fn rate_per_day(&self) -> Option<DriftRate> {
+        let drift = self.drift()?;
+        let latest = self.latest.as_ref()?;
+        let reference = self.reference.as_ref()?;
+        let elapsed = latest.stored_at.seconds_since(&reference.stored_at)?;
+        if elapsed == 0 {
+            return None;
+        }
+        let days = elapsed as f32 / SECS_PER_DAY;
+        Some(DriftRate {
+            co2eq: drift.co2eq as f32 / days,
+            tvoc: drift.tvoc as f32 / days,
+        })
+    }
+
+    /// Whether the drift of any signal exceeds the bound.
+    pub fn is_exceeded(&self) -> bool {
+        self.drift()
+            .is_some_and(|drift| drift.magnitude() > u32::from(self.max_drift))
+    }
+
+    /// Forget all baselines, e.g. after replacing the sensor. The next
+    /// baseline becomes the new reference.
+    pub fn reset(&mut self) {
+        self.reference = None;
+        self.latest = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn b(co2eq: u16, tvoc: u16) -> Baseline {
+        Baseline { co2eq, tvoc }
+    }
+
+    #[test]
+    fn first_baseline_is_reference() {
+        let mut monitor: DriftMonitor<u64> = DriftMonitor::new(100);
+        assert!(!monitor.update(b(1000, 2000), 10));
+        assert_eq!(monitor.reference().unwrap().baseline, b(1000, 2000));
+        assert_eq!(monitor.drift(), None);
+        assert_eq!(monitor.rate_per_day(), None);
+    }
+
+    #[test]
+    fn drift_and_rate() {
+        let mut monitor: DriftMonitor<u32> = DriftMonitor::new(100);
+        monitor.update(b(1000, 2000), 0);
+        assert!(!monitor.update(b(950, 2100), 2 * 86_400));
+        assert_eq!(monitor.drift(), Some(Drift { co2eq: -50, tvoc: 100 }));
+        assert_eq!(monitor.drift().unwrap().magnitude(), 100);
+        assert_eq!(monitor.rate_per_day(), Some(DriftRate { co2eq: -25.0, tvoc: 50.0 }));
+        assert!(monitor.update(b(899, 2000), 3 * 86_400));
+        monitor.reset();
+        assert!(monitor.reference().is_none());
+        assert!(!monitor.is_exceeded());
+    }
+
+    #[test]
+    fn time_going_backwards() {
+        let mut monitor: DriftMonitor<u32> = DriftMonitor::new(100);
+        monitor.update(b(1000, 2000), 100);
+        monitor.update(b(1010, 2000), 50);
+        assert_eq!(monitor.rate_per_day(), None);
+    }
+}
@@ -0,0 +1,152 @@
+//! Time-weighted average (TWA) exposure calculation.
+//!
+//! Occupational exposure limits are usually expressed as an eight hour
+//! time-weighted average. Storing eight hours of 1 Hz samples is not feasible
+//! on small microcontrollers, so [`Twa`](struct.Twa.html) decimates the
+//! samples into buckets (e.g. one minute each) and only keeps the bucket
+//! averages.
+//!
+//! ```
+//! use sgp30::exposure::Twa;
+//!
+//! // 8 h window with 1 minute buckets at 1 Hz sampling
+//! let mut twa: Twa<480> = Twa::new(60);
+//! for _ in 0..120 {
+//!     twa.push(480);
+//! }
+//! assert_eq!(twa.average(), Some(480.0));
+//! // Two minutes at 480 ppb, averaged over the full eight hours
+//! assert_eq!(twa.twa(), 2.0);
+//! ```
+
+use heapless::HistoryBuffer;
+
+use types::Measurement;
+
+/// A time-weighted average accumulator over the TVOC signal.
+///
+/// The window covers `BUCKETS` buckets of `samples_per_bucket` samples each.
+/// With a 1 Hz measurement cadence, `Twa::<480>::new(60)` covers eight hours.
+pub struct Twa<const BUCKETS: usize> {
+    /// Number of samples averaged into a bucket.
+    samples_per_bucket: u32,
+    /// Sum of the samples in the current, incomplete bucket.
+    bucket_sum: u32,
+    /// Number of samples in the current, incomplete bucket.
+    bucket_count: u32,
+    /// Averages of the completed buckets.
+    buckets: HistoryBuffer<u16, BUCKETS>,
+}
+
+impl<const BUCKETS: usize> Twa<BUCKETS> {
+    /// Create a new accumulator that averages `samples_per_bucket` samples
+    /// into one bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `samples_per_bucket` is zero.
+    pub fn new(samples_per_bucket: u32) -> Self {
+        assert!(samples_per_bucket > 0, "samples_per_bucket must not be zero");
+        Twa {
+            samples_per_bucket,
+            bucket_sum: 0,
+            bucket_count: 0,
+            buckets: HistoryBuffer::new(),
+        }
+    }
+
+    /// Add a TVOC sample (in ppb).
+    pub fn push(&mut self, tvoc_ppb: u16) {
+        self.bucket_sum += u32::from(tvoc_ppb);
+        self.bucket_count += 1;
+        if self.bucket_count == self.samples_per_bucket {
+            let mean = (self.bucket_sum + self.samples_per_bucket / 2) / self.samples_per_bucket;
+            self.buckets.write(mean as u16);
+            self.bucket_sum = 0;
+            self.bucket_count = 0;
+        }
+    }
+
+    /// Add the TVOC value of a measurement.
+    pub fn push_measurement(&mut self, measurement: &Measurement) {
+        self.push(measurement.tvoc_ppb);
+    }
+
+    /// Whether the full window has been covered by completed buckets.
+    pub fn is_window_complete(&self) -> bool {
+        self.buckets.len() == BUCKETS
+    }
+
+    /// Return the number of completed buckets in the window.
+    pub fn completed_buckets(&self) -> usize {
+        self.buckets.len()
+    }
+
+    /// Return the time-weighted average over the full window (in ppb).
+    ///
+    /// As in occupational exposure calculations, periods without samples
+    /// count as zero exposure, i.e. the sum of the completed buckets is
+    /// always divided by the full window length. Samples in the current,
+    /// incomplete bucket are not included.
+    pub fn twa(&self) -> f32 {
+        self.bucket_total() as f32 / BUCKETS as f32
+    }
+
+    /// Return the average over the completed buckets only (in ppb), or `None`
+    /// if no bucket was completed yet.
+    pub fn average(&self) -> Option<f32> {
+        match self.buckets.len() {
+            0 => None,
+            len => Some(self.bucket_total() as f32 / len as f32),
+        }
+    }
+
+    /// Remove all samples.
+    pub fn clear(&mut self) {
+        self.bucket_sum = 0;
+        self.bucket_count = 0;
+        self.buckets.clear();
+    }
+
+    fn bucket_total(&self) -> u64 {
+        self.buckets.as_slice().iter().map(|&v| u64::from(v)).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimation() {
+        let mut twa: Twa<4> = Twa::new(3);
+        twa.push(1);
+        twa.push(2);
+        assert_eq!(twa.completed_buckets(), 0);
+        assert_eq!(twa.average(), None);
+        twa.push(6);
+        assert_eq!(twa.completed_buckets(), 1);
+        assert_eq!(twa.average(), Some(3.0));
+        assert_eq!(twa.twa(), 0.75);
+    }
+
+    #[test]
+    fn rolling_window() {
+        let mut twa: Twa<2> = Twa::new(1);
+        twa.push_measurement(&Measurement { co2eq_ppm: 400, tvoc_ppb: 100 });
+        twa.push(200);
+        assert!(twa.is_window_complete());
+        assert_eq!(twa.twa(), 150.0);
+        twa.push(400);
+        assert_eq!(twa.twa(), 300.0);
+        twa.clear();
+        assert_eq!(twa.completed_buckets(), 0);
+        assert_eq!(twa.twa(), 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_bucket_size() {
+        let _: Twa<4> = Twa::new(0);
+    }
+}
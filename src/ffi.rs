@@ -0,0 +1,436 @@
+//! C API.
+//!
+//! With the `ffi` feature, this module exports `extern "C"` functions that
+//! allow C firmware and Linux applications to use this driver instead of
+//! the vendor C code. The sensor is accessed through an opaque handle
+//! created by [`sgp30_new()`](fn.sgp30_new.html), and the I²C bus and delay
+//! are provided by the application as callbacks in a
+//! [`Sgp30Bus`](struct.Sgp30Bus.html).
+//!
+//! All functions returning `int32_t` return `SGP30_OK` (0) on success or
+//! one of the negative `SGP30_ERR_*` codes. To build a shared or static
+//! library, run e.g.
+//!
+//! ```text
+//! cargo rustc --release --features ffi --crate-type cdylib
+//! ```
+//!
+//! and generate a header with [cbindgen](https://github.com/mozilla/cbindgen).
+#![allow(unsafe_code)]
+
+use core::ffi::c_void;
+use core::ptr;
+use core::slice;
+use std::boxed::Box;
+
+use hal::blocking::delay::{DelayMs, DelayUs};
+use hal::blocking::i2c::{Read, Write, WriteRead};
+
+use types::{Baseline, Humidity};
+use {Error, I2cAddress, Sgp30};
+
+/// Success
+pub const SGP30_OK: i32 = 0;
+/// An I²C transfer failed.
+pub const SGP30_ERR_I2C: i32 = -1;
+/// A response failed CRC validation.
+pub const SGP30_ERR_CRC: i32 = -2;
+/// The air quality measurement was not initialized.
+pub const SGP30_ERR_NOT_INITIALIZED: i32 = -3;
+/// The response did not come from the sensor.
+pub const SGP30_ERR_INVALID_RESPONSE: i32 = -4;
+/// A transfer exceeded the timeout.
+pub const SGP30_ERR_TIMEOUT: i32 = -5;
+/// The baseline read back after setting it did not match.
+pub const SGP30_ERR_BASELINE_MISMATCH: i32 = -6;
+/// A pointer argument was null.
+pub const SGP30_ERR_NULL: i32 = -7;
+/// An argument was out of range.
+pub const SGP30_ERR_INVALID_ARGUMENT: i32 = -8;
+
+/// Callback writing `len` bytes from `data` to the 7 bit `address`.
+pub type WriteFn = extern "C" fn(context: *mut c_void, address: u8, data: *const u8, len: usize) -> i32;
+/// Callback reading `len` bytes from the 7 bit `address` into `data`.
+pub type ReadFn = extern "C" fn(context: *mut c_void, address: u8, data: *mut u8, len: usize) -> i32;
+/// Callback sleeping for at least `us` microseconds.
+pub type DelayFn = extern "C" fn(context: *mut c_void, us: u32);
+
+/// The I²C bus and delay callbacks provided by the application.
+///
+/// `write` and `read` return 0 on success and any other value on failure.
+/// `context` is passed to every callback unchanged. The callbacks are
+/// nullable function pointers; [`sgp30_new()`](fn.sgp30_new.html) rejects a
+/// bus with a null callback.
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct Sgp30Bus {
+    /// Application data passed to the callbacks
+    pub context: *mut c_void,
+    /// Write `len` bytes from `data` to the 7 bit `address`
+    pub write: Option<WriteFn>,
+    /// Read `len` bytes from the 7 bit `address` into `data`
+    pub read: Option<ReadFn>,
+    /// Sleep for at least `us` microseconds
+    pub delay_us: Option<DelayFn>,
+}
+
+/// A [`Sgp30Bus`](struct.Sgp30Bus.html) with all callbacks present.
+#[derive(Debug, Copy, Clone)]
+struct Bus {
+    context: *mut c_void,
+    write: WriteFn,
+    read: ReadFn,
+    delay_us: DelayFn,
+}
+
+impl Bus {
+    /// Check that all callbacks of `bus` are present.
+    fn new(bus: Sgp30Bus) -> Option<Self> {
+        Some(Bus {
+            context: bus.context,
+            write: bus.write?,
+            read: bus.read?,
+            delay_us: bus.delay_us?,
+        })
+    }
+}
+
+impl Read for Bus {
+    type Error = i32;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), i32> {
+        match (self.read)(self.context, address, buffer.as_mut_ptr(), buffer.len()) {
+            0 => Ok(()),
+            e => Err(e),
+        }
+    }
+}
+
+impl Write for Bus {
+    type Error = i32;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), i32> {
+        match (self.write)(self.context, address, bytes.as_ptr(), bytes.len()) {
+            0 => Ok(()),
+            e => Err(e),
+        }
+    }
+}
+
+impl WriteRead for Bus {
+    type Error = i32;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), i32> {
+        Write::write(self, address, bytes)?;
+        Read::read(self, address, buffer)
+    }
+}
+
+impl DelayUs<u16> for Bus {
+    fn delay_us(&mut self, us: u16) {
+        (self.delay_us)(self.context, u32::from(us));
+    }
+}
+
+impl DelayMs<u16> for Bus {
+    fn delay_ms(&mut self, ms: u16) {
+        (self.delay_us)(self.context, u32::from(ms) * 1000);
+    }
+}
+
+/// Opaque handle of a sensor.
+pub struct Sgp30Handle(Sgp30<Bus, Bus>);
+
+/// Map a driver error to an error code.
+fn error_code(error: &Error<i32>) -> i32 {
+    match *error {
+        Error::I2c { .. } => SGP30_ERR_I2C,
+        Error::Crc { .. } => SGP30_ERR_CRC,
+        Error::NotInitialized => SGP30_ERR_NOT_INITIALIZED,
+        Error::InvalidResponse { .. } => SGP30_ERR_INVALID_RESPONSE,
+        Error::Timeout { .. } => SGP30_ERR_TIMEOUT,
+        Error::BaselineMismatch { .. } => SGP30_ERR_BASELINE_MISMATCH,
+        Error::InvalidCommand { .. } => SGP30_ERR_INVALID_ARGUMENT,
+    }
+}
+
+/// Run `f` on the driver behind `handle` and return the error code.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle returned by `sgp30_new()` that was not
+/// freed yet.
+unsafe fn with_handle<F>(handle: *mut Sgp30Handle, f: F) -> i32
+where
+    F: FnOnce(&mut Sgp30<Bus, Bus>) -> Result<(), Error<i32>>,
+{
+    match handle.as_mut() {
+        Some(handle) => f(&mut handle.0).map_or_else(|e| error_code(&e), |()| SGP30_OK),
+        None => SGP30_ERR_NULL,
+    }
+}
+
+/// Create a handle for the sensor at `address`, which must be `0x58`.
+///
+/// Return null if a callback of `bus` is null or the address is invalid.
+/// Otherwise, the handle must be released with
+/// [`sgp30_free()`](fn.sgp30_free.html).
+#[no_mangle]
+pub extern "C" fn sgp30_new(bus: Sgp30Bus, address: u8) -> *mut Sgp30Handle {
+    let (bus, address) = match (Bus::new(bus), I2cAddress::new(address)) {
+        (Some(bus), Ok(address)) => (bus, address),
+        _ => return ptr::null_mut(),
+    };
+    Box::into_raw(Box::new(Sgp30Handle(Sgp30::new(bus, address, bus))))
+}
+
+/// Release a handle created by [`sgp30_new()`](fn.sgp30_new.html). Null is
+/// ignored.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle returned by `sgp30_new()` that was not
+/// freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn sgp30_free(handle: *mut Sgp30Handle) {
+    if !handle.is_null() {
+        drop(Box::from_raw(handle));
+    }
+}
+
+/// Initialize the air quality measurement.
+///
+/// # Safety
+///
+/// `handle` must be null or a handle returned by `sgp30_new()` that was not
+/// freed yet. This applies to all functions taking a handle.
+#[no_mangle]
+pub unsafe extern "C" fn sgp30_init(handle: *mut Sgp30Handle) -> i32 {
+    with_handle(handle, |sgp| sgp.init())
+}
+
+/// Perform a soft reset using the I²C general call.
+///
+/// # Safety
+///
+/// See [`sgp30_init()`](fn.sgp30_init.html).
+#[no_mangle]
+pub unsafe extern "C" fn sgp30_soft_reset(handle: *mut Sgp30Handle) -> i32 {
+    with_handle(handle, |sgp| sgp.soft_reset())
+}
+
+/// Read the 48 bit serial number into the 6 byte buffer `serial`.
+///
+/// # Safety
+///
+/// `serial` must be null or point to 6 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn sgp30_serial(handle: *mut Sgp30Handle, serial: *mut u8) -> i32 {
+    if serial.is_null() {
+        return SGP30_ERR_NULL;
+    }
+    let serial = slice::from_raw_parts_mut(serial, 6);
+    with_handle(handle, |sgp| {
+        serial.copy_from_slice(&sgp.serial()?);
+        Ok(())
+    })
+}
+
+/// Run the on-chip self test and store whether it passed in `passed`.
+///
+/// # Safety
+///
+/// `passed` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sgp30_selftest(handle: *mut Sgp30Handle, passed: *mut bool) -> i32 {
+    let passed = match passed.as_mut() {
+        Some(passed) => passed,
+        None => return SGP30_ERR_NULL,
+    };
+    with_handle(handle, |sgp| {
+        *passed = sgp.selftest()?;
+        Ok(())
+    })
+}
+
+/// Measure the air quality.
+///
+/// # Safety
+///
+/// `co2eq_ppm` and `tvoc_ppb` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sgp30_measure(
+    handle: *mut Sgp30Handle,
+    co2eq_ppm: *mut u16,
+    tvoc_ppb: *mut u16,
+) -> i32 {
+    let (co2eq_ppm, tvoc_ppb) = match (co2eq_ppm.as_mut(), tvoc_ppb.as_mut()) {
+        (Some(co2eq_ppm), Some(tvoc_ppb)) => (co2eq_ppm, tvoc_ppb),
+        _ => return SGP30_ERR_NULL,
+    };
+    with_handle(handle, |sgp| {
+        let measurement = sgp.measure()?;
+        *co2eq_ppm = measurement.co2eq_ppm;
+        *tvoc_ppb = measurement.tvoc_ppb;
+        Ok(())
+    })
+}
+
+/// Measure the raw H2 and ethanol signals.
+///
+/// # Safety
+///
+/// `h2` and `ethanol` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sgp30_measure_raw_signals(
+    handle: *mut Sgp30Handle,
+    h2: *mut u16,
+    ethanol: *mut u16,
+) -> i32 {
+    let (h2, ethanol) = match (h2.as_mut(), ethanol.as_mut()) {
+        (Some(h2), Some(ethanol)) => (h2, ethanol),
+        _ => return SGP30_ERR_NULL,
+    };
+    with_handle(handle, |sgp| {
+        let signals = sgp.measure_raw_signals()?;
+        *h2 = signals.h2;
+        *ethanol = signals.ethanol;
+        Ok(())
+    })
+}
+
+/// Read the baseline values.
+///
+/// # Safety
+///
+/// `co2eq` and `tvoc` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn sgp30_get_baseline(
+    handle: *mut Sgp30Handle,
+    co2eq: *mut u16,
+    tvoc: *mut u16,
+) -> i32 {
+    let (co2eq, tvoc) = match (co2eq.as_mut(), tvoc.as_mut()) {
+        (Some(co2eq), Some(tvoc)) => (co2eq, tvoc),
+        _ => return SGP30_ERR_NULL,
+    };
+    with_handle(handle, |sgp| {
+        let baseline = sgp.get_baseline()?;
+        *co2eq = baseline.co2eq;
+        *tvoc = baseline.tvoc;
+        Ok(())
+    })
+}
+
+/// Restore previously read baseline values.
+///
+/// # Safety
+///
+/// See [`sgp30_init()`](fn.sgp30_init.html).
+#[no_mangle]
+pub unsafe extern "C" fn sgp30_set_baseline(handle: *mut Sgp30Handle, co2eq: u16, tvoc: u16) -> i32 {
+    with_handle(handle, |sgp| sgp.set_baseline(&Baseline { co2eq, tvoc }))
+}
+
+/// Set the absolute humidity for the humidity compensation as an 8.8 bit
+/// fixed-point number in g/m³, or disable it with 0.
+///
+/// # Safety
+///
+/// See [`sgp30_init()`](fn.sgp30_init.html).
+#[no_mangle]
+pub unsafe extern "C" fn sgp30_set_humidity(handle: *mut Sgp30Handle, humidity: u16) -> i32 {
+    if humidity == 0 {
+        return with_handle(handle, |sgp| sgp.set_humidity(None));
+    }
+    let [integer, fractional] = humidity.to_be_bytes();
+    match Humidity::new(integer, fractional) {
+        Ok(humidity) => with_handle(handle, |sgp| sgp.set_humidity(Some(&humidity))),
+        Err(_) => SGP30_ERR_INVALID_ARGUMENT,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::vec::Vec;
+
+    use super::*;
+
+    /// A fake bus recording writes and replaying reads.
+    #[derive(Default)]
+    struct Fake {
+        written: Vec<u8>,
+        responses: Vec<u8>,
+        delayed_us: u32,
+    }
+
+    extern "C" fn write(context: *mut c_void, _address: u8, data: *const u8, len: usize) -> i32 {
+        let fake = unsafe { &mut *(context as *mut Fake) };
+        fake.written.extend_from_slice(unsafe { slice::from_raw_parts(data, len) });
+        0
+    }
+
+    extern "C" fn read(context: *mut c_void, _address: u8, data: *mut u8, len: usize) -> i32 {
+        let fake = unsafe { &mut *(context as *mut Fake) };
+        if fake.responses.len() < len {
+            return 1;
+        }
+        let response: Vec<u8> = fake.responses.drain(..len).collect();
+        unsafe { slice::from_raw_parts_mut(data, len) }.copy_from_slice(&response);
+        0
+    }
+
+    extern "C" fn delay_us(context: *mut c_void, us: u32) {
+        let fake = unsafe { &mut *(context as *mut Fake) };
+        fake.delayed_us += us;
+    }
+
+    #[test]
+    fn measure() {
+        let mut fake = Fake {
+            responses: [0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4].to_vec(),
+            ..Fake::default()
+        };
+        let bus = Sgp30Bus {
+            context: &mut fake as *mut Fake as *mut c_void,
+            write: Some(write),
+            read: Some(read),
+            delay_us: Some(delay_us),
+        };
+        let handle = sgp30_new(bus, 0x58);
+        let (mut co2eq, mut tvoc) = (0, 0);
+        unsafe {
+            assert_eq!(sgp30_measure(handle, &mut co2eq, &mut tvoc), SGP30_ERR_NOT_INITIALIZED);
+            assert_eq!(sgp30_init(handle), SGP30_OK);
+            assert_eq!(sgp30_measure(handle, &mut co2eq, ptr::null_mut()), SGP30_ERR_NULL);
+            assert_eq!(sgp30_measure(handle, &mut co2eq, &mut tvoc), SGP30_OK);
+            assert_eq!(sgp30_measure(handle, &mut co2eq, &mut tvoc), SGP30_ERR_I2C);
+            assert_eq!(sgp30_set_humidity(handle, 0x0100), SGP30_OK);
+            sgp30_free(handle);
+        }
+        assert_eq!((co2eq, tvoc), (0x1234, 0xD402));
+        assert_eq!(&fake.written[..4], &[0x20, 0x03, 0x20, 0x08]);
+        assert_eq!(&fake.written[fake.written.len() - 5..], &[0x20, 0x61, 0x01, 0x00, 0x75]);
+        assert!(fake.delayed_us > 0);
+        assert_eq!(unsafe { sgp30_init(ptr::null_mut()) }, SGP30_ERR_NULL);
+    }
+
+    #[test]
+    fn new_invalid() {
+        let mut fake = Fake::default();
+        let bus = Sgp30Bus {
+            context: &mut fake as *mut Fake as *mut c_void,
+            write: Some(write),
+            read: Some(read),
+            delay_us: Some(delay_us),
+        };
+        assert!(sgp30_new(Sgp30Bus { write: None, ..bus }, 0x58).is_null());
+        assert!(sgp30_new(Sgp30Bus { read: None, ..bus }, 0x58).is_null());
+        assert!(sgp30_new(Sgp30Bus { delay_us: None, ..bus }, 0x58).is_null());
+        assert!(sgp30_new(bus, 0x59).is_null());
+        let handle = sgp30_new(bus, 0x58);
+        assert!(!handle.is_null());
+        unsafe { sgp30_free(handle) };
+        assert!(fake.written.is_empty());
+    }
+}
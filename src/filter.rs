@@ -0,0 +1,444 @@
+//! Smoothing filters for measurements.
+//!
+//! The TVOC signal in particular is quite noisy. The filters in this module
+//! can be fed with every new measurement and return a smoothed value.
+//!
+//! ```
+//! use sgp30::Measurement;
+//! use sgp30::filter::Ewma;
+//!
+//! let mut filter = Ewma::new(0.5);
+//! filter.update(Measurement { co2eq_ppm: 400, tvoc_ppb: 0 });
+//! let smoothed = filter.update(Measurement { co2eq_ppm: 600, tvoc_ppb: 100 });
+//! assert_eq!(smoothed, Measurement { co2eq_ppm: 500, tvoc_ppb: 50 });
+//! ```
+
+#[allow(unused_imports)] // Required for no_std
+use num_traits::float::FloatCore;
+
+use types::Measurement;
+
+/// An exponentially weighted moving average over measurements.
+///
+/// Every new value `x` updates the state `s` to `s + α · (x - s)`. A larger
+/// `α` follows changes more quickly, a smaller one smooths more strongly.
+/// The first measurement initializes the state.
+#[derive(Debug, Clone)]
+pub struct Ewma {
+    /// The smoothing factor (0 < α ≤ 1).
+    alpha: f32,
+    /// The current CO₂eq and TVOC state, if initialized.
+    state: Option<(f32, f32)>,
+}
+
+impl Ewma {
+    /// Create a new filter with the smoothing factor `alpha`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` is not in the range `(0, 1]`.
+    pub fn new(alpha: f32) -> Self {
+        assert!(alpha > 0.0 && alpha <= 1.0, "alpha must be in the range (0, 1]");
+        Ewma { alpha, state: None }
+    }
+
+    /// Return the smoothing factor.
+    pub fn alpha(&self) -> f32 {
+        self.alpha
+    }
+
+    /// Feed a new measurement into the filter and return the smoothed value.
+    pub fn update(&mut self, measurement: Measurement) -> Measurement {
+        let co2eq = f32::from(measurement.co2eq_ppm);
+        let tvoc = f32::from(measurement.tvoc_ppb);
+        let state = match self.state {
+            Some((s_co2eq, s_tvoc)) => (
+                s_co2eq + self.alpha * (co2eq - s_co2eq),
+                s_tvoc + self.alpha * (tvoc - s_tvoc),
+            ),
+            None => (co2eq, tvoc),
+        };
+        self.state = Some(state);
+        Ewma::to_measurement(state)
+    }
+
+    /// Return the current smoothed value, or `None` if no measurement was fed
+    /// into the filter yet.
+    pub fn value(&self) -> Option<Measurement> {
+        self.state.map(Ewma::to_measurement)
+    }
+
+    /// Reset the filter to its uninitialized state, e.g. after the sensor was
+    /// re-initialized.
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+
+    fn to_measurement((co2eq, tvoc): (f32, f32)) -> Measurement {
+        Measurement {
+            co2eq_ppm: co2eq.round() as u16,
+            tvoc_ppb: tvoc.round() as u16,
+        }
+    }
+}
+
+/// Rate-of-change limit for a single signal of the
+/// [`SpikeFilter`](struct.SpikeFilter.html).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SpikeLimit {
+    /// Maximum change between two consecutive accepted values.
+    pub max_step: u16,
+    /// Number of consecutive out-of-limit values after which the new level is
+    /// accepted as a real change instead of a spike.
+    pub confirm: u8,
+}
+
+/// The result of feeding a measurement into a
+/// [`SpikeFilter`](struct.SpikeFilter.html).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SpikeCheck {
+    /// The measurement with suppressed spikes replaced by the last accepted
+    /// value.
+    pub measurement: Measurement,
+    /// Whether the CO₂eq value was considered a spike.
+    pub co2eq_spike: bool,
+    /// Whether the TVOC value was considered a spike.
+    pub tvoc_spike: bool,
+}
+
+impl SpikeCheck {
+    /// Whether any of the signals was considered a spike.
+    pub fn is_spike(&self) -> bool {
+        self.co2eq_spike || self.tvoc_spike
+    }
+}
+
+/// State of a single signal of the spike filter.
+#[derive(Debug, Clone)]
+struct SpikeState {
+    limit: SpikeLimit,
+    last: Option<u16>,
+    rejected: u8,
+}
+
+impl SpikeState {
+    fn new(limit: SpikeLimit) -> Self {
+        SpikeState {
+            limit,
+            last: None,
+            rejected: 0,
+        }
+    }
+
+    /// Return the accepted value and whether the input was a spike.
+    fn update(&mut self, value: u16) -> (u16, bool) {
+        let last = match self.last {
+            Some(last) => last,
+            None => {
+                self.last = Some(value);
+                return (value, false);
+            }
+        };
+        let step = value.abs_diff(last);
+        if step <= self.limit.max_step {
+            self.rejected = 0;
+            self.last = Some(value);
+            return (value, false);
+        }
+        self.rejected += 1;
+        if self.rejected > self.limit.confirm {
+            // The level persisted, this is a real change
+            self.rejected = 0;
+            self.last = Some(value);
+            (value, false)
+        } else {
+            (last, true)
+        }
+    }
+}
+
+/// A rate-of-change limiter that suppresses single-sample spikes.
+///
+/// Spikes occur for example after a bus glitch or when cleaning agents are
+/// sprayed close to the sensor. A value that differs from the last accepted
+/// value by more than the configured step is flagged and replaced by the last
+/// accepted value. If the new level persists for more than `confirm`
+/// measurements, it is accepted.
+///
+/// ```
+/// use sgp30::Measurement;
+/// use sgp30::filter::{SpikeFilter, SpikeLimit};
+///
+/// let mut filter = SpikeFilter::new().with_tvoc(SpikeLimit { max_step: 500, confirm: 2 });
+/// filter.update(Measurement { co2eq_ppm: 400, tvoc_ppb: 100 });
+/// let check = filter.update(Measurement { co2eq_ppm: 400, tvoc_ppb: 5000 });
+/// assert!(check.tvoc_spike);
+/// assert_eq!(check.measurement.tvoc_ppb, 100);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct SpikeFilter {
+    co2eq: Option<SpikeState>,
+    tvoc: Option<SpikeState>,
+}
+
+impl SpikeFilter {
+    /// Create a new filter that does not limit any signal.
+    pub fn new() -> Self {
+        SpikeFilter::default()
+    }
+
+    /// Limit the CO₂eq signal (in ppm).
+    pub fn with_co2eq(mut self, limit: SpikeLimit) -> Self {
+        self.co2eq = Some(SpikeState::new(limit));
+        self
+    }
+
+    /// Limit the TVOC signal (in ppb).
+    pub fn with_tvoc(mut self, limit: SpikeLimit) -> Self {
+        self.tvoc = Some(SpikeState::new(limit));
+        self
+    }
+
+    /// Feed a new measurement into the filter.
+    pub fn update(&mut self, measurement: Measurement) -> SpikeCheck {
+        let (co2eq_ppm, co2eq_spike) = match self.co2eq {
+            Some(ref mut state) => state.update(measurement.co2eq_ppm),
+            None => (measurement.co2eq_ppm, false),
+        };
+        let (tvoc_ppb, tvoc_spike) = match self.tvoc {
+            Some(ref mut state) => state.update(measurement.tvoc_ppb),
+            None => (measurement.tvoc_ppb, false),
+        };
+        SpikeCheck {
+            measurement: Measurement { co2eq_ppm, tvoc_ppb },
+            co2eq_spike,
+            tvoc_spike,
+        }
+    }
+
+    /// Reset the filter, e.g. after the sensor was re-initialized.
+    pub fn reset(&mut self) {
+        for state in self.co2eq.iter_mut().chain(self.tvoc.iter_mut()) {
+            state.last = None;
+            state.rejected = 0;
+        }
+    }
+}
+
+/// Default process noise variance (ppb² per update) of the Kalman filter.
+///
+/// Allows the true TVOC level to drift by roughly 2 ppb per second, which is
+/// enough to follow typical indoor changes at the 1 Hz measurement rate.
+#[cfg(feature = "kalman")]
+pub const DEFAULT_PROCESS_NOISE: f32 = 4.0;
+
+/// Default measurement noise variance (ppb²) of the Kalman filter.
+///
+/// Corresponds to a standard deviation of 10 ppb, in line with the
+/// sample-to-sample noise of the SGP30 TVOC signal at low concentrations.
+#[cfg(feature = "kalman")]
+pub const DEFAULT_MEASUREMENT_NOISE: f32 = 100.0;
+
+/// A scalar Kalman filter for the TVOC signal.
+///
+/// The filter models the TVOC concentration as a random walk. Compared to
+/// [`Ewma`](struct.Ewma.html) the gain adapts automatically: The filter
+/// follows quickly after a reset and smooths more strongly once it settled.
+///
+/// This filter is only available with the `kalman` feature.
+///
+/// ```
+/// use sgp30::filter::Kalman;
+///
+/// let mut filter = Kalman::default();
+/// filter.update(100);
+/// let smoothed = filter.update(140);
+/// assert!(smoothed > 100.0 && smoothed < 140.0);
+/// ```
+#[cfg(feature = "kalman")]
+#[derive(Debug, Clone)]
+pub struct Kalman {
+    /// Process noise variance.
+    q: f32,
+    /// Measurement noise variance.
+    r: f32,
+    /// The current estimate and its variance, if initialized.
+    state: Option<(f32, f32)>,
+}
+
+#[cfg(feature = "kalman")]
+impl Kalman {
+    /// Create a new filter with the given process and measurement noise
+    /// variances.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `process_noise` is negative or `measurement_noise` is not
+    /// positive.
+    pub fn new(process_noise: f32, measurement_noise: f32) -> Self {
+        assert!(process_noise >= 0.0, "process noise must not be negative");
+        assert!(measurement_noise > 0.0, "measurement noise must be positive");
+        Kalman {
+            q: process_noise,
+            r: measurement_noise,
+            state: None,
+        }
+    }
+
+    /// Feed a new TVOC value (in ppb) into the filter and return the
+    /// estimate.
+    pub fn update(&mut self, tvoc_ppb: u16) -> f32 {
+        let z = f32::from(tvoc_ppb);
+        let (x, p) = match self.state {
+            Some((x, p)) => {
+                let p = p + self.q;
+                let k = p / (p + self.r);
+                (x + k * (z - x), (1.0 - k) * p)
+            }
+            None => (z, self.r),
+        };
+        self.state = Some((x, p));
+        x
+    }
+
+    /// Feed the TVOC value of a measurement into the filter and return the
+    /// measurement with a filtered TVOC value.
+    pub fn update_measurement(&mut self, measurement: Measurement) -> Measurement {
+        let tvoc = self.update(measurement.tvoc_ppb);
+        Measurement {
+            co2eq_ppm: measurement.co2eq_ppm,
+            tvoc_ppb: tvoc.round() as u16,
+        }
+    }
+
+    /// Return the current estimate, or `None` if no value was fed into the
+    /// filter yet.
+    pub fn value(&self) -> Option<f32> {
+        self.state.map(|(x, _)| x)
+    }
+
+    /// Return the variance of the current estimate.
+    pub fn variance(&self) -> Option<f32> {
+        self.state.map(|(_, p)| p)
+    }
+
+    /// Reset the filter to its uninitialized state.
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+#[cfg(feature = "kalman")]
+impl Default for Kalman {
+    /// Create a filter tuned with the default noise parameters.
+    fn default() -> Self {
+        Kalman::new(DEFAULT_PROCESS_NOISE, DEFAULT_MEASUREMENT_NOISE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(co2eq_ppm: u16, tvoc_ppb: u16) -> Measurement {
+        Measurement { co2eq_ppm, tvoc_ppb }
+    }
+
+    #[test]
+    fn first_value_initializes() {
+        let mut filter = Ewma::new(0.1);
+        assert_eq!(filter.value(), None);
+        assert_eq!(filter.update(m(612, 87)), m(612, 87));
+        assert_eq!(filter.value(), Some(m(612, 87)));
+    }
+
+    #[test]
+    fn smoothing() {
+        let mut filter = Ewma::new(0.25);
+        filter.update(m(400, 0));
+        assert_eq!(filter.update(m(800, 100)), m(500, 25));
+        assert_eq!(filter.update(m(800, 100)), m(575, 44));
+        filter.reset();
+        assert_eq!(filter.update(m(800, 100)), m(800, 100));
+    }
+
+    #[test]
+    fn alpha_one_is_passthrough() {
+        let mut filter = Ewma::new(1.0);
+        filter.update(m(400, 0));
+        assert_eq!(filter.update(m(1234, 567)), m(1234, 567));
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_alpha() {
+        Ewma::new(0.0);
+    }
+
+    #[test]
+    fn spike_suppressed() {
+        let limit = SpikeLimit { max_step: 100, confirm: 1 };
+        let mut filter = SpikeFilter::new().with_co2eq(limit).with_tvoc(limit);
+        assert!(!filter.update(m(400, 10)).is_spike());
+        let check = filter.update(m(2000, 50));
+        assert!(check.co2eq_spike);
+        assert!(!check.tvoc_spike);
+        assert_eq!(check.measurement, m(400, 50));
+        // Back to normal
+        let check = filter.update(m(420, 60));
+        assert!(!check.is_spike());
+        assert_eq!(check.measurement, m(420, 60));
+    }
+
+    #[test]
+    fn persistent_change_accepted() {
+        let mut filter = SpikeFilter::new().with_tvoc(SpikeLimit { max_step: 100, confirm: 2 });
+        filter.update(m(400, 0));
+        assert!(filter.update(m(400, 1000)).tvoc_spike);
+        assert!(filter.update(m(400, 1000)).tvoc_spike);
+        let check = filter.update(m(400, 1000));
+        assert!(!check.tvoc_spike);
+        assert_eq!(check.measurement, m(400, 1000));
+        filter.reset();
+        assert!(!filter.update(m(400, 0)).is_spike());
+    }
+
+    #[test]
+    fn unlimited_channel_passes() {
+        let mut filter = SpikeFilter::new();
+        filter.update(m(400, 0));
+        assert_eq!(filter.update(m(9000, 9000)).measurement, m(9000, 9000));
+    }
+
+    #[test]
+    #[cfg(feature = "kalman")]
+    fn kalman_converges() {
+        let mut filter = Kalman::default();
+        assert_eq!(filter.value(), None);
+        assert_eq!(filter.update(100), 100.0);
+        for _ in 0..100 {
+            filter.update(200);
+        }
+        assert!((filter.value().unwrap() - 200.0).abs() < 1.0);
+        // The variance settles below the measurement noise
+        assert!(filter.variance().unwrap() < DEFAULT_MEASUREMENT_NOISE);
+    }
+
+    #[test]
+    #[cfg(feature = "kalman")]
+    fn kalman_smooths_noise() {
+        let mut filter = Kalman::default();
+        for i in 0..50 {
+            filter.update(if i % 2 == 0 { 80 } else { 120 });
+        }
+        let m = filter.update_measurement(m(400, 80));
+        assert_eq!(m.co2eq_ppm, 400);
+        assert!(m.tvoc_ppb > 90 && m.tvoc_ppb < 110);
+        filter.reset();
+        assert_eq!(filter.value(), None);
+    }
+}
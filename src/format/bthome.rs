@@ -0,0 +1,148 @@
+use humidity::{RelativeHumidity, Temperature};
+use types::Measurement;
+
+use super::FormatError;
+
+/// The 16 bit service UUID of BTHome.
+pub const BTHOME_SERVICE_UUID: u16 = 0xFCD2;
+
+/// Device information byte: BTHome version 2, unencrypted, regular updates.
+const DEVICE_INFO: u8 = 0x40;
+/// AD type of 16 bit service data.
+const AD_TYPE_SERVICE_DATA: u8 = 0x16;
+/// Object ID: Temperature, 0.01 °C signed
+const OBJECT_TEMPERATURE: u8 = 0x02;
+/// Object ID: Humidity, 0.01 % unsigned
+const OBJECT_HUMIDITY: u8 = 0x03;
+/// Object ID: CO₂, 1 ppm unsigned
+const OBJECT_CO2: u8 = 0x12;
+/// Object ID: VOC, 1 µg/m³ unsigned
+const OBJECT_VOC: u8 = 0x13;
+
+/// µg/m³ per ppb TVOC (times 100), using the ethanol-equivalent factor
+/// that is also used for the [`AirQuality`](../enum.AirQuality.html)
+/// classes.
+const TVOC_UG_PER_PPB_X100: u32 = 455;
+
+/// Encoder for BTHome v2 service data, for broadcasting readings to Home
+/// Assistant via BLE advertisements.
+///
+/// CO₂eq is sent as the *CO₂* object. BTHome expects VOC in µg/m³, so TVOC
+/// is converted from ppb using the ethanol-equivalent factor of 4.55
+/// µg/m³ per ppb and saturates at 65535 µg/m³. Temperature and relative
+/// humidity, e.g. from the sensor used for humidity compensation, can be
+/// added as well.
+///
+/// ```
+/// use sgp30::Measurement;
+/// use sgp30::format::BtHome;
+///
+/// let mut buf = [0; 16];
+/// let measurement = Measurement { co2eq_ppm: 612, tvoc_ppb: 87 };
+/// let len = BtHome::new().service_data(&mut buf, &measurement).unwrap();
+/// assert_eq!(&buf[..len], &[0xD2, 0xFC, 0x40, 0x12, 0x64, 0x02, 0x13, 0x8C, 0x01]);
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct BtHome {
+    /// Temperature to include.
+    temperature: Option<Temperature>,
+    /// Relative humidity to include.
+    humidity: Option<RelativeHumidity>,
+}
+
+impl BtHome {
+    /// Create an encoder for measurements only.
+    pub fn new() -> Self {
+        BtHome::default()
+    }
+
+    /// Include a temperature.
+    pub fn with_temperature(mut self, temperature: Temperature) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    /// Include a relative humidity.
+    pub fn with_relative_humidity(mut self, humidity: RelativeHumidity) -> Self {
+        self.humidity = Some(humidity);
+        self
+    }
+
+    /// Write the service data into `buf` and return its length.
+    ///
+    /// The service data starts with the little-endian service UUID
+    /// ([`BTHOME_SERVICE_UUID`](constant.BTHOME_SERVICE_UUID.html)),
+    /// followed by the device information and the objects.
+    pub fn service_data(&self, buf: &mut [u8], measurement: &Measurement) -> Result<usize, FormatError> {
+        let mut data = [0; 17];
+        let mut len = 0;
+        {
+            let mut push = |bytes: &[u8]| {
+                data[len..len + bytes.len()].copy_from_slice(bytes);
+                len += bytes.len();
+            };
+            push(&BTHOME_SERVICE_UUID.to_le_bytes());
+            push(&[DEVICE_INFO]);
+            // Objects must be ordered by their ID
+            if let Some(temperature) = self.temperature {
+                let milli_celsius = temperature.as_milli_celsius();
+                let rounding = if milli_celsius < 0 { -5 } else { 5 };
+                let centidegrees = (milli_celsius.saturating_add(rounding) / 10)
+                    .clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16;
+                push(&[OBJECT_TEMPERATURE]);
+                push(&centidegrees.to_le_bytes());
+            }
+            if let Some(humidity) = self.humidity {
+                let centipercent = ((humidity.as_milli_percent() + 5) / 10).min(10_000) as u16;
+                push(&[OBJECT_HUMIDITY]);
+                push(&centipercent.to_le_bytes());
+            }
+            let voc = (u32::from(measurement.tvoc_ppb) * TVOC_UG_PER_PPB_X100 + 50) / 100;
+            push(&[OBJECT_CO2]);
+            push(&measurement.co2eq_ppm.to_le_bytes());
+            push(&[OBJECT_VOC]);
+            push(&(voc.min(u32::from(u16::MAX)) as u16).to_le_bytes());
+        }
+        if len > buf.len() {
+            return Err(FormatError::BufferFull);
+        }
+        buf[..len].copy_from_slice(&data[..len]);
+        Ok(len)
+    }
+
+    /// Write a complete advertising data structure (length, AD type and
+    /// service data) into `buf` and return its length, e.g. for BLE stacks
+    /// that take raw advertising data.
+    pub fn advertising_data(&self, buf: &mut [u8], measurement: &Measurement) -> Result<usize, FormatError> {
+        if buf.len() < 2 {
+            return Err(FormatError::BufferFull);
+        }
+        let len = self.service_data(&mut buf[2..], measurement)?;
+        buf[0] = (len + 1) as u8;
+        buf[1] = AD_TYPE_SERVICE_DATA;
+        Ok(len + 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advertising_data() {
+        let mut buf = [0; 17];
+        let bthome = BtHome::new()
+            .with_temperature(Temperature::from_milli_celsius(23_456))
+            .with_relative_humidity(RelativeHumidity::from_milli_percent(45_678));
+        let measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 60_000 };
+        let len = bthome.advertising_data(&mut buf, &measurement).unwrap();
+        assert_eq!(&buf[..len], &[
+            16, 0x16, 0xD2, 0xFC, 0x40,
+            0x02, 0x2A, 0x09,
+            0x03, 0xD8, 0x11,
+            0x12, 0x90, 0x01,
+            0x13, 0xFF, 0xFF,
+        ]);
+        assert_eq!(bthome.advertising_data(&mut buf[..16], &measurement), Err(FormatError::BufferFull));
+    }
+}
@@ -0,0 +1,113 @@
+use humidity::{RelativeHumidity, Temperature};
+use types::Measurement;
+
+use super::FormatError;
+
+/// Cayenne LPP data type: Temperature, 0.1 °C signed
+const TYPE_TEMPERATURE: u8 = 103;
+/// Cayenne LPP data type: Relative humidity, 0.5 % unsigned
+const TYPE_HUMIDITY: u8 = 104;
+/// Cayenne LPP data type: Concentration, 1 unit unsigned (extended type)
+const TYPE_CONCENTRATION: u8 = 125;
+
+/// Encoder for Cayenne LPP payloads, e.g. for LoRaWAN uplinks.
+///
+/// Every value is prefixed with a channel and a data type byte. CO₂eq and
+/// TVOC use the *Concentration* type (125) of the extended LPP data types,
+/// which is supported by the decoders of The Things Network and most LPP
+/// libraries. Standard LPP has no type that covers the range of both
+/// signals.
+///
+/// ```
+/// use sgp30::Measurement;
+/// use sgp30::format::CayenneLpp;
+///
+/// let mut buf = [0; 16];
+/// let mut lpp = CayenneLpp::new(&mut buf);
+/// lpp.add_measurement(1, &Measurement { co2eq_ppm: 612, tvoc_ppb: 87 }).unwrap();
+/// assert_eq!(lpp.finish(), &[1, 125, 0x02, 0x64, 2, 125, 0x00, 0x57]);
+/// ```
+#[derive(Debug)]
+pub struct CayenneLpp<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> CayenneLpp<'a> {
+    /// Create a new encoder writing into `buf`.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        CayenneLpp { buf, len: 0 }
+    }
+
+    /// Append CO₂eq (ppm) on `channel` and TVOC (ppb) on `channel + 1`.
+    ///
+    /// If the buffer is too small, `FormatError::BufferFull` is returned and
+    /// the buffer is left unchanged.
+    pub fn add_measurement(&mut self, channel: u8, measurement: &Measurement) -> Result<(), FormatError> {
+        let [co2eq_hi, co2eq_lo] = measurement.co2eq_ppm.to_be_bytes();
+        let [tvoc_hi, tvoc_lo] = measurement.tvoc_ppb.to_be_bytes();
+        self.append(&[
+            channel, TYPE_CONCENTRATION, co2eq_hi, co2eq_lo,
+            channel.wrapping_add(1), TYPE_CONCENTRATION, tvoc_hi, tvoc_lo,
+        ])
+    }
+
+    /// Append a temperature, rounded to 0.1 °C.
+    pub fn add_temperature(&mut self, channel: u8, temperature: Temperature) -> Result<(), FormatError> {
+        let milli_celsius = temperature.as_milli_celsius();
+        let rounding = if milli_celsius < 0 { -50 } else { 50 };
+        let decidegrees = (milli_celsius.saturating_add(rounding) / 100)
+            .clamp(i32::from(i16::MIN), i32::from(i16::MAX)) as i16;
+        let [hi, lo] = decidegrees.to_be_bytes();
+        self.append(&[channel, TYPE_TEMPERATURE, hi, lo])
+    }
+
+    /// Append a relative humidity, rounded to 0.5 %.
+    pub fn add_relative_humidity(&mut self, channel: u8, humidity: RelativeHumidity) -> Result<(), FormatError> {
+        let half_percent = ((humidity.as_milli_percent() + 250) / 500).min(200) as u8;
+        self.append(&[channel, TYPE_HUMIDITY, half_percent])
+    }
+
+    /// Return the number of bytes written so far.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether nothing was written yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Return the payload.
+    pub fn finish(self) -> &'a [u8] {
+        &self.buf[..self.len]
+    }
+
+    /// Append raw bytes if they fit.
+    fn append(&mut self, bytes: &[u8]) -> Result<(), FormatError> {
+        let end = self.len + bytes.len();
+        if end > self.buf.len() {
+            return Err(FormatError::BufferFull);
+        }
+        self.buf[self.len..end].copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload() {
+        let mut buf = [0; 14];
+        let mut lpp = CayenneLpp::new(&mut buf);
+        lpp.add_temperature(3, Temperature::from_milli_celsius(-4_140)).unwrap();
+        lpp.add_relative_humidity(4, RelativeHumidity::from_milli_percent(45_300)).unwrap();
+        let measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 0 };
+        assert_eq!(lpp.add_measurement(1, &measurement), Err(FormatError::BufferFull));
+        assert_eq!(lpp.len(), 7);
+        assert_eq!(lpp.finish(), &[3, 103, 0xFF, 0xD7, 4, 104, 91]);
+    }
+}
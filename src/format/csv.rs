@@ -0,0 +1,88 @@
+use core::fmt;
+
+use types::Measurement;
+
+use super::{write_fmt, FormatError};
+
+/// Formatter for comma-separated values.
+///
+/// Every measurement is written as one line of the form
+/// `timestamp,co2eq_ppm,tvoc_ppb\n`. The timestamp can be any `Display`
+/// type, e.g. seconds since boot or an ISO 8601 date.
+///
+/// ```
+/// use sgp30::Measurement;
+/// use sgp30::format::Csv;
+///
+/// let csv = Csv::new();
+/// let mut buf = [0; 32];
+/// let len = csv.line(&mut buf, 1_700_000_000u32, &Measurement { co2eq_ppm: 612, tvoc_ppb: 87 }).unwrap();
+/// assert_eq!(&buf[..len], b"1700000000,612,87\n");
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Csv {
+    /// The field separator.
+    separator: char,
+}
+
+impl Csv {
+    /// Create a formatter using `,` as the separator.
+    pub fn new() -> Self {
+        Csv { separator: ',' }
+    }
+
+    /// Use a different separator, e.g. `;` or `\t`.
+    pub fn with_separator(mut self, separator: char) -> Self {
+        self.separator = separator;
+        self
+    }
+
+    /// Write the header line into `buf` and return its length.
+    pub fn header(&self, buf: &mut [u8]) -> Result<usize, FormatError> {
+        let sep = self.separator;
+        write_fmt(buf, format_args!("timestamp{}co2eq_ppm{}tvoc_ppb\n", sep, sep))
+    }
+
+    /// Write a measurement line into `buf` and return its length.
+    pub fn line<T: fmt::Display>(
+        &self,
+        buf: &mut [u8],
+        timestamp: T,
+        measurement: &Measurement,
+    ) -> Result<usize, FormatError> {
+        let sep = self.separator;
+        write_fmt(buf, format_args!(
+            "{}{}{}{}{}\n",
+            timestamp, sep, measurement.co2eq_ppm, sep, measurement.tvoc_ppb,
+        ))
+    }
+}
+
+impl Default for Csv {
+    fn default() -> Self {
+        Csv::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_and_line() {
+        let mut buf = [0; 32];
+        let csv = Csv::new().with_separator(';');
+        let len = csv.header(&mut buf).unwrap();
+        assert_eq!(&buf[..len], b"timestamp;co2eq_ppm;tvoc_ppb\n");
+        let len = csv.line(&mut buf, 42u64, &Measurement { co2eq_ppm: 400, tvoc_ppb: 0 }).unwrap();
+        assert_eq!(&buf[..len], b"42;400;0\n");
+    }
+
+    #[test]
+    fn buffer_full() {
+        let mut buf = [0; 8];
+        let measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 0 };
+        assert_eq!(Csv::new().line(&mut buf, 1_000_000u32, &measurement), Err(FormatError::BufferFull));
+        assert_eq!(Csv::new().line(&mut buf, 1u32, &measurement), Ok(8));
+    }
+}
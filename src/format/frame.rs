@@ -0,0 +1,192 @@
+use byteorder::{BigEndian, ByteOrder};
+use core::fmt;
+
+use protocol::crc8;
+use types::{Measurement, RawSignals};
+
+/// Flag: The sensor was still warming up.
+pub const FLAG_WARMING_UP: u8 = 1 << 0;
+/// Flag: A measurement value is saturated.
+pub const FLAG_SATURATED: u8 = 1 << 1;
+/// Flag: The baseline was restored from storage after the last init.
+pub const FLAG_BASELINE_RESTORED: u8 = 1 << 2;
+/// Flag: The sensor health is degraded or failed.
+pub const FLAG_UNHEALTHY: u8 = 1 << 3;
+/// Flag: The frame contains raw signals. Set by
+/// [`TelemetryFrame::encode()`](struct.TelemetryFrame.html#method.encode).
+const FLAG_RAW_SIGNALS: u8 = 1 << 7;
+
+/// A compact binary telemetry frame for links with tight payload budgets,
+/// e.g. NB-IoT or satellite.
+///
+/// The encoded frame has a fixed length of
+/// [`ENCODED_LEN`](#associatedconstant.ENCODED_LEN) bytes, all multi-byte
+/// values are big-endian:
+///
+/// | Offset | Length | Content                                    |
+/// |--------|--------|--------------------------------------------|
+/// | 0      | 1      | Format version                             |
+/// | 1      | 1      | Flags (`FLAG_*`, bit 7: raw signals valid) |
+/// | 2      | 6      | Sensor serial number                       |
+/// | 8      | 4      | Timestamp in seconds                       |
+/// | 12     | 4      | Measurement (CO₂eq, TVOC)                  |
+/// | 16     | 4      | Raw signals (H2, ethanol), 0 if absent     |
+/// | 20     | 1      | CRC8 (as used by the SGP30) of 0..20       |
+///
+/// ```
+/// use sgp30::Measurement;
+/// use sgp30::format::{TelemetryFrame, FLAG_WARMING_UP};
+///
+/// let frame = TelemetryFrame {
+///     serial: [0, 0, 1, 2, 3, 4],
+///     timestamp: 1_700_000_000,
+///     measurement: Measurement { co2eq_ppm: 400, tvoc_ppb: 0 },
+///     raw_signals: None,
+///     flags: FLAG_WARMING_UP,
+/// };
+/// let bytes = frame.encode();
+/// assert_eq!(bytes.len(), 21);
+/// assert_eq!(TelemetryFrame::decode(&bytes), Ok(frame));
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TelemetryFrame {
+    /// The 48 bit serial number of the sensor
+    pub serial: [u8; 6],
+    /// The time of the measurement, in seconds
+    pub timestamp: u32,
+    /// The measurement
+    pub measurement: Measurement,
+    /// The raw signals, if measured
+    pub raw_signals: Option<RawSignals>,
+    /// Status flags (`FLAG_*`). Bit 7 is reserved.
+    pub flags: u8,
+}
+
+impl TelemetryFrame {
+    /// The current format version.
+    pub const VERSION: u8 = 1;
+
+    /// The length of an encoded frame in bytes.
+    pub const ENCODED_LEN: usize = 21;
+
+    /// Encode the frame.
+    pub fn encode(&self) -> [u8; TelemetryFrame::ENCODED_LEN] {
+        let mut buf = [0; TelemetryFrame::ENCODED_LEN];
+        buf[0] = TelemetryFrame::VERSION;
+        buf[1] = self.flags & !FLAG_RAW_SIGNALS;
+        buf[2..8].copy_from_slice(&self.serial);
+        BigEndian::write_u32(&mut buf[8..12], self.timestamp);
+        BigEndian::write_u16(&mut buf[12..14], self.measurement.co2eq_ppm);
+        BigEndian::write_u16(&mut buf[14..16], self.measurement.tvoc_ppb);
+        if let Some(raw_signals) = self.raw_signals {
+            buf[1] |= FLAG_RAW_SIGNALS;
+            BigEndian::write_u16(&mut buf[16..18], raw_signals.h2);
+            BigEndian::write_u16(&mut buf[18..20], raw_signals.ethanol);
+        }
+        buf[20] = crc8(&buf[0..20]);
+        buf
+    }
+
+    /// Decode a frame from the first
+    /// [`ENCODED_LEN`](#associatedconstant.ENCODED_LEN) bytes of `buf`.
+    pub fn decode(buf: &[u8]) -> Result<Self, FrameError> {
+        if buf.len() < TelemetryFrame::ENCODED_LEN {
+            return Err(FrameError::TooShort);
+        }
+        if buf[0] != TelemetryFrame::VERSION {
+            return Err(FrameError::UnsupportedVersion(buf[0]));
+        }
+        if crc8(&buf[0..20]) != buf[20] {
+            return Err(FrameError::Crc);
+        }
+        let mut serial = [0; 6];
+        serial.copy_from_slice(&buf[2..8]);
+        let raw_signals = if buf[1] & FLAG_RAW_SIGNALS != 0 {
+            Some(RawSignals {
+                h2: BigEndian::read_u16(&buf[16..18]),
+                ethanol: BigEndian::read_u16(&buf[18..20]),
+            })
+        } else {
+            None
+        };
+        Ok(TelemetryFrame {
+            serial,
+            timestamp: BigEndian::read_u32(&buf[8..12]),
+            measurement: Measurement {
+                co2eq_ppm: BigEndian::read_u16(&buf[12..14]),
+                tvoc_ppb: BigEndian::read_u16(&buf[14..16]),
+            },
+            raw_signals,
+            flags: buf[1] & !FLAG_RAW_SIGNALS,
+        })
+    }
+}
+
+/// Errors when decoding a telemetry frame.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameError {
+    /// The buffer is shorter than a frame.
+    TooShort,
+    /// The frame has an unknown format version.
+    UnsupportedVersion(u8),
+    /// The checksum does not match.
+    Crc,
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FrameError::TooShort => f.write_str("Telemetry frame is too short"),
+            FrameError::UnsupportedVersion(v) => write!(f, "Unsupported telemetry frame version {}", v),
+            FrameError::Crc => f.write_str("Telemetry frame has an invalid checksum"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FrameError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let frame = TelemetryFrame {
+            serial: [0x00, 0x00, 0x01, 0x2C, 0xA4, 0x7E],
+            timestamp: 0x6553_F100,
+            measurement: Measurement { co2eq_ppm: 612, tvoc_ppb: 87 },
+            raw_signals: Some(RawSignals { h2: 13_000, ethanol: 18_000 }),
+            flags: FLAG_SATURATED | FLAG_RAW_SIGNALS,
+        };
+        let bytes = frame.encode();
+        assert_eq!(&bytes[..16], &[
+            0x01, 0x82,
+            0x00, 0x00, 0x01, 0x2C, 0xA4, 0x7E,
+            0x65, 0x53, 0xF1, 0x00,
+            0x02, 0x64, 0x00, 0x57,
+        ]);
+        let decoded = TelemetryFrame::decode(&bytes).unwrap();
+        assert_eq!(decoded.raw_signals, frame.raw_signals);
+        assert_eq!(decoded.flags, FLAG_SATURATED);
+    }
+
+    #[test]
+    fn decode_errors() {
+        let mut bytes = TelemetryFrame {
+            serial: [0; 6],
+            timestamp: 0,
+            measurement: Measurement::default(),
+            raw_signals: None,
+            flags: 0,
+        }.encode();
+        assert_eq!(TelemetryFrame::decode(&bytes[..20]), Err(FrameError::TooShort));
+        bytes[12] ^= 1;
+        assert_eq!(TelemetryFrame::decode(&bytes), Err(FrameError::Crc));
+        bytes[0] = 2;
+        assert_eq!(TelemetryFrame::decode(&bytes), Err(FrameError::UnsupportedVersion(2)));
+    }
+}
@@ -0,0 +1,114 @@
+use core::fmt;
+
+use types::Measurement;
+
+use super::{write_fmt, FormatError};
+
+/// Encoder for the InfluxDB line protocol.
+///
+/// Every measurement is written as one line of the form
+/// `sgp30,serial=… co2eq=612i,tvoc=87i <timestamp>\n`, which can be sent to
+/// InfluxDB or Telegraf as is. The timestamp is optional; InfluxDB expects
+/// it in nanoseconds since the epoch unless a different precision is
+/// configured.
+///
+/// ```
+/// use sgp30::Measurement;
+/// use sgp30::format::Influx;
+///
+/// let influx = Influx::new().with_serial([0x00, 0x00, 0x01, 0x2c, 0xa4, 0x7e]);
+/// let mut buf = [0; 64];
+/// let measurement = Measurement { co2eq_ppm: 612, tvoc_ppb: 87 };
+/// let len = influx.line(&mut buf, &measurement, Some(1_700_000_000)).unwrap();
+/// assert_eq!(&buf[..len], &b"sgp30,serial=0000012ca47e co2eq=612i,tvoc=87i 1700000000\n"[..]);
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Influx<'a> {
+    /// The measurement name.
+    measurement: &'a str,
+    /// The serial number of the sensor, added as a tag.
+    serial: Option<[u8; 6]>,
+}
+
+impl<'a> Influx<'a> {
+    /// Create an encoder using the measurement name `sgp30` and no tags.
+    pub fn new() -> Self {
+        Influx { measurement: "sgp30", serial: None }
+    }
+
+    /// Use a different measurement name. Commas and spaces are escaped.
+    pub fn with_measurement(mut self, measurement: &'a str) -> Self {
+        self.measurement = measurement;
+        self
+    }
+
+    /// Add the serial number returned by
+    /// [`Sgp30::serial()`](../struct.Sgp30.html#method.serial) as the
+    /// `serial` tag, in hex.
+    pub fn with_serial(mut self, serial: [u8; 6]) -> Self {
+        self.serial = Some(serial);
+        self
+    }
+
+    /// Write a measurement line into `buf` and return its length.
+    pub fn line(
+        &self,
+        buf: &mut [u8],
+        measurement: &Measurement,
+        timestamp: Option<u64>,
+    ) -> Result<usize, FormatError> {
+        write_fmt(buf, format_args!("{}", Line { influx: self, measurement, timestamp }))
+    }
+}
+
+impl<'a> Default for Influx<'a> {
+    fn default() -> Self {
+        Influx::new()
+    }
+}
+
+/// A line to be formatted.
+struct Line<'a, 'b> {
+    influx: &'b Influx<'a>,
+    measurement: &'b Measurement,
+    timestamp: Option<u64>,
+}
+
+impl<'a, 'b> fmt::Display for Line<'a, 'b> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.influx.measurement.chars() {
+            if c == ',' || c == ' ' {
+                f.write_str("\\")?;
+            }
+            fmt::Write::write_char(f, c)?;
+        }
+        if let Some(serial) = self.influx.serial {
+            f.write_str(",serial=")?;
+            for byte in &serial {
+                write!(f, "{:02x}", byte)?;
+            }
+        }
+        write!(f, " co2eq={}i,tvoc={}i", self.measurement.co2eq_ppm, self.measurement.tvoc_ppb)?;
+        if let Some(timestamp) = self.timestamp {
+            write!(f, " {}", timestamp)?;
+        }
+        f.write_str("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line() {
+        let mut buf = [0; 64];
+        let measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 0 };
+        let len = Influx::new().line(&mut buf, &measurement, None).unwrap();
+        assert_eq!(&buf[..len], b"sgp30 co2eq=400i,tvoc=0i\n");
+        let influx = Influx::new().with_measurement("air quality,office");
+        let len = influx.line(&mut buf, &measurement, Some(5)).unwrap();
+        assert_eq!(&buf[..len], &b"air\\ quality\\,office co2eq=400i,tvoc=0i 5\n"[..]);
+        assert_eq!(influx.line(&mut buf[..16], &measurement, None), Err(FormatError::BufferFull));
+    }
+}
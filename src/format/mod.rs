@@ -0,0 +1,94 @@
+//! Output formats for measurements.
+//!
+//! Most formatters write into a caller-provided byte buffer and return the
+//! number of bytes written, so they can be used without an allocator, e.g.
+//! to write to an SD card or a serial port.
+//!
+//! - [`BtHome`](struct.BtHome.html): BTHome v2 BLE advertisements
+//! - [`CayenneLpp`](struct.CayenneLpp.html): Cayenne LPP payloads for
+//!   LoRaWAN
+//! - [`Csv`](struct.Csv.html): Comma-separated values
+//! - [`Influx`](struct.Influx.html): InfluxDB line protocol
+//! - [`ModbusMap`](modbus/struct.ModbusMap.html): Modbus input register
+//!   layout
+//! - [`Prometheus`](struct.Prometheus.html): Prometheus text exposition
+//!   format (requires the `std` feature)
+//! - [`SenMl`](struct.SenMl.html): SenML records in CBOR (requires the
+//!   `senml` feature)
+//! - [`TelemetryFrame`](struct.TelemetryFrame.html): Compact binary frame
+//!   for links with tight payload budgets
+
+use core::fmt;
+
+mod bthome;
+mod cayenne;
+mod csv;
+mod frame;
+mod influx;
+pub mod modbus;
+#[cfg(feature = "std")]
+mod prometheus;
+#[cfg(feature = "senml")]
+mod senml;
+
+pub use self::bthome::{BtHome, BTHOME_SERVICE_UUID};
+pub use self::cayenne::CayenneLpp;
+pub use self::csv::Csv;
+pub use self::frame::{
+    FrameError, TelemetryFrame, FLAG_BASELINE_RESTORED, FLAG_SATURATED, FLAG_UNHEALTHY, FLAG_WARMING_UP,
+};
+pub use self::influx::Influx;
+#[cfg(feature = "std")]
+pub use self::prometheus::Prometheus;
+#[cfg(feature = "senml")]
+pub use self::senml::SenMl;
+
+/// Errors when formatting measurements.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FormatError {
+    /// The output buffer is too small.
+    BufferFull,
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FormatError::BufferFull => f.write_str("Output buffer is too small"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for FormatError {}
+
+/// A `fmt::Write` implementation writing into a byte slice.
+struct SliceWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceWriter<'a> {
+    fn new(buf: &'a mut [u8]) -> Self {
+        SliceWriter { buf, len: 0 }
+    }
+}
+
+impl<'a> fmt::Write for SliceWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.buf.len() {
+            return Err(fmt::Error);
+        }
+        self.buf[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Format `args` into `buf` and return the number of bytes written.
+fn write_fmt(buf: &mut [u8], args: fmt::Arguments) -> Result<usize, FormatError> {
+    let mut writer = SliceWriter::new(buf);
+    fmt::Write::write_fmt(&mut writer, args).map_err(|_| FormatError::BufferFull)?;
+    Ok(writer.len)
+}
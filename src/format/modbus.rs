@@ -0,0 +1,155 @@
+//! Modbus input register layout.
+//!
+//! See [`ModbusMap`](struct.ModbusMap.html) for the register map and the
+//! `REG_*` and `STATUS_*` constants for the individual registers and bits.
+
+use health::Health;
+use types::{Baseline, Measurement, RawSignals};
+
+use super::FormatError;
+
+/// Input register: CO₂eq (ppm)
+pub const REG_CO2EQ_PPM: usize = 0;
+/// Input register: TVOC (ppb)
+pub const REG_TVOC_PPB: usize = 1;
+/// Input register: Raw H2 signal
+pub const REG_RAW_H2: usize = 2;
+/// Input register: Raw ethanol signal
+pub const REG_RAW_ETHANOL: usize = 3;
+/// Input register: CO₂eq baseline
+pub const REG_BASELINE_CO2EQ: usize = 4;
+/// Input register: TVOC baseline
+pub const REG_BASELINE_TVOC: usize = 5;
+/// Input register: Status bits, see the `STATUS_*` constants
+pub const REG_STATUS: usize = 6;
+/// Number of input registers in the map.
+pub const REGISTER_COUNT: usize = 7;
+
+/// Status bit: The measurement registers are valid.
+pub const STATUS_MEASUREMENT_VALID: u16 = 1 << 0;
+/// Status bit: The raw signal registers are valid.
+pub const STATUS_RAW_SIGNALS_VALID: u16 = 1 << 1;
+/// Status bit: The baseline registers are valid.
+pub const STATUS_BASELINE_VALID: u16 = 1 << 2;
+/// Status bit: The sensor is warming up.
+pub const STATUS_WARMING_UP: u16 = 1 << 3;
+/// Status bit: A measurement value is saturated.
+pub const STATUS_SATURATED: u16 = 1 << 4;
+/// Status bit: The sensor health is degraded.
+pub const STATUS_DEGRADED: u16 = 1 << 5;
+/// Status bit: The sensor has failed.
+pub const STATUS_FAILED: u16 = 1 << 6;
+
+/// A Modbus input register view of the sensor state.
+///
+/// Devices exposing the sensor via Modbus RTU or TCP can use this common
+/// mapping instead of defining their own. The registers are, starting at
+/// the offset chosen by the device:
+///
+/// | Register | Content                     |
+/// |----------|-----------------------------|
+/// | 0        | CO₂eq (ppm)                 |
+/// | 1        | TVOC (ppb)                  |
+/// | 2        | Raw H2 signal               |
+/// | 3        | Raw ethanol signal          |
+/// | 4        | CO₂eq baseline              |
+/// | 5        | TVOC baseline               |
+/// | 6        | Status bits (`STATUS_*`)    |
+///
+/// Registers of values that are not available are set to 0 and their
+/// valid bit in the status register is cleared.
+///
+/// ```
+/// use sgp30::Measurement;
+/// use sgp30::format::modbus::{ModbusMap, REGISTER_COUNT, STATUS_MEASUREMENT_VALID};
+///
+/// let mut registers = [0u16; 16];
+/// let map = ModbusMap {
+///     measurement: Some(Measurement { co2eq_ppm: 612, tvoc_ppb: 87 }),
+///     ..ModbusMap::default()
+/// };
+/// map.write(&mut registers[4..]).unwrap();
+/// assert_eq!(&registers[4..4 + REGISTER_COUNT], &[612, 87, 0, 0, 0, 0, STATUS_MEASUREMENT_VALID]);
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct ModbusMap {
+    /// The latest measurement
+    pub measurement: Option<Measurement>,
+    /// The latest raw signals
+    pub raw_signals: Option<RawSignals>,
+    /// The latest baseline
+    pub baseline: Option<Baseline>,
+    /// Whether the sensor is warming up
+    pub warming_up: bool,
+    /// The health of the sensor
+    pub health: Health,
+}
+
+impl ModbusMap {
+    /// Return the status register value.
+    pub fn status(&self) -> u16 {
+        let mut status = 0;
+        if let Some(measurement) = self.measurement {
+            status |= STATUS_MEASUREMENT_VALID;
+            if measurement.is_saturated() {
+                status |= STATUS_SATURATED;
+            }
+        }
+        if self.raw_signals.is_some() {
+            status |= STATUS_RAW_SIGNALS_VALID;
+        }
+        if self.baseline.is_some() {
+            status |= STATUS_BASELINE_VALID;
+        }
+        if self.warming_up {
+            status |= STATUS_WARMING_UP;
+        }
+        match self.health {
+            Health::Healthy => {}
+            Health::Degraded => status |= STATUS_DEGRADED,
+            Health::Failed => status |= STATUS_FAILED,
+        }
+        status
+    }
+
+    /// Write the registers to the start of `registers`.
+    ///
+    /// Returns `FormatError::BufferFull` if `registers` is shorter than
+    /// [`REGISTER_COUNT`](constant.REGISTER_COUNT.html).
+    pub fn write(&self, registers: &mut [u16]) -> Result<(), FormatError> {
+        if registers.len() < REGISTER_COUNT {
+            return Err(FormatError::BufferFull);
+        }
+        let measurement = self.measurement.unwrap_or_default();
+        let raw_signals = self.raw_signals.unwrap_or_default();
+        let baseline = self.baseline.unwrap_or_default();
+        registers[REG_CO2EQ_PPM] = measurement.co2eq_ppm;
+        registers[REG_TVOC_PPB] = measurement.tvoc_ppb;
+        registers[REG_RAW_H2] = raw_signals.h2;
+        registers[REG_RAW_ETHANOL] = raw_signals.ethanol;
+        registers[REG_BASELINE_CO2EQ] = baseline.co2eq;
+        registers[REG_BASELINE_TVOC] = baseline.tvoc;
+        registers[REG_STATUS] = self.status();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write() {
+        let map = ModbusMap {
+            measurement: Some(Measurement { co2eq_ppm: Measurement::MAX_CO2EQ_PPM, tvoc_ppb: 5 }),
+            raw_signals: Some(RawSignals { h2: 13_000, ethanol: 18_000 }),
+            baseline: Some(Baseline { co2eq: 0x8F00, tvoc: 0x9000 }),
+            warming_up: true,
+            health: Health::Degraded,
+        };
+        let mut registers = [0xFFFF; REGISTER_COUNT];
+        map.write(&mut registers).unwrap();
+        assert_eq!(registers, [57_330, 5, 13_000, 18_000, 0x8F00, 0x9000, 0b0111111]);
+        assert_eq!(map.write(&mut registers[1..]), Err(FormatError::BufferFull));
+    }
+}
@@ -0,0 +1,113 @@
+use std::fmt::{self, Write};
+use std::string::String;
+
+use diagnostics::DriverStats;
+use types::Measurement;
+
+/// Renders measurements and driver statistics in the Prometheus text
+/// exposition format, e.g. for a gateway scraped by a node exporter's
+/// textfile collector. Requires the `std` feature.
+///
+/// The following metrics are rendered, each with the configured labels:
+///
+/// - `sgp30_co2eq_ppm` and `sgp30_tvoc_ppb` (gauges, only if a measurement
+///   is given)
+/// - `sgp30_measurements_total`, `sgp30_i2c_errors_total`,
+///   `sgp30_crc_errors_total`, `sgp30_retries_total` and
+///   `sgp30_cadence_violations_total` (counters, from
+///   [`DriverStats`](../struct.DriverStats.html))
+///
+/// ```
+/// use sgp30::{DriverStats, Measurement};
+/// use sgp30::format::Prometheus;
+///
+/// let labels = [("room", "office")];
+/// let measurement = Measurement { co2eq_ppm: 612, tvoc_ppb: 87 };
+/// let text = Prometheus::new(&labels).to_string(Some(&measurement), &DriverStats::default());
+/// assert!(text.contains("sgp30_co2eq_ppm{room=\"office\"} 612\n"));
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Prometheus<'a> {
+    /// Labels added to every sample.
+    labels: &'a [(&'a str, &'a str)],
+}
+
+impl<'a> Prometheus<'a> {
+    /// Create a renderer adding `labels` to every sample.
+    pub fn new(labels: &'a [(&'a str, &'a str)]) -> Self {
+        Prometheus { labels }
+    }
+
+    /// Render the metrics into `out`.
+    pub fn render<W: Write>(
+        &self,
+        out: &mut W,
+        measurement: Option<&Measurement>,
+        stats: &DriverStats,
+    ) -> fmt::Result {
+        if let Some(m) = measurement {
+            self.metric(out, "sgp30_co2eq_ppm", "gauge", "CO2 equivalent in ppm", m.co2eq_ppm.into())?;
+            self.metric(out, "sgp30_tvoc_ppb", "gauge", "Total volatile organic compounds in ppb", m.tvoc_ppb.into())?;
+        }
+        self.metric(out, "sgp30_measurements_total", "counter", "Successful air quality measurements", stats.measurements)?;
+        self.metric(out, "sgp30_i2c_errors_total", "counter", "Failed I2C transfers", stats.i2c_errors)?;
+        self.metric(out, "sgp30_crc_errors_total", "counter", "Responses with an invalid CRC", stats.crc_errors)?;
+        self.metric(out, "sgp30_retries_total", "counter", "Retried transfers and measurements", stats.retries)?;
+        self.metric(out, "sgp30_cadence_violations_total", "counter", "Measurements taken too late", stats.cadence_violations)
+    }
+
+    /// Render the metrics into a new string.
+    pub fn to_string(&self, measurement: Option<&Measurement>, stats: &DriverStats) -> String {
+        let mut out = String::new();
+        // Writing to a string cannot fail
+        let _ = self.render(&mut out, measurement, stats);
+        out
+    }
+
+    /// Render a single metric with its metadata.
+    fn metric<W: Write>(&self, out: &mut W, name: &str, kind: &str, help: &str, value: u32) -> fmt::Result {
+        writeln!(out, "# HELP {} {}", name, help)?;
+        writeln!(out, "# TYPE {} {}", name, kind)?;
+        out.write_str(name)?;
+        for (i, &(label, label_value)) in self.labels.iter().enumerate() {
+            out.write_str(if i == 0 { "{" } else { "," })?;
+            write!(out, "{}=\"", label)?;
+            for c in label_value.chars() {
+                match c {
+                    '\\' => out.write_str("\\\\")?,
+                    '"' => out.write_str("\\\"")?,
+                    '\n' => out.write_str("\\n")?,
+                    c => out.write_char(c)?,
+                }
+            }
+            out.write_str("\"")?;
+        }
+        if !self.labels.is_empty() {
+            out.write_str("}")?;
+        }
+        writeln!(out, " {}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render() {
+        let stats = DriverStats { crc_errors: 3, ..DriverStats::default() };
+        let text = Prometheus::new(&[]).to_string(None, &stats);
+        assert!(!text.contains("sgp30_co2eq_ppm"));
+        assert!(text.contains(
+            "# HELP sgp30_crc_errors_total Responses with an invalid CRC\n\
+             # TYPE sgp30_crc_errors_total counter\n\
+             sgp30_crc_errors_total 3\n"
+        ));
+
+        let labels = [("serial", "0000012ca47e"), ("room", "a \"b\"\\c")];
+        let measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 0 };
+        let text = Prometheus::new(&labels).to_string(Some(&measurement), &stats);
+        assert!(text.contains("sgp30_tvoc_ppb{serial=\"0000012ca47e\",room=\"a \\\"b\\\"\\\\c\"} 0\n"));
+        assert_eq!(text.lines().count(), 7 * 3);
+    }
+}
@@ -0,0 +1,117 @@
+use minicbor::encode::write::{Cursor, EndOfSlice};
+use minicbor::encode::Error;
+use minicbor::Encoder;
+
+use types::Measurement;
+
+use super::FormatError;
+
+/// SenML label: Base name
+const LABEL_BASE_NAME: i8 = -2;
+/// SenML label: Base time
+const LABEL_BASE_TIME: i8 = -3;
+/// SenML label: Name
+const LABEL_NAME: i8 = 0;
+/// SenML label: Unit
+const LABEL_UNIT: i8 = 1;
+/// SenML label: Value
+const LABEL_VALUE: i8 = 2;
+
+/// Encoder for SenML records in CBOR representation (RFC 8428), as used
+/// by LwM2M and CoAP platforms. Requires the `senml` feature.
+///
+/// A measurement is encoded as a pack of two records named `co2eq` (unit
+/// `ppm`) and `tvoc` (unit `ppb`). The base name, e.g. a device URN, and
+/// the base time are added to the first record.
+///
+/// ```
+/// use sgp30::Measurement;
+/// use sgp30::format::SenMl;
+///
+/// let mut buf = [0; 80];
+/// let senml = SenMl::new().with_base_name("urn:dev:ow:10e2073a01080063:");
+/// let len = senml.encode(&mut buf, &Measurement { co2eq_ppm: 612, tvoc_ppb: 87 }, None).unwrap();
+/// assert_eq!(len, 64);
+/// ```
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct SenMl<'a> {
+    /// The base name.
+    base_name: Option<&'a str>,
+}
+
+impl<'a> SenMl<'a> {
+    /// Create an encoder without a base name.
+    pub fn new() -> Self {
+        SenMl::default()
+    }
+
+    /// Set the base name that is prepended to the record names.
+    pub fn with_base_name(mut self, base_name: &'a str) -> Self {
+        self.base_name = Some(base_name);
+        self
+    }
+
+    /// Encode a measurement taken at `time` (seconds since the epoch, if
+    /// known) into `buf` and return the number of bytes written.
+    pub fn encode(
+        &self,
+        buf: &mut [u8],
+        measurement: &Measurement,
+        time: Option<u64>,
+    ) -> Result<usize, FormatError> {
+        let mut encoder = Encoder::new(Cursor::new(buf));
+        self.encode_pack(&mut encoder, measurement, time)
+            .map_err(|_| FormatError::BufferFull)?;
+        Ok(encoder.writer().position())
+    }
+
+    /// Write the pack of records.
+    fn encode_pack(
+        &self,
+        e: &mut Encoder<Cursor<&mut [u8]>>,
+        measurement: &Measurement,
+        time: Option<u64>,
+    ) -> Result<(), Error<EndOfSlice>> {
+        let base_fields = self.base_name.map_or(0, |_| 1) + time.map_or(0, |_| 1);
+        e.array(2)?.map(3 + base_fields)?;
+        if let Some(base_name) = self.base_name {
+            e.i8(LABEL_BASE_NAME)?.str(base_name)?;
+        }
+        if let Some(time) = time {
+            e.i8(LABEL_BASE_TIME)?.u64(time)?;
+        }
+        e.i8(LABEL_NAME)?.str("co2eq")?
+            .i8(LABEL_UNIT)?.str("ppm")?
+            .i8(LABEL_VALUE)?.u16(measurement.co2eq_ppm)?;
+        e.map(3)?
+            .i8(LABEL_NAME)?.str("tvoc")?
+            .i8(LABEL_UNIT)?.str("ppb")?
+            .i8(LABEL_VALUE)?.u16(measurement.tvoc_ppb)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        let mut buf = [0; 40];
+        let measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 0 };
+        let len = SenMl::new().encode(&mut buf, &measurement, Some(1_700_000_000)).unwrap();
+        assert_eq!(&buf[..len], &[
+            0x82,
+            0xA4,
+            0x22, 0x1A, 0x65, 0x53, 0xF1, 0x00,
+            0x00, 0x65, b'c', b'o', b'2', b'e', b'q',
+            0x01, 0x63, b'p', b'p', b'm',
+            0x02, 0x19, 0x01, 0x90,
+            0xA3,
+            0x00, 0x64, b't', b'v', b'o', b'c',
+            0x01, 0x63, b'p', b'p', b'b',
+            0x02, 0x00,
+        ][..]);
+        assert_eq!(SenMl::new().encode(&mut buf[..20], &measurement, None), Err(FormatError::BufferFull));
+    }
+}
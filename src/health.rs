@@ -0,0 +1,128 @@
+/// The health of the sensor, as judged from the outcome of recent
+/// transactions.
+///
+/// Read it with [`Sgp30::health()`](struct.Sgp30.html#method.health) to
+/// decide when to recover the bus or to give up on the sensor.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Health {
+    /// Transactions succeed.
+    #[default]
+    Healthy,
+    /// Some consecutive transactions failed.
+    Degraded,
+    /// Too many consecutive transactions failed. This state is kept until
+    /// [`Sgp30::reset_health()`](struct.Sgp30.html#method.reset_health) is
+    /// called, e.g. after the bus was recovered or the sensor power-cycled.
+    Failed,
+}
+
+/// Thresholds for the transitions between [`Health`](enum.Health.html)
+/// states, set via
+/// [`Sgp30::set_health_thresholds()`](struct.Sgp30.html#method.set_health_thresholds).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HealthThresholds {
+    /// Consecutive failures after which the sensor is degraded
+    pub degraded_after: u32,
+    /// Consecutive failures after which the sensor has failed
+    pub failed_after: u32,
+    /// Consecutive successes after which a degraded sensor is healthy again
+    pub recover_after: u32,
+}
+
+impl HealthThresholds {
+    /// Create new thresholds.
+    pub fn new(degraded_after: u32, failed_after: u32, recover_after: u32) -> Self {
+        HealthThresholds { degraded_after, failed_after, recover_after }
+    }
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds::new(1, 5, 3)
+    }
+}
+
+/// Tracks consecutive transaction outcomes and the resulting health state.
+#[derive(Debug, Default, Copy, Clone)]
+pub(crate) struct HealthMonitor {
+    /// The transition thresholds.
+    pub thresholds: HealthThresholds,
+    /// The current state.
+    pub state: Health,
+    /// Number of consecutive failed transactions.
+    pub consecutive_failures: u32,
+    /// Number of consecutive successful transactions.
+    consecutive_successes: u32,
+}
+
+impl HealthMonitor {
+    /// Record the outcome of a transaction and return the new state if it
+    /// changed.
+    pub fn record(&mut self, success: bool) -> Option<Health> {
+        let previous = self.state;
+        if success {
+            self.consecutive_failures = 0;
+            self.consecutive_successes = self.consecutive_successes.saturating_add(1);
+            if self.state == Health::Degraded
+                && self.consecutive_successes >= self.thresholds.recover_after
+            {
+                self.state = Health::Healthy;
+            }
+        } else {
+            self.consecutive_successes = 0;
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+            if self.consecutive_failures >= self.thresholds.failed_after {
+                self.state = Health::Failed;
+            } else if self.consecutive_failures >= self.thresholds.degraded_after
+                && self.state == Health::Healthy
+            {
+                self.state = Health::Degraded;
+            }
+        }
+        if self.state != previous { Some(self.state) } else { None }
+    }
+
+    /// Return to the healthy state and clear the counters.
+    pub fn reset(&mut self) {
+        self.state = Health::Healthy;
+        self.consecutive_failures = 0;
+        self.consecutive_successes = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transitions() {
+        let mut monitor = HealthMonitor {
+            thresholds: HealthThresholds::new(2, 4, 2),
+            ..HealthMonitor::default()
+        };
+        assert_eq!(monitor.record(false), None);
+        assert_eq!(monitor.record(false), Some(Health::Degraded));
+        assert_eq!(monitor.record(true), None);
+        assert_eq!(monitor.record(true), Some(Health::Healthy));
+
+        for _ in 0..3 {
+            monitor.record(false);
+        }
+        assert_eq!(monitor.state, Health::Degraded);
+        assert_eq!(monitor.record(false), Some(Health::Failed));
+        assert_eq!(monitor.consecutive_failures, 4);
+
+        // Failed is kept until reset
+        for _ in 0..5 {
+            assert_eq!(monitor.record(true), None);
+        }
+        assert_eq!(monitor.state, Health::Failed);
+        monitor.reset();
+        assert_eq!(monitor.state, Health::Healthy);
+        assert_eq!(monitor.consecutive_failures, 0);
+    }
+}
@@ -0,0 +1,224 @@
+//! Humidity conversions.
+//!
+//! The SGP30 expects the absolute humidity (in g/m³) for its on-chip humidity
+//! compensation, while companion sensors like the SHT3x report temperature and
+//! relative humidity. This module provides the conversion between the two,
+//! together with small newtypes that keep the units of the inputs explicit.
+//!
+//! ```
+//! use sgp30::humidity::{self, RelativeHumidity, Temperature};
+//!
+//! let temperature = Temperature::from_fahrenheit(77.0);
+//! let rh = RelativeHumidity::from_fraction(0.5);
+//! let humidity = humidity::absolute_humidity(temperature, rh).unwrap();
+//! assert_eq!(humidity.to_mg_per_m3(), 11_480);
+//! ```
+
+#[allow(unused_imports)] // Required for no_std
+use num_traits::float::FloatCore;
+
+use types::{Humidity, HumidityError};
+
+/// Lowest temperature (in °C) covered by `SATURATION_TABLE`.
+const SATURATION_TABLE_MIN_TEMP: i32 = -40;
+
+/// Absolute humidity of saturated air (100 %RH) in 1/4096 g/m³ for every full
+/// degree Celsius from -40 °C to 87 °C, calculated with the Magnus formula used
+/// by `absolute_humidity()`.
+const SATURATION_TABLE: [u32; 128] = [
+    724, 800, 882, 972, 1070, 1177, 1293, 1419,
+    1557, 1706, 1868, 2043, 2233, 2439, 2661, 2901,
+    3161, 3441, 3742, 4067, 4417, 4793, 5197, 5631,
+    6097, 6597, 7133, 7707, 8321, 8979, 9682, 10432,
+    11234, 12090, 13002, 13975, 15011, 16114, 17287, 18535,
+    19861, 21270, 22765, 24352, 26035, 27818, 29708, 31708,
+    33826, 36065, 38433, 40935, 43578, 46368, 49312, 52417,
+    55691, 59141, 62775, 66601, 70627, 74862, 79315, 83995,
+    88912, 94076, 99497, 105184, 111150, 117405, 123961, 130829,
+    138021, 145551, 153431, 161674, 170293, 179304, 188720, 198555,
+    208826, 219547, 230735, 242405, 254576, 267263, 280485, 294260,
+    308606, 323542, 339087, 355262, 372087, 389582, 407770, 426671,
+    446308, 466703, 487880, 509863, 532676, 556343, 580891, 606343,
+    632728, 660071, 688400, 717743, 748129, 779585, 812143, 845831,
+    880680, 916722, 953988, 992511, 1032323, 1073458, 1115949, 1159832,
+    1205141, 1251912, 1300182, 1349986, 1401364, 1454352, 1508989, 1565315,
+];
+
+/// A temperature, stored in m°C.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub struct Temperature(i32);
+
+impl Temperature {
+    /// Create a new temperature from a value in m°C.
+    pub fn from_milli_celsius(val: i32) -> Self {
+        Temperature(val)
+    }
+
+    /// Create a new temperature from a value in °C, rounded to m°C.
+    pub fn from_celsius(val: f32) -> Self {
+        Temperature((val * 1000.0).round() as i32)
+    }
+
+    /// Create a new temperature from a value in °F, rounded to m°C.
+    pub fn from_fahrenheit(val: f32) -> Self {
+        Temperature::from_celsius((val - 32.0) * 5.0 / 9.0)
+    }
+
+    /// Create a new temperature from a value in K, rounded to m°C.
+    pub fn from_kelvin(val: f32) -> Self {
+        Temperature::from_celsius(val - 273.15)
+    }
+
+    /// Return the temperature in m°C.
+    pub fn as_milli_celsius(self) -> i32 {
+        self.0
+    }
+
+    /// Return the temperature in °C.
+    pub fn as_celsius(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+}
+
+/// A relative humidity, stored in m%RH.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
+pub struct RelativeHumidity(u32);
+
+impl RelativeHumidity {
+    /// Create a new relative humidity from a value in m%RH.
+    pub fn from_milli_percent(val: u32) -> Self {
+        RelativeHumidity(val)
+    }
+
+    /// Create a new relative humidity from a value in %RH, rounded to m%RH.
+    ///
+    /// Negative values are clamped to 0 %RH.
+    pub fn from_percent(val: f32) -> Self {
+        RelativeHumidity((val * 1000.0).round() as u32)
+    }
+
+    /// Create a new relative humidity from a fraction (0.0-1.0), rounded to
+    /// m%RH.
+    ///
+    /// Negative values are clamped to 0 %RH.
+    pub fn from_fraction(val: f32) -> Self {
+        RelativeHumidity::from_percent(val * 100.0)
+    }
+
+    /// Return the relative humidity in m%RH.
+    pub fn as_milli_percent(self) -> u32 {
+        self.0
+    }
+
+    /// Return the relative humidity in %RH.
+    pub fn as_percent(self) -> f32 {
+        self.0 as f32 / 1000.0
+    }
+}
+
+/// Calculate the absolute humidity from temperature and relative humidity.
+///
+/// The absolute humidity is calculated using the Magnus formula with the
+/// parameters recommended by Sensirion:
+///
+/// ```text
+///                      RH/100 % · 6.112 hPa · exp((17.62 · t) / (243.12 °C + t))
+/// dv(T, RH) = 216.7 · ---------------------------------------------------------
+///                                       273.15 °C + t
+/// ```
+///
+/// Relative humidity values above 100 %RH and results that exceed the
+/// representable range return `HumidityError::OutOfRange`.
+///
+/// Only available with the `libm` feature (enabled by default).
+#[cfg(feature = "libm")]
+pub fn absolute_humidity(temperature: Temperature, rh: RelativeHumidity) -> Result<Humidity, HumidityError> {
+    if rh.as_milli_percent() > 100_000 {
+        return Err(HumidityError::OutOfRange);
+    }
+    let t = temperature.as_celsius();
+    let saturation_vapor_pressure = 6.112 * libm::expf((17.62 * t) / (243.12 + t));
+    let absolute = 216.7 * (rh.as_percent() / 100.0) * saturation_vapor_pressure / (273.15 + t);
+    Humidity::from_f32(absolute)
+}
+
+/// Calculate the absolute humidity from temperature and relative humidity
+/// without using floating point math.
+///
+/// This is the integer counterpart of
+/// [`absolute_humidity()`](fn.absolute_humidity.html), intended for targets
+/// without an FPU. The saturation humidity is taken from a lookup table with
+/// 1 °C resolution and interpolated quadratically, which keeps the result
+/// within 1/256 g/m³ of the floating point calculation.
+///
+/// Temperatures outside the SGP30 operating range of -40 °C to 85 °C, relative
+/// humidity values above 100 %RH and results that exceed the representable
+/// range return `HumidityError::OutOfRange`.
+pub fn absolute_humidity_fixed(temperature: Temperature, rh: RelativeHumidity) -> Result<Humidity, HumidityError> {
+    let temp = temperature.as_milli_celsius();
+    let rh = rh.as_milli_percent();
+    if !(-40_000..=85_000).contains(&temp) || rh > 100_000 {
+        return Err(HumidityError::OutOfRange);
+    }
+
+    // Quadratic (Newton forward) interpolation between three table entries
+    let offset = i64::from(temp - SATURATION_TABLE_MIN_TEMP * 1000);
+    let index = (offset / 1000) as usize;
+    let frac = offset % 1000;
+    let f0 = i64::from(SATURATION_TABLE[index]);
+    let f1 = i64::from(SATURATION_TABLE[index + 1]);
+    let f2 = i64::from(SATURATION_TABLE[index + 2]);
+    let saturation = f0 * 2_000_000
+        + frac * (f1 - f0) * 2_000
+        + frac * (frac - 1000) * (f2 - 2 * f1 + f0);
+
+    // Scale by relative humidity, convert 1/4096 g/m³ to 1/256 g/m³
+    let absolute = (saturation * i64::from(rh) / 200_000_000_000) >> 4;
+    if absolute > 0xFFFF {
+        return Err(HumidityError::OutOfRange);
+    }
+    Humidity::new((absolute >> 8) as u8, absolute as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temperature_units() {
+        assert_eq!(Temperature::from_celsius(21.5).as_milli_celsius(), 21_500);
+        assert_eq!(Temperature::from_fahrenheit(212.0).as_milli_celsius(), 100_000);
+        assert_eq!(Temperature::from_kelvin(273.15).as_milli_celsius(), 0);
+        assert_eq!(Temperature::from_milli_celsius(-1_250).as_celsius(), -1.25);
+    }
+
+    #[test]
+    fn relative_humidity_units() {
+        assert_eq!(RelativeHumidity::from_percent(45.5).as_milli_percent(), 45_500);
+        assert_eq!(RelativeHumidity::from_fraction(0.455).as_milli_percent(), 45_500);
+        assert_eq!(RelativeHumidity::from_percent(-3.0).as_milli_percent(), 0);
+        assert_eq!(RelativeHumidity::from_milli_percent(45_500).as_percent(), 45.5);
+    }
+
+    #[cfg(feature = "libm")]
+    #[test]
+    fn float_and_fixed_agree() {
+        let t = Temperature::from_celsius(25.0);
+        let rh = RelativeHumidity::from_percent(50.0);
+        let float = absolute_humidity(t, rh).unwrap().to_mg_per_m3();
+        let fixed = absolute_humidity_fixed(t, rh).unwrap().to_mg_per_m3();
+        assert!((float as i32 - fixed as i32).abs() <= 4);
+    }
+
+    #[test]
+    fn out_of_range() {
+        let rh = RelativeHumidity::from_percent(100.1);
+        let t = Temperature::from_celsius(20.0);
+        #[cfg(feature = "libm")]
+        assert_eq!(absolute_humidity(t, rh), Err(HumidityError::OutOfRange));
+        assert_eq!(absolute_humidity_fixed(t, rh), Err(HumidityError::OutOfRange));
+        let rh = RelativeHumidity::from_percent(50.0);
+        let t = Temperature::from_celsius(90.0);
+        assert_eq!(absolute_humidity_fixed(t, rh), Err(HumidityError::OutOfRange));
+    }
+}
@@ -22,12 +22,11 @@
 //! extern crate sgp30;
 //!
 //! use hal::{Delay, I2cdev};
-//! use sgp30::Sgp30;
+//! use sgp30::{I2cAddress, Sgp30};
 //!
 //! # fn main() {
 //! let dev = I2cdev::new("/dev/i2c-1").unwrap();
-//! let address = 0x58;
-//! let mut sgp = Sgp30::new(dev, address, Delay);
+//! let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, Delay);
 //! # }
 //! ```
 //!
@@ -40,12 +39,12 @@
 //! # extern crate linux_embedded_hal as hal;
 //! # extern crate sgp30;
 //! # use hal::{Delay, I2cdev};
-//! # use sgp30::Sgp30;
+//! # use sgp30::{I2cAddress, Sgp30};
 //! use sgp30::FeatureSet;
 //!
 //! # fn main() {
 //! # let dev = I2cdev::new("/dev/i2c-1").unwrap();
-//! # let mut sgp = Sgp30::new(dev, 0x58, Delay);
+//! # let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, Delay);
 //! let serial_number: [u8; 6] = sgp.serial().unwrap();
 //! let feature_set: FeatureSet = sgp.get_feature_set().unwrap();
 //! # }
@@ -59,10 +58,10 @@
 //! # extern crate linux_embedded_hal as hal;
 //! # extern crate sgp30;
 //! # use hal::{Delay, I2cdev};
-//! # use sgp30::Sgp30;
+//! # use sgp30::{I2cAddress, Sgp30};
 //! # fn main() {
 //! # let dev = I2cdev::new("/dev/i2c-1").unwrap();
-//! # let mut sgp = Sgp30::new(dev, 0x58, Delay);
+//! # let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, Delay);
 //! sgp.init().unwrap();
 //! # }
 //! ```
@@ -80,14 +79,14 @@
 //! # extern crate linux_embedded_hal as hal;
 //! # extern crate sgp30;
 //! # use hal::I2cdev;
-//! # use sgp30::Sgp30;
+//! # use sgp30::{I2cAddress, Sgp30};
 //! use embedded_hal::blocking::delay::DelayMs;
 //! use hal::Delay;
 //! use sgp30::Measurement;
 //!
 //! # fn main() {
 //! # let dev = I2cdev::new("/dev/i2c-1").unwrap();
-//! # let mut sgp = Sgp30::new(dev, 0x58, Delay);
+//! # let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, Delay);
 //! # sgp.init().unwrap();
 //! loop {
 //!     let measurement: Measurement = sgp.measure().unwrap();
@@ -125,12 +124,12 @@
 //! # extern crate linux_embedded_hal as hal;
 //! # extern crate sgp30;
 //! # use hal::{I2cdev, Delay};
-//! # use sgp30::Sgp30;
+//! # use sgp30::{I2cAddress, Sgp30};
 //! use sgp30::Baseline;
 //!
 //! # fn main() {
 //! # let dev = I2cdev::new("/dev/i2c-1").unwrap();
-//! # let mut sgp = Sgp30::new(dev, 0x58, Delay);
+//! # let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, Delay);
 //! # sgp.init().unwrap();
 //! let baseline: Baseline = sgp.get_baseline().unwrap();
 //! // …
@@ -150,12 +149,12 @@
 //! # extern crate linux_embedded_hal as hal;
 //! # extern crate sgp30;
 //! # use hal::{I2cdev, Delay};
-//! # use sgp30::Sgp30;
+//! # use sgp30::{I2cAddress, Sgp30};
 //! use sgp30::Humidity;
 //!
 //! # fn main() {
 //! # let dev = I2cdev::new("/dev/i2c-1").unwrap();
-//! # let mut sgp = Sgp30::new(dev, 0x58, Delay);
+//! # let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, Delay);
 //! // This value must be obtained from a separate humidity sensor
 //! let humidity = Humidity::from_f32(23.42).unwrap();
 //!
@@ -175,78 +174,277 @@
 #![deny(missing_docs)]
 #![no_std]
 
+#[cfg(feature = "std")]
+extern crate std;
+
 extern crate byteorder;
+#[cfg(feature = "critical-section")]
+extern crate critical_section;
+#[cfg(feature = "defmt")]
+extern crate defmt;
 extern crate embedded_hal as hal;
+#[cfg(feature = "embedded-storage")]
+extern crate embedded_storage;
+#[cfg(feature = "stream")]
+extern crate futures_core;
+#[cfg(feature = "heapless")]
+extern crate heapless;
+#[cfg(feature = "libm")]
+extern crate libm;
+#[cfg(feature = "linux")]
+extern crate linux_embedded_hal;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
 extern crate num_traits;
+#[cfg(feature = "measurements")]
+extern crate measurements;
+#[cfg(feature = "senml")]
+extern crate minicbor;
+#[cfg(feature = "rppal")]
+extern crate rppal;
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+#[cfg(feature = "serde-json-core")]
+extern crate serde_json_core;
+#[cfg(feature = "static-cell")]
+extern crate static_cell;
+#[cfg(feature = "uom")]
+extern crate uom;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+
+use core::fmt;
+use core::ops::ControlFlow;
 
-use byteorder::{BigEndian, ByteOrder};
 use hal::blocking::delay::{DelayMs, DelayUs};
 use hal::blocking::i2c::{Read, Write, WriteRead};
 
-mod types;
-
-pub use types::{Measurement, RawSignals, Baseline, Humidity, FeatureSet, ProductType};
-
+/// Emit a log entry through the given `log` macro if the `log` feature is
+/// enabled, otherwise do nothing.
+macro_rules! log_event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        $level!($($arg)+);
+    }};
+}
 
-const CRC8_POLYNOMIAL: u8 = 0x31;
+mod address;
+mod air_quality;
+#[cfg(feature = "heapless")]
+pub mod alerts;
+pub mod aqi;
+mod autosave;
+pub mod batch;
+mod calibration;
+pub mod compress;
+pub mod conformance;
+pub mod crc;
+mod diagnostics;
+pub mod drift;
+#[cfg(feature = "heapless")]
+pub mod exposure;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod filter;
+pub mod format;
+mod health;
+pub mod humidity;
+#[cfg(feature = "linux")]
+pub mod linux;
+#[cfg(feature = "log")]
+pub mod logging;
+pub mod manager;
+pub mod middleware;
+pub mod mux;
+pub mod observer;
+#[cfg(feature = "heapless")]
+pub mod plausibility;
+pub mod protocol;
+#[cfg(feature = "heapless")]
+pub mod recorder;
+pub mod recovery;
+#[cfg(feature = "rppal")]
+mod rpi;
+mod retry;
+#[cfg(any(feature = "std", feature = "critical-section"))]
+pub mod shared;
+#[cfg(all(feature = "heapless", feature = "libm"))]
+pub mod simulator;
+#[cfg(feature = "static-cell")]
+mod singleton;
+mod snapshot;
+#[cfg(feature = "spsc")]
+pub mod spsc;
+pub mod storage;
+#[cfg(feature = "station")]
+pub mod station;
+#[cfg(all(feature = "heapless", feature = "libm"))]
+pub mod stats;
+#[cfg(feature = "stream")]
+pub mod stream;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+mod timeout;
+mod timings;
+pub mod transcript;
+#[cfg(feature = "heapless")]
+pub mod trend;
+mod types;
+#[cfg(any(feature = "measurements", feature = "uom"))]
+mod units;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watchdog;
+
+use protocol::MAX_FRAME_LEN;
+pub use protocol::Command;
+use crc::{Crc8, SoftwareCrc};
+#[cfg(test)]
+use protocol::crc8;
+pub use address::{I2cAddress, InvalidAddress};
+pub use air_quality::AirQuality;
+pub use autosave::{AUTOSAVE_RETRY_SECS, DEFAULT_AUTOSAVE_INTERVAL_SECS, INITIAL_BASELINE_SECS};
+use autosave::Autosave;
+pub use calibration::Calibration;
+pub use retry::RetryPolicy;
+pub use timeout::Timeout;
+pub use timings::{DelayStrategy, NoWait, Timed, Timings};
+pub use diagnostics::{DriverState, DriverStats, LastError};
+pub use health::{Health, HealthThresholds};
+#[cfg(feature = "static-cell")]
+pub use singleton::StaticSgp30;
+use diagnostics::count;
+use health::HealthMonitor;
+pub use snapshot::{BaselineSnapshot, Timestamp};
+use storage::{BaselineRecord, BaselineStore, PersistError, RestoreOutcome};
+use protocol::WARMUP_SAMPLES;
+use watchdog::Watchdog;
+use observer::{NoObserver, Observer, Transaction, TransactionResult};
+use recovery::BusRecovery;
+pub use types::{Measurement, MeasurementDelta, RawSignals, Baseline, Humidity, HumidityError, FeatureSet, ProductType};
 
 
 /// All possible errors in this crate
+///
+/// Bus and checksum errors carry the [`Command`](enum.Command.html) that was
+/// being executed when the failure occurred.
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum Error<E> {
     /// I²C bus error
-    I2c(E),
+    I2c {
+        /// The command during which the error occurred
+        command: Command,
+        /// The error returned by the I²C implementation
+        source: E,
+    },
     /// CRC checksum validation failed
-    Crc,
+    Crc {
+        /// The command whose response failed validation
+        command: Command,
+        /// The data word the checksum was calculated over
+        word: u16,
+        /// The checksum byte received from the sensor
+        received: u8,
+        /// The checksum calculated for the received data word
+        computed: u8,
+    },
+    /// The response did not look like it came from the sensor, e.g. because
+    /// the transfer ended early or the bus is floating.
+    InvalidResponse {
+        /// The command whose response was rejected
+        command: Command,
+    },
+    /// A transfer exceeded the [`Timeout`](struct.Timeout.html).
+    Timeout {
+        /// The command during which the timeout occurred
+        command: Command,
+    },
+    /// The baseline read back after `set_baseline()` did not match the
+    /// written one.
+    BaselineMismatch {
+        /// The baseline that was written
+        expected: Baseline,
+        /// The baseline that was read back
+        actual: Baseline,
+    },
     /// User tried to measure the air quality without starting the
     /// initialization phase.
     NotInitialized,
+    /// The command cannot be used with this method, e.g. a command that
+    /// requires data passed to `start()`.
+    InvalidCommand {
+        /// The rejected command
+        command: Command,
+    },
 }
 
+impl<E> Error<E> {
+    /// Return the command that was being executed when the error occurred,
+    /// if any.
+    pub fn command(&self) -> Option<Command> {
+        match *self {
+            Error::I2c { command, .. }
+            | Error::Crc { command, .. }
+            | Error::InvalidResponse { command }
+            | Error::Timeout { command }
+            | Error::InvalidCommand { command } => Some(command),
+            Error::BaselineMismatch { .. } => Some(Command::SetBaseline),
+            Error::NotInitialized => None,
+        }
+    }
 
-/// I²C commands sent to the sensor.
-#[derive(Debug, Copy, Clone)]
-enum Command {
-    /// Return the serial number.
-    GetSerial,
-    /// Run an on-chip self-test.
-    SelfTest,
-    /// Initialize air quality measurements.
-    InitAirQuality,
-    /// Get a current air quality measurement.
-    MeasureAirQuality,
-    /// Measure raw signals.
-    MeasureRawSignals,
-    /// Return the baseline value.
-    GetBaseline,
-    /// Set the baseline value.
-    SetBaseline,
-    /// Set the current absolute humidity.
-    SetHumidity,
-    /// Set the feature set.
-    GetFeatureSet,
+    /// Whether repeating the failed operation may succeed. Bus, CRC and
+    /// timeout errors are transient; usage errors like
+    /// `Error::NotInitialized` are not.
+    pub fn is_transient(&self) -> bool {
+        match *self {
+            Error::I2c { .. }
+            | Error::Crc { .. }
+            | Error::InvalidResponse { .. }
+            | Error::Timeout { .. }
+            | Error::BaselineMismatch { .. } => true,
+            Error::NotInitialized | Error::InvalidCommand { .. } => false,
+        }
+    }
 }
 
-impl Command {
-    fn as_bytes(self) -> [u8; 2] {
-        match self {
-            Command::GetSerial => [0x36, 0x82],
-            Command::SelfTest => [0x20, 0x32],
-            Command::InitAirQuality => [0x20, 0x03],
-            Command::MeasureAirQuality => [0x20, 0x08],
-            Command::MeasureRawSignals => [0x20, 0x50],
-            Command::GetBaseline => [0x20, 0x15],
-            Command::SetBaseline => [0x20, 0x1E],
-            Command::SetHumidity => [0x20, 0x61],
-            Command::GetFeatureSet => [0x20, 0x2F],
+impl<E: fmt::Debug> fmt::Display for Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::I2c { command, ref source } => {
+                write!(f, "I²C bus error during {:?}: {:?}", command, source)
+            }
+            Error::Crc { command, word, received, computed } => write!(
+                f,
+                "CRC checksum validation failed for {:?}: word 0x{:04X}, received 0x{:02X}, computed 0x{:02X}",
+                command, word, received, computed,
+            ),
+            Error::InvalidResponse { command } => {
+                write!(f, "invalid response to {:?}", command)
+            }
+            Error::Timeout { command } => write!(f, "timeout during {:?}", command),
+            Error::BaselineMismatch { expected, actual } => write!(
+                f,
+                "baseline read-back mismatch: wrote 0x{:04X}/0x{:04X}, read 0x{:04X}/0x{:04X}",
+                expected.co2eq, expected.tvoc, actual.co2eq, actual.tvoc,
+            ),
+            Error::NotInitialized => f.write_str("air quality measurement not initialized"),
+            Error::InvalidCommand { command } => write!(f, "{:?} cannot be used here", command),
         }
     }
 }
 
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> std::error::Error for Error<E> {}
+
 
 /// Driver for the SGP30
 #[derive(Debug, Default)]
-pub struct Sgp30<I2C, D> {
+pub struct Sgp30<I2C, D, C = SoftwareCrc, W = Timed, O = NoObserver> {
     /// The concrete I²C device implementation.
     i2c: I2C,
     /// The I²C device address.
@@ -255,54 +453,552 @@ pub struct Sgp30<I2C, D> {
     delay: D,
     /// Whether the air quality measurement was initialized.
     initialized: bool,
+    /// Air quality measurements since the initialization.
+    samples_since_init: u32,
+    /// Calibration applied to every measurement.
+    calibration: Option<Calibration>,
+    /// Whether to capture the reference signals during initialization.
+    capture_sref: bool,
+    /// The reference raw signals.
+    sref: Option<RawSignals>,
+    /// Automatic baseline saving, if enabled.
+    autosave: Option<Autosave>,
+    /// Diagnostic counters.
+    stats: DriverStats,
+    /// Function called after every transaction.
+    observer: O,
+    /// Whether to read a response again if its CRC is invalid.
+    reread_on_crc_error: bool,
+    /// Retry policy for failed transfers.
+    retry: RetryPolicy,
+    /// Function recovering the bus before a retry.
+    bus_recovery: Option<fn(&mut I2C)>,
+    /// Command execution times.
+    timings: Timings,
+    /// How to wait for command execution.
+    strategy: W,
+    /// The CRC-8 implementation.
+    crc: C,
+    /// Health state derived from consecutive transaction outcomes.
+    health: HealthMonitor,
+    /// Tolerance for reading back the baseline after setting it, if enabled.
+    verify_baseline: Option<u16>,
+    /// Time limit for transactions, if enabled.
+    timeout: Option<Timeout>,
+    /// When the current transaction started, if a time limit is set.
+    transaction_started: Option<u32>,
+    /// The last failed transaction.
+    last_error: Option<LastError>,
+    /// The feature set read last.
+    feature_set: Option<FeatureSet>,
+    /// The baseline read or written last.
+    last_baseline: Option<Baseline>,
 }
 
 impl<I2C, D, E> Sgp30<I2C, D>
 where
     I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
-    D: DelayUs<u16> + DelayMs<u16>,
+    D: DelayUs<u16>,
 {
     /// Create a new instance of the SGP30 driver.
-    pub fn new(i2c: I2C, address: u8, delay: D) -> Self {
+    pub fn new(i2c: I2C, address: I2cAddress, delay: D) -> Self {
         Sgp30 {
             i2c,
-            address,
+            address: address.as_u8(),
             delay,
             initialized: false,
+            samples_since_init: 0,
+            calibration: None,
+            capture_sref: false,
+            sref: None,
+            autosave: None,
+            stats: DriverStats::default(),
+            observer: NoObserver,
+            reread_on_crc_error: false,
+            retry: RetryPolicy::NONE,
+            bus_recovery: None,
+            timings: Timings::MAX,
+            strategy: Timed,
+            crc: SoftwareCrc,
+            health: HealthMonitor::default(),
+            verify_baseline: None,
+            timeout: None,
+            transaction_started: None,
+            last_error: None,
+            feature_set: None,
+            last_baseline: None,
+        }
+    }
+}
+
+impl<I2C, D, E, C, W, O> Sgp30<I2C, D, C, W, O>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+    C: Crc8,
+    W: DelayStrategy<D>,
+    O: Observer,
+{
+    /// Use a different CRC-8 implementation, e.g. a hardware CRC peripheral.
+    /// See the [`crc`](crc/index.html) module.
+    pub fn with_crc<C2: Crc8>(self, crc: C2) -> Sgp30<I2C, D, C2, W, O> {
+        Sgp30 {
+            i2c: self.i2c,
+            address: self.address,
+            delay: self.delay,
+            initialized: self.initialized,
+            samples_since_init: self.samples_since_init,
+            calibration: self.calibration,
+            capture_sref: self.capture_sref,
+            sref: self.sref,
+            autosave: self.autosave,
+            stats: self.stats,
+            observer: self.observer,
+            reread_on_crc_error: self.reread_on_crc_error,
+            retry: self.retry,
+            bus_recovery: self.bus_recovery,
+            timings: self.timings,
+            strategy: self.strategy,
+            crc,
+            health: self.health,
+            verify_baseline: self.verify_baseline,
+            timeout: self.timeout,
+            transaction_started: None,
+            last_error: self.last_error,
+            feature_set: self.feature_set,
+            last_baseline: self.last_baseline,
+        }
+    }
+
+    /// Use a different strategy for waiting while the sensor executes a
+    /// command. See [`DelayStrategy`](trait.DelayStrategy.html).
+    pub fn with_delay_strategy<W2: DelayStrategy<D>>(self, strategy: W2) -> Sgp30<I2C, D, C, W2, O> {
+        Sgp30 {
+            i2c: self.i2c,
+            address: self.address,
+            delay: self.delay,
+            initialized: self.initialized,
+            samples_since_init: self.samples_since_init,
+            calibration: self.calibration,
+            capture_sref: self.capture_sref,
+            sref: self.sref,
+            autosave: self.autosave,
+            stats: self.stats,
+            observer: self.observer,
+            reread_on_crc_error: self.reread_on_crc_error,
+            retry: self.retry,
+            bus_recovery: self.bus_recovery,
+            timings: self.timings,
+            strategy,
+            crc: self.crc,
+            health: self.health,
+            verify_baseline: self.verify_baseline,
+            timeout: self.timeout,
+            transaction_started: None,
+            last_error: self.last_error,
+            feature_set: self.feature_set,
+            last_baseline: self.last_baseline,
+        }
+    }
+
+    /// Call `observer` after every transaction with the sensor, e.g. a
+    /// closure recording the protocol. See the
+    /// [`observer`](observer/index.html) module.
+    pub fn with_observer<O2: Observer>(self, observer: O2) -> Sgp30<I2C, D, C, W, O2> {
+        Sgp30 {
+            i2c: self.i2c,
+            address: self.address,
+            delay: self.delay,
+            initialized: self.initialized,
+            samples_since_init: self.samples_since_init,
+            calibration: self.calibration,
+            capture_sref: self.capture_sref,
+            sref: self.sref,
+            autosave: self.autosave,
+            stats: self.stats,
+            observer,
+            reread_on_crc_error: self.reread_on_crc_error,
+            retry: self.retry,
+            bus_recovery: self.bus_recovery,
+            timings: self.timings,
+            strategy: self.strategy,
+            crc: self.crc,
+            health: self.health,
+            verify_baseline: self.verify_baseline,
+            timeout: self.timeout,
+            transaction_started: None,
+            last_error: self.last_error,
+            feature_set: self.feature_set,
+            last_baseline: self.last_baseline,
+        }
+    }
+
+    /// Attach a per-sensor calibration profile that will be applied to every
+    /// [`measure()`](#method.measure) result, or remove it with `None`.
+    pub fn set_calibration(&mut self, calibration: Option<Calibration>) {
+        self.calibration = calibration;
+    }
+
+    /// Return the attached calibration profile.
+    pub fn calibration(&self) -> Option<&Calibration> {
+        self.calibration.as_ref()
+    }
+
+    /// Enable or disable capturing of the reference raw signals (`sref`)
+    /// during [`init()`](#method.init).
+    ///
+    /// When enabled, the raw signals are measured right after initialization
+    /// and stored as reference for the concentration conversion using
+    /// [`RawSignals::h2_ppm()`](struct.RawSignals.html#method.h2_ppm) and
+    /// [`RawSignals::ethanol_ppm()`](struct.RawSignals.html#method.ethanol_ppm).
+    /// Sensirion recommends capturing the reference in clean air.
+    pub fn set_capture_sref(&mut self, enable: bool) {
+        self.capture_sref = enable;
+    }
+
+    /// Return the reference raw signals, if captured or set.
+    pub fn sref(&self) -> Option<RawSignals> {
+        self.sref
+    }
+
+    /// Set the reference raw signals, e.g. when restoring a reference that
+    /// was captured during production.
+    pub fn set_sref(&mut self, sref: Option<RawSignals>) {
+        self.sref = sref;
+    }
+
+    /// Enable automatic baseline saving with the default interval of one
+    /// hour.
+    ///
+    /// See [`enable_autosave_with_interval()`](#method.enable_autosave_with_interval).
+    pub fn enable_autosave(&mut self) {
+        self.enable_autosave_with_interval(DEFAULT_AUTOSAVE_INTERVAL_SECS);
+    }
+
+    /// Enable automatic baseline saving.
+    ///
+    /// Once the baseline is valid,
+    /// [`measure_with_autosave()`](#method.measure_with_autosave) reads the
+    /// baseline every `interval_secs` seconds and passes it to its save
+    /// function, which should persist it (e.g. using a
+    /// [`BaselineStore`](storage/trait.BaselineStore.html)).
+    ///
+    /// According to the datasheet, the baseline is valid 12 hours after
+    /// initialization, or right after a stored baseline was restored using
+    /// [`set_baseline()`](#method.set_baseline). Since the driver has no
+    /// notion of time, it counts measurements and assumes the recommended
+    /// interval of 1 s between them.
+    pub fn enable_autosave_with_interval(&mut self, interval_secs: u32) {
+        match self.autosave {
+            Some(ref mut autosave) => autosave.configure(interval_secs),
+            None => self.autosave = Some(Autosave::new(interval_secs)),
+        }
+    }
+
+    /// Disable automatic baseline saving.
+    pub fn disable_autosave(&mut self) {
+        self.autosave = None;
+    }
+
+    /// Return the diagnostic counters.
+    pub fn stats(&self) -> DriverStats {
+        self.stats
+    }
+
+    /// Reset all diagnostic counters to zero.
+    pub fn reset_stats(&mut self) {
+        self.stats = DriverStats::default();
+    }
+
+    /// Enable or disable reading a response again once if its CRC is invalid
+    /// (disabled by default).
+    ///
+    /// Single bit errors caused by glitches on long cables are not uncommon.
+    /// The datasheet does not specify whether a response can be read twice,
+    /// so if enabled, a CRC mismatch triggers sending the command again and
+    /// reading its response before `Error::Crc` is returned. This only applies to commands that
+    /// query the sensor state (serial number, feature set and baseline):
+    /// Repeating a measurement would break the 1 Hz cadence, so a
+    /// measurement with an invalid CRC is returned as an error.
+    pub fn set_reread_on_crc_error(&mut self, enable: bool) {
+        self.reread_on_crc_error = enable;
+    }
+
+    /// Set the retry policy for failed command writes and response reads.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) {
+        self.retry = policy;
+    }
+
+    /// Limit the time spent on a single transaction, including retries and
+    /// the wait for command execution, or remove the limit with `None` (the
+    /// default). See
+    /// [`Timeout`](struct.Timeout.html).
+    pub fn set_timeout(&mut self, timeout: Option<Timeout>) {
+        self.timeout = timeout;
+    }
+
+    /// Register a function that recovers a stuck bus before a failed
+    /// transfer is retried (or `None` to remove it). See the
+    /// [`recovery`](recovery/index.html) module.
+    pub fn set_bus_recovery(&mut self, recovery: Option<fn(&mut I2C)>) {
+        self.bus_recovery = recovery;
+    }
+
+    /// Recover the bus using its [`BusRecovery`](recovery/trait.BusRecovery.html)
+    /// implementation before a failed transfer is retried.
+    pub fn enable_bus_recovery(&mut self)
+    where
+        I2C: BusRecovery,
+    {
+        self.bus_recovery = Some(|i2c: &mut I2C| i2c.recover());
+    }
+
+    /// Set the time the driver waits for the sensor to execute each command
+    /// (default `Timings::MAX`).
+    pub fn set_timings(&mut self, timings: Timings) {
+        self.timings = timings;
+    }
+
+    /// Return the command execution times.
+    pub fn timings(&self) -> &Timings {
+        &self.timings
+    }
+
+    /// Read the baseline back after every
+    /// [`set_baseline()`](#method.set_baseline) and verify that both values
+    /// are within `tolerance` of the written ones, or disable the
+    /// verification with `None` (the default).
+    ///
+    /// This catches writes that were silently lost on a marginal bus, at the
+    /// cost of an additional transaction. A tolerance of 0 requires an exact
+    /// match.
+    pub fn set_baseline_verification(&mut self, tolerance: Option<u16>) {
+        self.verify_baseline = tolerance;
+    }
+
+    /// Return the health of the sensor.
+    ///
+    /// The state is updated after every transaction, see
+    /// [`Health`](enum.Health.html). `Error::NotInitialized` does not count
+    /// as a failure.
+    pub fn health(&self) -> Health {
+        self.health.state
+    }
+
+    /// Return a snapshot of the driver state for field diagnostics. See
+    /// [`DriverState`](struct.DriverState.html).
+    pub fn dump_state(&self) -> DriverState {
+        DriverState {
+            initialized: self.initialized,
+            samples_since_init: self.samples_since_init,
+            last_error: self.last_error,
+            feature_set: self.feature_set,
+            baseline: self.last_baseline,
+            health: self.health.state,
+            stats: self.stats,
         }
     }
 
+    /// Return the number of consecutive failed transactions.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.health.consecutive_failures
+    }
+
+    /// Set the thresholds for the transitions between health states.
+    pub fn set_health_thresholds(&mut self, thresholds: HealthThresholds) {
+        self.health.thresholds = thresholds;
+    }
+
+    /// Return to the `Healthy` state, e.g. after recovering the bus or
+    /// power-cycling the sensor.
+    pub fn reset_health(&mut self) {
+        self.health.reset();
+    }
+
     /// Destroy driver instance, return I²C bus instance.
     pub fn destroy(self) -> I2C {
         self.i2c
     }
 
-    /// Write an I²C command to the sensor.
-    fn send_command(&mut self, command: Command) -> Result<(), Error<E>> {
-        self.i2c
-            .write(self.address, &command.as_bytes())
-            .map_err(Error::I2c)
+    /// Execute a command: Write the command and data, wait for the sensor to
+    /// execute it (see `Timings`) and read the response into `response`,
+    /// unless it is empty.
+    ///
+    /// The data slice must have a length of 0, 2 or 4.
+    ///
+    /// CRC checksums will automatically be added to the data. If the command
+    /// requires an initialized air quality measurement and the sensor wasn't
+    /// initialized yet, `Error::NotInitialized` is returned without touching
+    /// the bus.
+    fn transaction(
+        &mut self,
+        command: Command,
+        data: &[u8],
+        response: &mut [u8],
+    ) -> Result<(), Error<E>> {
+        self.fed_transaction(command, data, response, None)
     }
 
-    /// Write an I²C command and data to the sensor.
-    ///
-    /// The data slice must have a length of 2 or 4.
+    /// Like `transaction`, but feed `watchdog` while waiting for the
+    /// command to execute.
+    fn fed_transaction(
+        &mut self,
+        command: Command,
+        data: &[u8],
+        response: &mut [u8],
+        mut watchdog: Option<&mut dyn Watchdog>,
+    ) -> Result<(), Error<E>> {
+        if command.requires_init() && !self.initialized {
+            return Err(Error::NotInitialized);
+        }
+        let wait_us = self.timings.duration_us(command);
+        let mut request = [0; MAX_FRAME_LEN];
+        let len = protocol::encode(command, data, &mut request, &mut self.crc);
+        log_event!(trace, "Sending {:?}: {:02x?}", command, &request[..len]);
+        self.transaction_started = self.timeout.map(|timeout| timeout.now());
+        let written = self.write(command, self.address, &request[..len]);
+        let write_ok = written.is_ok();
+        let result = written.and_then(|()| {
+            self.wait_for(command, wait_us, &mut watchdog);
+            self.check_deadline(command, self.transaction_started)?;
+            if response.is_empty() {
+                return Ok(());
+            }
+            match self.read_with_crc(command, response) {
+                Err(Error::Crc { .. }) if self.reread_on_crc_error && command.is_query() => {
+                    // A response may only be read once, so the command is
+                    // sent again
+                    count(&mut self.stats.retries);
+                    self.write(command, self.address, &request[..len])?;
+                    self.wait_for(command, wait_us, &mut watchdog);
+                    self.check_deadline(command, self.transaction_started)?;
+                    self.read_with_crc(command, response)
+                }
+                result => result,
+            }
+        });
+        self.transaction_started = None;
+        self.record_health(&result);
+        self.observer.observe(&Transaction {
+            command,
+            request: &request[..len],
+            response: if write_ok { response } else { &[] },
+            wait_us,
+            result: TransactionResult::from(&result),
+        });
+        match result {
+            Ok(()) if response.is_empty() => {}
+            Ok(()) => log_event!(trace, "Received {:?} response: {:02x?}", command, response),
+            Err(Error::Crc { .. }) => log_event!(warn, "CRC error in {:?} response: {:02x?}", command, response),
+            Err(Error::InvalidResponse { .. }) => log_event!(warn, "Invalid {:?} response: {:02x?}", command, response),
+            Err(Error::Timeout { .. }) => log_event!(warn, "Timeout during {:?}", command),
+            Err(_) => log_event!(warn, "I²C error during {:?}", command),
+        }
+        result
+    }
+
+    /// Wait for the execution of `command` through the delay strategy. With
+    /// a watchdog, the wait is split into steps of at most
+    /// `FEED_INTERVAL_MS`, feeding the watchdog after each step.
+    fn wait_for(&mut self, command: Command, duration_us: u32, watchdog: &mut Option<&mut dyn Watchdog>) {
+        let watchdog = match *watchdog {
+            Some(ref mut watchdog) => watchdog,
+            None => return self.strategy.wait(command, duration_us, &mut self.delay),
+        };
+        let mut remaining = duration_us;
+        while remaining > 0 {
+            let step = remaining.min(u32::from(watchdog::FEED_INTERVAL_MS) * 1000);
+            self.strategy.wait(command, step, &mut self.delay);
+            watchdog.feed();
+            remaining -= step;
+        }
+    }
+
+    /// Update the health state with the outcome of an operation. Usage
+    /// errors like `Error::NotInitialized` do not count.
+    fn record_health<T>(&mut self, result: &Result<T, Error<E>>) {
+        let success = match *result {
+            Ok(_) => true,
+            Err(Error::NotInitialized) | Err(Error::InvalidCommand { .. }) => return,
+            Err(ref e) => {
+                self.last_error = Some(LastError {
+                    command: e.command(),
+                    result: TransactionResult::from(e),
+                });
+                false
+            }
+        };
+        if self.health.record(success).is_some() {
+            log_event!(warn, "Sensor health changed to {:?}", self.health.state);
+        }
+    }
+
+    /// Read a response of the split-phase API and update the health state.
+    fn read_response(&mut self, command: Command, buf: &mut [u8]) -> Result<(), Error<E>> {
+        let result = self.read_with_crc(command, buf);
+        self.record_health(&result);
+        result
+    }
+
+    /// Write raw bytes to the given address.
+    fn write(&mut self, command: Command, address: u8, bytes: &[u8]) -> Result<(), Error<E>> {
+        self.with_retries(command, |i2c| i2c.write(address, bytes))
+    }
+
+    /// Run an I²C transfer, retrying it according to the retry policy.
     ///
-    /// CRC checksums will automatically be added to the data.
-    fn send_command_and_data(&mut self, command: Command, data: &[u8]) -> Result<(), Error<E>> {
-        assert!(data.len() == 2 || data.len() == 4);
-        let mut buf = [0; 2 /* command */ + 6 /* max length of data + crc */];
-        buf[0..2].copy_from_slice(&command.as_bytes());
-        buf[2..4].copy_from_slice(&data[0..2]);
-        buf[4] = crc8(&data[0..2]);
-        if data.len() > 2 {
-            buf[5..7].copy_from_slice(&data[2..4]);
-            buf[7] = crc8(&data[2..4]);
+    /// The time limit is checked after every attempt, successful or not,
+    /// against the start of the current transaction or, outside of one, the
+    /// start of this transfer.
+    fn with_retries<F>(&mut self, command: Command, mut transfer: F) -> Result<(), Error<E>>
+    where
+        F: FnMut(&mut I2C) -> Result<(), E>,
+    {
+        let mut backoff = self.retry.backoff_us;
+        let mut attempt = 1;
+        let timeout = self.timeout;
+        let started = self
+            .transaction_started
+            .or_else(|| timeout.map(|timeout| timeout.now()));
+        loop {
+            let result = transfer(&mut self.i2c);
+            if result.is_err() {
+                count(&mut self.stats.i2c_errors);
+            }
+            self.check_deadline(command, started)?;
+            match result {
+                Ok(()) => return Ok(()),
+                Err(source) => {
+                    if attempt >= self.retry.attempts {
+                        return Err(Error::I2c { command, source });
+                    }
+                }
+            }
+            log_event!(debug, "Retrying {:?} after I²C error", command);
+            count(&mut self.stats.retries);
+            if let Some(recover) = self.bus_recovery {
+                recover(&mut self.i2c);
+            }
+            self.delay.delay_us(backoff);
+            backoff = backoff.saturating_mul(2);
+            attempt += 1;
         }
-        let payload = if data.len() > 2 { &buf[0..8] } else { &buf[0..5] };
-        self.i2c
-            .write(self.address, payload)
-            .map_err(Error::I2c)
+    }
+
+    /// Return `Error::Timeout` if the time limit was exceeded since
+    /// `started`.
+    fn check_deadline(&self, command: Command, started: Option<u32>) -> Result<(), Error<E>> {
+        match (self.timeout, started) {
+            (Some(timeout), Some(started)) if timeout.is_expired(started) => {
+                Err(Error::Timeout { command })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Wait for the given duration.
+    fn wait(&mut self, duration_us: u32) {
+        timings::delay_us(&mut self.delay, duration_us);
     }
 
     /// Iterate over the provided buffer and validate the CRC8 checksum.
@@ -312,14 +1008,8 @@ where
     /// Note: This method will consider every third byte a checksum byte. If
     /// the buffer size is not a multiple of 3, then not all data will be
     /// validated.
-    fn validate_crc(&self, buf: &[u8]) -> Result<(), Error<E>> {
-        for chunk in buf.chunks(3) {
-            if chunk.len() == 3
-            && crc8(&[chunk[0], chunk[1]]) != chunk[2] {
-                return Err(Error::Crc);
-            }
-        }
-        Ok(())
+    fn validate_crc(&mut self, command: Command, buf: &[u8]) -> Result<(), Error<E>> {
+        protocol::validate_crc(command, buf, &mut self.crc)
     }
 
     /// Read data into the provided buffer and validate the CRC8 checksum.
@@ -329,46 +1019,66 @@ where
     /// Note: This method will consider every third byte a checksum byte. If
     /// the buffer size is not a multiple of 3, then not all data will be
     /// validated.
-    fn read_with_crc(&mut self, mut buf: &mut [u8]) -> Result<(), Error<E>> {
-        self.i2c
-            .read(self.address, &mut buf)
-            .map_err(Error::I2c)?;
-        self.validate_crc(buf)
+    fn read_with_crc(&mut self, command: Command, buf: &mut [u8]) -> Result<(), Error<E>> {
+        let address = self.address;
+        // The I²C read does not report how many bytes were transferred, so
+        // pre-fill the buffer: a short read leaves whole 0xFF chunks behind.
+        for byte in buf.iter_mut() {
+            *byte = 0xFF;
+        }
+        self.with_retries(command, |i2c| i2c.read(address, buf))?;
+        if !protocol::is_plausible_response(buf) {
+            return Err(Error::InvalidResponse { command });
+        }
+        self.validate_crc(command, buf)
+            .inspect_err(|_| count(&mut self.stats.crc_errors))
     }
 
     /// Return the 48 bit serial number of the SGP30.
     pub fn serial(&mut self) -> Result<[u8; 6], Error<E>> {
-        // Request serial number
-        self.send_command(Command::GetSerial)?;
-
-        // Recommended wait time according to datasheet (6.5)
-        self.delay.delay_us(500);
-
-        // Read serial number
+        // Request and read serial number
         let mut buf = [0; 9];
-        self.read_with_crc(&mut buf)?;
+        self.transaction(Command::GetSerial, &[], &mut buf)?;
+        Ok(Self::decode_serial(&buf))
+    }
 
-        Ok([
+    /// Decode a serial number response.
+    fn decode_serial(buf: &[u8; 9]) -> [u8; 6] {
+        let serial = [
            buf[0], buf[1],
            buf[3], buf[4],
            buf[6], buf[7],
-        ])
+        ];
+        log_event!(debug, "Serial number: {:02x?}", serial);
+        serial
     }
 
     /// Run an on-chip self-test. Return a boolean indicating whether the test succeeded.
     pub fn selftest(&mut self) -> Result<bool, Error<E>> {
-        // Start self test
-        self.send_command(Command::SelfTest)?;
-
-        // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(220);
+        // Run self test and read result
+        let mut buf = [0; 3];
+        self.transaction(Command::SelfTest, &[], &mut buf)?;
+        Ok(Self::decode_selftest(&buf))
+    }
 
-        // Read result
+    /// Run an on-chip self-test, feeding `watchdog` while it runs.
+    ///
+    /// Like [`selftest`](#method.selftest), but the wait for the self-test
+    /// to complete (up to 220 ms) is split into steps short enough to keep
+    /// a hardware watchdog with a timeout of a few hundred milliseconds
+    /// alive.
+    pub fn selftest_with_watchdog(&mut self, watchdog: &mut dyn Watchdog) -> Result<bool, Error<E>> {
         let mut buf = [0; 3];
-        self.read_with_crc(&mut buf)?;
+        self.fed_transaction(Command::SelfTest, &[], &mut buf, Some(watchdog))?;
+        Ok(Self::decode_selftest(&buf))
+    }
 
+    /// Decode a self-test response.
+    fn decode_selftest(buf: &[u8; 3]) -> bool {
         // Compare with self-test success pattern
-        Ok(buf[0..2] == [0xd4, 0x00])
+        let success = buf[0..2] == [0xd4, 0x00];
+        log_event!(debug, "Self-test {}", if success { "passed" } else { "failed" });
+        success
     }
 
     /// Initialize the air quality measurement.
@@ -403,16 +1113,53 @@ where
     ///
     /// This might be necessary after a sensor soft or hard reset.
     pub fn force_init(&mut self) -> Result<(), Error<E>> {
-        // Send command to sensor
-        self.send_command(Command::InitAirQuality)?;
+        self.send_init()?;
+        self.capture_sref_after_init()
+    }
 
-        // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10);
+    /// Send the initialization command and reset the initialization state.
+    fn send_init(&mut self) -> Result<(), Error<E>> {
+        // Send command to sensor
+        self.transaction(Command::InitAirQuality, &[], &mut [])?;
 
         self.initialized = true;
+        self.samples_since_init = 0;
+        if let Some(ref mut autosave) = self.autosave {
+            autosave.on_init();
+        }
+        Ok(())
+    }
+
+    /// Capture the reference signals, if enabled.
+    fn capture_sref_after_init(&mut self) -> Result<(), Error<E>> {
+        if self.capture_sref {
+            self.sref = Some(self.measure_raw_signals()?);
+        }
         Ok(())
     }
 
+    /// Perform a soft reset using the I²C general call.
+    ///
+    /// The sensor returns to its power-up state, so the air quality
+    /// measurement must be initialized again and a previously saved baseline
+    /// must be restored. Note that the general call resets *all* devices on
+    /// the bus that support it, not just the SGP30.
+    pub fn soft_reset(&mut self) -> Result<(), Error<E>> {
+        let [address, byte] = Command::SoftReset.as_bytes();
+        self.transaction_started = self.timeout.map(|timeout| timeout.now());
+        let result = self.write(Command::SoftReset, address, &[byte]).and_then(|()| {
+            // The sensor resets even if the time limit is exceeded below
+            self.initialized = false;
+
+            // Wait for the sensor to power up
+            self.strategy.wait(Command::SoftReset, self.timings.soft_reset_us, &mut self.delay);
+            self.check_deadline(Command::SoftReset, self.transaction_started)
+        });
+        self.transaction_started = None;
+        self.record_health(&result);
+        result
+    }
+
     /// Get an air quality measurement.
     ///
     /// Before calling this method, the air quality measurements must have been
@@ -430,29 +1177,128 @@ where
     /// For the first 15 s after initializing the air quality measurement, the
     /// sensor is in an initialization phase during which it returns fixed
     /// values of 400 ppm CO₂eq and 0 ppb TVOC. After 15 s (15 measurements)
-    /// the values should start to change.
+    /// the values should start to change, see
+    /// [`warmup_remaining()`](#method.warmup_remaining).
     pub fn measure(&mut self) -> Result<Measurement, Error<E>> {
-        if !self.initialized {
-            // Measurements weren't initialized
-            return Err(Error::NotInitialized);
+        // Measure and read result
+        let mut buf = [0; 6];
+        self.transaction(Command::MeasureAirQuality, &[], &mut buf)?;
+        let measurement = self.decode_measurement(&buf);
+        if let Some(ref mut autosave) = self.autosave {
+            autosave.tick();
         }
+        Ok(measurement)
+    }
 
-        // Send command to sensor
-        self.send_command(Command::MeasureAirQuality)?;
+    /// Like [`measure()`](#method.measure), but also read the baseline and
+    /// pass it to `save` if automatic baseline saving is enabled (see
+    /// [`enable_autosave()`](#method.enable_autosave)) and a save is due.
+    ///
+    /// The measurement is returned even if reading or saving the baseline
+    /// fails. The failure is counted in
+    /// [`DriverStats::autosave_failures`](struct.DriverStats.html#structfield.autosave_failures)
+    /// and the save is retried after
+    /// [`AUTOSAVE_RETRY_SECS`](constant.AUTOSAVE_RETRY_SECS.html).
+    pub fn measure_with_autosave<F, X>(&mut self, save: F) -> Result<Measurement, Error<E>>
+    where
+        F: FnOnce(&Baseline) -> Result<(), X>,
+    {
+        let measurement = self.measure()?;
+        if self.autosave.as_ref().is_some_and(Autosave::is_due) {
+            let saved = match self.get_baseline() {
+                Ok(baseline) => save(&baseline).is_ok(),
+                Err(_) => false,
+            };
+            if let Some(ref mut autosave) = self.autosave {
+                if saved {
+                    autosave.on_baseline_valid();
+                } else {
+                    log_event!(warn, "Saving the baseline failed, retrying in {} s", AUTOSAVE_RETRY_SECS);
+                    count(&mut self.stats.autosave_failures);
+                    autosave.on_failure();
+                }
+            }
+        }
+        Ok(measurement)
+    }
 
-        // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(12);
+    /// Return the number of measurements left of the initialization phase,
+    /// or `None` if the air quality measurement is not initialized.
+    ///
+    /// With measurements every second, this is also the number of seconds
+    /// until the sensor reports real values instead of the fixed 400 ppm
+    /// CO₂eq and 0 ppb TVOC, e.g. to show "warming up: 7 s" on a display.
+    /// It is 0 once the initialization phase is over.
+    pub fn warmup_remaining(&self) -> Option<u32> {
+        if !self.initialized {
+            return None;
+        }
+        Some(WARMUP_SAMPLES.saturating_sub(self.samples_since_init))
+    }
 
-        // Read result
-        let mut buf = [0; 6];
-        self.read_with_crc(&mut buf)?;
-        let co2eq_ppm = (u16::from(buf[0]) << 8) | u16::from(buf[1]);
-        let tvoc_ppb = (u16::from(buf[3]) << 8) | u16::from(buf[4]);
+    /// Decode an air quality measurement response and apply the calibration.
+    fn decode_measurement(&mut self, buf: &[u8; 6]) -> Measurement {
+        let co2eq_ppm = protocol::decode_word(buf, 0);
+        let tvoc_ppb = protocol::decode_word(buf, 1);
 
-        Ok(Measurement {
+        let measurement = Measurement {
             co2eq_ppm,
             tvoc_ppb,
-        })
+        };
+        count(&mut self.stats.measurements);
+        self.samples_since_init = self.samples_since_init.saturating_add(1);
+
+        let measurement = match self.calibration {
+            Some(ref calibration) => calibration.apply(measurement),
+            None => measurement,
+        };
+        log_event!(debug, "Measured {:?}", measurement);
+        measurement
+    }
+
+    /// Run a blocking measurement loop.
+    ///
+    /// The air quality measurement is initialized (unless it already is) and
+    /// a measurement is taken every second, using the delay of the driver for
+    /// the cadence. Each measurement is passed to `f`, which returns
+    /// `ControlFlow::Continue(())` to keep measuring or
+    /// `ControlFlow::Break(value)` to stop the loop and return `value`.
+    ///
+    /// The interval is 1 s minus the measurement duration (see
+    /// [`Timings`](struct.Timings.html)); time spent in `f`
+    /// is not accounted for, so the callback should return quickly.
+    pub fn run<B, F>(&mut self, f: F) -> Result<B, Error<E>>
+    where
+        F: FnMut(Measurement) -> ControlFlow<B>,
+    {
+        self.run_with_watchdog(&mut (), f)
+    }
+
+    /// Like [`run()`](struct.Sgp30.html#method.run), but feed a watchdog
+    /// after every measurement and at least every 100 ms while waiting for
+    /// the next one.
+    pub fn run_with_watchdog<B, F>(&mut self, watchdog: &mut dyn Watchdog, mut f: F) -> Result<B, Error<E>>
+    where
+        F: FnMut(Measurement) -> ControlFlow<B>,
+    {
+        if !self.initialized {
+            self.init()?;
+        }
+        loop {
+            let measurement = self.measure()?;
+            watchdog.feed();
+            if let ControlFlow::Break(value) = f(measurement) {
+                return Ok(value);
+            }
+            let measure_ms = (self.timings.measure_air_quality_us / 1000).min(1000) as u16;
+            let mut remaining = 1000 - measure_ms;
+            while remaining > 0 {
+                let step = remaining.min(watchdog::FEED_INTERVAL_MS);
+                self.wait(u32::from(step) * 1000);
+                watchdog.feed();
+                remaining -= step;
+            }
+        }
     }
 
     /// Return sensor raw signals.
@@ -463,27 +1309,23 @@ where
     /// measurement to which the sensor responds with the two signals for H2
     /// and Ethanol.
     pub fn measure_raw_signals(&mut self) -> Result<RawSignals, Error<E>> {
-        if !self.initialized {
-            // Measurements weren't initialized
-            return Err(Error::NotInitialized);
-        }
-
-        // Send command to sensor
-        self.send_command(Command::MeasureRawSignals)?;
-
-        // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(25);
-
-        // Read result
+        // Measure and read result
         let mut buf = [0; 6];
-        self.read_with_crc(&mut buf)?;
-        let h2_signal = (u16::from(buf[0]) << 8) | u16::from(buf[1]);
-        let ethanol_signal = (u16::from(buf[3]) << 8) | u16::from(buf[4]);
+        self.transaction(Command::MeasureRawSignals, &[], &mut buf)?;
+        Ok(Self::decode_raw_signals(&buf))
+    }
 
-        Ok(RawSignals {
+    /// Decode a raw signals response.
+    fn decode_raw_signals(buf: &[u8; 6]) -> RawSignals {
+        let h2_signal = protocol::decode_word(buf, 0);
+        let ethanol_signal = protocol::decode_word(buf, 1);
+
+        let signals = RawSignals {
             h2: h2_signal,
             ethanol: ethanol_signal,
-        })
+        };
+        log_event!(debug, "Measured {:?}", signals);
+        signals
     }
 
     /// Return the baseline values of the baseline correction algorithm.
@@ -500,22 +1342,24 @@ where
     /// [`init()`](struct.Sgp30.html#method.init) followed by
     /// [`set_baseline()`](struct.Sgp30.html#method.set_baseline).
     pub fn get_baseline(&mut self) -> Result<Baseline, Error<E>> {
-        // Send command to sensor
-        self.send_command(Command::GetBaseline)?;
-
-        // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10);
-
-        // Read result
+        // Request and read baseline
         let mut buf = [0; 6];
-        self.read_with_crc(&mut buf)?;
-        let co2eq_baseline = (u16::from(buf[0]) << 8) | u16::from(buf[1]);
-        let tvoc_baseline = (u16::from(buf[3]) << 8) | u16::from(buf[4]);
+        self.transaction(Command::GetBaseline, &[], &mut buf)?;
+        Ok(self.decode_baseline(&buf))
+    }
 
-        Ok(Baseline {
+    /// Decode a baseline response.
+    fn decode_baseline(&mut self, buf: &[u8; 6]) -> Baseline {
+        let co2eq_baseline = protocol::decode_word(buf, 0);
+        let tvoc_baseline = protocol::decode_word(buf, 1);
+
+        let baseline = Baseline {
             co2eq: co2eq_baseline,
             tvoc: tvoc_baseline,
-        })
+        };
+        log_event!(debug, "Read {:?}", baseline);
+        self.last_baseline = Some(baseline);
+        baseline
     }
 
     /// Set the baseline values for the baseline correction algorithm.
@@ -531,23 +1375,132 @@ where
     /// memory and restore it after a new power-up or soft reset of the sensor.
     ///
     /// This function sets the baseline values for the two air quality
-    /// signals.
+    /// signals. If [read-back verification](#method.set_baseline_verification)
+    /// is enabled, the baseline is read again afterwards and
+    /// `Error::BaselineMismatch` is returned if it differs.
     pub fn set_baseline(&mut self, baseline: &Baseline) -> Result<(), Error<E>> {
-        if !self.initialized {
-            // Measurements weren't initialized
-            return Err(Error::NotInitialized);
+        // Send command and data to sensor
+        self.transaction(Command::SetBaseline, &baseline.to_bytes(), &mut [])?;
+        if let Some(tolerance) = self.verify_baseline {
+            let actual = self.get_baseline()?;
+            if actual.co2eq.abs_diff(baseline.co2eq) > tolerance
+                || actual.tvoc.abs_diff(baseline.tvoc) > tolerance
+            {
+                log_event!(warn, "Baseline read-back mismatch: wrote {:?}, read {:?}", baseline, actual);
+                return Err(Error::BaselineMismatch { expected: *baseline, actual });
+            }
         }
+        self.on_baseline_set(baseline);
+        Ok(())
+    }
 
-        // Send command and data to sensor
-        let mut buf = [0; 4];
-        BigEndian::write_u16(&mut buf[0..2], baseline.co2eq);
-        BigEndian::write_u16(&mut buf[2..4], baseline.tvoc);
-        self.send_command_and_data(Command::SetBaseline, &buf)?;
+    /// Remember the baseline and update the autosave schedule after it was
+    /// set.
+    fn on_baseline_set(&mut self, baseline: &Baseline) {
+        self.last_baseline = Some(*baseline);
+        if let Some(ref mut autosave) = self.autosave {
+            autosave.on_baseline_valid();
+        }
+    }
 
-        // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10);
+    /// Restore a previously stored baseline if it is not older than one week.
+    ///
+    /// Sensirion recommends discarding baselines older than a week. If the
+    /// snapshot is still valid at time `now`, this calls
+    /// [`set_baseline()`](struct.Sgp30.html#method.set_baseline) and returns
+    /// `true`. Stale snapshots are ignored and `false` is returned, in which
+    /// case the sensor starts a new baseline calibration.
+    ///
+    /// Like `set_baseline()`, this requires the air quality measurement to be
+    /// initialized.
+    pub fn restore_baseline<T: Timestamp>(&mut self, snapshot: &BaselineSnapshot<T>, now: &T) -> Result<bool, Error<E>> {
+        match snapshot.valid_baseline(now) {
+            Some(baseline) => {
+                self.set_baseline(baseline)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
 
-        Ok(())
+    /// Recover after the sensor lost power or was reset: Initialize the air
+    /// quality measurement, restore `baseline` and set the humidity
+    /// compensation, in the order mandated by the datasheet.
+    ///
+    /// The baseline must be written right after the initialization, before
+    /// the first measurement. The initialization is always sent, since the
+    /// driver cannot tell that the sensor lost its state. Each command waits
+    /// for its execution time (see `Timings`), so the sensor is ready for
+    /// [`measure()`](#method.measure) when this returns. The initialization
+    /// phase still applies, see
+    /// [`warmup_remaining()`](#method.warmup_remaining).
+    ///
+    /// With [`set_capture_sref()`](#method.set_capture_sref) enabled, the
+    /// reference signals are captured last.
+    pub fn reinit_after_power_loss(&mut self, baseline: &Baseline, humidity: Option<&Humidity>) -> Result<(), Error<E>> {
+        self.send_init()?;
+        self.set_baseline(baseline)?;
+        self.set_humidity(humidity)?;
+        self.capture_sref_after_init()
+    }
+
+    /// Read the baseline and the serial number from the sensor and save them
+    /// to `store`, timestamped with `now` (in seconds).
+    ///
+    /// Return the saved record.
+    pub fn save_baseline<S: BaselineStore>(
+        &mut self,
+        store: &mut S,
+        now: u64,
+    ) -> Result<BaselineRecord, PersistError<E, S::Error>> {
+        let serial = self.serial().map_err(PersistError::Sensor)?;
+        let baseline = self.get_baseline().map_err(PersistError::Sensor)?;
+        let record = BaselineRecord {
+            serial,
+            baseline,
+            timestamp: now,
+        };
+        store.save(&record).map_err(PersistError::Storage)?;
+        Ok(record)
+    }
+
+    /// Load a baseline from `store` and restore it, if it is not older than
+    /// one week at time `now` (in seconds).
+    ///
+    /// Pass `None` as `now` if the clock used for the timestamps does not
+    /// survive restarts (e.g. uptime). The age of the stored baseline is
+    /// unknown then, and it is restored without checking it.
+    ///
+    /// A baseline captured from a different sensor would corrupt the
+    /// readings, so the serial number stored with the baseline must match the
+    /// serial number of this sensor. Otherwise, the baseline is ignored and
+    /// [`RestoreOutcome::SerialMismatch`](storage/enum.RestoreOutcome.html#variant.SerialMismatch)
+    /// is returned.
+    ///
+    /// Like [`set_baseline()`](#method.set_baseline), this requires the air
+    /// quality measurement to be initialized.
+    pub fn restore_baseline_from<S: BaselineStore>(
+        &mut self,
+        store: &mut S,
+        now: Option<u64>,
+    ) -> Result<RestoreOutcome, PersistError<E, S::Error>> {
+        let record = match store.load().map_err(PersistError::Storage)? {
+            Some(record) => record,
+            None => return Ok(RestoreOutcome::NotFound),
+        };
+        let serial = self.serial().map_err(PersistError::Sensor)?;
+        if !record.matches_serial(&serial) {
+            return Ok(RestoreOutcome::SerialMismatch { stored: record.serial });
+        }
+        let restored = match now {
+            Some(now) => self.restore_baseline(&record.to_snapshot(), &now),
+            None => self.set_baseline(&record.baseline).map(|()| true),
+        };
+        if restored.map_err(PersistError::Sensor)? {
+            Ok(RestoreOutcome::Restored)
+        } else {
+            Ok(RestoreOutcome::Expired)
+        }
     }
 
     /// Set the humidity value for the baseline correction algorithm.
@@ -569,20 +1522,13 @@ where
     /// Otherwise an [`Error::NotInitialized`](enum.Error.html#variant.NotInitialized)
     /// will be returned.
     pub fn set_humidity(&mut self, humidity: Option<&Humidity>) -> Result<(), Error<E>> {
-        if !self.initialized {
-            // Measurements weren't initialized
-            return Err(Error::NotInitialized);
-        }
-
-        // Send command and data to sensor
         let buf = match humidity {
             Some(humi) => humi.as_bytes(),
             None => [0, 0],
         };
-        self.send_command_and_data(Command::SetHumidity, &buf)?;
 
-        // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(10);
+        // Send command and data to sensor
+        self.transaction(Command::SetHumidity, &buf, &mut [])?;
 
         Ok(())
     }
@@ -593,78 +1539,201 @@ where
     /// measurement commands and on-chip algorithms. This so called feature set
     /// version number can be read out with this method.
     pub fn get_feature_set(&mut self) -> Result<FeatureSet, Error<E>> {
-        // Send command to sensor
-        self.send_command(Command::GetFeatureSet)?;
-
-        // Max duration according to datasheet (Table 10)
-        self.delay.delay_ms(2);
-
-        // Read result
+        // Request and read feature set
         let mut buf = [0; 3];
-        self.read_with_crc(&mut buf)?;
+        self.transaction(Command::GetFeatureSet, &[], &mut buf)?;
+        Ok(self.decode_feature_set(&buf))
+    }
 
-        Ok(FeatureSet::parse(buf[0], buf[1]))
+    /// Decode a feature set response.
+    fn decode_feature_set(&mut self, buf: &[u8; 3]) -> FeatureSet {
+        let feature_set = FeatureSet::parse(buf[0], buf[1]);
+        log_event!(debug, "Read {:?}", feature_set);
+        self.feature_set = Some(feature_set);
+        feature_set
     }
-}
 
-/// Calculate the CRC8 checksum.
-///
-/// Implementation based on the reference implementation by Sensirion.
-fn crc8(data: &[u8]) -> u8 {
-    let mut crc: u8 = 0xff;
-    for byte in data {
-        crc ^= byte;
-        for _ in 0..8 {
-            if (crc & 0x80) > 0 {
-                crc = (crc << 1) ^ CRC8_POLYNOMIAL;
-            } else {
-                crc <<= 1;
-            }
+    /// Send a command without waiting for its execution.
+    ///
+    /// Return the time in µs the caller must wait (see
+    /// [`Timings`](struct.Timings.html)) before reading the response with the
+    /// matching `read_*()` method or sending the next command. Together,
+    /// these methods allow using the driver without any internal delays,
+    /// e.g. with [`NoDelay`](struct.NoDelay.html) on systems where the only
+    /// allowed sleeps are OS or RTOS primitives:
+    ///
+    /// ```ignore
+    /// let wait_us = sgp.start(Command::MeasureAirQuality)?;
+    /// rtos::sleep_us(wait_us);
+    /// let measurement = sgp.read_measurement()?;
+    /// ```
+    ///
+    /// Commands with data must be sent with
+    /// [`start_set_baseline()`](#method.start_set_baseline) and
+    /// [`start_set_humidity()`](#method.start_set_humidity); passing
+    /// `SetBaseline` or `SetHumidity` here returns `Error::InvalidCommand`.
+    /// Automatic baseline saving and the transaction observer are not applied
+    /// in this mode, and sending the command and reading the response count
+    /// as separate transactions for the [health state](#method.health).
+    pub fn start(&mut self, command: Command) -> Result<u32, Error<E>> {
+        if command == Command::SetBaseline || command == Command::SetHumidity {
+            return Err(Error::InvalidCommand { command });
         }
+        self.start_with_data(command, &[])
     }
-    crc
-}
 
-#[cfg(test)]
-mod tests {
-    extern crate embedded_hal_mock as hal;
+    /// Like [`start()`](#method.start), but for
+    /// [`set_baseline()`](#method.set_baseline).
+    pub fn start_set_baseline(&mut self, baseline: &Baseline) -> Result<u32, Error<E>> {
+        let wait_us = self.start_with_data(Command::SetBaseline, &baseline.to_bytes())?;
+        self.on_baseline_set(baseline);
+        Ok(wait_us)
+    }
 
-    use super::*;
+    /// Like [`start()`](#method.start), but for
+    /// [`set_humidity()`](#method.set_humidity).
+    pub fn start_set_humidity(&mut self, humidity: Option<&Humidity>) -> Result<u32, Error<E>> {
+        let buf = match humidity {
+            Some(humi) => humi.as_bytes(),
+            None => [0, 0],
+        };
+        self.start_with_data(Command::SetHumidity, &buf)
+    }
 
-    /// Test the crc8 function against the test value provided in the
-    /// datasheet (section 6.6).
-    #[test]
-    fn crc8_test_value() {
-        assert_eq!(crc8(&[0xbe, 0xef]), 0x92);
+    /// Send a command and data and update the driver and health state.
+    fn start_with_data(&mut self, command: Command, data: &[u8]) -> Result<u32, Error<E>> {
+        let result = self.send_with_data(command, data);
+        self.record_health(&result);
+        result
     }
 
-    /// Test the `validate_crc` function.
-    #[test]
+    /// Send a command and data and update the driver state.
+    fn send_with_data(&mut self, command: Command, data: &[u8]) -> Result<u32, Error<E>> {
+        if command == Command::SoftReset {
+            let [address, byte] = command.as_bytes();
+            self.write(command, address, &[byte])?;
+            self.initialized = false;
+        } else {
+            if command.requires_init() && !self.initialized {
+                return Err(Error::NotInitialized);
+            }
+            let mut request = [0; MAX_FRAME_LEN];
+            let len = protocol::encode(command, data, &mut request, &mut self.crc);
+            self.write(command, self.address, &request[..len])?;
+            if command == Command::InitAirQuality {
+                self.initialized = true;
+                self.samples_since_init = 0;
+                if let Some(ref mut autosave) = self.autosave {
+                    autosave.on_init();
+                }
+            }
+        }
+        Ok(self.timings.duration_us(command))
+    }
+
+    /// Read the response of `start(Command::MeasureAirQuality)`.
+    pub fn read_measurement(&mut self) -> Result<Measurement, Error<E>> {
+        let mut buf = [0; 6];
+        self.read_response(Command::MeasureAirQuality, &mut buf)?;
+        Ok(self.decode_measurement(&buf))
+    }
+
+    /// Read the response of `start(Command::MeasureRawSignals)`.
+    pub fn read_raw_signals(&mut self) -> Result<RawSignals, Error<E>> {
+        let mut buf = [0; 6];
+        self.read_response(Command::MeasureRawSignals, &mut buf)?;
+        Ok(Self::decode_raw_signals(&buf))
+    }
+
+    /// Read the response of `start(Command::GetBaseline)`.
+    pub fn read_baseline(&mut self) -> Result<Baseline, Error<E>> {
+        let mut buf = [0; 6];
+        self.read_response(Command::GetBaseline, &mut buf)?;
+        Ok(self.decode_baseline(&buf))
+    }
+
+    /// Read the response of `start(Command::GetSerial)`.
+    pub fn read_serial(&mut self) -> Result<[u8; 6], Error<E>> {
+        let mut buf = [0; 9];
+        self.read_response(Command::GetSerial, &mut buf)?;
+        Ok(Self::decode_serial(&buf))
+    }
+
+    /// Read the response of `start(Command::SelfTest)`.
+    pub fn read_selftest(&mut self) -> Result<bool, Error<E>> {
+        let mut buf = [0; 3];
+        self.read_response(Command::SelfTest, &mut buf)?;
+        Ok(Self::decode_selftest(&buf))
+    }
+
+    /// Read the response of `start(Command::GetFeatureSet)`.
+    pub fn read_feature_set(&mut self) -> Result<FeatureSet, Error<E>> {
+        let mut buf = [0; 3];
+        self.read_response(Command::GetFeatureSet, &mut buf)?;
+        Ok(self.decode_feature_set(&buf))
+    }
+}
+
+/// A delay that does nothing.
+///
+/// For use with the [`start()`](struct.Sgp30.html#method.start) and `read_*()`
+/// methods, which do not wait internally. The blocking methods of the driver
+/// must not be used with this delay, since they would read responses before
+/// the sensor is ready.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct NoDelay;
+
+impl DelayMs<u16> for NoDelay {
+    fn delay_ms(&mut self, _ms: u16) {}
+}
+
+impl DelayUs<u16> for NoDelay {
+    fn delay_us(&mut self, _us: u16) {}
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as hal;
+    extern crate std;
+
+    use self::std::string::ToString;
+    use super::*;
+    use storage::tests::MemoryStore;
+
+    /// Test the crc8 function against the test value provided in the
+    /// datasheet (section 6.6).
+    #[test]
+    fn crc8_test_value() {
+        assert_eq!(crc8(&[0xbe, 0xef]), 0x92);
+    }
+
+    /// Test the `validate_crc` function.
+    #[test]
     fn validate_crc() {
         let dev = hal::I2cMock::new();
-        let sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
 
         // Not enough data
-        sgp.validate_crc(&[]).unwrap();
-        sgp.validate_crc(&[0xbe]).unwrap();
-        sgp.validate_crc(&[0xbe, 0xef]).unwrap();
+        sgp.validate_crc(Command::GetSerial, &[]).unwrap();
+        sgp.validate_crc(Command::GetSerial, &[0xbe]).unwrap();
+        sgp.validate_crc(Command::GetSerial, &[0xbe, 0xef]).unwrap();
 
         // Valid CRC
-        sgp.validate_crc(&[0xbe, 0xef, 0x92]).unwrap();
+        sgp.validate_crc(Command::GetSerial, &[0xbe, 0xef, 0x92]).unwrap();
 
         // Invalid CRC
-        match sgp.validate_crc(&[0xbe, 0xef, 0x91]) {
-            Err(Error::Crc) => {},
+        match sgp.validate_crc(Command::GetSerial, &[0xbe, 0xef, 0x91]) {
+            Err(Error::Crc { word: 0xbeef, received: 0x91, computed: 0x92, .. }) => {},
             Err(_) => panic!("Invalid error: Must be Crc"),
             Ok(_) => panic!("CRC check did not fail"),
         }
 
         // Valid CRC (8 bytes)
-        sgp.validate_crc(&[0xbe, 0xef, 0x92, 0xbe, 0xef, 0x92, 0x00, 0x00]).unwrap();
+        sgp.validate_crc(Command::GetSerial, &[0xbe, 0xef, 0x92, 0xbe, 0xef, 0x92, 0x00, 0x00]).unwrap();
 
         // Invalid CRC (8 bytes)
-        match sgp.validate_crc(&[0xbe, 0xef, 0x91, 0xbe, 0xef, 0xff, 0x00, 0x00]) {
-            Err(Error::Crc) => {},
+        match sgp.validate_crc(Command::GetSerial, &[0xbe, 0xef, 0x91, 0xbe, 0xef, 0xff, 0x00, 0x00]) {
+            Err(Error::Crc { .. }) => {},
             Err(_) => panic!("Invalid error: Must be Crc"),
             Ok(_) => panic!("CRC check did not fail"),
         }
@@ -678,20 +1747,33 @@ mod tests {
         // Valid CRC
         let mut dev = hal::I2cMock::new();
         dev.set_read_data(&[0xbe, 0xef, 0x92]);
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
-        sgp.read_with_crc(&mut buf).unwrap();
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.read_with_crc(Command::GetSerial, &mut buf).unwrap();
         assert_eq!(buf, [0xbe, 0xef, 0x92]);
 
         // Invalid CRC
         let mut dev = hal::I2cMock::new();
         dev.set_read_data(&[0xbe, 0xef, 0x00]);
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
-        match sgp.read_with_crc(&mut buf) {
-            Err(Error::Crc) => {},
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        match sgp.read_with_crc(Command::GetSerial, &mut buf) {
+            Err(Error::Crc { .. }) => {},
             Err(_) => panic!("Invalid error: Must be Crc"),
             Ok(_) => panic!("CRC check did not fail"),
         }
         assert_eq!(buf, [0xbe, 0xef, 0x00]); // Buf was changed
+
+        // Floating bus and SDA stuck low
+        for data in &[[0xff; 3], [0x00; 3]] {
+            let mut dev = hal::I2cMock::new();
+            dev.set_read_data(data);
+            let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+            match sgp.read_with_crc(Command::GetSerial, &mut buf) {
+                Err(Error::InvalidResponse { command: Command::GetSerial }) => {},
+                Err(_) => panic!("Invalid error: Must be InvalidResponse"),
+                Ok(_) => panic!("Invalid response not rejected"),
+            }
+            assert_eq!(sgp.stats().crc_errors, 0);
+        }
     }
 
     /// Test the `serial` function
@@ -699,7 +1781,7 @@ mod tests {
     fn serial() {
         let mut dev = hal::I2cMock::new();
         dev.set_read_data(&[0, 0, 129, 0, 100, 254, 204, 130, 135]);
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         let serial = sgp.serial().unwrap();
         assert_eq!(serial, [0, 0, 0, 100, 204, 130]);
     }
@@ -709,7 +1791,7 @@ mod tests {
     fn selftest_ok() {
         let mut dev = hal::I2cMock::new();
         dev.set_read_data(&[0xD4, 0x00, 0xC6]);
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         assert!(sgp.selftest().unwrap());
     }
 
@@ -718,16 +1800,27 @@ mod tests {
     fn selftest_fail() {
         let mut dev = hal::I2cMock::new();
         dev.set_read_data(&[0x12, 0x34, 0x37]);
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         assert!(!sgp.selftest().unwrap());
     }
 
+    /// Test the `selftest_with_watchdog` function: The 220 ms wait is split
+    #[test]
+    fn selftest_with_watchdog() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0xD4, 0x00, 0xC6]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        let mut fed = 0;
+        assert!(sgp.selftest_with_watchdog(&mut || fed += 1).unwrap());
+        assert_eq!(fed, 3);
+    }
+
     /// Test the `measure` function: Require initialization
     #[test]
     fn measure_initialization_required() {
         let mut dev = hal::I2cMock::new();
         dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         match sgp.measure() {
             Err(Error::NotInitialized) => {},
             Ok(_) => panic!("Error::NotInitialized not returned"),
@@ -740,19 +1833,649 @@ mod tests {
     fn measure_success() {
         let mut dev = hal::I2cMock::new();
         dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         sgp.init().unwrap();
         let measurements = sgp.measure().unwrap();
         assert_eq!(measurements.co2eq_ppm, 4_660);
         assert_eq!(measurements.tvoc_ppb, 54_274);
     }
 
+    /// Test that an attached calibration is applied to measurements
+    #[test]
+    fn measure_calibrated() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.set_calibration(Some(Calibration {
+            co2eq_offset: -60,
+            co2eq_gain: 1.0,
+            tvoc_offset: 0,
+            tvoc_gain: 0.5,
+        }));
+        sgp.init().unwrap();
+        let measurements = sgp.measure().unwrap();
+        assert_eq!(measurements.co2eq_ppm, 4_600);
+        assert_eq!(measurements.tvoc_ppb, 27_137);
+    }
+
+    /// Test the `soft_reset` function
+    #[test]
+    fn soft_reset() {
+        let dev = hal::I2cMock::new();
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        sgp.soft_reset().unwrap();
+        assert!(!sgp.initialized);
+        assert_eq!(sgp.destroy().get_write_data(), &[0x06]);
+    }
+
+    /// Test that measurements and CRC errors are counted
+    #[test]
+    fn stats() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0x00,
+        ]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        sgp.measure().unwrap();
+        assert!(sgp.measure().is_err());
+        assert_eq!(sgp.stats(), DriverStats { measurements: 1, crc_errors: 1, ..Default::default() });
+        sgp.reset_stats();
+        assert_eq!(sgp.stats(), DriverStats::default());
+    }
+
+    /// Test that the observer sees every transaction
+    #[test]
+    fn observer() {
+        let mut calls = 0;
+        let mut observe = |t: &Transaction| {
+            match calls {
+                0 => {
+                    assert_eq!(t.command, Command::InitAirQuality);
+                    assert_eq!(t.request, &[0x20, 0x03]);
+                    assert_eq!(t.response, &[]);
+                    assert_eq!(t.wait_us, 10_000);
+                    assert_eq!(t.result, TransactionResult::Ok);
+                }
+                1 => {
+                    assert_eq!(t.command, Command::MeasureAirQuality);
+                    assert_eq!(t.response, &[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA5]);
+                    assert_eq!(t.result, TransactionResult::CrcError);
+                }
+                _ => panic!("unexpected transaction"),
+            }
+            calls += 1;
+        };
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA5]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop).with_observer(&mut observe);
+        sgp.init().unwrap();
+        assert!(sgp.measure().is_err());
+        assert_eq!(calls, 2);
+    }
+
+    /// Test that a query with an invalid CRC is sent again if enabled
+    #[test]
+    fn reread_on_crc_error() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA5,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+        ]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.set_reread_on_crc_error(true);
+        sgp.init().unwrap();
+        let baseline = sgp.get_baseline().unwrap();
+        assert_eq!(baseline, Baseline { co2eq: 0x1234, tvoc: 0xD402 });
+        assert_eq!(sgp.stats().crc_errors, 1);
+        assert_eq!(sgp.stats().retries, 1);
+    }
+
+    /// Test reading again on CRC errors against the simulated sensor, which
+    /// answers every command only once
+    #[test]
+    fn reread_on_crc_error_simulated() {
+        use simulator::{Fault, SimulatedSgp30, Trajectory};
+
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement { co2eq_ppm: 400, tvoc_ppb: 0 }))
+            .with_fault(1, Fault::CorruptCrc)
+            .with_fault(5, Fault::CorruptCrc);
+        let mut sgp = Sgp30::simulated(sensor);
+        sgp.set_reread_on_crc_error(true);
+        sgp.init().unwrap();
+        // The baseline query is sent again
+        assert!(sgp.get_baseline().is_ok());
+        assert_eq!(sgp.stats().retries, 1);
+        // A measurement is not repeated
+        assert!(matches!(sgp.measure(), Err(Error::Crc { .. })));
+        assert_eq!(sgp.stats().retries, 1);
+        assert_eq!(sgp.destroy().time_secs(), 1);
+    }
+
+    /// An I²C bus failing the first `failures` transfers.
+    struct FlakyBus<'a> {
+        inner: hal::I2cMock<'a>,
+        failures: usize,
+    }
+
+    impl<'a> FlakyBus<'a> {
+        fn fail(&mut self) -> Result<(), ()> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(());
+            }
+            Ok(())
+        }
+    }
+
+    impl<'a> Read for FlakyBus<'a> {
+        type Error = ();
+        fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), ()> {
+            self.fail()?;
+            self.inner.read(address, buffer).map_err(|_| ())
+        }
+    }
+
+    impl<'a> Write for FlakyBus<'a> {
+        type Error = ();
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), ()> {
+            self.fail()?;
+            self.inner.write(address, bytes).map_err(|_| ())
+        }
+    }
+
+    impl<'a> WriteRead for FlakyBus<'a> {
+        type Error = ();
+        fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), ()> {
+            self.fail()?;
+            self.inner.write_read(address, bytes, buffer).map_err(|_| ())
+        }
+    }
+
+    /// Test that failed transfers are retried according to the policy
+    #[test]
+    fn retry_policy() {
+        let mut inner = hal::I2cMock::new();
+        inner.set_read_data(&[0x00, 0x42, 0xDE]);
+        let dev = FlakyBus { inner, failures: 2 };
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+
+        // Without retries, the error is returned
+        match sgp.get_feature_set() {
+            Err(Error::I2c { command: Command::GetFeatureSet, .. }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // One failure left, which is retried
+        sgp.set_retry_policy(RetryPolicy::new(3, 100));
+        let feature_set = sgp.get_feature_set().unwrap();
+        assert_eq!(feature_set.product_type, ProductType::Sgp30);
+        assert_eq!(sgp.stats().i2c_errors, 2);
+        assert_eq!(sgp.stats().retries, 1);
+    }
+
+    /// A delay providing only microsecond delays, recording their total
+    struct UsDelay {
+        total_us: u32,
+        calls: u32,
+    }
+
+    impl DelayUs<u16> for UsDelay {
+        fn delay_us(&mut self, us: u16) {
+            self.total_us += u32::from(us);
+            self.calls += 1;
+        }
+    }
+
+    /// Test that the driver works with a delay without `DelayMs`
+    #[test]
+    fn delay_us_only() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, UsDelay { total_us: 0, calls: 0 });
+        sgp.init().unwrap();
+        sgp.measure().unwrap();
+        assert_eq!(sgp.delay.total_us, 10_000 + 12_000);
+
+        // Long waits are split into several delays
+        sgp.delay = UsDelay { total_us: 0, calls: 0 };
+        sgp.wait(200_000);
+        assert_eq!(sgp.delay.total_us, 200_000);
+        assert_eq!(sgp.delay.calls, 4);
+    }
+
+    /// A strategy recording the waits it is asked for
+    #[derive(Default)]
+    struct Recording {
+        waits: std::vec::Vec<(Command, u32)>,
+    }
+
+    impl<D> DelayStrategy<D> for Recording {
+        fn wait(&mut self, command: Command, duration_us: u32, _delay: &mut D) {
+            self.waits.push((command, duration_us));
+        }
+    }
+
+    /// Test that every command's wait goes through the delay strategy
+    #[test]
+    fn delay_strategy() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, UsDelay { total_us: 0, calls: 0 })
+            .with_delay_strategy(Recording::default());
+        sgp.set_timings(Timings::TYPICAL);
+        sgp.init().unwrap();
+        sgp.measure().unwrap();
+        sgp.soft_reset().unwrap();
+        assert_eq!(
+            sgp.strategy.waits,
+            [
+                (Command::InitAirQuality, 2_000),
+                (Command::MeasureAirQuality, 10_000),
+                (Command::SoftReset, 600),
+            ],
+        );
+        assert_eq!(sgp.delay.calls, 0);
+
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, UsDelay { total_us: 0, calls: 0 })
+            .with_delay_strategy(NoWait);
+        sgp.init().unwrap();
+        sgp.measure().unwrap();
+        assert_eq!(sgp.delay.calls, 0);
+    }
+
+    /// Test the countdown of the initialization phase
+    #[test]
+    fn warmup_remaining() {
+        let polluted = Measurement { co2eq_ppm: 1200, tvoc_ppb: 300 };
+        let sensor = simulator::SimulatedSgp30::new(simulator::Trajectory::Constant(polluted));
+        let mut sgp = Sgp30::simulated(sensor);
+        assert_eq!(sgp.warmup_remaining(), None);
+
+        sgp.init().unwrap();
+        assert_eq!(sgp.warmup_remaining(), Some(15));
+        for remaining in (0..15).rev() {
+            assert_eq!(sgp.measure().unwrap(), Measurement { co2eq_ppm: 400, tvoc_ppb: 0 });
+            assert_eq!(sgp.warmup_remaining(), Some(remaining));
+        }
+        assert_ne!(sgp.measure().unwrap().co2eq_ppm, 400);
+        assert_eq!(sgp.warmup_remaining(), Some(0));
+
+        sgp.soft_reset().unwrap();
+        assert_eq!(sgp.warmup_remaining(), None);
+        sgp.start(Command::InitAirQuality).unwrap();
+        assert_eq!(sgp.warmup_remaining(), Some(15));
+    }
+
+    /// Test the recovery sequence after a power loss
+    #[test]
+    fn reinit_after_power_loss() {
+        use simulator::{Fault, SimulatedSgp30, Trajectory};
+
+        // The sensor loses power before the second measurement
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement::default())).with_fault(3, Fault::Reset);
+        let mut sgp = Sgp30::simulated(sensor);
+        sgp.init().unwrap();
+        sgp.measure().unwrap();
+        assert!(matches!(sgp.measure(), Err(Error::I2c { .. })));
+
+        let baseline = Baseline { co2eq: 0x8F3C, tvoc: 0x91A5 };
+        let humidity = Humidity::new(0x0F, 0x80).unwrap();
+        sgp.reinit_after_power_loss(&baseline, Some(&humidity)).unwrap();
+        assert!(sgp.i2c.is_initialized());
+        assert_eq!(sgp.i2c.baseline(), baseline);
+        assert_eq!(sgp.i2c.humidity(), Some(humidity));
+        assert_eq!(sgp.warmup_remaining(), Some(15));
+        sgp.measure().unwrap();
+    }
+
+    /// Test `reinit_after_power_loss`: The reference signals are captured
+    /// after the baseline was restored
+    #[test]
+    fn reinit_after_power_loss_capture_sref() {
+        let mut commands = [None; 4];
+        let mut calls = 0;
+        let mut observe = |t: &Transaction| {
+            commands[calls] = Some(t.command);
+            calls += 1;
+        };
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop).with_observer(&mut observe);
+        sgp.set_capture_sref(true);
+        sgp.reinit_after_power_loss(&Baseline { co2eq: 0x8F3C, tvoc: 0x91A5 }, None).unwrap();
+        assert_eq!(sgp.sref(), Some(RawSignals { h2: 0x1234, ethanol: 0xD402 }));
+        assert_eq!(calls, 4);
+        assert_eq!(commands, [
+            Some(Command::InitAirQuality),
+            Some(Command::SetBaseline),
+            Some(Command::SetHumidity),
+            Some(Command::MeasureRawSignals),
+        ]);
+    }
+
+    /// Test the state snapshot for diagnostics
+    #[test]
+    fn dump_state() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[
+            0x00, 0x42, 0xDE,
+            0x12, 0x34, 0x00, 0xD4, 0x02, 0xA4,
+        ]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        let state = sgp.dump_state();
+        assert!(!state.initialized);
+        assert_eq!(state.last_error, None);
+        assert_eq!(state.feature_set, None);
+        assert_eq!(state.baseline, None);
+
+        sgp.init().unwrap();
+        let feature_set = sgp.get_feature_set().unwrap();
+        assert!(matches!(sgp.get_baseline(), Err(Error::Crc { .. })));
+        let baseline = Baseline { co2eq: 0x8F3C, tvoc: 0x91A5 };
+        sgp.set_baseline(&baseline).unwrap();
+
+        let state = sgp.dump_state();
+        assert!(state.initialized);
+        assert_eq!(state.samples_since_init, 0);
+        assert_eq!(
+            state.last_error,
+            Some(LastError { command: Some(Command::GetBaseline), result: TransactionResult::CrcError }),
+        );
+        assert_eq!(state.feature_set, Some(feature_set));
+        assert_eq!(state.baseline, Some(baseline));
+        assert_eq!(state.stats.crc_errors, 1);
+    }
+
+    /// Test the health state transitions of the driver
+    #[test]
+    fn health() {
+        let mut inner = hal::I2cMock::new();
+        inner.set_read_data(&[0x00, 0x42, 0xDE]);
+        let dev = FlakyBus { inner, failures: 2 };
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.set_health_thresholds(HealthThresholds::new(1, 2, 1));
+
+        // Usage errors do not count
+        assert!(sgp.measure().is_err());
+        assert_eq!(sgp.health(), Health::Healthy);
+
+        assert!(sgp.get_feature_set().is_err());
+        assert_eq!(sgp.health(), Health::Degraded);
+        assert!(sgp.soft_reset().is_err());
+        assert_eq!(sgp.health(), Health::Failed);
+        assert_eq!(sgp.consecutive_failures(), 2);
+
+        // Failed is kept until reset
+        sgp.get_feature_set().unwrap();
+        assert_eq!(sgp.health(), Health::Failed);
+        assert_eq!(sgp.consecutive_failures(), 0);
+        sgp.reset_health();
+        assert_eq!(sgp.health(), Health::Healthy);
+    }
+
+    /// Test that retries stop once the timeout is exceeded
+    #[test]
+    fn timeout() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        // Every reading of the clock advances it by 400 µs
+        static NOW: AtomicU32 = AtomicU32::new(u32::MAX - 500);
+        fn clock() -> u32 {
+            NOW.fetch_add(400, Ordering::Relaxed)
+        }
+
+        let dev = FlakyBus { inner: hal::I2cMock::new(), failures: 5 };
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.set_retry_policy(RetryPolicy::new(10, 100));
+        sgp.set_timeout(Some(Timeout::new(clock, 1000)));
+        match sgp.init() {
+            Err(Error::Timeout { command: Command::InitAirQuality }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(sgp.stats().i2c_errors, 3);
+        assert_eq!(sgp.stats().retries, 2);
+    }
+
+    /// Test that a slow transaction times out even if every transfer succeeds
+    #[test]
+    fn timeout_slow_transaction() {
+        use core::sync::atomic::{AtomicU32, Ordering};
+
+        // Every reading of the clock advances it by 600 µs
+        static NOW: AtomicU32 = AtomicU32::new(0);
+        fn clock() -> u32 {
+            NOW.fetch_add(600, Ordering::Relaxed)
+        }
+
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0b00000000, 0x42, 0xDE]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.set_timeout(Some(Timeout::new(clock, 1000)));
+        match sgp.get_feature_set() {
+            Err(Error::Timeout { command: Command::GetFeatureSet }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(sgp.stats().i2c_errors, 0);
+        assert_eq!(sgp.stats().retries, 0);
+
+        // The limit is not carried over to the next transaction
+        sgp.set_timeout(Some(Timeout::new(clock, 2000)));
+        sgp.get_feature_set().unwrap();
+    }
+
+    impl<'a> BusRecovery for FlakyBus<'a> {
+        fn recover(&mut self) {
+            // Recovery fixes the bus
+            self.failures = 0;
+        }
+    }
+
+    /// Test that the bus is recovered before retrying
+    #[test]
+    fn bus_recovery() {
+        let mut inner = hal::I2cMock::new();
+        inner.set_read_data(&[0x00, 0x42, 0xDE]);
+        let dev = FlakyBus { inner, failures: 5 };
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.set_retry_policy(RetryPolicy::new(2, 0));
+        sgp.enable_bus_recovery();
+        sgp.get_feature_set().unwrap();
+        assert_eq!(sgp.stats().i2c_errors, 1);
+    }
+
+    /// Test the split-phase API without internal delays
+    #[test]
+    fn start_and_read() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, NoDelay);
+        match sgp.start(Command::MeasureAirQuality) {
+            Err(Error::NotInitialized) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(sgp.start(Command::InitAirQuality).unwrap(), 10_000);
+        assert!(sgp.initialized);
+        assert_eq!(sgp.start(Command::MeasureAirQuality).unwrap(), 12_000);
+        let measurement = sgp.read_measurement().unwrap();
+        assert_eq!(measurement, Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 });
+        // Commands with data are rejected without touching the bus
+        match sgp.start(Command::SetHumidity) {
+            Err(Error::InvalidCommand { command: Command::SetHumidity }) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(sgp.start(Command::SetBaseline), Err(Error::InvalidCommand { .. })));
+        assert_eq!(sgp.health(), Health::Healthy);
+        sgp.start_set_humidity(None).unwrap();
+        assert_eq!(sgp.destroy().get_write_data(), &[0x20, 0x61, 0x00, 0x00, 0x81]);
+    }
+
+    /// Test that a custom CRC implementation is used
+    #[test]
+    fn with_crc() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0x00, 0x42, 0x00]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop).with_crc(|_: &[u8]| 0);
+        assert_eq!(sgp.get_feature_set().unwrap().product_type, ProductType::Sgp30);
+        sgp.init().unwrap();
+        sgp.set_humidity(None).unwrap();
+        assert_eq!(sgp.destroy().get_write_data(), &[0x20, 0x61, 0x00, 0x00, 0x00]);
+    }
+
+    /// Test that the measurement loop runs until the callback breaks
+    #[test]
+    fn run() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+        ]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        let mut count = 0;
+        let result = sgp.run(|m| {
+            assert_eq!(m, Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 });
+            count += 1;
+            if count == 3 { ControlFlow::Break(count * 10) } else { ControlFlow::Continue(()) }
+        });
+        assert_eq!(result.unwrap(), 30);
+        assert!(sgp.initialized);
+    }
+
+    /// Test that the measurement loop feeds the watchdog
+    #[test]
+    fn run_with_watchdog() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+        ]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        let mut fed = 0;
+        let mut count = 0;
+        sgp.run_with_watchdog(&mut || fed += 1, |_| {
+            count += 1;
+            if count == 2 { ControlFlow::Break(()) } else { ControlFlow::Continue(()) }
+        }).unwrap();
+        // Once per measurement and ten times while waiting
+        assert_eq!(fed, 2 + 10);
+    }
+
+    /// Test the `measure_with_autosave` function
+    #[test]
+    fn autosave() {
+        let mut dev = hal::I2cMock::new();
+        let data = [0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4];
+        let mut read_data = [0; 60];
+        for chunk in read_data.chunks_mut(6) {
+            chunk.copy_from_slice(&data);
+        }
+        // The second baseline read has an invalid CRC
+        read_data[41] = 0;
+        dev.set_read_data(&read_data);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.enable_autosave_with_interval(2);
+        sgp.init().unwrap();
+        let mut saved = std::vec::Vec::new();
+        let expected = Baseline { co2eq: 0x1234, tvoc: 0xD402 };
+
+        // Without a restored baseline, nothing is saved for 12 h
+        sgp.measure_with_autosave(|b| { saved.push(*b); Ok::<(), ()>(()) }).unwrap();
+        assert!(saved.is_empty());
+        // After restoring a baseline, it is saved every interval
+        sgp.set_baseline(&Baseline { co2eq: 1, tvoc: 2 }).unwrap();
+        sgp.measure_with_autosave(|b| { saved.push(*b); Ok::<(), ()>(()) }).unwrap();
+        assert!(saved.is_empty());
+        sgp.measure_with_autosave(|b| { saved.push(*b); Ok::<(), ()>(()) }).unwrap();
+        assert_eq!(saved, [expected]);
+
+        // A failed read or save keeps the measurement and is retried
+        sgp.measure().unwrap();
+        assert_eq!(sgp.measure_with_autosave(|_| Ok::<(), ()>(())).unwrap().co2eq_ppm, 0x1234);
+        sgp.measure().unwrap();
+        assert_eq!(sgp.measure_with_autosave(|_| Err(())).unwrap().co2eq_ppm, 0x1234);
+        assert_eq!(sgp.stats().autosave_failures, 2);
+    }
+
+    /// Test saving a baseline to a store
+    #[test]
+    fn save_baseline() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[
+            0, 0, 129, 0, 100, 254, 204, 130, 135, // Serial
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4, // Baseline
+        ]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        let mut store = MemoryStore(None);
+        let record = sgp.save_baseline(&mut store, 1000).unwrap();
+        assert_eq!(record.serial, [0, 0, 0, 100, 204, 130]);
+        assert_eq!(record.baseline, Baseline { co2eq: 0x1234, tvoc: 0xD402 });
+        assert_eq!(record.timestamp, 1000);
+        assert_eq!(store.0, Some(record));
+    }
+
+    /// Test restoring a baseline from a store
+    #[test]
+    fn restore_baseline_from() {
+        let serial_data = [0, 0, 129, 0, 100, 254, 204, 130, 135];
+        let record = BaselineRecord {
+            serial: [0, 0, 0, 100, 204, 130],
+            baseline: Baseline { co2eq: 0x1234, tvoc: 0x5678 },
+            timestamp: 1000,
+        };
+
+        // Empty store
+        let mut sgp = Sgp30::new(hal::I2cMock::new(), I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        let outcome = sgp.restore_baseline_from(&mut MemoryStore(None), Some(2000)).unwrap();
+        assert_eq!(outcome, RestoreOutcome::NotFound);
+
+        // Matching serial
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&serial_data);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        let outcome = sgp.restore_baseline_from(&mut MemoryStore(Some(record)), Some(2000)).unwrap();
+        assert_eq!(outcome, RestoreOutcome::Restored);
+        assert_eq!(sgp.destroy().get_write_data(), &[0x20, 0x1E, 0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]);
+
+        // Expired
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&serial_data);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        let now = 1000 + BaselineSnapshot::<u64>::MAX_AGE_SECS + 1;
+        let outcome = sgp.restore_baseline_from(&mut MemoryStore(Some(record)), Some(now)).unwrap();
+        assert_eq!(outcome, RestoreOutcome::Expired);
+
+        // Unknown age
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&serial_data);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        let outcome = sgp.restore_baseline_from(&mut MemoryStore(Some(record)), None).unwrap();
+        assert_eq!(outcome, RestoreOutcome::Restored);
+
+        // Different sensor
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&serial_data);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        let foreign = BaselineRecord { serial: [1, 2, 3, 4, 5, 6], ..record };
+        let outcome = sgp.restore_baseline_from(&mut MemoryStore(Some(foreign)), Some(2000)).unwrap();
+        assert_eq!(outcome, RestoreOutcome::SerialMismatch { stored: [1, 2, 3, 4, 5, 6] });
+    }
+
     /// Test the `get_baseline` function
     #[test]
     fn get_baseline() {
         let mut dev = hal::I2cMock::new();
         dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         sgp.init().unwrap();
         let baseline = sgp.get_baseline().unwrap();
         assert_eq!(baseline.co2eq, 4_660);
@@ -763,7 +2486,7 @@ mod tests {
     #[test]
     fn set_baseline() {
         let dev = hal::I2cMock::new();
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         sgp.init().unwrap();
         let baseline = Baseline {
             co2eq: 0x1234,
@@ -778,11 +2501,34 @@ mod tests {
         ]);
     }
 
+    /// Test the read-back verification of `set_baseline`
+    #[test]
+    fn set_baseline_verification() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[
+            0x12, 0x34, 0x37, 0x56, 0x7A, 0x1F,
+            0x12, 0x34, 0x37, 0x56, 0x80, 0x46,
+        ]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.set_baseline_verification(Some(2));
+        sgp.init().unwrap();
+        let baseline = Baseline { co2eq: 0x1234, tvoc: 0x5678 };
+        sgp.set_baseline(&baseline).unwrap();
+        match sgp.set_baseline(&baseline) {
+            Err(Error::BaselineMismatch { expected, actual }) => {
+                assert_eq!(expected, baseline);
+                assert_eq!(actual, Baseline { co2eq: 0x1234, tvoc: 0x5680 });
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(sgp.destroy().get_write_data(), &[0x20, 0x15]);
+    }
+
     /// Test the `set_humidity` function
     #[test]
     fn set_humidity() {
         let dev = hal::I2cMock::new();
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         sgp.init().unwrap();
         let humidity = Humidity::from_f32(15.5).unwrap();
         sgp.set_humidity(Some(&humidity)).unwrap();
@@ -798,7 +2544,7 @@ mod tests {
     #[test]
     fn set_humidity_none() {
         let dev = hal::I2cMock::new();
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         sgp.init().unwrap();
         sgp.set_humidity(None).unwrap();
         let dev = sgp.destroy();
@@ -809,12 +2555,26 @@ mod tests {
         ]);
     }
 
+    /// Test that `Humidity::ZERO` turns off the compensation like `None`
+    #[test]
+    fn set_humidity_zero() {
+        let dev = hal::I2cMock::new();
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        sgp.set_humidity(Some(&Humidity::ZERO)).unwrap();
+        let dev = sgp.destroy();
+        assert_eq!(dev.get_write_data(), &[
+            /* command: */ 0x20, 0x61,
+            /* data + crc8: */ 0x00, 0x00, 0x81,
+        ]);
+    }
+
     /// Test the `get_feature_set` function.
     #[test]
     fn get_feature_set() {
         let mut dev = hal::I2cMock::new();
         dev.set_read_data(&[0b00000000, 0x42, 0xDE]);
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         sgp.init().unwrap();
         let feature_set = sgp.get_feature_set().unwrap();
         assert_eq!(feature_set.product_type, ProductType::Sgp30);
@@ -826,10 +2586,65 @@ mod tests {
     fn measure_raw_signals() {
         let mut dev = hal::I2cMock::new();
         dev.set_read_data(&[0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]);
-        let mut sgp = Sgp30::new(dev, 0x58, hal::DelayMockNoop);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
         sgp.init().unwrap();
         let signals = sgp.measure_raw_signals().unwrap();
         assert_eq!(signals.h2, (0x12 << 8) + 0x34);
         assert_eq!(signals.ethanol, (0x56 << 8) + 0x78);
     }
+
+    /// Test capturing the reference signals during initialization.
+    #[test]
+    fn capture_sref() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        assert_eq!(sgp.sref(), None);
+        sgp.set_capture_sref(true);
+        sgp.init().unwrap();
+        let sref = sgp.sref().unwrap();
+        assert_eq!(sref, RawSignals { h2: 0x1234, ethanol: 0x5678 });
+        #[cfg(feature = "libm")]
+        assert_eq!(sref.h2_ppm(&sref), 0.5);
+        sgp.set_sref(None);
+        assert_eq!(sgp.sref(), None);
+    }
+
+    /// Test the `Display` implementation of the error type.
+    #[test]
+    fn error_display() {
+        let err: Error<()> = Error::I2c { command: Command::GetSerial, source: () };
+        assert_eq!(err.to_string(), "I²C bus error during GetSerial: ()");
+        let err: Error<()> = Error::Crc {
+            command: Command::MeasureAirQuality,
+            word: 0xbeef,
+            received: 0x91,
+            computed: 0x92,
+        };
+        assert_eq!(
+            err.to_string(),
+            "CRC checksum validation failed for MeasureAirQuality: word 0xBEEF, received 0x91, computed 0x92",
+        );
+    }
+
+    /// Test the `restore_baseline` function with a valid and a stale snapshot
+    #[test]
+    fn restore_baseline() {
+        let dev = hal::I2cMock::new();
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        let snapshot = BaselineSnapshot::new(Baseline { co2eq: 0x1234, tvoc: 0x5678 }, 0u32);
+        assert!(!sgp.restore_baseline(&snapshot, &(8 * 24 * 60 * 60)).unwrap());
+        let dev = sgp.destroy();
+        assert_eq!(dev.get_write_data(), &[/* command: */ 0x20, 0x03]);
+
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        assert!(sgp.restore_baseline(&snapshot, &3600).unwrap());
+        let dev = sgp.destroy();
+        assert_eq!(dev.get_write_data(), &[
+            /* command: */ 0x20, 0x1E,
+            /* data + crc8: */ 0x12, 0x34, 0x37, 0x56, 0x78, 0x7D,
+        ]);
+    }
 }
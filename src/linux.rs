@@ -0,0 +1,122 @@
+//! Linux convenience layer.
+//!
+//! With the `linux` feature, a sensor on a Linux I²C bus (e.g. on a
+//! Raspberry Pi) can be opened by path, without wiring up `I2cdev` and
+//! `Delay` from `linux-embedded-hal` manually, and sampled periodically with
+//! a [`Sampler`](struct.Sampler.html):
+//!
+//! ```no_run
+//! # extern crate sgp30;
+//! use std::time::Duration;
+//! use sgp30::Sgp30;
+//! use sgp30::linux::Sampler;
+//!
+//! # fn main() {
+//! let mut sgp = Sgp30::open("/dev/i2c-1").unwrap();
+//! sgp.init().unwrap();
+//! for measurement in Sampler::new(&mut sgp, Duration::from_secs(1)) {
+//!     println!("{}", measurement.unwrap());
+//! }
+//! # }
+//! ```
+
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use hal::blocking::delay::DelayUs;
+use hal::blocking::i2c::{Read, Write, WriteRead};
+use linux_embedded_hal::{Delay, I2cdev};
+
+pub use linux_embedded_hal::i2cdev::linux::LinuxI2CError;
+
+use crc::Crc8;
+use observer::Observer;
+use timings::DelayStrategy;
+use types::Measurement;
+use {Error, I2cAddress, Sgp30};
+
+/// The I²C address of the SGP30.
+pub const SGP30_ADDRESS: u8 = 0x58;
+
+impl Sgp30<I2cdev, Delay> {
+    /// Open the sensor on the I²C bus device at `path`, e.g. `/dev/i2c-1`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, LinuxI2CError> {
+        Ok(Sgp30::new(I2cdev::new(path)?, I2cAddress::DEFAULT, Delay))
+    }
+}
+
+/// An iterator taking a measurement every `interval`.
+///
+/// The first measurement is taken immediately. Every call to `next()`
+/// blocks until the next measurement is due, so the cadence does not drift
+/// with the time spent processing the results. If the caller falls behind
+/// by more than one interval, the schedule restarts from the current time
+/// instead of taking several measurements in a row.
+///
+/// The iterator never ends; errors are returned as items.
+pub struct Sampler<'a, I2C: 'a, D: 'a, C: 'a, W: 'a, O: 'a> {
+    sgp: &'a mut Sgp30<I2C, D, C, W, O>,
+    interval: Duration,
+    next: Option<Instant>,
+}
+
+impl<'a, I2C, D, C, W, O> Sampler<'a, I2C, D, C, W, O> {
+    /// Create a sampler for an initialized sensor. The SGP30 needs an
+    /// interval of 1 s for its baseline compensation to work correctly.
+    pub fn new(sgp: &'a mut Sgp30<I2C, D, C, W, O>, interval: Duration) -> Self {
+        Sampler { sgp, interval, next: None }
+    }
+}
+
+impl<'a, I2C, D, E, C, W, O> Iterator for Sampler<'a, I2C, D, C, W, O>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+    C: Crc8,
+    W: DelayStrategy<D>,
+    O: Observer,
+{
+    type Item = Result<Measurement, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let now = Instant::now();
+        let due = match self.next {
+            Some(due) if due + self.interval > now => due,
+            _ => now,
+        };
+        if due > now {
+            thread::sleep(due - now);
+        }
+        self.next = Some(due + self.interval);
+        Some(self.sgp.measure())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as mock;
+
+    use std::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn sampler() {
+        let mut dev = mock::I2cMock::new();
+        dev.set_read_data(&[
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+        ]);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, mock::DelayMockNoop);
+        sgp.init().unwrap();
+        let start = Instant::now();
+        let measurements: Vec<_> = Sampler::new(&mut sgp, Duration::from_millis(20))
+            .take(3)
+            .map(Result::unwrap)
+            .collect();
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        assert_eq!(measurements[2], Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 });
+    }
+}
@@ -0,0 +1,110 @@
+//! I²C bus logging.
+//!
+//! [`LoggingBus`](struct.LoggingBus.html) wraps any `embedded-hal` I²C
+//! implementation and logs every transfer it forwards through the
+//! [`log`](https://docs.rs/log) crate, so it can be dropped between a HAL and
+//! the driver when debugging:
+//!
+//! ```ignore
+//! let bus = LoggingBus::new(I2cdev::new("/dev/i2c-1")?);
+//! let mut sgp = Sgp30::new(bus, I2cAddress::DEFAULT, Delay);
+//! ```
+//!
+//! Transfers are logged at `trace` level, failed transfers at `warn` level.
+//! This module is only available with the `log` feature.
+
+use core::fmt::Debug;
+
+use hal::blocking::i2c::{Read, Write, WriteRead};
+
+/// An I²C bus wrapper that logs every transfer.
+#[derive(Debug, Default)]
+pub struct LoggingBus<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C> LoggingBus<I2C> {
+    /// Wrap an I²C bus.
+    pub fn new(i2c: I2C) -> Self {
+        LoggingBus { i2c }
+    }
+
+    /// Return a reference to the wrapped bus.
+    pub fn inner(&self) -> &I2C {
+        &self.i2c
+    }
+
+    /// Return the wrapped bus.
+    pub fn into_inner(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C, E> Write for LoggingBus<I2C>
+where
+    I2C: Write<Error = E>,
+    E: Debug,
+{
+    type Error = E;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), E> {
+        trace!("I²C write to 0x{:02x}: {:02x?}", address, bytes);
+        self.i2c
+            .write(address, bytes)
+            .inspect_err(|e| warn!("I²C write to 0x{:02x} failed: {:?}", address, e))
+    }
+}
+
+impl<I2C, E> Read for LoggingBus<I2C>
+where
+    I2C: Read<Error = E>,
+    E: Debug,
+{
+    type Error = E;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), E> {
+        self.i2c
+            .read(address, buffer)
+            .inspect_err(|e| warn!("I²C read from 0x{:02x} failed: {:?}", address, e))?;
+        trace!("I²C read from 0x{:02x}: {:02x?}", address, buffer);
+        Ok(())
+    }
+}
+
+impl<I2C, E> WriteRead for LoggingBus<I2C>
+where
+    I2C: WriteRead<Error = E>,
+    E: Debug,
+{
+    type Error = E;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), E> {
+        trace!("I²C write to 0x{:02x}: {:02x?}", address, bytes);
+        self.i2c
+            .write_read(address, bytes, buffer)
+            .inspect_err(|e| warn!("I²C write-read at 0x{:02x} failed: {:?}", address, e))?;
+        trace!("I²C read from 0x{:02x}: {:02x?}", address, buffer);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as mock;
+
+    use super::*;
+    use {I2cAddress, Sgp30};
+
+    #[test]
+    fn forwards_transfers() {
+        let mut dev = mock::I2cMock::new();
+        dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
+        let mut sgp = Sgp30::new(LoggingBus::new(dev), I2cAddress::DEFAULT, mock::DelayMockNoop);
+        sgp.init().unwrap();
+        let measurement = sgp.measure().unwrap();
+        assert_eq!(measurement.co2eq_ppm, 0x1234);
+        let bus = sgp.destroy();
+        assert_eq!(bus.inner().get_last_address(), Some(0x58));
+        assert_eq!(bus.into_inner().get_write_data(), &[0x20, 0x08]);
+    }
+}
@@ -0,0 +1,663 @@
+//! High-level sensor supervisor.
+//!
+//! Using the SGP30 correctly requires quite some glue code: The sensor must
+//! be probed and initialized, measured every second, fed with humidity
+//! values, and its baseline must be restored after start-up and saved
+//! periodically. [`Sgp30Manager`](struct.Sgp30Manager.html) owns the driver
+//! and a [`BaselineStore`](../storage/trait.BaselineStore.html) and takes
+//! care of all of this. The application only calls
+//! [`tick()`](struct.Sgp30Manager.html#method.tick) regularly (at least once
+//! per second) with the current time:
+//!
+//! ```ignore
+//! let mut manager = Sgp30Manager::new(sgp, store, ManagerConfig::default());
+//! loop {
+//!     match manager.tick(clock.now_ms()) {
+//!         Ok(Tick::Measurement(m)) => display.show(&m),
+//!         Ok(_) => {}
+//!         Err(e) => log_error(&e),
+//!     }
+//!     sleep_until_next_tick();
+//! }
+//! ```
+//!
+//! Timestamps are milliseconds since an arbitrary epoch, e.g. since boot.
+//! A stored baseline is then restored regardless of its age, since the
+//! timestamps of earlier runs cannot be compared to the current time. If the
+//! epoch is stable across restarts (e.g. Unix time from an RTC), set
+//! [`ManagerConfig::persistent_clock`](struct.ManagerConfig.html#structfield.persistent_clock)
+//! to also discard stored baselines older than one week.
+
+use core::fmt;
+
+use hal::blocking::delay::DelayUs;
+use hal::blocking::i2c::{Read, Write, WriteRead};
+
+use crc::{Crc8, SoftwareCrc};
+use diagnostics::count;
+use observer::{NoObserver, Observer};
+use storage::{BaselineStore, PersistError, RestoreOutcome};
+use timings::{DelayStrategy, Timed};
+use types::{Humidity, Measurement, ProductType};
+use watchdog::Watchdog;
+use {Error, RetryPolicy, Sgp30};
+
+/// Duration of the initialization phase after `init()`, during which the
+/// sensor returns fixed values.
+const WARMUP_MS: u64 = 15_000;
+
+/// Time until the baseline is valid after initialization without a restored
+/// baseline (12 hours).
+const INITIAL_BASELINE_MS: u64 = 12 * 60 * 60 * 1000;
+
+/// Time until a failed baseline save is retried.
+const SAVE_RETRY_MS: u64 = 60 * 1000;
+
+/// Configuration of the [`Sgp30Manager`](struct.Sgp30Manager.html).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ManagerConfig {
+    /// Interval between two measurements (default 1000 ms)
+    pub measurement_interval_ms: u32,
+    /// Interval between two baseline saves once the baseline is valid
+    /// (default 1 h)
+    pub baseline_save_interval_ms: u32,
+    /// Retry policy for failed transfers, applied to the driver (default 3
+    /// attempts, 5 ms backoff)
+    pub retry: RetryPolicy,
+    /// When the sensor is reset after failed measurements
+    pub reset: ResetPolicy,
+    /// Whether to run the on-chip self-test during start-up (default true)
+    pub run_selftest: bool,
+    /// Whether the timestamps passed to `tick()` survive restarts, e.g. Unix
+    /// time from an RTC (default false). Only then are stored baselines
+    /// older than one week discarded.
+    pub persistent_clock: bool,
+}
+
+impl Default for ManagerConfig {
+    fn default() -> Self {
+        ManagerConfig {
+            measurement_interval_ms: 1000,
+            baseline_save_interval_ms: 60 * 60 * 1000,
+            retry: RetryPolicy::new(3, 5000),
+            reset: ResetPolicy::default(),
+            run_selftest: true,
+            persistent_clock: false,
+        }
+    }
+}
+
+/// When the [`Sgp30Manager`](struct.Sgp30Manager.html) resets the sensor
+/// after failed measurements.
+///
+/// Retries stack in two levels: Within a tick, every failed transfer is
+/// retried by the driver according to the
+/// [`RetryPolicy`](../struct.RetryPolicy.html) in
+/// [`ManagerConfig::retry`](struct.ManagerConfig.html#structfield.retry).
+/// A measurement that still fails, or fails with an error that transfer
+/// retries cannot fix (e.g. a CRC error), fails the tick and the error is
+/// returned. After `reset_after` consecutive failed ticks, the sensor is
+/// reset (if `soft_reset` is set) and re-initialized, and the stored
+/// baseline is restored. With the defaults, a dead bus sees up to 3
+/// attempts per tick for 3 ticks before the reset.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ResetPolicy {
+    /// Number of consecutive failed ticks after which the sensor is
+    /// re-initialized (default 3)
+    pub reset_after: u8,
+    /// Whether to send a soft reset before re-initializing (default true).
+    /// Disable this if other devices on the bus must not be reset by the
+    /// I²C general call.
+    pub soft_reset: bool,
+}
+
+impl Default for ResetPolicy {
+    fn default() -> Self {
+        ResetPolicy {
+            reset_after: 3,
+            soft_reset: true,
+        }
+    }
+}
+
+/// The result of a [`tick()`](struct.Sgp30Manager.html#method.tick).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Tick {
+    /// No measurement was due.
+    Idle,
+    /// A measurement was taken during the initialization phase. The values
+    /// are fixed to 400 ppm CO₂eq and 0 ppb TVOC.
+    WarmingUp(Measurement),
+    /// A valid measurement was taken.
+    Measurement(Measurement),
+}
+
+/// Errors of the [`Sgp30Manager`](struct.Sgp30Manager.html).
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ManagerError<E, S> {
+    /// Communication with the sensor failed.
+    Sensor(Error<E>),
+    /// Loading or saving the baseline failed.
+    Storage(S),
+    /// The connected sensor is not an SGP30.
+    UnsupportedProduct(ProductType),
+    /// The on-chip self-test failed.
+    SelfTestFailed,
+}
+
+impl<E, S> From<Error<E>> for ManagerError<E, S> {
+    fn from(e: Error<E>) -> Self {
+        ManagerError::Sensor(e)
+    }
+}
+
+impl<E, S> From<PersistError<E, S>> for ManagerError<E, S> {
+    fn from(e: PersistError<E, S>) -> Self {
+        match e {
+            PersistError::Sensor(e) => ManagerError::Sensor(e),
+            PersistError::Storage(e) => ManagerError::Storage(e),
+        }
+    }
+}
+
+impl<E: fmt::Debug, S: fmt::Debug> fmt::Display for ManagerError<E, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ManagerError::Sensor(ref e) => write!(f, "Sensor error: {}", e),
+            ManagerError::Storage(ref e) => write!(f, "Baseline storage error: {:?}", e),
+            ManagerError::UnsupportedProduct(ref p) => write!(f, "Unsupported product: {}", p),
+            ManagerError::SelfTestFailed => f.write_str("Self-test failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug, S: fmt::Debug> ::std::error::Error for ManagerError<E, S> {}
+
+/// Something called with every valid measurement of the
+/// [`Sgp30Manager`](struct.Sgp30Manager.html), see
+/// [`on_measurement()`](struct.Sgp30Manager.html#method.on_measurement).
+///
+/// Closures implement the trait, so they can capture e.g. a display or a
+/// queue.
+pub trait MeasurementHandler {
+    /// Handle a valid measurement.
+    fn handle(&mut self, measurement: &Measurement);
+}
+
+/// A handler that does nothing.
+impl MeasurementHandler for () {
+    fn handle(&mut self, _measurement: &Measurement) {}
+}
+
+impl<F: FnMut(&Measurement)> MeasurementHandler for F {
+    fn handle(&mut self, measurement: &Measurement) {
+        self(measurement)
+    }
+}
+
+/// A supervisor owning the driver and managing the full sensor lifecycle.
+///
+/// On the first [`tick()`](#method.tick), the sensor is probed (the product
+/// type must be SGP30), self-tested and initialized, and a stored baseline
+/// is restored. Afterwards, every tick takes a measurement if one is due,
+/// sends pending humidity values and saves the baseline periodically once it
+/// is valid. Failed measurements are handled according to the
+/// [`ResetPolicy`](struct.ResetPolicy.html).
+///
+/// The baseline is saved after a successful measurement. A failed save does
+/// not affect the measurement: it is counted (see
+/// [`save_failures()`](#method.save_failures)) and retried after a minute.
+#[derive(Debug)]
+pub struct Sgp30Manager<I2C, D, S, C = SoftwareCrc, W = Timed, O = NoObserver, H = ()> {
+    sgp: Sgp30<I2C, D, C, W, O>,
+    store: S,
+    config: ManagerConfig,
+    /// Whether the start-up sequence completed.
+    started: bool,
+    /// Time of the last (re-)initialization.
+    initialized_at: u64,
+    /// Time of the next measurement.
+    next_measurement: u64,
+    /// Time of the next baseline save.
+    next_baseline_save: u64,
+    /// The humidity used for compensation.
+    humidity: Option<Humidity>,
+    /// Whether the humidity needs to be sent to the sensor.
+    humidity_pending: bool,
+    /// Number of consecutive failed measurements.
+    consecutive_errors: u8,
+    /// Called with every valid measurement.
+    on_measurement: H,
+    /// Number of failed baseline saves.
+    save_failures: u32,
+}
+
+impl<I2C, D, S, E, C, W, O> Sgp30Manager<I2C, D, S, C, W, O>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+    S: BaselineStore,
+    C: Crc8,
+    W: DelayStrategy<D>,
+    O: Observer,
+{
+    /// Create a new manager. The sensor is started on the first
+    /// [`tick()`](#method.tick).
+    ///
+    /// The retry policy of the configuration replaces the one of the driver.
+    pub fn new(mut sgp: Sgp30<I2C, D, C, W, O>, store: S, config: ManagerConfig) -> Self {
+        sgp.set_retry_policy(config.retry);
+        Sgp30Manager {
+            sgp,
+            store,
+            config,
+            started: false,
+            initialized_at: 0,
+            next_measurement: 0,
+            next_baseline_save: 0,
+            humidity: None,
+            humidity_pending: false,
+            consecutive_errors: 0,
+            on_measurement: (),
+            save_failures: 0,
+        }
+    }
+}
+
+impl<I2C, D, S, E, C, W, O, H> Sgp30Manager<I2C, D, S, C, W, O, H>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+    S: BaselineStore,
+    C: Crc8,
+    W: DelayStrategy<D>,
+    O: Observer,
+    H: MeasurementHandler,
+{
+    /// Destroy the manager, return the driver and the baseline store.
+    pub fn destroy(self) -> (Sgp30<I2C, D, C, W, O>, S) {
+        (self.sgp, self.store)
+    }
+
+    /// Return a mutable reference to the driver, e.g. for reading raw
+    /// signals.
+    pub fn sgp(&mut self) -> &mut Sgp30<I2C, D, C, W, O> {
+        &mut self.sgp
+    }
+
+    /// Set a handler, e.g. a closure, that is called with every valid
+    /// measurement. Measurements taken during the initialization phase are
+    /// not passed to it.
+    pub fn on_measurement<H2: MeasurementHandler>(self, handler: H2) -> Sgp30Manager<I2C, D, S, C, W, O, H2> {
+        Sgp30Manager {
+            sgp: self.sgp,
+            store: self.store,
+            config: self.config,
+            started: self.started,
+            initialized_at: self.initialized_at,
+            next_measurement: self.next_measurement,
+            next_baseline_save: self.next_baseline_save,
+            humidity: self.humidity,
+            humidity_pending: self.humidity_pending,
+            consecutive_errors: self.consecutive_errors,
+            on_measurement: handler,
+            save_failures: self.save_failures,
+        }
+    }
+
+    /// Set the absolute humidity used for compensation (or `None` to disable
+    /// compensation). The value is sent with the next measurement and again
+    /// after every re-initialization.
+    pub fn set_humidity(&mut self, humidity: Option<Humidity>) {
+        self.humidity = humidity;
+        self.humidity_pending = true;
+    }
+
+    /// Return the number of failed baseline saves.
+    pub fn save_failures(&self) -> u32 {
+        self.save_failures
+    }
+
+    /// Whether the sensor is in the initialization phase at time `now_ms`.
+    pub fn is_warming_up(&self, now_ms: u64) -> bool {
+        !self.started || now_ms < self.initialized_at + WARMUP_MS
+    }
+
+    /// Drive the sensor. Must be called at least once per measurement
+    /// interval.
+    pub fn tick(&mut self, now_ms: u64) -> Result<Tick, ManagerError<E, S::Error>> {
+        self.tick_with_watchdog(now_ms, &mut ())
+    }
+
+    /// Like [`tick()`](#method.tick), but feed a watchdog between the steps
+    /// of the start-up sequence and after every measurement.
+    pub fn tick_with_watchdog(
+        &mut self,
+        now_ms: u64,
+        watchdog: &mut dyn Watchdog,
+    ) -> Result<Tick, ManagerError<E, S::Error>> {
+        if !self.started {
+            self.start(now_ms, watchdog)?;
+        }
+        if now_ms < self.next_measurement {
+            return Ok(Tick::Idle);
+        }
+
+        let interval = u64::from(self.config.measurement_interval_ms);
+        self.next_measurement += interval;
+        if self.next_measurement <= now_ms {
+            // We fell behind, do not try to catch up
+            count(&mut self.sgp.stats.cadence_violations);
+            self.next_measurement = now_ms + interval;
+        }
+
+        let result = self.measure();
+        watchdog.feed();
+        match result {
+            Ok(measurement) => {
+                self.consecutive_errors = 0;
+                if now_ms >= self.next_baseline_save {
+                    self.save_baseline(now_ms);
+                    watchdog.feed();
+                }
+                if self.is_warming_up(now_ms) {
+                    return Ok(Tick::WarmingUp(measurement));
+                }
+                self.on_measurement.handle(&measurement);
+                Ok(Tick::Measurement(measurement))
+            }
+            Err(e) => {
+                self.consecutive_errors += 1;
+                let policy = self.config.reset;
+                if self.consecutive_errors >= policy.reset_after {
+                    log_event!(warn, "{} consecutive failed measurements, re-initializing", self.consecutive_errors);
+                    self.consecutive_errors = 0;
+                    if policy.soft_reset {
+                        self.sgp.soft_reset()?;
+                    }
+                    self.reinit(now_ms)?;
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Save the baseline, retrying after a minute if that fails.
+    fn save_baseline(&mut self, now_ms: u64) {
+        log_event!(debug, "Saving baseline");
+        match self.sgp.save_baseline(&mut self.store, now_ms / 1000) {
+            Ok(_) => {
+                self.next_baseline_save = now_ms + u64::from(self.config.baseline_save_interval_ms);
+            }
+            Err(_) => {
+                log_event!(warn, "Saving the baseline failed, retrying in {} s", SAVE_RETRY_MS / 1000);
+                self.save_failures = self.save_failures.wrapping_add(1);
+                self.next_baseline_save = now_ms + SAVE_RETRY_MS;
+            }
+        }
+    }
+
+    /// Send pending humidity values and take a measurement.
+    fn measure(&mut self) -> Result<Measurement, Error<E>> {
+        if self.humidity_pending {
+            self.sgp.set_humidity(self.humidity.as_ref())?;
+            self.humidity_pending = false;
+        }
+        self.sgp.measure()
+    }
+
+    /// Probe, self-test and initialize the sensor.
+    fn start(&mut self, now_ms: u64, watchdog: &mut dyn Watchdog) -> Result<(), ManagerError<E, S::Error>> {
+        let feature_set = self.sgp.get_feature_set()?;
+        if feature_set.product_type != ProductType::Sgp30 {
+            return Err(ManagerError::UnsupportedProduct(feature_set.product_type));
+        }
+        if self.config.run_selftest {
+            watchdog.feed();
+            if !self.sgp.selftest_with_watchdog(watchdog)? {
+                return Err(ManagerError::SelfTestFailed);
+            }
+        }
+        watchdog.feed();
+        self.reinit(now_ms)?;
+        watchdog.feed();
+        self.started = true;
+        Ok(())
+    }
+
+    /// (Re-)initialize the sensor and restore the stored baseline.
+    fn reinit(&mut self, now_ms: u64) -> Result<(), ManagerError<E, S::Error>> {
+        self.sgp.force_init()?;
+        self.initialized_at = now_ms;
+        self.next_measurement = now_ms;
+        self.humidity_pending = self.humidity.is_some();
+        self.next_baseline_save = now_ms + INITIAL_BASELINE_MS;
+        let now = if self.config.persistent_clock { Some(now_ms / 1000) } else { None };
+        let outcome = self.sgp.restore_baseline_from(&mut self.store, now)?;
+        log_event!(debug, "Baseline restore: {:?}", outcome);
+        if outcome == RestoreOutcome::Restored {
+            self.next_baseline_save = now_ms + u64::from(self.config.baseline_save_interval_ms);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as mock;
+
+    use super::*;
+    use storage::tests::MemoryStore;
+    use observer::Transaction;
+    use simulator::{Fault, SimulatedSgp30, Trajectory};
+    use storage::BaselineRecord;
+    use types::Baseline;
+    use {I2cAddress, NoWait};
+
+    const FEATURE_SET: [u8; 3] = [0x00, 0x42, 0xDE];
+    const SELFTEST_OK: [u8; 3] = [0xD4, 0x00, 0xC6];
+    const MEASUREMENT: [u8; 6] = [0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4];
+
+    fn manager(read_data: &[u8]) -> Sgp30Manager<mock::I2cMock<'_>, mock::DelayMockNoop, MemoryStore> {
+        let mut dev = mock::I2cMock::new();
+        dev.set_read_data(read_data);
+        let sgp = Sgp30::new(dev, I2cAddress::DEFAULT, mock::DelayMockNoop);
+        Sgp30Manager::new(sgp, MemoryStore(None), ManagerConfig::default())
+    }
+
+    #[test]
+    fn startup_and_cadence() {
+        let mut data = [0; 6 + 3 * 6];
+        data[0..3].copy_from_slice(&FEATURE_SET);
+        data[3..6].copy_from_slice(&SELFTEST_OK);
+        for chunk in data[6..].chunks_mut(6) {
+            chunk.copy_from_slice(&MEASUREMENT);
+        }
+        let mut manager = manager(&data);
+        let expected = Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 };
+        assert_eq!(manager.tick(0).unwrap(), Tick::WarmingUp(expected));
+        assert_eq!(manager.tick(500).unwrap(), Tick::Idle);
+        assert_eq!(manager.tick(1000).unwrap(), Tick::WarmingUp(expected));
+        // After falling behind, the cadence restarts
+        assert_eq!(manager.tick(20_500).unwrap(), Tick::Measurement(expected));
+        assert_eq!(manager.tick(21_000).unwrap(), Tick::Idle);
+        assert_eq!(manager.sgp().stats().cadence_violations, 1);
+    }
+
+    #[test]
+    fn on_measurement() {
+        let mut data = [0; 6 + 2 * 6];
+        data[0..3].copy_from_slice(&FEATURE_SET);
+        data[3..6].copy_from_slice(&SELFTEST_OK);
+        for chunk in data[6..].chunks_mut(6) {
+            chunk.copy_from_slice(&MEASUREMENT);
+        }
+        let mut handled = 0;
+        let mut last = None;
+        let mut manager = manager(&data).on_measurement(|m: &Measurement| {
+            handled += 1;
+            last = Some(*m);
+        });
+        manager.tick(0).unwrap();
+        manager.tick(20_000).unwrap();
+        // Measurements taken during the initialization phase are not handled
+        assert_eq!(handled, 1);
+        assert_eq!(last, Some(Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 }));
+    }
+
+    #[test]
+    fn watchdog() {
+        let mut data = [0; 6 + 6];
+        data[0..3].copy_from_slice(&FEATURE_SET);
+        data[3..6].copy_from_slice(&SELFTEST_OK);
+        data[6..].copy_from_slice(&MEASUREMENT);
+        let mut manager = manager(&data);
+        let mut fed = 0;
+        manager.tick_with_watchdog(0, &mut || fed += 1).unwrap();
+        assert_eq!(fed, 7);
+    }
+
+    #[test]
+    fn configured_driver() {
+        let mut data = [0; 6 + 6];
+        data[0..3].copy_from_slice(&FEATURE_SET);
+        data[3..6].copy_from_slice(&SELFTEST_OK);
+        data[6..].copy_from_slice(&MEASUREMENT);
+        let mut dev = mock::I2cMock::new();
+        dev.set_read_data(&data);
+        let mut commands = 0;
+        let mut observe = |_: &Transaction| commands += 1;
+        let sgp = Sgp30::new(dev, I2cAddress::DEFAULT, mock::DelayMockNoop)
+            .with_delay_strategy(NoWait)
+            .with_observer(&mut observe);
+        let mut manager = Sgp30Manager::new(sgp, MemoryStore(None), ManagerConfig::default());
+        assert!(matches!(manager.tick(0).unwrap(), Tick::WarmingUp(_)));
+        assert_eq!(commands, 4);
+    }
+
+    #[test]
+    fn unsupported_product() {
+        let mut manager = manager(&[0x10, 0x06, 0x49]);
+        match manager.tick(0) {
+            Err(ManagerError::UnsupportedProduct(ProductType::Sgpc3)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn selftest_failed() {
+        let mut data = [0; 6];
+        data[0..3].copy_from_slice(&FEATURE_SET);
+        data[3..6].copy_from_slice(&[0x12, 0x34, 0x37]);
+        let mut manager = manager(&data);
+        match manager.tick(0) {
+            Err(ManagerError::SelfTestFailed) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn retry() {
+        // The first measurement command is not acknowledged
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement { co2eq_ppm: 400, tvoc_ppb: 0 }))
+            .with_fault(3, Fault::Nack);
+        let config = ManagerConfig { run_selftest: false, ..ManagerConfig::default() };
+        let mut manager = Sgp30Manager::new(Sgp30::simulated(sensor), MemoryStore(None), config);
+        assert_eq!(manager.tick(0).unwrap(), Tick::WarmingUp(Measurement { co2eq_ppm: 400, tvoc_ppb: 0 }));
+        assert_eq!(manager.consecutive_errors, 0);
+        assert_eq!(manager.sgp().stats().i2c_errors, 1);
+        assert_eq!(manager.sgp().stats().retries, 1);
+    }
+
+    /// A store whose saves always fail.
+    struct FailingStore;
+
+    impl BaselineStore for FailingStore {
+        type Error = ();
+        fn save(&mut self, _record: &BaselineRecord) -> Result<(), ()> {
+            Err(())
+        }
+        fn load(&mut self) -> Result<Option<BaselineRecord>, ()> {
+            Ok(None)
+        }
+    }
+
+    #[test]
+    fn failed_save_keeps_measurement() {
+        let mut data = [0; 6 + 2 * 6 + 9 + 6];
+        data[0..3].copy_from_slice(&FEATURE_SET);
+        data[3..6].copy_from_slice(&SELFTEST_OK);
+        data[6..12].copy_from_slice(&MEASUREMENT);
+        data[12..18].copy_from_slice(&MEASUREMENT);
+        data[18..27].copy_from_slice(&[0, 0, 129, 0, 100, 254, 204, 130, 135]);
+        data[27..].copy_from_slice(&MEASUREMENT);
+        let mut dev = mock::I2cMock::new();
+        dev.set_read_data(&data);
+        let sgp = Sgp30::new(dev, I2cAddress::DEFAULT, mock::DelayMockNoop);
+        let mut manager = Sgp30Manager::new(sgp, FailingStore, ManagerConfig::default());
+        let expected = Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 };
+        assert_eq!(manager.tick(0).unwrap(), Tick::WarmingUp(expected));
+
+        // The baseline becomes valid, but cannot be saved
+        let now = INITIAL_BASELINE_MS;
+        assert_eq!(manager.tick(now).unwrap(), Tick::Measurement(expected));
+        assert_eq!(manager.save_failures(), 1);
+        assert_eq!(manager.next_baseline_save, now + SAVE_RETRY_MS);
+    }
+
+    #[test]
+    fn restore_after_restart() {
+        let measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 0 };
+        let baseline = Baseline { co2eq: 0x8A3B, tvoc: 0x8D1C };
+        let config = ManagerConfig { run_selftest: false, ..ManagerConfig::default() };
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(measurement)).with_reference_baseline(baseline);
+        let mut manager = Sgp30Manager::new(Sgp30::simulated(sensor), MemoryStore(None), config);
+        manager.tick(0).unwrap();
+        manager.sgp().set_baseline(&baseline).unwrap();
+        manager.tick(INITIAL_BASELINE_MS).unwrap();
+        let (_, store) = manager.destroy();
+        assert_eq!(store.0.unwrap().timestamp, INITIAL_BASELINE_MS / 1000);
+
+        // The uptime clock restarts at 0, before the stored timestamp
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(measurement));
+        let mut manager = Sgp30Manager::new(Sgp30::simulated(sensor), store, config);
+        manager.tick(0).unwrap();
+        assert_eq!(manager.next_baseline_save, u64::from(config.baseline_save_interval_ms));
+        let (sgp, store) = manager.destroy();
+        assert_eq!(sgp.destroy().baseline(), baseline);
+
+        // With a persistent clock, the stored timestamp lies in the future
+        let config = ManagerConfig { persistent_clock: true, ..config };
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(measurement));
+        let mut manager = Sgp30Manager::new(Sgp30::simulated(sensor), store, config);
+        manager.tick(0).unwrap();
+        assert_eq!(manager.next_baseline_save, INITIAL_BASELINE_MS);
+    }
+
+    #[test]
+    fn reinit_after_errors() {
+        let mut data = [0; 6 + 3 * 6];
+        data[0..3].copy_from_slice(&FEATURE_SET);
+        data[3..6].copy_from_slice(&SELFTEST_OK);
+        // Three ticks reading zeros, which transfer retries cannot fix
+        let mut manager = manager(&data);
+        for i in 0..3 {
+            match manager.tick(i * 1000) {
+                Err(ManagerError::Sensor(Error::InvalidResponse { .. })) => {}
+                other => panic!("unexpected result: {:?}", other),
+            }
+        }
+        // The sensor was reset and re-initialized, so the warmup restarts
+        assert_eq!(manager.sgp.i2c.get_write_data(), &[0x20, 0x03]);
+        assert_eq!(manager.initialized_at, 2000);
+        assert!(manager.is_warming_up(16_000));
+    }
+}
@@ -0,0 +1,347 @@
+//! Composable driver decorators.
+//!
+//! [`Sgp30Api`](trait.Sgp30Api.html) is the small set of operations needed
+//! to run a measurement cycle. It is implemented by the blocking
+//! [`Sgp30`](../struct.Sgp30.html) driver and by the decorators in this
+//! module, which wrap any other implementation:
+//!
+//! - [`Retrying`](struct.Retrying.html) repeats operations failing with a
+//!   transient error.
+//! - [`Logged`](struct.Logged.html) logs every operation and its outcome
+//!   (with the `log` feature).
+//!
+//! Decorators stack, and can be used wherever an `Sgp30Api` is expected,
+//! e.g. by the [`stream::Samples`](../stream/struct.Samples.html) stream:
+//!
+//! ```ignore
+//! let sgp = Logged::new(Retrying::new(Sgp30::new(i2c, I2cAddress::DEFAULT, delay), 3));
+//! let mut samples = Samples::new(sgp, ticker);
+//! ```
+//!
+//! `Retrying` repeats a whole operation, including the command write, the
+//! execution wait and the response read, on top of the transfer retries of
+//! the driver's [`RetryPolicy`](../struct.RetryPolicy.html). The two
+//! multiply: with 3 attempts per transfer and 2 per operation, a failing
+//! bus sees up to 6 writes per operation. Measurements are not repeated,
+//! since that would break their 1 Hz cadence.
+
+#[cfg(feature = "log")]
+use core::fmt::Debug;
+
+use hal::blocking::delay::DelayUs;
+use hal::blocking::i2c::{Read, Write, WriteRead};
+
+use crc::Crc8;
+use observer::Observer;
+use timings::DelayStrategy;
+use types::{Baseline, Humidity, Measurement, RawSignals};
+use {Error, Sgp30};
+
+/// The operations of a measurement cycle, shared by the driver and its
+/// decorators.
+pub trait Sgp30Api {
+    /// The error returned by the operations.
+    type Error;
+
+    /// Whether the air quality measurement was initialized.
+    fn is_initialized(&self) -> bool;
+
+    /// Initialize the air quality measurement.
+    fn init(&mut self) -> Result<(), Self::Error>;
+
+    /// Get an air quality measurement.
+    fn measure(&mut self) -> Result<Measurement, Self::Error>;
+
+    /// Get the raw signals.
+    fn measure_raw_signals(&mut self) -> Result<RawSignals, Self::Error>;
+
+    /// Get the baseline of the baseline compensation algorithm.
+    fn get_baseline(&mut self) -> Result<Baseline, Self::Error>;
+
+    /// Restore the baseline of the baseline compensation algorithm.
+    fn set_baseline(&mut self, baseline: &Baseline) -> Result<(), Self::Error>;
+
+    /// Set the humidity compensation, or disable it with `None`.
+    fn set_humidity(&mut self, humidity: Option<&Humidity>) -> Result<(), Self::Error>;
+
+    /// Whether repeating the failed operation may succeed.
+    fn is_transient(error: &Self::Error) -> bool;
+}
+
+impl<I2C, D, E, C, W, O> Sgp30Api for Sgp30<I2C, D, C, W, O>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+    C: Crc8,
+    W: DelayStrategy<D>,
+    O: Observer,
+{
+    type Error = Error<E>;
+
+    fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    fn init(&mut self) -> Result<(), Error<E>> {
+        Sgp30::init(self)
+    }
+
+    fn measure(&mut self) -> Result<Measurement, Error<E>> {
+        Sgp30::measure(self)
+    }
+
+    fn measure_raw_signals(&mut self) -> Result<RawSignals, Error<E>> {
+        Sgp30::measure_raw_signals(self)
+    }
+
+    fn get_baseline(&mut self) -> Result<Baseline, Error<E>> {
+        Sgp30::get_baseline(self)
+    }
+
+    fn set_baseline(&mut self, baseline: &Baseline) -> Result<(), Error<E>> {
+        Sgp30::set_baseline(self, baseline)
+    }
+
+    fn set_humidity(&mut self, humidity: Option<&Humidity>) -> Result<(), Error<E>> {
+        Sgp30::set_humidity(self, humidity)
+    }
+
+    fn is_transient(error: &Error<E>) -> bool {
+        error.is_transient()
+    }
+}
+
+/// A decorator repeating operations that fail with a transient error.
+///
+/// Each operation is attempted up to `attempts` times in total. Errors
+/// that do not go away by repeating, such as `Error::NotInitialized`, are
+/// returned immediately.
+///
+/// Measurements are never repeated: After a failed read, the sensor has
+/// already run the measurement, and a second one within milliseconds would
+/// break the 1 Hz cadence the baseline compensation relies on. Failed
+/// transfers are still retried by the driver according to its
+/// [`RetryPolicy`](../struct.RetryPolicy.html); a measurement with a CRC
+/// error is returned as an error, and the sample should be skipped.
+#[derive(Debug, Default)]
+pub struct Retrying<A> {
+    inner: A,
+    attempts: u8,
+    retries: u32,
+}
+
+impl<A: Sgp30Api> Retrying<A> {
+    /// Wrap `inner`, attempting each operation up to `attempts` times.
+    pub fn new(inner: A, attempts: u8) -> Self {
+        Retrying { inner, attempts, retries: 0 }
+    }
+
+    /// Return the number of operations repeated so far.
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    /// Access the wrapped implementation.
+    pub fn inner(&mut self) -> &mut A {
+        &mut self.inner
+    }
+
+    /// Return the wrapped implementation.
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+
+    /// Run `operation`, repeating it while it fails with a transient error.
+    fn retry<T, F>(&mut self, mut operation: F) -> Result<T, A::Error>
+    where
+        F: FnMut(&mut A) -> Result<T, A::Error>,
+    {
+        let mut attempt = 1;
+        loop {
+            match operation(&mut self.inner) {
+                Err(ref e) if attempt < self.attempts && A::is_transient(e) => {
+                    self.retries = self.retries.wrapping_add(1);
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<A: Sgp30Api> Sgp30Api for Retrying<A> {
+    type Error = A::Error;
+
+    fn is_initialized(&self) -> bool {
+        self.inner.is_initialized()
+    }
+
+    fn init(&mut self) -> Result<(), A::Error> {
+        self.retry(|sgp| sgp.init())
+    }
+
+    fn measure(&mut self) -> Result<Measurement, A::Error> {
+        self.inner.measure()
+    }
+
+    fn measure_raw_signals(&mut self) -> Result<RawSignals, A::Error> {
+        self.inner.measure_raw_signals()
+    }
+
+    fn get_baseline(&mut self) -> Result<Baseline, A::Error> {
+        self.retry(|sgp| sgp.get_baseline())
+    }
+
+    fn set_baseline(&mut self, baseline: &Baseline) -> Result<(), A::Error> {
+        self.retry(|sgp| sgp.set_baseline(baseline))
+    }
+
+    fn set_humidity(&mut self, humidity: Option<&Humidity>) -> Result<(), A::Error> {
+        self.retry(|sgp| sgp.set_humidity(humidity))
+    }
+
+    fn is_transient(error: &A::Error) -> bool {
+        A::is_transient(error)
+    }
+}
+
+/// A decorator logging every operation through the
+/// [`log`](https://docs.rs/log) crate.
+///
+/// Successful operations are logged at `debug` level, failed ones at
+/// `warn` level. Only available with the `log` feature.
+#[cfg(feature = "log")]
+#[derive(Debug, Default)]
+pub struct Logged<A> {
+    inner: A,
+}
+
+#[cfg(feature = "log")]
+impl<A> Logged<A> {
+    /// Wrap `inner`.
+    pub fn new(inner: A) -> Self {
+        Logged { inner }
+    }
+
+    /// Access the wrapped implementation.
+    pub fn inner(&mut self) -> &mut A {
+        &mut self.inner
+    }
+
+    /// Return the wrapped implementation.
+    pub fn into_inner(self) -> A {
+        self.inner
+    }
+}
+
+/// Log the outcome of an operation.
+#[cfg(feature = "log")]
+fn log_result<T: Debug, E: Debug>(operation: &str, result: Result<T, E>) -> Result<T, E> {
+    match result {
+        Ok(ref value) => debug!("SGP30 {}: {:?}", operation, value),
+        Err(ref e) => warn!("SGP30 {} failed: {:?}", operation, e),
+    }
+    result
+}
+
+#[cfg(feature = "log")]
+impl<A> Sgp30Api for Logged<A>
+where
+    A: Sgp30Api,
+    A::Error: Debug,
+{
+    type Error = A::Error;
+
+    fn is_initialized(&self) -> bool {
+        self.inner.is_initialized()
+    }
+
+    fn init(&mut self) -> Result<(), A::Error> {
+        log_result("init", self.inner.init())
+    }
+
+    fn measure(&mut self) -> Result<Measurement, A::Error> {
+        log_result("measure", self.inner.measure())
+    }
+
+    fn measure_raw_signals(&mut self) -> Result<RawSignals, A::Error> {
+        log_result("measure_raw_signals", self.inner.measure_raw_signals())
+    }
+
+    fn get_baseline(&mut self) -> Result<Baseline, A::Error> {
+        log_result("get_baseline", self.inner.get_baseline())
+    }
+
+    fn set_baseline(&mut self, baseline: &Baseline) -> Result<(), A::Error> {
+        log_result("set_baseline", self.inner.set_baseline(baseline))
+    }
+
+    fn set_humidity(&mut self, humidity: Option<&Humidity>) -> Result<(), A::Error> {
+        log_result("set_humidity", self.inner.set_humidity(humidity))
+    }
+
+    fn is_transient(error: &A::Error) -> bool {
+        A::is_transient(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simulator::{Fault, SimulatedSgp30, Trajectory};
+
+    #[test]
+    fn retrying() {
+        // The first two initialization writes are not acknowledged
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement { co2eq_ppm: 400, tvoc_ppb: 0 }))
+            .with_fault(0, Fault::Nack)
+            .with_fault(1, Fault::Nack);
+        let mut sgp = Retrying::new(Sgp30::simulated(sensor), 3);
+        sgp.init().unwrap();
+        assert!(sgp.is_initialized());
+        assert_eq!(sgp.measure().unwrap(), Measurement { co2eq_ppm: 400, tvoc_ppb: 0 });
+        assert_eq!(sgp.retries(), 2);
+    }
+
+    #[test]
+    fn measurements_not_repeated() {
+        // The response of the first measurement is corrupted
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement { co2eq_ppm: 400, tvoc_ppb: 0 }))
+            .with_fault(2, Fault::CorruptCrc);
+        let mut sgp = Retrying::new(Sgp30::simulated(sensor), 3);
+        sgp.init().unwrap();
+        for tick in 1..=3 {
+            let result = sgp.measure();
+            assert_eq!(result.is_ok(), tick > 1);
+            // One measurement per tick
+            assert_eq!(sgp.inner().i2c.time_secs(), tick);
+        }
+        assert_eq!(sgp.retries(), 0);
+    }
+
+    #[test]
+    fn retrying_gives_up() {
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement { co2eq_ppm: 400, tvoc_ppb: 0 }))
+            .with_fault(0, Fault::Nack)
+            .with_fault(1, Fault::Nack);
+        let mut sgp = Retrying::new(Sgp30::simulated(sensor), 2);
+        assert!(matches!(sgp.init(), Err(Error::I2c { .. })));
+        assert_eq!(sgp.retries(), 1);
+
+        // Usage errors are not repeated
+        assert!(matches!(sgp.measure(), Err(Error::NotInitialized)));
+        assert_eq!(sgp.retries(), 1);
+        assert_eq!(sgp.into_inner().destroy().transfers(), 2);
+    }
+
+    #[cfg(feature = "log")]
+    #[test]
+    fn stacked() {
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement { co2eq_ppm: 400, tvoc_ppb: 0 }))
+            .with_fault(0, Fault::Nack);
+        let mut sgp = Logged::new(Retrying::new(Sgp30::simulated(sensor), 2));
+        sgp.init().unwrap();
+        assert_eq!(sgp.measure().unwrap(), Measurement { co2eq_ppm: 400, tvoc_ppb: 0 });
+        assert_eq!(sgp.inner().retries(), 1);
+    }
+}
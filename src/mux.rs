@@ -0,0 +1,163 @@
+//! Support for multiple sensors behind a TCA9548A / PCA9548A I²C multiplexer.
+//!
+//! All SGP30 sensors share the fixed I²C address `0x58`, so only one of them
+//! can be connected to a bus segment. A TCA9548A multiplexer splits a bus into
+//! eight downstream channels. A [`MuxChannel`](struct.MuxChannel.html) wraps
+//! a bus, selects its channel before every transaction and can then be passed
+//! to [`Sgp30::new`](../struct.Sgp30.html#method.new) like a regular bus.
+//!
+//! To drive several sensors at once, every channel needs its own handle to the
+//! same bus, e.g. a proxy from the [`shared-bus`](https://docs.rs/shared-bus)
+//! crate:
+//!
+//! ```ignore
+//! let bus = shared_bus::BusManagerSimple::new(i2c);
+//! let sgp_a = Sgp30::new(MuxChannel::new(bus.acquire_i2c(), 0x70, 0), I2cAddress::DEFAULT, delay_a);
+//! let sgp_b = Sgp30::new(MuxChannel::new(bus.acquire_i2c(), 0x70, 1), I2cAddress::DEFAULT, delay_b);
+//! ```
+
+use hal::blocking::i2c::{Read, Write, WriteRead};
+
+/// Default I²C address of the TCA9548A with all address pins pulled low.
+pub const DEFAULT_MUX_ADDRESS: u8 = 0x70;
+
+/// Number of downstream channels of the TCA9548A.
+pub const CHANNEL_COUNT: u8 = 8;
+
+/// An I²C bus wrapper that routes every transaction through one channel of a
+/// TCA9548A multiplexer.
+#[derive(Debug)]
+pub struct MuxChannel<I2C> {
+    /// The upstream I²C bus the multiplexer is connected to.
+    i2c: I2C,
+    /// The I²C address of the multiplexer.
+    mux_address: u8,
+    /// The downstream channel (0-7).
+    channel: u8,
+}
+
+impl<I2C, E> MuxChannel<I2C>
+where
+    I2C: Write<Error = E>,
+{
+    /// Create a new multiplexer channel wrapper.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channel` is not in the range 0-7.
+    pub fn new(i2c: I2C, mux_address: u8, channel: u8) -> Self {
+        assert!(channel < CHANNEL_COUNT, "TCA9548A channel must be 0-7");
+        MuxChannel {
+            i2c,
+            mux_address,
+            channel,
+        }
+    }
+
+    /// Return the downstream channel this wrapper selects.
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    /// Destroy the wrapper, return the upstream I²C bus instance.
+    pub fn destroy(self) -> I2C {
+        self.i2c
+    }
+
+    /// Write the control register of the multiplexer to enable this channel.
+    fn select(&mut self) -> Result<(), E> {
+        self.i2c.write(self.mux_address, &[1 << self.channel])
+    }
+}
+
+impl<I2C, E> Write for MuxChannel<I2C>
+where
+    I2C: Write<Error = E>,
+{
+    type Error = E;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), E> {
+        self.select()?;
+        self.i2c.write(address, bytes)
+    }
+}
+
+impl<I2C, E> Read for MuxChannel<I2C>
+where
+    I2C: Read<Error = E> + Write<Error = E>,
+{
+    type Error = E;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), E> {
+        self.select()?;
+        self.i2c.read(address, buffer)
+    }
+}
+
+impl<I2C, E> WriteRead for MuxChannel<I2C>
+where
+    I2C: WriteRead<Error = E> + Write<Error = E>,
+{
+    type Error = E;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), E> {
+        self.select()?;
+        self.i2c.write_read(address, bytes, buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as mock;
+
+    use super::*;
+    use {I2cAddress, Sgp30};
+
+    /// A bus that records the address and first byte of every write.
+    struct RecordingBus {
+        writes: [(u8, u8); 8],
+        count: usize,
+    }
+
+    impl Write for RecordingBus {
+        type Error = ();
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), ()> {
+            self.writes[self.count] = (address, bytes[0]);
+            self.count += 1;
+            Ok(())
+        }
+    }
+
+    impl Read for RecordingBus {
+        type Error = ();
+        fn read(&mut self, _address: u8, _buffer: &mut [u8]) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    impl WriteRead for RecordingBus {
+        type Error = ();
+        fn write_read(&mut self, _address: u8, _bytes: &[u8], _buffer: &mut [u8]) -> Result<(), ()> {
+            Ok(())
+        }
+    }
+
+    /// The channel must be selected before every command.
+    #[test]
+    fn selects_channel_before_transaction() {
+        let bus = RecordingBus { writes: [(0, 0); 8], count: 0 };
+        let channel = MuxChannel::new(bus, DEFAULT_MUX_ADDRESS, 3);
+        let mut sgp = Sgp30::new(channel, I2cAddress::DEFAULT, mock::DelayMockNoop);
+        sgp.init().unwrap();
+        let bus = sgp.destroy().destroy();
+        assert_eq!(bus.count, 2);
+        assert_eq!(bus.writes[0], (0x70, 0b0000_1000));
+        assert_eq!(bus.writes[1], (0x58, 0x20));
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_channel() {
+        MuxChannel::new(mock::I2cMock::new(), DEFAULT_MUX_ADDRESS, 8);
+    }
+}
@@ -0,0 +1,145 @@
+//! Transaction observer.
+//!
+//! An [`Observer`](trait.Observer.html) attached with
+//! [`Sgp30::with_observer()`](../struct.Sgp30.html#method.with_observer) is
+//! called after every transaction with the sensor, i.e. a command frame, the
+//! wait for its execution and the response, if any. This allows tracing the
+//! protocol or recording it in the field without a logic analyzer. Any
+//! closure taking a `&Transaction` is an observer, so it can keep state:
+//!
+//! ```
+//! # extern crate embedded_hal_mock;
+//! # extern crate sgp30;
+//! use sgp30::observer::{Transaction, TransactionResult};
+//!
+//! # fn main() {
+//! # let (i2c, delay) = (embedded_hal_mock::I2cMock::new(), embedded_hal_mock::DelayMockNoop);
+//! let mut failures = 0;
+//! let mut sgp = sgp30::Sgp30::new(i2c, sgp30::I2cAddress::DEFAULT, delay).with_observer(|t: &Transaction| {
+//!     // e.g. write to a ring buffer or a log
+//!     if t.result != TransactionResult::Ok {
+//!         failures += 1;
+//!     }
+//! });
+//! # }
+//! ```
+
+use protocol::Command;
+use Error;
+
+/// The result of a transaction.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TransactionResult {
+    /// The transaction succeeded.
+    Ok,
+    /// Writing the command or reading the response failed.
+    I2cError,
+    /// The response failed CRC validation.
+    CrcError,
+    /// The response was rejected as implausible, e.g. a floating bus.
+    InvalidResponse,
+    /// The transaction exceeded the time limit.
+    Timeout,
+    /// The baseline read back after setting it did not match.
+    BaselineMismatch,
+    /// The air quality measurement was not initialized.
+    NotInitialized,
+    /// The command cannot be used with the called method.
+    InvalidCommand,
+}
+
+impl<'a, E> From<&'a Result<(), Error<E>>> for TransactionResult {
+    fn from(result: &'a Result<(), Error<E>>) -> Self {
+        match *result {
+            Ok(()) => TransactionResult::Ok,
+            Err(ref e) => TransactionResult::from(e),
+        }
+    }
+}
+
+impl<'a, E> From<&'a Error<E>> for TransactionResult {
+    fn from(error: &'a Error<E>) -> Self {
+        match *error {
+            Error::I2c { .. } => TransactionResult::I2cError,
+            Error::Crc { .. } => TransactionResult::CrcError,
+            Error::InvalidResponse { .. } => TransactionResult::InvalidResponse,
+            Error::Timeout { .. } => TransactionResult::Timeout,
+            Error::BaselineMismatch { .. } => TransactionResult::BaselineMismatch,
+            Error::NotInitialized => TransactionResult::NotInitialized,
+            Error::InvalidCommand { .. } => TransactionResult::InvalidCommand,
+        }
+    }
+}
+
+/// A transaction with the sensor.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Transaction<'a> {
+    /// The command that was sent
+    pub command: Command,
+    /// The bytes written to the sensor, including data CRCs
+    pub request: &'a [u8],
+    /// The bytes read from the sensor, including CRCs. Empty if the command
+    /// has no response or writing the command failed.
+    pub response: &'a [u8],
+    /// The configured execution time of the command, in µs. This is how
+    /// long the [`DelayStrategy`](../trait.DelayStrategy.html) was asked to
+    /// wait, not a measured duration.
+    pub wait_us: u32,
+    /// The result of the transaction
+    pub result: TransactionResult,
+}
+
+/// A handler called after every transaction with the sensor.
+///
+/// Implemented for every `FnMut(&Transaction)` closure.
+pub trait Observer {
+    /// Handle a transaction.
+    fn observe(&mut self, transaction: &Transaction);
+}
+
+impl<F> Observer for F
+where
+    F: FnMut(&Transaction),
+{
+    fn observe(&mut self, transaction: &Transaction) {
+        self(transaction)
+    }
+}
+
+/// The default observer, which ignores all transactions.
+#[derive(Debug, Default, Copy, Clone)]
+pub struct NoObserver;
+
+impl Observer for NoObserver {
+    fn observe(&mut self, _transaction: &Transaction) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_error() {
+        use types::Baseline;
+
+        let command = Command::GetBaseline;
+        let cases: [(Error<()>, TransactionResult); 7] = [
+            (Error::I2c { command, source: () }, TransactionResult::I2cError),
+            (Error::Crc { command, word: 0, received: 0, computed: 1 }, TransactionResult::CrcError),
+            (Error::InvalidResponse { command }, TransactionResult::InvalidResponse),
+            (Error::Timeout { command }, TransactionResult::Timeout),
+            (
+                Error::BaselineMismatch { expected: Baseline { co2eq: 1, tvoc: 2 }, actual: Baseline { co2eq: 3, tvoc: 4 } },
+                TransactionResult::BaselineMismatch,
+            ),
+            (Error::NotInitialized, TransactionResult::NotInitialized),
+            (Error::InvalidCommand { command }, TransactionResult::InvalidCommand),
+        ];
+        for &(ref error, result) in cases.iter() {
+            assert_eq!(TransactionResult::from(error), result);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! Plausibility validation of measurements.
+//!
+//! A measurement can pass the CRC check and still be physically implausible,
+//! e.g. a CO₂eq value below the outdoor background of 400 ppm after the
+//! warmup phase, or a jump that no real change of the air could cause. A
+//! [`PlausibilityCheck`](struct.PlausibilityCheck.html) flags such samples,
+//! so that data pipelines can tag them as suspect instead of ingesting them
+//! silently.
+//!
+//! ```
+//! use sgp30::Measurement;
+//! use sgp30::plausibility::{Issue, Limits, PlausibilityCheck};
+//!
+//! let mut check = PlausibilityCheck::new(Limits::default()).with_warmup_samples(0);
+//! let sample = Measurement { co2eq_ppm: 350, tvoc_ppb: 12 };
+//! assert_eq!(check.check(&sample).as_slice(), &[Issue::Co2eqBelowFloor]);
+//! ```
+
+use heapless::Vec;
+
+use types::Measurement;
+
+pub use protocol::WARMUP_SAMPLES;
+
+/// A reason for considering a measurement implausible.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Issue {
+    /// The CO₂eq value is below the lower limit after the warmup phase.
+    Co2eqBelowFloor,
+    /// The CO₂eq value is above the upper limit.
+    Co2eqAboveLimit,
+    /// The TVOC value is above the upper limit.
+    TvocAboveLimit,
+    /// The CO₂eq value changed more than allowed since the last sample.
+    Co2eqJump,
+    /// The TVOC value changed more than allowed since the last sample.
+    TvocJump,
+}
+
+/// The issues found in a single measurement. Empty if it is plausible.
+pub type Issues = Vec<Issue, 5>;
+
+/// Limits of plausible measurement values.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Limits {
+    /// Lowest plausible CO₂eq value after warmup (ppm).
+    pub min_co2eq_ppm: u16,
+    /// Highest plausible CO₂eq value (ppm).
+    pub max_co2eq_ppm: u16,
+    /// Highest plausible TVOC value (ppb).
+    pub max_tvoc_ppb: u16,
+    /// Largest plausible change of CO₂eq between two samples (ppm).
+    pub max_co2eq_step_ppm: u16,
+    /// Largest plausible change of TVOC between two samples (ppb).
+    pub max_tvoc_step_ppb: u16,
+}
+
+impl Limits {
+    /// The output range of the sensor according to the datasheet, without
+    /// any limit on the change between samples.
+    pub const DATASHEET: Limits = Limits {
+        min_co2eq_ppm: 400,
+        max_co2eq_ppm: Measurement::MAX_CO2EQ_PPM,
+        max_tvoc_ppb: Measurement::MAX_TVOC_PPB,
+        max_co2eq_step_ppm: u16::MAX,
+        max_tvoc_step_ppb: u16::MAX,
+    };
+
+    /// Limit the change between two samples.
+    pub fn with_max_step(mut self, co2eq_ppm: u16, tvoc_ppb: u16) -> Self {
+        self.max_co2eq_step_ppm = co2eq_ppm;
+        self.max_tvoc_step_ppb = tvoc_ppb;
+        self
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits::DATASHEET
+    }
+}
+
+/// Validates a series of measurements against [`Limits`](struct.Limits.html).
+///
+/// During the warmup phase the sensor reports fixed values of 400 ppm CO₂eq
+/// and 0 ppb TVOC, so only the upper limits are checked for the first
+/// [`WARMUP_SAMPLES`](constant.WARMUP_SAMPLES.html) samples. Call
+/// [`reset()`](#method.reset) whenever the sensor is re-initialized.
+#[derive(Debug, Clone)]
+pub struct PlausibilityCheck {
+    /// The limits to check against.
+    limits: Limits,
+    /// Number of samples to treat as warmup.
+    warmup_samples: u32,
+    /// Number of samples checked since the last reset.
+    samples: u32,
+    /// The previous sample, used for the step limits.
+    previous: Option<Measurement>,
+}
+
+impl PlausibilityCheck {
+    /// Create a new check with the given limits.
+    pub fn new(limits: Limits) -> Self {
+        PlausibilityCheck {
+            limits,
+            warmup_samples: WARMUP_SAMPLES,
+            samples: 0,
+            previous: None,
+        }
+    }
+
+    /// Set the number of samples to treat as warmup, e.g. if the sensor was
+    /// already running or measurements are taken at a different interval.
+    pub fn with_warmup_samples(mut self, samples: u32) -> Self {
+        self.warmup_samples = samples;
+        self
+    }
+
+    /// Return the limits.
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Whether the check still considers the sensor to be warming up.
+    pub fn is_warming_up(&self) -> bool {
+        self.samples < self.warmup_samples
+    }
+
+    /// Restart the warmup phase and forget the previous sample.
+    pub fn reset(&mut self) {
+        self.samples = 0;
+        self.previous = None;
+    }
+
+    /// Check a measurement and return the issues found.
+    pub fn check(&mut self, measurement: &Measurement) -> Issues {
+        let mut issues = Issues::new();
+        let limits = &self.limits;
+        if measurement.co2eq_ppm > limits.max_co2eq_ppm {
+            let _ = issues.push(Issue::Co2eqAboveLimit);
+        }
+        if measurement.tvoc_ppb > limits.max_tvoc_ppb {
+            let _ = issues.push(Issue::TvocAboveLimit);
+        }
+        if !self.is_warming_up() {
+            if measurement.co2eq_ppm < limits.min_co2eq_ppm {
+                let _ = issues.push(Issue::Co2eqBelowFloor);
+            }
+            if let Some(previous) = self.previous {
+                if measurement.co2eq_ppm.abs_diff(previous.co2eq_ppm) > limits.max_co2eq_step_ppm {
+                    let _ = issues.push(Issue::Co2eqJump);
+                }
+                if measurement.tvoc_ppb.abs_diff(previous.tvoc_ppb) > limits.max_tvoc_step_ppb {
+                    let _ = issues.push(Issue::TvocJump);
+                }
+            }
+            self.previous = Some(*measurement);
+        }
+        self.samples = self.samples.saturating_add(1);
+        issues
+    }
+}
+
+impl Default for PlausibilityCheck {
+    fn default() -> Self {
+        PlausibilityCheck::new(Limits::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(co2eq_ppm: u16, tvoc_ppb: u16) -> Measurement {
+        Measurement { co2eq_ppm, tvoc_ppb }
+    }
+
+    #[test]
+    fn warmup() {
+        let mut check = PlausibilityCheck::new(Limits::default()).with_warmup_samples(2);
+        assert!(check.check(&m(0, 0)).is_empty());
+        assert!(check.check(&m(400, 0)).is_empty());
+        assert!(!check.is_warming_up());
+        assert_eq!(check.check(&m(399, 0)).as_slice(), &[Issue::Co2eqBelowFloor]);
+
+        check.reset();
+        assert!(check.is_warming_up());
+        assert!(check.check(&m(0, 0)).is_empty());
+    }
+
+    #[test]
+    fn upper_limits() {
+        let mut check = PlausibilityCheck::default();
+        assert_eq!(
+            check.check(&m(60_000, 60_001)).as_slice(),
+            &[Issue::Co2eqAboveLimit, Issue::TvocAboveLimit],
+        );
+        assert!(check.check(&m(Measurement::MAX_CO2EQ_PPM, Measurement::MAX_TVOC_PPB)).is_empty());
+    }
+
+    #[test]
+    fn steps() {
+        let limits = Limits::default().with_max_step(500, 1000);
+        let mut check = PlausibilityCheck::new(limits).with_warmup_samples(0);
+        assert!(check.check(&m(450, 100)).is_empty());
+        assert!(check.check(&m(940, 1100)).is_empty());
+        assert_eq!(check.check(&m(430, 99)).as_slice(), &[Issue::Co2eqJump, Issue::TvocJump]);
+        // Compared to the implausible sample, not the last plausible one
+        assert!(check.check(&m(430, 99)).is_empty());
+    }
+}
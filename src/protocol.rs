@@ -0,0 +1,460 @@
+//! HAL-free protocol core.
+//!
+//! This module contains everything needed to talk to the SGP30 that does not
+//! depend on a concrete bus implementation: command encoding, CRC calculation
+//! and validation, response decoding and the rules about which commands may
+//! be sent before the air quality measurement was initialized.
+//!
+//! The [`Sgp30`](../struct.Sgp30.html) driver is a thin `embedded-hal`
+//! front-end on top of these functions. They can also be used to talk to the
+//! sensor over a custom transport:
+//!
+//! ```
+//! use sgp30::Command;
+//! use sgp30::protocol::{self, MAX_FRAME_LEN};
+//!
+//! let mut frame = [0; MAX_FRAME_LEN];
+//! let len = protocol::encode_frame(Command::SetHumidity, &[0x0F80], &mut frame);
+//! assert_eq!(&frame[..len], &[0x20, 0x61, 0x0F, 0x80, 0x62]);
+//!
+//! let mut words = [0; 2];
+//! protocol::decode_response(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4], &mut words).unwrap();
+//! assert_eq!(words, [0x1234, 0xD402]);
+//! ```
+
+use byteorder::{BigEndian, ByteOrder};
+use core::fmt;
+
+use crc::{Crc8, SoftwareCrc};
+use Error;
+
+const CRC8_POLYNOMIAL: u8 = 0x31;
+
+/// Maximum length of a command frame: Two command bytes followed by up to two
+/// data words, each with a CRC byte.
+pub const MAX_FRAME_LEN: usize = 2 + 6;
+
+/// Number of measurements during which the sensor reports fixed values
+/// after initialization, at the recommended interval of 1 s.
+pub const WARMUP_SAMPLES: u32 = 15;
+
+/// I²C commands sent to the sensor.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Command {
+    /// Return the serial number.
+    GetSerial,
+    /// Run an on-chip self-test.
+    SelfTest,
+    /// Initialize air quality measurements.
+    InitAirQuality,
+    /// Get a current air quality measurement.
+    MeasureAirQuality,
+    /// Measure raw signals.
+    MeasureRawSignals,
+    /// Return the baseline value.
+    GetBaseline,
+    /// Set the baseline value.
+    SetBaseline,
+    /// Set the current absolute humidity.
+    SetHumidity,
+    /// Set the feature set.
+    GetFeatureSet,
+    /// Reset all devices on the bus that support the I²C general call.
+    SoftReset,
+}
+
+/// All commands.
+const COMMANDS: [Command; 10] = [
+    Command::GetSerial,
+    Command::SelfTest,
+    Command::InitAirQuality,
+    Command::MeasureAirQuality,
+    Command::MeasureRawSignals,
+    Command::GetBaseline,
+    Command::SetBaseline,
+    Command::SetHumidity,
+    Command::GetFeatureSet,
+    Command::SoftReset,
+];
+
+impl Command {
+    /// Return the command with the given command bytes, if there is one.
+    pub fn from_bytes(bytes: [u8; 2]) -> Option<Command> {
+        COMMANDS.iter().copied().find(|command| command.as_bytes() == bytes)
+    }
+
+    /// Return the two command bytes.
+    pub fn as_bytes(self) -> [u8; 2] {
+        match self {
+            Command::GetSerial => [0x36, 0x82],
+            Command::SelfTest => [0x20, 0x32],
+            Command::InitAirQuality => [0x20, 0x03],
+            Command::MeasureAirQuality => [0x20, 0x08],
+            Command::MeasureRawSignals => [0x20, 0x50],
+            Command::GetBaseline => [0x20, 0x15],
+            Command::SetBaseline => [0x20, 0x1E],
+            Command::SetHumidity => [0x20, 0x61],
+            Command::GetFeatureSet => [0x20, 0x2F],
+            Command::SoftReset => [0x00, 0x06],
+        }
+    }
+
+    /// Whether this command only queries the state of the sensor, so it can
+    /// be sent again without side effects. Measurements are not queries:
+    /// Repeating them would break their cadence.
+    pub(crate) fn is_query(self) -> bool {
+        match self {
+            Command::GetSerial | Command::GetBaseline | Command::GetFeatureSet => true,
+            Command::SelfTest
+            | Command::InitAirQuality
+            | Command::MeasureAirQuality
+            | Command::MeasureRawSignals
+            | Command::SetBaseline
+            | Command::SetHumidity
+            | Command::SoftReset => false,
+        }
+    }
+
+    /// Whether this command may only be sent after the air quality
+    /// measurement was initialized.
+    pub fn requires_init(self) -> bool {
+        match self {
+            Command::MeasureAirQuality
+            | Command::MeasureRawSignals
+            | Command::SetBaseline
+            | Command::SetHumidity => true,
+            Command::GetSerial
+            | Command::SelfTest
+            | Command::InitAirQuality
+            | Command::GetBaseline
+            | Command::GetFeatureSet
+            | Command::SoftReset => false,
+        }
+    }
+}
+
+/// Encode a command frame into the provided buffer.
+///
+/// The data slice must have a length of 0, 2 or 4. CRC checksums calculated
+/// with `crc` will automatically be added to every data word.
+///
+/// Return the number of bytes in `buf` that make up the frame.
+pub fn encode<C: Crc8>(command: Command, data: &[u8], buf: &mut [u8; MAX_FRAME_LEN], crc: &mut C) -> usize {
+    assert!(data.is_empty() || data.len() == 2 || data.len() == 4);
+    buf[0..2].copy_from_slice(&command.as_bytes());
+    let mut len = 2;
+    for word in data.chunks(2) {
+        buf[len..len + 2].copy_from_slice(word);
+        buf[len + 2] = crc.checksum(word);
+        len += 3;
+    }
+    len
+}
+
+/// Encode a command frame with up to two data words into the provided
+/// buffer, using the software CRC implementation.
+///
+/// Return the number of bytes in `buf` that make up the frame.
+///
+/// # Panics
+///
+/// Panics if more than two words are passed.
+pub fn encode_frame(command: Command, words: &[u16], buf: &mut [u8; MAX_FRAME_LEN]) -> usize {
+    assert!(words.len() <= 2, "a command frame has at most two data words");
+    let mut data = [0; 4];
+    for (chunk, &word) in data.chunks_mut(2).zip(words) {
+        BigEndian::write_u16(chunk, word);
+    }
+    encode(command, &data[..words.len() * 2], buf, &mut SoftwareCrc)
+}
+
+/// Why a response could not be decoded.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResponseError {
+    /// The response does not consist of the expected number of words, or
+    /// cannot have come from the sensor (see
+    /// [`is_plausible_response()`](fn.is_plausible_response.html)).
+    Invalid,
+    /// A checksum is wrong.
+    Crc {
+        /// Index of the data word
+        index: usize,
+        /// The data word the checksum was calculated over
+        word: u16,
+        /// The checksum byte received from the sensor
+        received: u8,
+        /// The checksum calculated for the received data word
+        computed: u8,
+    },
+}
+
+impl fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ResponseError::Invalid => write!(f, "Invalid response"),
+            ResponseError::Crc { index, word, received, computed } => write!(
+                f,
+                "CRC error in word {} (0x{:04x}): received 0x{:02x}, computed 0x{:02x}",
+                index, word, received, computed
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for ResponseError {}
+
+/// Validate a response and decode its data words into `words`, using the
+/// software CRC implementation.
+///
+/// The response must consist of exactly `words.len()` data words, each
+/// followed by its checksum.
+pub fn decode_response(buf: &[u8], words: &mut [u16]) -> Result<(), ResponseError> {
+    if buf.len() != words.len() * 3 || !is_plausible_response(buf) {
+        return Err(ResponseError::Invalid);
+    }
+    for (index, (chunk, word)) in buf.chunks(3).zip(words.iter_mut()).enumerate() {
+        let computed = crc8(&chunk[0..2]);
+        *word = BigEndian::read_u16(&chunk[0..2]);
+        if computed != chunk[2] {
+            return Err(ResponseError::Crc { index, word: *word, received: chunk[2], computed });
+        }
+    }
+    Ok(())
+}
+
+/// Check that a response buffer can have come from the sensor.
+///
+/// The buffer must consist of whole words with their checksums. A word of
+/// `0xFFFF` has the checksum `0xAC`, so a chunk of three `0xFF` bytes is
+/// never valid; it is what a floating bus or a read that ended early (into
+/// a buffer pre-filled with `0xFF`) produces. A response of only zeros
+/// indicates SDA stuck low.
+pub fn is_plausible_response(buf: &[u8]) -> bool {
+    !buf.is_empty()
+        && buf.len().is_multiple_of(3)
+        && !buf.chunks(3).any(|chunk| chunk == [0xFF; 3])
+        && buf.iter().any(|&byte| byte != 0)
+}
+
+/// Iterate over the provided buffer and validate the CRC8 checksum using
+/// `crc`.
+///
+/// If the checksum is wrong, return `Error::Crc` for the given command.
+///
+/// Note: This function will consider every third byte a checksum byte. If
+/// the buffer size is not a multiple of 3, then not all data will be
+/// validated.
+pub fn validate_crc<E, C: Crc8>(command: Command, buf: &[u8], crc: &mut C) -> Result<(), Error<E>> {
+    for chunk in buf.chunks(3) {
+        if chunk.len() < 3 {
+            continue;
+        }
+        let computed = crc.checksum(&chunk[0..2]);
+        if computed != chunk[2] {
+            return Err(Error::Crc {
+                command,
+                word: BigEndian::read_u16(&chunk[0..2]),
+                received: chunk[2],
+                computed,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Return the data word at position `index` of a response buffer.
+///
+/// The response is expected to consist of data words, each followed by a
+/// CRC byte.
+pub fn decode_word(buf: &[u8], index: usize) -> u16 {
+    BigEndian::read_u16(&buf[index * 3..index * 3 + 2])
+}
+
+/// Calculate the CRC8 checksum.
+///
+/// With the `fast-crc` feature, a 256 byte lookup table is used instead of
+/// the bitwise calculation.
+pub fn crc8(data: &[u8]) -> u8 {
+    #[cfg(feature = "fast-crc")]
+    {
+        data.iter().fold(0xff, |crc, byte| CRC8_TABLE[usize::from(crc ^ byte)])
+    }
+    #[cfg(not(feature = "fast-crc"))]
+    {
+        crc8_bitwise(data)
+    }
+}
+
+/// Calculate the CRC8 checksum bit by bit.
+///
+/// Implementation based on the reference implementation by Sensirion.
+#[cfg_attr(feature = "fast-crc", allow(dead_code))]
+fn crc8_bitwise(data: &[u8]) -> u8 {
+    let mut crc: u8 = 0xff;
+    for byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            if (crc & 0x80) > 0 {
+                crc = (crc << 1) ^ CRC8_POLYNOMIAL;
+            } else {
+                crc <<= 1;
+            }
+        }
+    }
+    crc
+}
+
+/// CRC8 of every possible byte value, without the initial value.
+#[cfg(feature = "fast-crc")]
+static CRC8_TABLE: [u8; 256] = crc8_table();
+
+/// Calculate the CRC8 lookup table.
+#[cfg(feature = "fast-crc")]
+const fn crc8_table() -> [u8; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x80 > 0 { (crc << 1) ^ CRC8_POLYNOMIAL } else { crc << 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate proptest;
+    extern crate std;
+
+    use self::proptest::prelude::*;
+    use self::std::format;
+    use self::std::vec::Vec;
+    use super::*;
+
+    #[test]
+    fn encode_command_only() {
+        let mut buf = [0; MAX_FRAME_LEN];
+        let len = encode(Command::MeasureAirQuality, &[], &mut buf, &mut SoftwareCrc);
+        assert_eq!(&buf[..len], &[0x20, 0x08]);
+    }
+
+    #[test]
+    fn encode_command_and_data() {
+        let mut buf = [0; MAX_FRAME_LEN];
+        let len = encode(Command::SetHumidity, &[0x0F, 0x80], &mut buf, &mut SoftwareCrc);
+        assert_eq!(&buf[..len], &[0x20, 0x61, 0x0F, 0x80, 0x62]);
+        let len = encode(Command::SetBaseline, &[0x12, 0x34, 0x56, 0x78], &mut buf, &mut SoftwareCrc);
+        assert_eq!(&buf[..len], &[0x20, 0x1E, 0x12, 0x34, 0x37, 0x56, 0x78, 0x7D]);
+    }
+
+    #[test]
+    fn decode_words() {
+        let buf = [0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4];
+        assert_eq!(decode_word(&buf, 0), 0x1234);
+        assert_eq!(decode_word(&buf, 1), 0xD402);
+    }
+
+    #[test]
+    fn crc8_reference() {
+        // Example from the datasheet (Table 17)
+        assert_eq!(crc8(&[0xBE, 0xEF]), 0x92);
+        for a in 0..=255 {
+            for b in [0x00, 0x5A, 0xFF] {
+                assert_eq!(crc8(&[a, b]), crc8_bitwise(&[a, b]));
+            }
+        }
+    }
+
+    #[test]
+    fn plausible_response() {
+        assert!(is_plausible_response(&[0xBE, 0xEF, 0x92]));
+        assert!(is_plausible_response(&[0x00, 0x00, 0x81, 0xBE, 0xEF, 0x92]));
+        assert!(!is_plausible_response(&[]));
+        assert!(!is_plausible_response(&[0xBE, 0xEF]));
+        assert!(!is_plausible_response(&[0x00; 6]));
+        assert!(!is_plausible_response(&[0xFF; 6]));
+        // Short read into a pre-filled buffer
+        assert!(!is_plausible_response(&[0xBE, 0xEF, 0x92, 0xFF, 0xFF, 0xFF]));
+    }
+
+    #[test]
+    fn requires_init() {
+        assert!(Command::MeasureAirQuality.requires_init());
+        assert!(Command::SetHumidity.requires_init());
+        assert!(!Command::InitAirQuality.requires_init());
+        assert!(!Command::GetSerial.requires_init());
+    }
+
+    #[test]
+    fn decode_response_errors() {
+        let mut words = [0; 2];
+        assert_eq!(decode_response(&[0x12, 0x34, 0x37], &mut words), Err(ResponseError::Invalid));
+        assert_eq!(
+            decode_response(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA5], &mut words),
+            Err(ResponseError::Crc { index: 1, word: 0xD402, received: 0xA5, computed: 0xA4 })
+        );
+    }
+
+    #[test]
+    fn command_from_bytes() {
+        for &command in &COMMANDS {
+            assert_eq!(Command::from_bytes(command.as_bytes()), Some(command));
+        }
+        assert_eq!(Command::from_bytes([0x12, 0x34]), None);
+    }
+
+    proptest! {
+        /// The data words of a command frame have the same layout as a
+        /// response, so encoding and decoding must round-trip.
+        #[test]
+        fn frame_roundtrip(
+            command in prop::sample::select(COMMANDS.to_vec()),
+            words in prop::collection::vec(any::<u16>(), 0..=2),
+        ) {
+            let mut frame = [0; MAX_FRAME_LEN];
+            let len = encode_frame(command, &words, &mut frame);
+            prop_assert_eq!(len, 2 + 3 * words.len());
+            prop_assert_eq!(Command::from_bytes([frame[0], frame[1]]), Some(command));
+            let mut decoded = [0; 2];
+            let decoded = &mut decoded[..words.len()];
+            match decode_response(&frame[2..len], decoded) {
+                Ok(()) => prop_assert_eq!(&decoded[..], &words[..]),
+                // All-zero or 0xFFFF words are rejected as implausible
+                Err(e) => prop_assert!(e == ResponseError::Invalid && !is_plausible_response(&frame[2..len])),
+            }
+        }
+
+        /// Arbitrary input never panics and is only accepted if every
+        /// checksum matches.
+        #[test]
+        fn garbage_response(buf in prop::collection::vec(any::<u8>(), 0..32), count in 0usize..11) {
+            let mut words = [0; 10];
+            if decode_response(&buf, &mut words[..count]).is_ok() {
+                prop_assert!(is_plausible_response(&buf));
+                prop_assert!(buf.chunks(3).all(|chunk| crc8(&chunk[0..2]) == chunk[2]));
+                let expected: Vec<u16> = buf.chunks(3).map(BigEndian::read_u16).collect();
+                prop_assert_eq!(&words[..count], &expected[..]);
+            }
+        }
+
+        /// CRC-8 detects every single bit error in a word.
+        #[test]
+        fn bit_flip_detected(word in any::<u16>(), bit in 0usize..24) {
+            let mut buf = [0; 3];
+            BigEndian::write_u16(&mut buf, word);
+            buf[2] = crc8(&buf[0..2]);
+            buf[bit / 8] ^= 0x80 >> (bit % 8);
+            prop_assert!(decode_response(&buf, &mut [0]).is_err());
+        }
+    }
+}
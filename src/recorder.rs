@@ -0,0 +1,168 @@
+//! A fixed-size recorder for recent measurement history.
+//!
+//! [`Recorder`](struct.Recorder.html) keeps the last `N` measurements in a
+//! ring buffer, so that the recent history can be shown when a display or a
+//! debug console is connected. With decimation, every stored entry is the
+//! average of several consecutive measurements, which extends the covered
+//! time span without using more memory.
+//!
+//! ```
+//! use sgp30::Measurement;
+//! use sgp30::recorder::Recorder;
+//!
+//! // Store one-minute averages of the last hour when measuring at 1 Hz
+//! let mut recorder: Recorder<60> = Recorder::with_decimation(60);
+//! for _ in 0..60 {
+//!     recorder.push(Measurement { co2eq_ppm: 612, tvoc_ppb: 87 });
+//! }
+//! assert_eq!(recorder.len(), 1);
+//! assert_eq!(recorder.latest(), Some(Measurement { co2eq_ppm: 612, tvoc_ppb: 87 }));
+//! ```
+
+use heapless::HistoryBuffer;
+
+use types::Measurement;
+
+/// A ring buffer storing the last `N` (optionally decimated) measurements.
+pub struct Recorder<const N: usize> {
+    /// Number of measurements averaged into one entry.
+    decimation: u32,
+    /// Sums and count of the current, incomplete entry.
+    pending: (u32, u32, u32),
+    /// The recorded entries.
+    history: HistoryBuffer<Measurement, N>,
+}
+
+impl<const N: usize> Recorder<N> {
+    /// Create a new recorder that stores every measurement.
+    pub const fn new() -> Self {
+        Recorder {
+            decimation: 1,
+            pending: (0, 0, 0),
+            history: HistoryBuffer::new(),
+        }
+    }
+
+    /// Create a new recorder that stores the average of every `decimation`
+    /// measurements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `decimation` is zero.
+    pub fn with_decimation(decimation: u32) -> Self {
+        assert!(decimation > 0, "decimation must not be zero");
+        Recorder {
+            decimation,
+            ..Recorder::new()
+        }
+    }
+
+    /// Return the decimation factor.
+    pub fn decimation(&self) -> u32 {
+        self.decimation
+    }
+
+    /// Add a measurement. The oldest entry is dropped if the buffer is full.
+    pub fn push(&mut self, measurement: Measurement) {
+        let (co2eq, tvoc, count) = &mut self.pending;
+        *co2eq += u32::from(measurement.co2eq_ppm);
+        *tvoc += u32::from(measurement.tvoc_ppb);
+        *count += 1;
+        if *count == self.decimation {
+            let half = self.decimation / 2;
+            self.history.write(Measurement {
+                co2eq_ppm: ((*co2eq + half) / self.decimation) as u16,
+                tvoc_ppb: ((*tvoc + half) / self.decimation) as u16,
+            });
+            self.pending = (0, 0, 0);
+        }
+    }
+
+    /// Return the most recent entry.
+    pub fn latest(&self) -> Option<Measurement> {
+        self.history.recent().cloned()
+    }
+
+    /// Iterate over the recorded entries, from oldest to newest.
+    pub fn iter(&self) -> impl Iterator<Item = &Measurement> {
+        self.history.oldest_ordered()
+    }
+
+    /// Iterate over the last `n` entries, from newest to oldest.
+    pub fn recent(&self, n: usize) -> impl Iterator<Item = &Measurement> {
+        let (older, newer) = self.history.as_slices();
+        newer.iter().rev().chain(older.iter().rev()).take(n)
+    }
+
+    /// Return the number of recorded entries.
+    pub fn len(&self) -> usize {
+        self.history.len()
+    }
+
+    /// Whether no entries were recorded yet.
+    pub fn is_empty(&self) -> bool {
+        self.history.len() == 0
+    }
+
+    /// Return the maximum number of entries.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Remove all entries.
+    pub fn clear(&mut self) {
+        self.pending = (0, 0, 0);
+        self.history.clear();
+    }
+}
+
+impl<const N: usize> Default for Recorder<N> {
+    fn default() -> Self {
+        Recorder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(co2eq_ppm: u16, tvoc_ppb: u16) -> Measurement {
+        Measurement { co2eq_ppm, tvoc_ppb }
+    }
+
+    #[test]
+    fn ring_buffer() {
+        let mut recorder: Recorder<3> = Recorder::new();
+        assert!(recorder.is_empty());
+        assert_eq!(recorder.latest(), None);
+        for i in 0..5 {
+            recorder.push(m(400 + i, i));
+        }
+        assert_eq!(recorder.len(), 3);
+        assert_eq!(recorder.capacity(), 3);
+        assert_eq!(recorder.latest(), Some(m(404, 4)));
+        let tvoc: ::heapless::Vec<u16, 3> = recorder.iter().map(|m| m.tvoc_ppb).collect();
+        assert_eq!(tvoc.as_slice(), &[2, 3, 4]);
+        let tvoc: ::heapless::Vec<u16, 3> = recorder.recent(2).map(|m| m.tvoc_ppb).collect();
+        assert_eq!(tvoc.as_slice(), &[4, 3]);
+    }
+
+    #[test]
+    fn decimation() {
+        let mut recorder: Recorder<4> = Recorder::with_decimation(2);
+        recorder.push(m(400, 10));
+        assert!(recorder.is_empty());
+        recorder.push(m(500, 21));
+        assert_eq!(recorder.latest(), Some(m(450, 16)));
+        recorder.push(m(400, 10));
+        recorder.clear();
+        recorder.push(m(600, 0));
+        assert!(recorder.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_decimation() {
+        let _: Recorder<4> = Recorder::with_decimation(0);
+    }
+}
@@ -0,0 +1,149 @@
+//! Stuck bus recovery.
+//!
+//! If a transfer is interrupted halfway, e.g. by a reset of the host during
+//! a read, the SGP30 may still be waiting to clock out the rest of its
+//! response and keep SDA low. The bus then stays blocked until the sensor
+//! receives enough clock pulses to finish.
+//!
+//! Bus implementations that can release their pins implement
+//! [`BusRecovery`](trait.BusRecovery.html), typically using
+//! [`unstick()`](fn.unstick.html) to clock out the stuck device. `unstick()`
+//! uses the digital pin traits of `embedded-hal`, which are behind its
+//! `unproven` feature, and is therefore only available with the
+//! `bus-recovery` feature of this crate. Once enabled
+//! with [`Sgp30::enable_bus_recovery()`](../struct.Sgp30.html#method.enable_bus_recovery),
+//! the driver recovers the bus before retrying a failed transfer (see
+//! [`RetryPolicy`](../struct.RetryPolicy.html)). Alternatively, any function
+//! can be registered with
+//! [`Sgp30::set_bus_recovery()`](../struct.Sgp30.html#method.set_bus_recovery).
+
+#[cfg(feature = "bus-recovery")]
+use hal::blocking::delay::DelayUs;
+#[cfg(feature = "bus-recovery")]
+use hal::digital::v2::{InputPin, OutputPin};
+
+/// An I²C bus that can recover from a device holding SDA low.
+pub trait BusRecovery {
+    /// Recover the bus. Errors cannot be handled by the driver and should be
+    /// ignored; the following retry fails if the bus is still blocked.
+    fn recover(&mut self);
+}
+
+/// Half period of the recovery clock in µs (100 kHz).
+#[cfg(feature = "bus-recovery")]
+const HALF_PERIOD_US: u16 = 5;
+
+/// Clock out a device holding SDA low and generate a STOP condition.
+///
+/// Both pins must be configured as open-drain outputs (with SDA readable)
+/// and be released (high) when calling this. Up to nine clock pulses are
+/// generated on SCL until SDA is released. Return whether the bus is free.
+#[cfg(feature = "bus-recovery")]
+pub fn unstick<SCL, SDA, D, E>(scl: &mut SCL, sda: &mut SDA, delay: &mut D) -> Result<bool, E>
+where
+    SCL: OutputPin<Error = E>,
+    SDA: InputPin<Error = E> + OutputPin<Error = E>,
+    D: DelayUs<u16>,
+{
+    for _ in 0..9 {
+        if sda.is_high()? {
+            break;
+        }
+        scl.set_low()?;
+        delay.delay_us(HALF_PERIOD_US);
+        scl.set_high()?;
+        delay.delay_us(HALF_PERIOD_US);
+    }
+
+    // STOP condition: SDA rises while SCL is high
+    scl.set_low()?;
+    delay.delay_us(HALF_PERIOD_US);
+    sda.set_low()?;
+    delay.delay_us(HALF_PERIOD_US);
+    scl.set_high()?;
+    delay.delay_us(HALF_PERIOD_US);
+    sda.set_high()?;
+    delay.delay_us(HALF_PERIOD_US);
+
+    sda.is_high()
+}
+
+#[cfg(all(test, feature = "bus-recovery"))]
+mod tests {
+    extern crate embedded_hal_mock as mock;
+
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    use super::*;
+
+    /// A device holding SDA low for a number of clock pulses.
+    struct Bus {
+        pulses_until_release: Cell<u8>,
+        pulses: Cell<u8>,
+        sda_driven_low: Cell<bool>,
+    }
+
+    struct Scl<'a>(&'a Bus);
+    struct Sda<'a>(&'a Bus);
+
+    impl<'a> OutputPin for Scl<'a> {
+        type Error = Infallible;
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.pulses.set(self.0.pulses.get() + 1);
+            let remaining = self.0.pulses_until_release.get();
+            self.0.pulses_until_release.set(remaining.saturating_sub(1));
+            Ok(())
+        }
+    }
+
+    impl<'a> OutputPin for Sda<'a> {
+        type Error = Infallible;
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.sda_driven_low.set(true);
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.sda_driven_low.set(false);
+            Ok(())
+        }
+    }
+
+    impl<'a> InputPin for Sda<'a> {
+        type Error = Infallible;
+        fn is_high(&self) -> Result<bool, Infallible> {
+            Ok(!self.0.sda_driven_low.get() && self.0.pulses_until_release.get() == 0)
+        }
+        fn is_low(&self) -> Result<bool, Infallible> {
+            self.is_high().map(|high| !high)
+        }
+    }
+
+    fn bus(pulses_until_release: u8) -> Bus {
+        Bus {
+            pulses_until_release: Cell::new(pulses_until_release),
+            pulses: Cell::new(0),
+            sda_driven_low: Cell::new(false),
+        }
+    }
+
+    #[test]
+    fn unstick_released() {
+        let bus = bus(3);
+        let free = unstick(&mut Scl(&bus), &mut Sda(&bus), &mut mock::DelayMockNoop).unwrap();
+        assert!(free);
+        // Three clock pulses plus the STOP condition
+        assert_eq!(bus.pulses.get(), 4);
+    }
+
+    #[test]
+    fn unstick_still_blocked() {
+        let bus = bus(20);
+        let free = unstick(&mut Scl(&bus), &mut Sda(&bus), &mut mock::DelayMockNoop).unwrap();
+        assert!(!free);
+        assert_eq!(bus.pulses.get(), 10);
+    }
+}
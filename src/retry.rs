@@ -0,0 +1,38 @@
+/// Retry policy for I²C transfers.
+///
+/// The SGP30 does not acknowledge its address while it is busy executing a
+/// command, e.g. if it is polled too early. With a retry policy set via
+/// [`Sgp30::set_retry_policy()`](struct.Sgp30.html#method.set_retry_policy),
+/// failed command writes and response reads are repeated up to `attempts`
+/// times in total, waiting `backoff_us` before the first retry and doubling
+/// the wait for every further one.
+///
+/// The default policy performs a single attempt, i.e. no retries.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RetryPolicy {
+    /// Total number of attempts per transfer (at least 1)
+    pub attempts: u8,
+    /// Delay before the first retry in µs
+    pub backoff_us: u16,
+}
+
+impl RetryPolicy {
+    /// A policy without retries.
+    pub const NONE: RetryPolicy = RetryPolicy {
+        attempts: 1,
+        backoff_us: 0,
+    };
+
+    /// Create a new retry policy.
+    pub fn new(attempts: u8, backoff_us: u16) -> Self {
+        RetryPolicy { attempts, backoff_us }
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy::NONE
+    }
+}
@@ -0,0 +1,33 @@
+//! Raspberry Pi support via `rppal`.
+//!
+//! `rppal` implements the `embedded-hal` traits for its own I²C and delay
+//! types, so with the `rppal` feature a bus opened with `rppal` can be
+//! passed to the driver without going through `linux-embedded-hal`.
+
+use rppal::hal::Delay;
+use rppal::i2c::I2c;
+
+use {I2cAddress, Sgp30};
+
+impl Sgp30<I2c, Delay> {
+    /// Create a new instance from an `rppal` I²C bus, e.g. one opened with
+    /// `I2c::new()` or `I2c::with_bus()`.
+    ///
+    /// The slave address is set by the driver on every transfer, so the bus
+    /// may be shared with other devices.
+    ///
+    /// ```no_run
+    /// # extern crate rppal;
+    /// # extern crate sgp30;
+    /// use rppal::i2c::I2c;
+    /// use sgp30::Sgp30;
+    ///
+    /// # fn main() {
+    /// let mut sgp = Sgp30::from_rppal(I2c::new().unwrap());
+    /// sgp.init().unwrap();
+    /// # }
+    /// ```
+    pub fn from_rppal(i2c: I2c) -> Self {
+        Sgp30::new(i2c, I2cAddress::DEFAULT, Delay::new())
+    }
+}
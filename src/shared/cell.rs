@@ -0,0 +1,207 @@
+use core::cell::RefCell;
+use core::fmt;
+use core::mem;
+
+use critical_section::Mutex;
+
+use crc::SoftwareCrc;
+use observer::NoObserver;
+use timings::Timed;
+use Sgp30;
+
+/// Why a [`Sgp30Cell`](struct.Sgp30Cell.html) could not provide the driver.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CellError {
+    /// No driver was put into the cell.
+    Empty,
+    /// The driver is in use by another context, e.g. the interrupted main
+    /// loop.
+    Busy,
+}
+
+/// A slot guarded by a critical section.
+type Guarded<T> = Mutex<RefCell<Slot<T>>>;
+
+impl fmt::Display for CellError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CellError::Empty => write!(f, "No driver in the cell"),
+            CellError::Busy => write!(f, "Driver is in use"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for CellError {}
+
+/// The content of a cell.
+enum Slot<T> {
+    Empty,
+    Ready(T),
+    Busy,
+}
+
+/// A cell sharing the driver between a main loop and interrupt handlers,
+/// guarded by a critical section.
+///
+/// The cell can be created in a `static` and is filled at runtime:
+///
+/// ```ignore
+/// static SGP30: Sgp30Cell<I2c, Delay> = Sgp30Cell::new();
+///
+/// fn main() -> ! {
+///     SGP30.put(Sgp30::new(i2c, I2cAddress::DEFAULT, delay));
+///     SGP30.lock(|sgp| sgp.init()).unwrap().unwrap();
+///     loop {
+///         if let Ok(baseline) = SGP30.lock(|sgp| sgp.get_baseline()) {
+///             // ...
+///         }
+///     }
+/// }
+///
+/// #[interrupt]
+/// fn TIM2() {
+///     // Skips the measurement if the main loop is in the middle of a command
+///     if let Ok(measurement) = SGP30.lock(|sgp| sgp.measure()) {
+///         // ...
+///     }
+/// }
+/// ```
+///
+/// # Non-reentrancy
+///
+/// While a closure passed to [`lock()`](#method.lock) or
+/// [`with()`](#method.with) runs, the driver is taken out of the cell.
+/// Every other access in the meantime, from an interrupt handler or from
+/// within the closure itself, fails with
+/// [`CellError::Busy`](enum.CellError.html#variant.Busy) instead of
+/// touching the bus. A command that is in flight, i.e. written to the
+/// sensor but not yet read back, is therefore never interleaved with
+/// another command.
+///
+/// `lock()` only holds the critical section while taking the driver out and
+/// putting it back, so interrupts stay enabled during the command, which
+/// can take up to 200 ms (see [`Timings`](../struct.Timings.html)). Handlers
+/// must cope with `Busy`, e.g. by skipping a sample. `with()` runs the whole
+/// closure inside the critical section instead, which rules out `Busy` from
+/// interrupts but delays all of them for the duration of the command.
+///
+/// If the closure panics, the driver is lost and the cell stays busy.
+pub struct Sgp30Cell<I2C, D, C = SoftwareCrc, W = Timed, O = NoObserver> {
+    /// The shared driver.
+    slot: Guarded<Sgp30<I2C, D, C, W, O>>,
+}
+
+impl<I2C, D, C, W, O> Sgp30Cell<I2C, D, C, W, O> {
+    /// Create an empty cell.
+    pub const fn new() -> Self {
+        Sgp30Cell {
+            slot: Mutex::new(RefCell::new(Slot::Empty)),
+        }
+    }
+
+    /// Put a driver into the cell.
+    ///
+    /// Return the previous driver, if the cell was not empty. If the previous
+    /// driver is in use, it is dropped when it is returned to the cell.
+    pub fn put(&self, sgp: Sgp30<I2C, D, C, W, O>) -> Option<Sgp30<I2C, D, C, W, O>> {
+        critical_section::with(|cs| {
+            match self.slot.borrow(cs).replace(Slot::Ready(sgp)) {
+                Slot::Ready(previous) => Some(previous),
+                Slot::Empty | Slot::Busy => None,
+            }
+        })
+    }
+
+    /// Take the driver out of the cell, leaving it empty.
+    pub fn take(&self) -> Result<Sgp30<I2C, D, C, W, O>, CellError> {
+        critical_section::with(|cs| take(&mut self.slot.borrow(cs).borrow_mut(), Slot::Empty))
+    }
+
+    /// Run `f` with exclusive access to the driver. Interrupts are enabled
+    /// while `f` runs, see the [type documentation](#non-reentrancy).
+    pub fn lock<R, F>(&self, f: F) -> Result<R, CellError>
+    where
+        F: FnOnce(&mut Sgp30<I2C, D, C, W, O>) -> R,
+    {
+        let mut sgp = critical_section::with(|cs| {
+            take(&mut self.slot.borrow(cs).borrow_mut(), Slot::Busy)
+        })?;
+        let result = f(&mut sgp);
+        critical_section::with(|cs| give_back(&mut self.slot.borrow(cs).borrow_mut(), sgp));
+        Ok(result)
+    }
+
+    /// Run `f` with exclusive access to the driver inside a critical
+    /// section, see the [type documentation](#non-reentrancy).
+    pub fn with<R, F>(&self, f: F) -> Result<R, CellError>
+    where
+        F: FnOnce(&mut Sgp30<I2C, D, C, W, O>) -> R,
+    {
+        critical_section::with(|cs| {
+            let mut sgp = take(&mut self.slot.borrow(cs).borrow_mut(), Slot::Busy)?;
+            let result = f(&mut sgp);
+            give_back(&mut self.slot.borrow(cs).borrow_mut(), sgp);
+            Ok(result)
+        })
+    }
+}
+
+impl<I2C, D, C, W, O> Default for Sgp30Cell<I2C, D, C, W, O> {
+    fn default() -> Self {
+        Sgp30Cell::new()
+    }
+}
+
+/// Take the driver out of `slot`, replacing it with `replacement`.
+fn take<T>(slot: &mut Slot<T>, replacement: Slot<T>) -> Result<T, CellError> {
+    match *slot {
+        Slot::Empty => Err(CellError::Empty),
+        Slot::Busy => Err(CellError::Busy),
+        Slot::Ready(_) => match mem::replace(slot, replacement) {
+            Slot::Ready(sgp) => Ok(sgp),
+            Slot::Empty | Slot::Busy => unreachable!(),
+        },
+    }
+}
+
+/// Return the driver to `slot`, unless another driver was put into the cell
+/// in the meantime.
+fn give_back<T>(slot: &mut Slot<T>, sgp: T) {
+    if let Slot::Busy = *slot {
+        *slot = Slot::Ready(sgp);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as mock;
+
+    use super::*;
+    use types::Measurement;
+    use I2cAddress;
+
+    #[test]
+    fn lock_and_with() {
+        let cell = Sgp30Cell::new();
+        assert_eq!(cell.lock(|_| ()), Err(CellError::Empty));
+
+        let mut dev = mock::I2cMock::new();
+        dev.set_read_data(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
+        assert!(cell.put(Sgp30::new(dev, I2cAddress::DEFAULT, mock::DelayMockNoop)).is_none());
+        cell.with(|sgp| sgp.init()).unwrap().unwrap();
+
+        // Nested access, like an interrupt during a command, is rejected
+        let measurement = cell.lock(|sgp| {
+            assert_eq!(cell.with(|_| ()), Err(CellError::Busy));
+            assert_eq!(cell.take().err(), Some(CellError::Busy));
+            sgp.measure().unwrap()
+        });
+        assert_eq!(measurement, Ok(Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 }));
+
+        let sgp = cell.take().unwrap();
+        assert_eq!(sgp.stats().measurements, 1);
+        assert_eq!(cell.with(|_| ()), Err(CellError::Empty));
+    }
+}
@@ -0,0 +1,22 @@
+//! Shared access to a sensor.
+//!
+//! The driver methods take `&mut self`, so a sensor used from several
+//! contexts needs a wrapper that serializes the access:
+//!
+//! - [`SharedSgp30`](struct.SharedSgp30.html): A cloneable
+//!   `Arc<Mutex<_>>` handle for sharing the driver between threads
+//!   (requires the `std` feature)
+//! - [`Sgp30Cell`](struct.Sgp30Cell.html): A `static`-friendly cell guarded
+//!   by a critical section, for sharing the driver between a main loop and
+//!   interrupt handlers on bare metal (requires the `critical-section`
+//!   feature)
+
+#[cfg(feature = "critical-section")]
+mod cell;
+#[cfg(feature = "std")]
+mod mutex;
+
+#[cfg(feature = "critical-section")]
+pub use self::cell::{CellError, Sgp30Cell};
+#[cfg(feature = "std")]
+pub use self::mutex::SharedSgp30;
@@ -0,0 +1,195 @@
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use hal::blocking::delay::DelayUs;
+use hal::blocking::i2c::{Read, Write, WriteRead};
+
+use crc::{Crc8, SoftwareCrc};
+use diagnostics::DriverStats;
+use health::Health;
+use observer::{NoObserver, Observer};
+use timings::{DelayStrategy, Timed};
+use types::{Baseline, FeatureSet, Humidity, Measurement, RawSignals};
+use {Error, Sgp30};
+
+/// A driver behind a reference-counted lock.
+type Shared<T> = Arc<Mutex<T>>;
+
+/// A driver that can be cloned and shared between threads.
+///
+/// Multi-threaded services, e.g. a sampler thread next to an HTTP endpoint
+/// serving the latest baseline, need shared access to the driver. A
+/// `SharedSgp30` keeps the driver behind an `Arc<Mutex<_>>` and can be
+/// cloned into every thread:
+///
+/// ```no_run
+/// # extern crate linux_embedded_hal as hal;
+/// # extern crate sgp30;
+/// use std::thread;
+/// use std::time::Duration;
+/// use sgp30::{I2cAddress, Sgp30};
+/// use sgp30::shared::SharedSgp30;
+///
+/// # fn main() {
+/// # let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+/// let sgp = SharedSgp30::new(Sgp30::new(dev, I2cAddress::DEFAULT, hal::Delay));
+/// sgp.init().unwrap();
+///
+/// let sampler = sgp.clone();
+/// thread::spawn(move || loop {
+///     println!("{}", sampler.measure().unwrap());
+///     thread::sleep(Duration::from_secs(1));
+/// });
+///
+/// // Elsewhere, e.g. in a request handler
+/// println!("{}", sgp.get_baseline().unwrap());
+/// # }
+/// ```
+///
+/// Every method locks the driver for the duration of one command, including
+/// the wait for the sensor, so commands from different threads never
+/// interleave on the bus. For sequences that must not be interrupted by
+/// other threads, or for the configuration methods, hold the lock returned
+/// by [`lock()`](#method.lock).
+pub struct SharedSgp30<I2C, D, C = SoftwareCrc, W = Timed, O = NoObserver> {
+    /// The shared driver.
+    inner: Shared<Sgp30<I2C, D, C, W, O>>,
+}
+
+impl<I2C, D, C, W, O> Clone for SharedSgp30<I2C, D, C, W, O> {
+    fn clone(&self) -> Self {
+        SharedSgp30 {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<I2C, D, C, W, O> SharedSgp30<I2C, D, C, W, O> {
+    /// Wrap a driver instance.
+    pub fn new(sgp: Sgp30<I2C, D, C, W, O>) -> Self {
+        SharedSgp30 {
+            inner: Arc::new(Mutex::new(sgp)),
+        }
+    }
+
+    /// Lock the driver for exclusive access.
+    ///
+    /// If another thread panicked while holding the lock, the driver is
+    /// returned anyway: A panic can at most interrupt a command, which the
+    /// sensor tolerates like any other failed transfer.
+    pub fn lock(&self) -> MutexGuard<'_, Sgp30<I2C, D, C, W, O>> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Return the driver if this is the last handle to it, or the handle
+    /// otherwise.
+    pub fn into_inner(self) -> Result<Sgp30<I2C, D, C, W, O>, Self> {
+        match Arc::try_unwrap(self.inner) {
+            Ok(mutex) => Ok(mutex.into_inner().unwrap_or_else(PoisonError::into_inner)),
+            Err(inner) => Err(SharedSgp30 { inner }),
+        }
+    }
+}
+
+impl<I2C, D, E, C, W, O> SharedSgp30<I2C, D, C, W, O>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+    C: Crc8,
+    W: DelayStrategy<D>,
+    O: Observer,
+{
+    /// See [`Sgp30::serial()`](../struct.Sgp30.html#method.serial).
+    pub fn serial(&self) -> Result<[u8; 6], Error<E>> {
+        self.lock().serial()
+    }
+
+    /// See [`Sgp30::selftest()`](../struct.Sgp30.html#method.selftest).
+    pub fn selftest(&self) -> Result<bool, Error<E>> {
+        self.lock().selftest()
+    }
+
+    /// See [`Sgp30::init()`](../struct.Sgp30.html#method.init).
+    pub fn init(&self) -> Result<(), Error<E>> {
+        self.lock().init()
+    }
+
+    /// See [`Sgp30::force_init()`](../struct.Sgp30.html#method.force_init).
+    pub fn force_init(&self) -> Result<(), Error<E>> {
+        self.lock().force_init()
+    }
+
+    /// See [`Sgp30::soft_reset()`](../struct.Sgp30.html#method.soft_reset).
+    pub fn soft_reset(&self) -> Result<(), Error<E>> {
+        self.lock().soft_reset()
+    }
+
+    /// See [`Sgp30::measure()`](../struct.Sgp30.html#method.measure).
+    pub fn measure(&self) -> Result<Measurement, Error<E>> {
+        self.lock().measure()
+    }
+
+    /// See [`Sgp30::measure_raw_signals()`](../struct.Sgp30.html#method.measure_raw_signals).
+    pub fn measure_raw_signals(&self) -> Result<RawSignals, Error<E>> {
+        self.lock().measure_raw_signals()
+    }
+
+    /// See [`Sgp30::get_baseline()`](../struct.Sgp30.html#method.get_baseline).
+    pub fn get_baseline(&self) -> Result<Baseline, Error<E>> {
+        self.lock().get_baseline()
+    }
+
+    /// See [`Sgp30::set_baseline()`](../struct.Sgp30.html#method.set_baseline).
+    pub fn set_baseline(&self, baseline: &Baseline) -> Result<(), Error<E>> {
+        self.lock().set_baseline(baseline)
+    }
+
+    /// See [`Sgp30::set_humidity()`](../struct.Sgp30.html#method.set_humidity).
+    pub fn set_humidity(&self, humidity: Option<&Humidity>) -> Result<(), Error<E>> {
+        self.lock().set_humidity(humidity)
+    }
+
+    /// See [`Sgp30::get_feature_set()`](../struct.Sgp30.html#method.get_feature_set).
+    pub fn get_feature_set(&self) -> Result<FeatureSet, Error<E>> {
+        self.lock().get_feature_set()
+    }
+
+    /// See [`Sgp30::stats()`](../struct.Sgp30.html#method.stats).
+    pub fn stats(&self) -> DriverStats {
+        self.lock().stats()
+    }
+
+    /// See [`Sgp30::health()`](../struct.Sgp30.html#method.health).
+    pub fn health(&self) -> Health {
+        self.lock().health()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as mock;
+
+    use std::thread;
+
+    use super::*;
+    use I2cAddress;
+
+    #[test]
+    fn shared_between_threads() {
+        let mut dev = mock::I2cMock::new();
+        dev.set_read_data(&[
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+        ]);
+        let sgp = SharedSgp30::new(Sgp30::new(dev, I2cAddress::DEFAULT, mock::DelayMockNoop));
+        sgp.init().unwrap();
+
+        let sampler = sgp.clone();
+        let measurement = thread::spawn(move || sampler.measure().unwrap()).join().unwrap();
+        assert_eq!(measurement, Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 });
+        assert_eq!(sgp.get_baseline().unwrap(), Baseline { co2eq: 0x1234, tvoc: 0xD402 });
+        assert_eq!(sgp.stats().measurements, 1);
+
+        let sgp = sgp.into_inner().ok().unwrap();
+        assert_eq!(sgp.destroy().get_last_address(), Some(0x58));
+    }
+}
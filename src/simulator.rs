@@ -0,0 +1,851 @@
+//! A simulated SGP30.
+//!
+//! [`SimulatedSgp30`](struct.SimulatedSgp30.html) emulates the sensor on the
+//! I²C level: It implements the `embedded-hal` I²C traits and answers the
+//! commands sent by the driver like the real sensor would. Wrapped in an
+//! [`Sgp30`](../struct.Sgp30.html), it provides the full driver API, so
+//! application logic can be developed and unit-tested without hardware:
+//!
+//! ```
+//! use sgp30::{Measurement, Sgp30};
+//! use sgp30::simulator::{SimulatedSgp30, Trajectory};
+//!
+//! let trajectory = Trajectory::Ramp {
+//!     from: Measurement { co2eq_ppm: 400, tvoc_ppb: 0 },
+//!     to: Measurement { co2eq_ppm: 1400, tvoc_ppb: 500 },
+//!     duration_secs: 100,
+//! };
+//! let mut sgp = Sgp30::simulated(SimulatedSgp30::new(trajectory));
+//! sgp.init().unwrap();
+//!
+//! // Initialization phase
+//! for _ in 0..15 {
+//!     assert_eq!(sgp.measure().unwrap(), Measurement { co2eq_ppm: 400, tvoc_ppb: 0 });
+//! }
+//! let measurement = sgp.measure().unwrap();
+//! assert!(measurement.co2eq_ppm > 400);
+//! ```
+//!
+//! The simulator has no clock of its own. Every "measure air quality"
+//! command advances the simulated time by one second, which matches the
+//! 1 Hz cadence the sensor requires.
+//!
+//! The model of the sensor is simple:
+//!
+//! - The true CO₂eq and TVOC concentrations follow a
+//!   [`Trajectory`](enum.Trajectory.html).
+//! - For the first 15 measurements after initialization, the sensor returns
+//!   400 ppm CO₂eq and 0 ppb TVOC.
+//! - After initialization, the baseline starts at
+//!   [`INITIAL_BASELINE`](constant.INITIAL_BASELINE.html) and converges
+//!   towards the reference baseline of the sensor with a time constant of
+//!   one hour, so it has settled after the 12 hours stated in the datasheet.
+//!   Until then, the readings are offset by the difference. Restoring the
+//!   baseline with `set_baseline()` removes the offset right away.
+//! - The raw signals follow the concentrations according to the formula in
+//!   the datasheet, see
+//!   [`RawSignals::ethanol_ppm()`](../struct.RawSignals.html#method.ethanol_ppm).
+//!
+//! For long-running soak tests, a [`Drift`](struct.Drift.html) model adds a
+//! random walk of the reference baseline, a daily TVOC cycle and noise on the
+//! readings. It is driven by a seeded pseudo-random number generator, so a
+//! run can be reproduced exactly:
+//!
+//! ```
+//! use sgp30::{Measurement, Sgp30};
+//! use sgp30::simulator::{Drift, SimulatedSgp30, Trajectory};
+//!
+//! let run = |seed| {
+//!     let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement::default()))
+//!         .with_drift(Drift::new(seed));
+//!     let mut sgp = Sgp30::simulated(sensor);
+//!     sgp.init().unwrap();
+//!     (0..100).map(|_| sgp.measure().unwrap()).last()
+//! };
+//! assert_eq!(run(42), run(42));
+//! assert_ne!(run(42), run(43));
+//! ```
+//!
+//! To test error handling and recovery, [`Fault`](enum.Fault.html)s can be
+//! scheduled for specific transfers:
+//!
+//! ```
+//! use sgp30::{Error, Measurement, Sgp30};
+//! use sgp30::simulator::{Fault, SimulatedSgp30, SimulatorError, Trajectory};
+//!
+//! // Transfer 0 is the init command, transfers 1 and 2 the first measurement
+//! let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement::default()))
+//!     .with_fault(1, Fault::Nack)
+//!     .with_fault(4, Fault::CorruptCrc);
+//! let mut sgp = Sgp30::simulated(sensor);
+//! sgp.init().unwrap();
+//! assert!(matches!(sgp.measure(), Err(Error::I2c { source: SimulatorError::Nack, .. })));
+//! assert!(sgp.measure().is_ok());
+//! assert!(matches!(sgp.measure(), Err(Error::Crc { .. })));
+//! ```
+
+use byteorder::{BigEndian, ByteOrder};
+use core::fmt;
+use hal::blocking::i2c::{Read, Write, WriteRead};
+use heapless::Vec;
+
+#[allow(unused_imports)] // Required for no_std
+use num_traits::float::FloatCore;
+
+use plausibility::WARMUP_SAMPLES;
+use protocol::{self, Command};
+use types::{Baseline, Humidity, Measurement, RawSignals};
+use {I2cAddress, NoDelay, Sgp30};
+
+/// The I²C address of the simulated sensor.
+const ADDRESS: u8 = 0x58;
+
+/// The reference baseline the baseline of a simulated sensor converges to by
+/// default.
+pub const DEFAULT_BASELINE: Baseline = Baseline { co2eq: 0x8F00, tvoc: 0x9000 };
+
+/// The baseline after initialization, if no baseline is restored.
+pub const INITIAL_BASELINE: Baseline = Baseline { co2eq: 0x8000, tvoc: 0x8000 };
+
+/// The raw signals of a simulated sensor in clean air by default.
+pub const DEFAULT_SREF: RawSignals = RawSignals { h2: 13_600, ethanol: 18_500 };
+
+/// Time constant of the baseline convergence (in seconds).
+const BASELINE_TIME_CONSTANT_SECS: i64 = 60 * 60;
+
+/// Offset of the readings per baseline tick of difference to the reference
+/// baseline.
+const BASELINE_TICKS_PER_UNIT: i32 = 16;
+
+/// Fractional bits of the baseline state.
+const BASELINE_FRACTION_BITS: u32 = 16;
+
+/// H2 concentration (ppm) in clean air, as for the datasheet reference.
+const H2_CLEAN_AIR_PPM: f32 = 0.5;
+
+/// Ethanol concentration (ppm) in clean air, as for the datasheet reference.
+const ETHANOL_CLEAN_AIR_PPM: f32 = 0.4;
+
+/// Sensitivity of the raw signals (ticks per e-fold change of concentration).
+const RAW_SIGNAL_SENSITIVITY: f32 = 512.0;
+
+/// Largest CO₂eq and TVOC value reported by the sensor.
+const MAX_OUTPUT: i32 = 60_000;
+
+/// Maximum number of faults that can be scheduled.
+pub const MAX_FAULTS: usize = 16;
+
+/// Length of a day in seconds.
+const DAY_SECS: u32 = 24 * 60 * 60;
+
+/// Maximum step of the baseline random walk per second (in fractional
+/// baseline ticks) for a standard deviation of one tick after an hour: A
+/// uniform step in `[-a, a]` has a variance of `a² / 3`, so the walk has a
+/// standard deviation of `a * sqrt(3600 / 3)` after an hour.
+const WANDER_STEP_PER_TICK: u32 = 1892;
+
+/// The true air quality over time.
+#[derive(Debug, Copy, Clone)]
+pub enum Trajectory {
+    /// Constant concentrations.
+    Constant(Measurement),
+    /// A linear transition from `from` to `to` over `duration_secs`, then
+    /// constant at `to`.
+    Ramp {
+        /// Concentrations at the start
+        from: Measurement,
+        /// Concentrations at the end
+        to: Measurement,
+        /// Duration of the transition in seconds
+        duration_secs: u32,
+    },
+    /// Concentrations returned by a function of the time in seconds.
+    Custom(fn(u32) -> Measurement),
+}
+
+impl Trajectory {
+    /// Return the concentrations at `time_secs`.
+    pub fn at(&self, time_secs: u32) -> Measurement {
+        match *self {
+            Trajectory::Constant(measurement) => measurement,
+            Trajectory::Ramp { from, to, duration_secs } => {
+                if time_secs >= duration_secs {
+                    return to;
+                }
+                let interpolate = |from: u16, to: u16| {
+                    let delta = i64::from(to) - i64::from(from);
+                    (i64::from(from) + delta * i64::from(time_secs) / i64::from(duration_secs)) as u16
+                };
+                Measurement {
+                    co2eq_ppm: interpolate(from.co2eq_ppm, to.co2eq_ppm),
+                    tvoc_ppb: interpolate(from.tvoc_ppb, to.tvoc_ppb),
+                }
+            }
+            Trajectory::Custom(f) => f(time_secs),
+        }
+    }
+}
+
+/// A seeded model of slow drift and noise.
+///
+/// The simulated time starts at midnight.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Drift {
+    /// Seed of the pseudo-random number generator
+    pub seed: u64,
+    /// Standard deviation of the random walk of the reference baseline after
+    /// one hour, in baseline ticks
+    pub baseline_wander: u16,
+    /// Amplitude of the daily TVOC cycle (ppb), added to the trajectory
+    pub diurnal_tvoc_ppb: u16,
+    /// Time of day of the TVOC peak, in seconds after midnight
+    pub diurnal_peak_secs: u32,
+    /// Maximum noise on the CO₂eq (ppm) and TVOC (ppb) readings
+    pub noise: Measurement,
+}
+
+impl Drift {
+    /// Create a model with the given seed and moderate drift: A baseline
+    /// wander of 4 ticks per hour, 50 ppb TVOC peaking at 18:00 and noise of
+    /// up to 5 ppm CO₂eq and 3 ppb TVOC.
+    pub const fn new(seed: u64) -> Self {
+        Drift {
+            seed,
+            baseline_wander: 4,
+            diurnal_tvoc_ppb: 50,
+            diurnal_peak_secs: 18 * 60 * 60,
+            noise: Measurement { co2eq_ppm: 5, tvoc_ppb: 3 },
+        }
+    }
+
+    /// Return the TVOC of the daily cycle at `time_secs`.
+    fn diurnal_tvoc(&self, time_secs: u32) -> f32 {
+        let since_peak = (time_secs % DAY_SECS + DAY_SECS - self.diurnal_peak_secs % DAY_SECS) % DAY_SECS;
+        let phase = since_peak as f32 / DAY_SECS as f32;
+        f32::from(self.diurnal_tvoc_ppb) * (1.0 + libm::cosf(2.0 * core::f32::consts::PI * phase)) / 2.0
+    }
+}
+
+/// The SplitMix64 pseudo-random number generator.
+#[derive(Debug, Clone)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Return a uniformly distributed value in `[-amplitude, amplitude]`.
+    fn uniform(&mut self, amplitude: u32) -> i64 {
+        let range = 2 * u64::from(amplitude) + 1;
+        (self.next_u64() % range) as i64 - i64::from(amplitude)
+    }
+}
+
+/// A fault injected into a transfer.
+///
+/// Every I²C write or read is a transfer. Commands with a response, like
+/// measurements, consist of two transfers; the others of one. Transfers are
+/// counted from 0, starting with the creation of the simulator, so retries
+/// and re-reads of the driver count as well.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Fault {
+    /// The transfer is not acknowledged. A command write is not executed;
+    /// a pending response is lost.
+    Nack,
+    /// The checksum of the first word of the next response that is read is
+    /// wrong.
+    CorruptCrc,
+    /// The sensor resets spontaneously before the transfer, e.g. due to a
+    /// brown-out, and loses its initialization, baseline and humidity.
+    Reset,
+    /// The air quality output is stuck at the last value for the given
+    /// number of measurements.
+    Stuck(u32),
+}
+
+/// Errors of the simulated bus.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum SimulatorError {
+    /// The simulated sensor did not acknowledge the transfer, e.g. because
+    /// of a wrong address, an unknown command, a data CRC error or a read
+    /// without a pending response.
+    Nack,
+}
+
+impl fmt::Display for SimulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SimulatorError::Nack => write!(f, "NACK from simulated sensor"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for SimulatorError {}
+
+/// A software SGP30 on a simulated I²C bus.
+#[derive(Debug, Clone)]
+pub struct SimulatedSgp30 {
+    /// The true air quality.
+    trajectory: Trajectory,
+    /// The serial number.
+    serial: [u8; 6],
+    /// The baseline the sensor converges to, with `BASELINE_FRACTION_BITS`
+    /// fractional bits.
+    reference: [u32; 2],
+    /// The raw signals in clean air.
+    sref: RawSignals,
+    /// Simulated time in seconds.
+    time_secs: u32,
+    /// Whether the air quality measurement was initialized.
+    initialized: bool,
+    /// Number of air quality measurements since initialization.
+    samples_since_init: u32,
+    /// The current CO₂eq and TVOC baseline, with `BASELINE_FRACTION_BITS`
+    /// fractional bits.
+    baseline: [u32; 2],
+    /// The raw humidity word.
+    humidity: u16,
+    /// The response to the last command, if it was not read yet.
+    response: Vec<u8, 9>,
+    /// Number of transfers so far.
+    transfers: u32,
+    /// Scheduled faults with the transfer they apply to.
+    faults: Vec<(u32, Fault), MAX_FAULTS>,
+    /// Whether the checksum of the next response is corrupted.
+    corrupt_crc: bool,
+    /// Number of measurements that repeat the last output.
+    stuck: u32,
+    /// The last air quality output.
+    last_output: Measurement,
+    /// The drift model and its random number generator.
+    drift: Option<(Drift, Rng)>,
+}
+
+impl SimulatedSgp30 {
+    /// Create a powered-up, uninitialized sensor measuring `trajectory`.
+    pub fn new(trajectory: Trajectory) -> Self {
+        SimulatedSgp30 {
+            trajectory,
+            serial: [0x00, 0x00, 0x01, 0x23, 0x45, 0x67],
+            reference: baseline_state(&DEFAULT_BASELINE),
+            sref: DEFAULT_SREF,
+            time_secs: 0,
+            initialized: false,
+            samples_since_init: 0,
+            baseline: baseline_state(&INITIAL_BASELINE),
+            humidity: u16::from_be_bytes(Humidity::DEFAULT.as_bytes()),
+            response: Vec::new(),
+            transfers: 0,
+            faults: Vec::new(),
+            corrupt_crc: false,
+            stuck: 0,
+            last_output: Measurement { co2eq_ppm: 400, tvoc_ppb: 0 },
+            drift: None,
+        }
+    }
+
+    /// Use a different serial number.
+    pub fn with_serial(mut self, serial: [u8; 6]) -> Self {
+        self.serial = serial;
+        self
+    }
+
+    /// Use a different reference baseline.
+    pub fn with_reference_baseline(mut self, baseline: Baseline) -> Self {
+        self.reference = baseline_state(&baseline);
+        self
+    }
+
+    /// Use different raw signals in clean air.
+    pub fn with_sref(mut self, sref: RawSignals) -> Self {
+        self.sref = sref;
+        self
+    }
+
+    /// Add slow drift and noise.
+    pub fn with_drift(mut self, drift: Drift) -> Self {
+        self.drift = Some((drift, Rng(drift.seed)));
+        self
+    }
+
+    /// Schedule a fault for the transfer with index `transfer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than [`MAX_FAULTS`](constant.MAX_FAULTS.html) faults
+    /// are scheduled.
+    pub fn with_fault(mut self, transfer: u32, fault: Fault) -> Self {
+        assert!(self.faults.push((transfer, fault)).is_ok(), "too many faults scheduled");
+        self
+    }
+
+    /// Return the number of transfers so far, i.e. the index of the next
+    /// transfer.
+    pub fn transfers(&self) -> u32 {
+        self.transfers
+    }
+
+    /// Return the simulated time in seconds.
+    pub fn time_secs(&self) -> u32 {
+        self.time_secs
+    }
+
+    /// Whether the air quality measurement was initialized.
+    pub fn is_initialized(&self) -> bool {
+        self.initialized
+    }
+
+    /// Return the current baseline.
+    pub fn baseline(&self) -> Baseline {
+        Baseline {
+            co2eq: (self.baseline[0] >> BASELINE_FRACTION_BITS) as u16,
+            tvoc: (self.baseline[1] >> BASELINE_FRACTION_BITS) as u16,
+        }
+    }
+
+    /// Return the baseline the sensor currently converges to.
+    pub fn reference_baseline(&self) -> Baseline {
+        Baseline {
+            co2eq: (self.reference[0] >> BASELINE_FRACTION_BITS) as u16,
+            tvoc: (self.reference[1] >> BASELINE_FRACTION_BITS) as u16,
+        }
+    }
+
+    /// Return the humidity used for the compensation, or `None` if the
+    /// compensation was disabled.
+    pub fn humidity(&self) -> Option<Humidity> {
+        let [integer, fractional] = self.humidity.to_be_bytes();
+        Humidity::new(integer, fractional).ok()
+    }
+
+    /// Return the true concentrations at the current time, including the
+    /// daily TVOC cycle of the drift model.
+    pub fn true_measurement(&self) -> Measurement {
+        let truth = self.trajectory.at(self.time_secs);
+        match self.drift {
+            Some((ref drift, _)) => Measurement {
+                tvoc_ppb: (f32::from(truth.tvoc_ppb) + drift.diurnal_tvoc(self.time_secs))
+                    .round()
+                    .min(f32::from(u16::MAX)) as u16,
+                ..truth
+            },
+            None => truth,
+        }
+    }
+
+    /// Count a transfer and apply the faults scheduled for it. Return
+    /// whether the transfer is not acknowledged.
+    fn begin_transfer(&mut self) -> bool {
+        let transfer = self.transfers;
+        self.transfers = self.transfers.wrapping_add(1);
+        let mut nack = false;
+        let mut i = 0;
+        while i < self.faults.len() {
+            if self.faults[i].0 != transfer {
+                i += 1;
+                continue;
+            }
+            match self.faults.swap_remove(i).1 {
+                Fault::Nack => nack = true,
+                Fault::CorruptCrc => self.corrupt_crc = true,
+                Fault::Reset => self.reset(),
+                Fault::Stuck(measurements) => self.stuck = measurements,
+            }
+        }
+        nack
+    }
+
+    /// Handle a write to the sensor.
+    fn handle_write(&mut self, address: u8, bytes: &[u8]) -> Result<(), SimulatorError> {
+        if self.begin_transfer() {
+            return Err(SimulatorError::Nack);
+        }
+        let [reset_address, reset_byte] = Command::SoftReset.as_bytes();
+        if address == reset_address && bytes == [reset_byte] {
+            self.reset();
+            return Ok(());
+        }
+        if address != ADDRESS || bytes.len() < 2 || !words_valid(&bytes[2..]) {
+            return Err(SimulatorError::Nack);
+        }
+        let command = Command::from_bytes([bytes[0], bytes[1]]).ok_or(SimulatorError::Nack)?;
+        if command.requires_init() && !self.initialized {
+            return Err(SimulatorError::Nack);
+        }
+        let data = &bytes[2..];
+        let word = |index: usize| BigEndian::read_u16(&data[index * 3..index * 3 + 2]);
+        let expected_words = match command {
+            Command::SetBaseline => 2,
+            Command::SetHumidity => 1,
+            _ => 0,
+        };
+        if data.len() != expected_words * 3 {
+            return Err(SimulatorError::Nack);
+        }
+        self.response.clear();
+        match command {
+            Command::GetSerial => {
+                let serial = self.serial;
+                self.respond(&[
+                    BigEndian::read_u16(&serial[0..2]),
+                    BigEndian::read_u16(&serial[2..4]),
+                    BigEndian::read_u16(&serial[4..6]),
+                ]);
+            }
+            Command::SelfTest => self.respond(&[0xD400]),
+            Command::InitAirQuality => {
+                self.initialized = true;
+                self.samples_since_init = 0;
+                self.baseline = baseline_state(&INITIAL_BASELINE);
+            }
+            Command::MeasureAirQuality => {
+                let measurement = self.measure();
+                self.respond(&[measurement.co2eq_ppm, measurement.tvoc_ppb]);
+            }
+            Command::MeasureRawSignals => {
+                let signals = self.raw_signals();
+                self.respond(&[signals.h2, signals.ethanol]);
+            }
+            Command::GetBaseline => {
+                let baseline = self.baseline();
+                self.respond(&[baseline.co2eq, baseline.tvoc]);
+            }
+            Command::SetBaseline => {
+                self.baseline = baseline_state(&Baseline { co2eq: word(0), tvoc: word(1) });
+            }
+            Command::SetHumidity => self.humidity = word(0),
+            Command::GetFeatureSet => self.respond(&[0x0022]),
+            Command::SoftReset => return Err(SimulatorError::Nack),
+        }
+        Ok(())
+    }
+
+    /// Handle a read from the sensor.
+    fn handle_read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), SimulatorError> {
+        if self.begin_transfer() {
+            self.response.clear();
+            return Err(SimulatorError::Nack);
+        }
+        if address != ADDRESS || self.response.is_empty() || buffer.len() > self.response.len() {
+            return Err(SimulatorError::Nack);
+        }
+        buffer.copy_from_slice(&self.response[..buffer.len()]);
+        self.response.clear();
+        if self.corrupt_crc && buffer.len() >= 3 {
+            buffer[2] ^= 0x01;
+            self.corrupt_crc = false;
+        }
+        Ok(())
+    }
+
+    /// Queue a response of data words with their checksums.
+    fn respond(&mut self, words: &[u16]) {
+        for &word in words {
+            let bytes = word.to_be_bytes();
+            // The capacity fits the longest response (the serial number)
+            let _ = self.response.extend_from_slice(&bytes);
+            let _ = self.response.push(protocol::crc8(&bytes));
+        }
+    }
+
+    /// Reset the sensor to its power-up state.
+    fn reset(&mut self) {
+        self.initialized = false;
+        self.samples_since_init = 0;
+        self.baseline = baseline_state(&INITIAL_BASELINE);
+        self.humidity = u16::from_be_bytes(Humidity::DEFAULT.as_bytes());
+        self.response.clear();
+    }
+
+    /// Advance the time by one second and return the air quality output.
+    fn measure(&mut self) -> Measurement {
+        self.time_secs = self.time_secs.wrapping_add(1);
+        self.samples_since_init = self.samples_since_init.saturating_add(1);
+        let noise = self.drift();
+        self.learn_baseline();
+        if self.stuck > 0 {
+            self.stuck -= 1;
+            return self.last_output;
+        }
+        self.last_output = self.output(noise);
+        self.last_output
+    }
+
+    /// Advance the drift model by one second: Move the reference baseline
+    /// and return the noise of the next output.
+    fn drift(&mut self) -> [i32; 2] {
+        let (drift, rng) = match self.drift {
+            Some((ref drift, ref mut rng)) => (drift, rng),
+            None => return [0, 0],
+        };
+        let step = u32::from(drift.baseline_wander) * WANDER_STEP_PER_TICK;
+        for reference in &mut self.reference {
+            let max = i64::from(u16::MAX) << BASELINE_FRACTION_BITS;
+            *reference = (i64::from(*reference) + rng.uniform(step)).clamp(0, max) as u32;
+        }
+        [
+            rng.uniform(u32::from(drift.noise.co2eq_ppm)) as i32,
+            rng.uniform(u32::from(drift.noise.tvoc_ppb)) as i32,
+        ]
+    }
+
+    /// Return the air quality output for the current state.
+    fn output(&self, noise: [i32; 2]) -> Measurement {
+        if self.samples_since_init <= WARMUP_SAMPLES {
+            return Measurement { co2eq_ppm: 400, tvoc_ppb: 0 };
+        }
+        let truth = self.true_measurement();
+        let baseline = self.baseline();
+        let reference = self.reference_baseline();
+        let offset = |reference: u16, current: u16| {
+            (i32::from(reference) - i32::from(current)) / BASELINE_TICKS_PER_UNIT
+        };
+        let co2eq = i32::from(truth.co2eq_ppm) + offset(reference.co2eq, baseline.co2eq) + noise[0];
+        let tvoc = i32::from(truth.tvoc_ppb) + offset(reference.tvoc, baseline.tvoc) + noise[1];
+        Measurement {
+            co2eq_ppm: co2eq.clamp(400, MAX_OUTPUT) as u16,
+            tvoc_ppb: tvoc.clamp(0, MAX_OUTPUT) as u16,
+        }
+    }
+
+    /// Move the baseline one second towards the reference baseline.
+    fn learn_baseline(&mut self) {
+        for (current, &target) in self.baseline.iter_mut().zip(&self.reference) {
+            let delta = i64::from(target) - i64::from(*current);
+            *current = (i64::from(*current) + delta / BASELINE_TIME_CONSTANT_SECS) as u32;
+        }
+    }
+
+    /// Return the raw signals for the true concentrations.
+    fn raw_signals(&self) -> RawSignals {
+        let tvoc_ppm = f32::from(self.true_measurement().tvoc_ppb) / 1000.0;
+        let signal = |sref: u16, reference_ppm: f32, ppm: f32| {
+            let signal = f32::from(sref) - RAW_SIGNAL_SENSITIVITY * libm::logf(ppm / reference_ppm);
+            signal.round().clamp(0.0, f32::from(u16::MAX)) as u16
+        };
+        RawSignals {
+            h2: signal(self.sref.h2, H2_CLEAN_AIR_PPM, H2_CLEAN_AIR_PPM + tvoc_ppm / 2.0),
+            ethanol: signal(self.sref.ethanol, ETHANOL_CLEAN_AIR_PPM, ETHANOL_CLEAN_AIR_PPM + tvoc_ppm),
+        }
+    }
+}
+
+/// Convert a baseline into the internal fixed-point representation.
+fn baseline_state(baseline: &Baseline) -> [u32; 2] {
+    [
+        u32::from(baseline.co2eq) << BASELINE_FRACTION_BITS,
+        u32::from(baseline.tvoc) << BASELINE_FRACTION_BITS,
+    ]
+}
+
+/// Whether `data` consists of whole data words with valid checksums.
+fn words_valid(data: &[u8]) -> bool {
+    data.len().is_multiple_of(3) && data.chunks(3).all(|chunk| protocol::crc8(&chunk[0..2]) == chunk[2])
+}
+
+impl Write for SimulatedSgp30 {
+    type Error = SimulatorError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), SimulatorError> {
+        self.handle_write(address, bytes)
+    }
+}
+
+impl Read for SimulatedSgp30 {
+    type Error = SimulatorError;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), SimulatorError> {
+        self.handle_read(address, buffer)
+    }
+}
+
+impl WriteRead for SimulatedSgp30 {
+    type Error = SimulatorError;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), SimulatorError> {
+        self.handle_write(address, bytes)?;
+        self.handle_read(address, buffer)
+    }
+}
+
+impl Sgp30<SimulatedSgp30, NoDelay> {
+    /// Create a driver for a simulated sensor. The driver does not wait for
+    /// the simulated sensor to execute the commands.
+    pub fn simulated(sensor: SimulatedSgp30) -> Self {
+        Sgp30::new(sensor, I2cAddress::DEFAULT, NoDelay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Error;
+
+    const CLEAN_AIR: Measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 0 };
+
+    #[test]
+    fn trajectory() {
+        let ramp = Trajectory::Ramp {
+            from: CLEAN_AIR,
+            to: Measurement { co2eq_ppm: 1400, tvoc_ppb: 100 },
+            duration_secs: 10,
+        };
+        assert_eq!(ramp.at(0), CLEAN_AIR);
+        assert_eq!(ramp.at(5), Measurement { co2eq_ppm: 900, tvoc_ppb: 50 });
+        assert_eq!(ramp.at(20), Measurement { co2eq_ppm: 1400, tvoc_ppb: 100 });
+        let custom = Trajectory::Custom(|t| Measurement { co2eq_ppm: 400 + t as u16, tvoc_ppb: 0 });
+        assert_eq!(custom.at(7).co2eq_ppm, 407);
+    }
+
+    #[test]
+    fn driver_api() {
+        let polluted = Measurement { co2eq_ppm: 1000, tvoc_ppb: 200 };
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(polluted)).with_serial([1, 2, 3, 4, 5, 6]);
+        let mut sgp = Sgp30::simulated(sensor);
+        assert_eq!(sgp.serial().unwrap(), [1, 2, 3, 4, 5, 6]);
+        assert!(sgp.selftest().unwrap());
+        assert_eq!(sgp.get_feature_set().unwrap().product_version, 0x22);
+
+        sgp.init().unwrap();
+        for _ in 0..WARMUP_SAMPLES {
+            assert_eq!(sgp.measure().unwrap(), CLEAN_AIR);
+        }
+
+        // Not converged yet: Offset by the baseline difference
+        assert_eq!(sgp.get_baseline().unwrap(), Baseline { co2eq: 0x800F, tvoc: 0x8011 });
+        let offset = sgp.measure().unwrap();
+        assert_eq!(offset, Measurement { co2eq_ppm: 1238, tvoc_ppb: 454 });
+
+        // Restored baseline
+        sgp.set_baseline(&DEFAULT_BASELINE).unwrap();
+        assert_eq!(sgp.measure().unwrap(), polluted);
+
+        let raw = sgp.measure_raw_signals().unwrap();
+        assert!((raw.ethanol_ppm(&DEFAULT_SREF) - 0.6).abs() < 0.01);
+        assert!((raw.h2_ppm(&DEFAULT_SREF) - 0.6).abs() < 0.01);
+
+        let humidity = Humidity::from_f32(8.5).unwrap();
+        sgp.set_humidity(Some(&humidity)).unwrap();
+        sgp.soft_reset().unwrap();
+        assert!(matches!(sgp.measure(), Err(Error::NotInitialized)));
+
+        let sensor = sgp.destroy();
+        assert_eq!(sensor.time_secs(), WARMUP_SAMPLES + 2);
+        assert!(!sensor.is_initialized());
+        assert_eq!(sensor.humidity(), Some(Humidity::DEFAULT));
+    }
+
+    #[test]
+    fn baseline_converges() {
+        let mut sgp = Sgp30::simulated(SimulatedSgp30::new(Trajectory::Constant(CLEAN_AIR)));
+        sgp.init().unwrap();
+        for _ in 0..12 * 60 * 60 {
+            sgp.measure().unwrap();
+        }
+        let baseline = sgp.get_baseline().unwrap();
+        assert!(DEFAULT_BASELINE.co2eq - baseline.co2eq < 16);
+        assert!(DEFAULT_BASELINE.tvoc - baseline.tvoc < 16);
+    }
+
+    #[test]
+    fn faults() {
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(CLEAN_AIR))
+            .with_fault(2, Fault::Nack)
+            .with_fault(4, Fault::CorruptCrc)
+            .with_fault(7, Fault::Reset);
+        let mut sgp = Sgp30::simulated(sensor);
+        sgp.init().unwrap();
+        assert!(matches!(
+            sgp.get_baseline(),
+            Err(Error::I2c { command: Command::GetBaseline, source: SimulatorError::Nack })
+        ));
+        assert!(matches!(sgp.get_baseline(), Err(Error::Crc { command: Command::GetBaseline, .. })));
+        assert_eq!(sgp.get_baseline().unwrap(), INITIAL_BASELINE);
+
+        // The reset lost the initialization
+        assert!(matches!(sgp.measure(), Err(Error::I2c { source: SimulatorError::Nack, .. })));
+        let sensor = sgp.destroy();
+        assert!(!sensor.is_initialized());
+        assert_eq!(sensor.transfers(), 8);
+    }
+
+    #[test]
+    fn drift() {
+        let drift = Drift {
+            seed: 7,
+            baseline_wander: 16,
+            diurnal_tvoc_ppb: 100,
+            diurnal_peak_secs: 6 * 60 * 60,
+            noise: Measurement { co2eq_ppm: 10, tvoc_ppb: 0 },
+        };
+        assert_eq!(drift.diurnal_tvoc(6 * 60 * 60), 100.0);
+        assert!(drift.diurnal_tvoc(18 * 60 * 60) < 0.001);
+        assert!((drift.diurnal_tvoc(0) - 50.0).abs() < 0.001);
+
+        let run = |drift: Drift| {
+            let sensor = SimulatedSgp30::new(Trajectory::Constant(CLEAN_AIR)).with_drift(drift);
+            let mut sgp = Sgp30::simulated(sensor);
+            sgp.init().unwrap();
+            sgp.set_baseline(&DEFAULT_BASELINE).unwrap();
+            let mut outputs = [CLEAN_AIR; 60];
+            for output in outputs.iter_mut() {
+                *output = sgp.measure().unwrap();
+            }
+            (outputs, sgp.destroy())
+        };
+        let (outputs, sensor) = run(drift);
+        assert_eq!(run(drift).0, outputs);
+        assert_ne!(run(Drift { seed: 8, ..drift }).0, outputs);
+
+        // Noise around the clean air value after the initialization phase
+        let noisy = &outputs[WARMUP_SAMPLES as usize..];
+        assert!(noisy.iter().all(|m| m.co2eq_ppm >= 400 && m.co2eq_ppm <= 411));
+        assert!(noisy.iter().any(|m| m.co2eq_ppm != noisy[0].co2eq_ppm));
+        // The TVOC follows the daily cycle, which is at half its amplitude
+        // at midnight
+        assert!(noisy.iter().all(|m| m.tvoc_ppb >= 50 && m.tvoc_ppb <= 52));
+
+        // The reference baseline wanders slowly, and the baseline follows
+        let reference = sensor.reference_baseline();
+        assert_ne!(reference, DEFAULT_BASELINE);
+        assert!(reference.co2eq.abs_diff(DEFAULT_BASELINE.co2eq) < 16);
+        assert!(reference.tvoc.abs_diff(DEFAULT_BASELINE.tvoc) < 16);
+    }
+
+    #[test]
+    fn stuck() {
+        let trajectory = Trajectory::Custom(|t| Measurement { co2eq_ppm: 400 + t as u16, tvoc_ppb: 0 });
+        let mut sensor = SimulatedSgp30::new(trajectory).with_fault(0, Fault::Stuck(2));
+        sensor.initialized = true;
+        sensor.samples_since_init = WARMUP_SAMPLES;
+        sensor.baseline = baseline_state(&DEFAULT_BASELINE);
+        assert_eq!(sensor.measure().co2eq_ppm, 401);
+        let mut buf = [0; 6];
+        for &expected in &[401, 401, 404] {
+            sensor.write(ADDRESS, &[0x20, 0x08]).unwrap();
+            sensor.read(ADDRESS, &mut buf).unwrap();
+            assert_eq!(protocol::decode_word(&buf, 0), expected);
+        }
+    }
+
+    #[test]
+    fn protocol_errors() {
+        let mut sensor = SimulatedSgp30::new(Trajectory::Constant(CLEAN_AIR));
+        // Wrong address, unknown command, wrong data CRC, missing response
+        assert_eq!(sensor.write(0x59, &[0x36, 0x82]), Err(SimulatorError::Nack));
+        assert_eq!(sensor.write(ADDRESS, &[0x12, 0x34]), Err(SimulatorError::Nack));
+        sensor.write(ADDRESS, &[0x20, 0x03]).unwrap();
+        assert_eq!(sensor.write(ADDRESS, &[0x20, 0x61, 0x01, 0x00, 0x00]), Err(SimulatorError::Nack));
+        assert_eq!(sensor.read(ADDRESS, &mut [0; 3]), Err(SimulatorError::Nack));
+    }
+}
@@ -0,0 +1,85 @@
+use static_cell::StaticCell;
+
+use crc::SoftwareCrc;
+use observer::NoObserver;
+use timings::Timed;
+use Sgp30;
+
+/// Storage for a driver with `'static` lifetime.
+///
+/// Embassy tasks and interrupt handlers need their resources to be
+/// `'static`. A `StaticSgp30` can be declared as a `static` and initialized
+/// at runtime, once the bus and delay are available, without `unsafe`:
+///
+/// ```ignore
+/// static SGP30: StaticSgp30<I2c<'static, I2C1, Blocking>, Delay> = StaticSgp30::new();
+///
+/// #[embassy_executor::main]
+/// async fn main(spawner: Spawner) {
+///     let p = embassy_stm32::init(Default::default());
+///     let i2c = I2c::new_blocking(p.I2C1, p.PB8, p.PB9, Hertz(100_000), Default::default());
+///     let sgp = SGP30.init(Sgp30::new(i2c, I2cAddress::DEFAULT, Delay));
+///     spawner.spawn(sample(sgp)).unwrap();
+/// }
+///
+/// #[embassy_executor::task]
+/// async fn sample(sgp: &'static mut Sgp30<I2c<'static, I2C1, Blocking>, Delay>) {
+///     sgp.init().unwrap();
+///     // ...
+/// }
+/// ```
+///
+/// This type is only available with the `static-cell` feature.
+pub struct StaticSgp30<I2C, D, C = SoftwareCrc, W = Timed, O = NoObserver> {
+    /// The driver, once initialized.
+    cell: StaticCell<Sgp30<I2C, D, C, W, O>>,
+}
+
+impl<I2C, D, C, W, O> StaticSgp30<I2C, D, C, W, O> {
+    /// Create an uninitialized instance.
+    pub const fn new() -> Self {
+        StaticSgp30 {
+            cell: StaticCell::new(),
+        }
+    }
+
+    /// Store the driver and return a `'static` reference to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this is called more than once.
+    pub fn init(&'static self, sgp: Sgp30<I2C, D, C, W, O>) -> &'static mut Sgp30<I2C, D, C, W, O> {
+        self.cell.init(sgp)
+    }
+
+    /// Store the driver and return a `'static` reference to it, or `None` if
+    /// a driver was already stored.
+    ///
+    /// In this case, `sgp` is dropped.
+    pub fn try_init(&'static self, sgp: Sgp30<I2C, D, C, W, O>) -> Option<&'static mut Sgp30<I2C, D, C, W, O>> {
+        self.cell.try_init(sgp)
+    }
+}
+
+impl<I2C, D, C, W, O> Default for StaticSgp30<I2C, D, C, W, O> {
+    fn default() -> Self {
+        StaticSgp30::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as mock;
+
+    use super::*;
+    use I2cAddress;
+
+    static SGP30: StaticSgp30<mock::I2cMock, mock::DelayMockNoop> = StaticSgp30::new();
+
+    #[test]
+    fn init_once() {
+        let sgp: &'static mut Sgp30<_, _> = SGP30.init(Sgp30::new(mock::I2cMock::new(), I2cAddress::DEFAULT, mock::DelayMockNoop));
+        sgp.init().unwrap();
+        assert!(SGP30.try_init(Sgp30::new(mock::I2cMock::new(), I2cAddress::DEFAULT, mock::DelayMockNoop)).is_none());
+    }
+}
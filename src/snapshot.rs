@@ -0,0 +1,108 @@
+use types::Baseline;
+
+/// A point in time used to timestamp baseline snapshots.
+///
+/// The driver has no notion of wall-clock time, so the application decides
+/// what a timestamp is (e.g. seconds since epoch from an RTC, or seconds of
+/// uptime). Implementations are provided for `u32` and `u64` seconds.
+pub trait Timestamp {
+    /// Return the number of seconds elapsed between `earlier` and `self`, or
+    /// `None` if `earlier` lies in the future.
+    fn seconds_since(&self, earlier: &Self) -> Option<u64>;
+}
+
+impl Timestamp for u32 {
+    fn seconds_since(&self, earlier: &Self) -> Option<u64> {
+        self.checked_sub(*earlier).map(u64::from)
+    }
+}
+
+impl Timestamp for u64 {
+    fn seconds_since(&self, earlier: &Self) -> Option<u64> {
+        self.checked_sub(*earlier)
+    }
+}
+
+/// A baseline together with the time it was read from the sensor.
+///
+/// Sensirion recommends to discard stored baselines that are older than one
+/// week, since the baseline correction algorithm will then have drifted too
+/// far from the stored state. [`is_valid()`](#method.is_valid) implements
+/// this check and is used by
+/// [`Sgp30::restore_baseline()`](struct.Sgp30.html#method.restore_baseline).
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BaselineSnapshot<T> {
+    /// The baseline values
+    pub baseline: Baseline,
+    /// The time at which the baseline was read from the sensor
+    pub stored_at: T,
+}
+
+impl<T: Timestamp> BaselineSnapshot<T> {
+    /// The maximum age of a baseline that may still be restored (one week).
+    pub const MAX_AGE_SECS: u64 = 7 * 24 * 60 * 60;
+
+    /// Create a new snapshot.
+    pub fn new(baseline: Baseline, stored_at: T) -> Self {
+        BaselineSnapshot { baseline, stored_at }
+    }
+
+    /// Return the age of the snapshot in seconds, or `None` if the snapshot
+    /// was taken after `now`.
+    pub fn age(&self, now: &T) -> Option<u64> {
+        now.seconds_since(&self.stored_at)
+    }
+
+    /// Whether the snapshot is not older than one week at time `now`.
+    pub fn is_valid(&self, now: &T) -> bool {
+        self.is_valid_within(now, Self::MAX_AGE_SECS)
+    }
+
+    /// Whether the snapshot is not older than `max_age_secs` at time `now`.
+    ///
+    /// Snapshots with a timestamp in the future are considered invalid.
+    pub fn is_valid_within(&self, now: &T, max_age_secs: u64) -> bool {
+        match self.age(now) {
+            Some(age) => age <= max_age_secs,
+            None => false,
+        }
+    }
+
+    /// Return the baseline if the snapshot is still valid at time `now`.
+    pub fn valid_baseline(&self, now: &T) -> Option<&Baseline> {
+        if self.is_valid(now) {
+            Some(&self.baseline)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WEEK: u32 = 7 * 24 * 60 * 60;
+
+    #[test]
+    fn validity_window() {
+        let snapshot = BaselineSnapshot::new(Baseline { co2eq: 1, tvoc: 2 }, 1000u32);
+        assert_eq!(snapshot.age(&1500), Some(500));
+        assert!(snapshot.is_valid(&1000));
+        assert!(snapshot.is_valid(&(1000 + WEEK)));
+        assert!(!snapshot.is_valid(&(1001 + WEEK)));
+        assert!(snapshot.is_valid_within(&1010, 10));
+        assert!(!snapshot.is_valid_within(&1011, 10));
+    }
+
+    #[test]
+    fn future_timestamp_is_invalid() {
+        let snapshot = BaselineSnapshot::new(Baseline { co2eq: 1, tvoc: 2 }, 1000u64);
+        assert_eq!(snapshot.age(&999), None);
+        assert!(!snapshot.is_valid(&999));
+        assert_eq!(snapshot.valid_baseline(&999), None);
+        assert_eq!(snapshot.valid_baseline(&1000), Some(&Baseline { co2eq: 1, tvoc: 2 }));
+    }
+}
@@ -0,0 +1,145 @@
+//! Sampling into a `heapless::spsc` queue.
+//!
+//! On bare metal, the 1 Hz measurements are best taken in a timer interrupt,
+//! while the results are processed in the main loop. With the `spsc`
+//! feature, a [`Sampler`](struct.Sampler.html) owns the driver and the
+//! producer end of a queue provided by the caller: Every call to
+//! [`sample()`](struct.Sampler.html#method.sample) takes a measurement and
+//! enqueues the result, which the main loop dequeues from the consumer end.
+//!
+//! ```
+//! # extern crate embedded_hal_mock as hal;
+//! # extern crate heapless;
+//! # extern crate sgp30;
+//! use heapless::spsc::Queue;
+//! use sgp30::{I2cAddress, Sgp30};
+//! use sgp30::spsc::{Sample, Sampler};
+//!
+//! # fn main() {
+//! # let mut i2c = hal::I2cMock::new();
+//! # i2c.set_read_data(&[0x01, 0x90, 0x4C, 0x00, 0x00, 0x81]);
+//! # let (i2c, delay) = (i2c, hal::DelayMockNoop);
+//! let mut queue: Queue<Sample<_>, 4> = Queue::new();
+//! let (producer, mut consumer) = queue.split();
+//! let mut sampler = Sampler::new(Sgp30::new(i2c, I2cAddress::DEFAULT, delay), producer);
+//!
+//! // In the timer interrupt, every second
+//! sampler.sample();
+//!
+//! // In the main loop
+//! while let Some(sample) = consumer.dequeue() {
+//!     println!("{}", sample.unwrap());
+//! }
+//! # }
+//! ```
+//!
+//! The queue can hold `N - 1` samples. If the main loop falls behind and the
+//! queue is full, new samples are dropped and counted.
+
+use hal::blocking::delay::DelayUs;
+use hal::blocking::i2c::{Read, Write, WriteRead};
+use heapless::spsc::Producer;
+
+use crc::{Crc8, SoftwareCrc};
+use observer::{NoObserver, Observer};
+use timings::{DelayStrategy, Timed};
+use types::Measurement;
+use {Error, Sgp30};
+
+/// The result of a measurement, as enqueued by the sampler.
+pub type Sample<E> = Result<Measurement, Error<E>>;
+
+/// The driver and the producer returned by
+/// [`Sampler::destroy()`](struct.Sampler.html#method.destroy).
+pub type Parts<'q, I2C, D, E, const N: usize, C, W, O> = (Sgp30<I2C, D, C, W, O>, Producer<'q, Sample<E>, N>);
+
+/// Takes measurements and enqueues them into a `heapless::spsc` queue.
+pub struct Sampler<'q, I2C, D, E, const N: usize, C = SoftwareCrc, W = Timed, O = NoObserver> {
+    sgp: Sgp30<I2C, D, C, W, O>,
+    producer: Producer<'q, Sample<E>, N>,
+    dropped: u32,
+}
+
+impl<'q, I2C, D, E, const N: usize, C, W, O> Sampler<'q, I2C, D, E, N, C, W, O>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+    C: Crc8,
+    W: DelayStrategy<D>,
+    O: Observer,
+{
+    /// Create a sampler enqueuing into `producer`.
+    pub fn new(sgp: Sgp30<I2C, D, C, W, O>, producer: Producer<'q, Sample<E>, N>) -> Self {
+        Sampler { sgp, producer, dropped: 0 }
+    }
+
+    /// Take a measurement and enqueue the result. The air quality
+    /// measurement is initialized first unless it already is; an error
+    /// initializing it is enqueued as well.
+    ///
+    /// Call this every second, e.g. from a timer interrupt. Return whether
+    /// the sample was enqueued, i.e. the queue was not full.
+    pub fn sample(&mut self) -> bool {
+        let sample = if self.sgp.initialized {
+            self.sgp.measure()
+        } else {
+            self.sgp.init().and_then(|()| self.sgp.measure())
+        };
+        if self.producer.enqueue(sample).is_ok() {
+            return true;
+        }
+        self.dropped = self.dropped.wrapping_add(1);
+        log_event!(warn, "Sample queue full, dropped {} samples", self.dropped);
+        false
+    }
+
+    /// Return the number of samples dropped because the queue was full.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Access the driver, e.g. to save the baseline between samples.
+    pub fn sgp(&mut self) -> &mut Sgp30<I2C, D, C, W, O> {
+        &mut self.sgp
+    }
+
+    /// Destroy the sampler and return the driver and the producer.
+    pub fn destroy(self) -> Parts<'q, I2C, D, E, N, C, W, O> {
+        (self.sgp, self.producer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as hal;
+
+    use heapless::spsc::Queue;
+
+    use super::*;
+    use I2cAddress;
+
+    #[test]
+    fn sample() {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&[
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+            0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4,
+        ]);
+        let mut queue: Queue<Sample<_>, 3> = Queue::new();
+        let (producer, mut consumer) = queue.split();
+        let mut sampler = Sampler::new(Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop), producer);
+
+        assert!(sampler.sample());
+        assert!(sampler.sample());
+        // The queue holds two samples
+        assert!(!sampler.sample());
+        assert_eq!(sampler.dropped(), 1);
+        assert!(sampler.sgp().initialized);
+
+        let expected = Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 };
+        assert_eq!(consumer.dequeue().unwrap().unwrap(), expected);
+        assert_eq!(consumer.dequeue().unwrap().unwrap(), expected);
+        assert!(consumer.dequeue().is_none());
+    }
+}
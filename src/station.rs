@@ -0,0 +1,332 @@
+//! SGP30 + SHT3x/SHT4x pairing.
+//!
+//! Sensirion recommends pairing the SGP30 with one of its humidity and
+//! temperature sensors, e.g. on the SVM30 module. The
+//! [`IaqStation`](struct.IaqStation.html) reads the companion sensor on the
+//! same I²C bus as the SGP30, converts its reading to absolute humidity, feeds
+//! it to the on-chip humidity compensation and takes an air quality
+//! measurement:
+//!
+//! ```ignore
+//! use sgp30::station::{IaqStation, Sht3x};
+//!
+//! let mut station = IaqStation::new(sgp, Sht3x::new(0x44));
+//! station.init()?;
+//! loop {
+//!     let record = station.measure()?;
+//!     delay.delay_ms(1000 - 30);
+//! }
+//! ```
+//!
+//! Other humidity sensors can be paired by implementing the
+//! [`HumiditySensor`](trait.HumiditySensor.html) trait.
+//!
+//! This module is only available with the `station` feature.
+
+use core::fmt;
+
+use hal::blocking::delay::DelayUs;
+use hal::blocking::i2c::{Read, Write, WriteRead};
+
+use humidity::{self, RelativeHumidity, Temperature};
+use crc::{Crc8, SoftwareCrc};
+use observer::{NoObserver, Observer};
+use protocol::crc8;
+use timings::{DelayStrategy, Timed};
+use types::Measurement;
+use {Error, Sgp30};
+
+/// Default I²C address of the SHT3x and SHT4x.
+pub const DEFAULT_SHT_ADDRESS: u8 = 0x44;
+
+/// A temperature and relative humidity sensor sharing the bus with the SGP30.
+///
+/// The sensor does not own the bus: It is borrowed from the SGP30 driver for
+/// every reading.
+pub trait HumiditySensor<I2C, D> {
+    /// The error type returned by `read()`.
+    type Error;
+
+    /// Take a measurement and return temperature and relative humidity.
+    fn read(&mut self, i2c: &mut I2C, delay: &mut D) -> Result<(Temperature, RelativeHumidity), Self::Error>;
+}
+
+/// Errors of the SHT3x and SHT4x readers.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ShtError<E> {
+    /// I²C bus error
+    I2c(E),
+    /// CRC checksum validation failed
+    Crc,
+}
+
+impl<E: fmt::Debug> fmt::Display for ShtError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ShtError::I2c(ref e) => write!(f, "I²C bus error: {:?}", e),
+            ShtError::Crc => f.write_str("CRC checksum validation failed"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> ::std::error::Error for ShtError<E> {}
+
+/// Send a single-shot command, wait and read both words of the response.
+fn read_words<I2C, D, E>(
+    i2c: &mut I2C,
+    delay: &mut D,
+    address: u8,
+    command: &[u8],
+    duration_us: u16,
+) -> Result<(u16, u16), ShtError<E>>
+where
+    I2C: Read<Error = E> + Write<Error = E>,
+    D: DelayUs<u16>,
+{
+    i2c.write(address, command).map_err(ShtError::I2c)?;
+    delay.delay_us(duration_us);
+    let mut buf = [0; 6];
+    i2c.read(address, &mut buf).map_err(ShtError::I2c)?;
+    if crc8(&buf[0..2]) != buf[2] || crc8(&buf[3..5]) != buf[5] {
+        return Err(ShtError::Crc);
+    }
+    Ok((
+        u16::from_be_bytes([buf[0], buf[1]]),
+        u16::from_be_bytes([buf[3], buf[4]]),
+    ))
+}
+
+/// Convert a raw temperature value of an SHT3x/SHT4x.
+fn temperature(raw: u16) -> Temperature {
+    Temperature::from_milli_celsius((-45_000 + 175_000 * i64::from(raw) / 65_535) as i32)
+}
+
+/// SHT3x reader using single-shot measurements with high repeatability.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Sht3x {
+    address: u8,
+}
+
+impl Sht3x {
+    /// Create a new SHT3x reader for the sensor at the given address.
+    pub fn new(address: u8) -> Self {
+        Sht3x { address }
+    }
+}
+
+impl Default for Sht3x {
+    fn default() -> Self {
+        Sht3x::new(DEFAULT_SHT_ADDRESS)
+    }
+}
+
+impl<I2C, D, E> HumiditySensor<I2C, D> for Sht3x
+where
+    I2C: Read<Error = E> + Write<Error = E>,
+    D: DelayUs<u16>,
+{
+    type Error = ShtError<E>;
+
+    fn read(&mut self, i2c: &mut I2C, delay: &mut D) -> Result<(Temperature, RelativeHumidity), ShtError<E>> {
+        // Single shot, high repeatability, no clock stretching. Max duration
+        // according to datasheet (Table 4) is 15.5 ms.
+        let (t, rh) = read_words(i2c, delay, self.address, &[0x24, 0x00], 16_000)?;
+        let rh = 100_000 * u64::from(rh) / 65_535;
+        Ok((temperature(t), RelativeHumidity::from_milli_percent(rh as u32)))
+    }
+}
+
+/// SHT4x reader using measurements with high precision.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Sht4x {
+    address: u8,
+}
+
+impl Sht4x {
+    /// Create a new SHT4x reader for the sensor at the given address.
+    pub fn new(address: u8) -> Self {
+        Sht4x { address }
+    }
+}
+
+impl Default for Sht4x {
+    fn default() -> Self {
+        Sht4x::new(DEFAULT_SHT_ADDRESS)
+    }
+}
+
+impl<I2C, D, E> HumiditySensor<I2C, D> for Sht4x
+where
+    I2C: Read<Error = E> + Write<Error = E>,
+    D: DelayUs<u16>,
+{
+    type Error = ShtError<E>;
+
+    fn read(&mut self, i2c: &mut I2C, delay: &mut D) -> Result<(Temperature, RelativeHumidity), ShtError<E>> {
+        // High precision measurement. Max duration according to datasheet
+        // (Table 5) is 8.3 ms.
+        let (t, rh) = read_words(i2c, delay, self.address, &[0xFD], 9_000)?;
+        // The conversion can yield values slightly outside of 0..100 %RH,
+        // which must be cropped (datasheet 4.6).
+        let rh = (-6_000 + 125_000 * i64::from(rh) / 65_535).clamp(0, 100_000);
+        Ok((temperature(t), RelativeHumidity::from_milli_percent(rh as u32)))
+    }
+}
+
+/// A combined air quality and climate reading.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct IaqRecord {
+    /// The air quality measurement of the SGP30
+    pub measurement: Measurement,
+    /// The temperature measured by the companion sensor
+    pub temperature: Temperature,
+    /// The relative humidity measured by the companion sensor
+    pub humidity: RelativeHumidity,
+}
+
+/// Errors of the [`IaqStation`](struct.IaqStation.html).
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StationError<E, H> {
+    /// Communication with the SGP30 failed.
+    Sgp30(Error<E>),
+    /// Reading the humidity sensor failed.
+    Humidity(H),
+}
+
+impl<E, H> From<Error<E>> for StationError<E, H> {
+    fn from(e: Error<E>) -> Self {
+        StationError::Sgp30(e)
+    }
+}
+
+impl<E: fmt::Debug, H: fmt::Debug> fmt::Display for StationError<E, H> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StationError::Sgp30(ref e) => write!(f, "SGP30 error: {}", e),
+            StationError::Humidity(ref e) => write!(f, "Humidity sensor error: {:?}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug, H: fmt::Debug> ::std::error::Error for StationError<E, H> {}
+
+/// An SGP30 paired with a humidity sensor on the same bus.
+#[derive(Debug)]
+pub struct IaqStation<I2C, D, SHT, C = SoftwareCrc, W = Timed, O = NoObserver> {
+    sgp: Sgp30<I2C, D, C, W, O>,
+    sht: SHT,
+}
+
+impl<I2C, D, E, SHT, C, W, O> IaqStation<I2C, D, SHT, C, W, O>
+where
+    I2C: Read<Error = E> + Write<Error = E> + WriteRead<Error = E>,
+    D: DelayUs<u16>,
+    SHT: HumiditySensor<I2C, D>,
+    C: Crc8,
+    W: DelayStrategy<D>,
+    O: Observer,
+{
+    /// Create a new station from an SGP30 driver and a humidity sensor.
+    pub fn new(sgp: Sgp30<I2C, D, C, W, O>, sht: SHT) -> Self {
+        IaqStation { sgp, sht }
+    }
+
+    /// Destroy the station, return the SGP30 driver and the humidity sensor.
+    pub fn destroy(self) -> (Sgp30<I2C, D, C, W, O>, SHT) {
+        (self.sgp, self.sht)
+    }
+
+    /// Return a mutable reference to the SGP30 driver.
+    pub fn sgp(&mut self) -> &mut Sgp30<I2C, D, C, W, O> {
+        &mut self.sgp
+    }
+
+    /// Initialize the air quality measurement of the SGP30.
+    pub fn init(&mut self) -> Result<(), Error<E>> {
+        self.sgp.init()
+    }
+
+    /// Read the humidity sensor, update the humidity compensation and take an
+    /// air quality measurement.
+    ///
+    /// Like [`Sgp30::measure()`](../struct.Sgp30.html#method.measure), this
+    /// must be called in regular intervals of 1 s. If the reading cannot be
+    /// converted to absolute humidity, compensation is disabled for this
+    /// measurement.
+    pub fn measure(&mut self) -> Result<IaqRecord, StationError<E, SHT::Error>> {
+        let (temperature, rh) = self
+            .sht
+            .read(&mut self.sgp.i2c, &mut self.sgp.delay)
+            .map_err(StationError::Humidity)?;
+        let absolute = humidity::absolute_humidity(temperature, rh).ok();
+        self.sgp.set_humidity(absolute.as_ref())?;
+        let measurement = self.sgp.measure()?;
+        Ok(IaqRecord {
+            measurement,
+            temperature,
+            humidity: rh,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as mock;
+
+    use super::*;
+    use I2cAddress;
+
+    /// 25 °C and 50 %RH as raw SHT3x values, with CRC
+    const SHT3X_DATA: [u8; 6] = [0x66, 0x66, 0x93, 0x80, 0x00, 0xA2];
+
+    #[test]
+    fn sht3x_read() {
+        let mut i2c = mock::I2cMock::new();
+        i2c.set_read_data(&SHT3X_DATA);
+        let (t, rh) = Sht3x::default().read(&mut i2c, &mut mock::DelayMockNoop).unwrap();
+        assert_eq!(i2c.get_write_data(), &[0x24, 0x00]);
+        assert_eq!(t.as_milli_celsius(), 25_000);
+        assert_eq!(rh.as_milli_percent(), 50_000);
+    }
+
+    #[test]
+    fn sht4x_read() {
+        let mut i2c = mock::I2cMock::new();
+        i2c.set_read_data(&[0x66, 0x66, 0x93, 0x00, 0x00, 0x81]);
+        let (t, rh) = Sht4x::default().read(&mut i2c, &mut mock::DelayMockNoop).unwrap();
+        assert_eq!(i2c.get_write_data(), &[0xFD]);
+        assert_eq!(t.as_milli_celsius(), 25_000);
+        // Cropped to 0 %RH
+        assert_eq!(rh.as_milli_percent(), 0);
+    }
+
+    #[test]
+    fn sht_crc_error() {
+        let mut i2c = mock::I2cMock::new();
+        i2c.set_read_data(&[0x66, 0x66, 0x00, 0x80, 0x00, 0xA2]);
+        match Sht3x::default().read(&mut i2c, &mut mock::DelayMockNoop) {
+            Err(ShtError::Crc) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn station_measure() {
+        let mut i2c = mock::I2cMock::new();
+        let mut data = [0; 12];
+        data[..6].copy_from_slice(&SHT3X_DATA);
+        data[6..].copy_from_slice(&[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
+        i2c.set_read_data(&data);
+        let sgp = Sgp30::new(i2c, I2cAddress::DEFAULT, mock::DelayMockNoop);
+        let mut station = IaqStation::new(sgp, Sht3x::default());
+        station.init().unwrap();
+        let record = station.measure().unwrap();
+        assert_eq!(record.measurement, Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 });
+        assert_eq!(record.temperature.as_milli_celsius(), 25_000);
+        assert_eq!(record.humidity.as_milli_percent(), 50_000);
+    }
+}
@@ -0,0 +1,178 @@
+//! Sliding-window statistics over measurements.
+//!
+//! [`Stats`](struct.Stats.html) keeps the last `N` measurements in a
+//! fixed-size buffer and summarizes them, e.g. for 1 minute (`N = 60`) or
+//! 15 minute (`N = 900`) reports when measuring at 1 Hz.
+//!
+//! ```
+//! use sgp30::Measurement;
+//! use sgp30::stats::Stats;
+//!
+//! let mut stats: Stats<60> = Stats::new();
+//! stats.push(Measurement { co2eq_ppm: 400, tvoc_ppb: 10 });
+//! stats.push(Measurement { co2eq_ppm: 600, tvoc_ppb: 30 });
+//! let summary = stats.summary().unwrap();
+//! assert_eq!(summary.co2eq.min, 400);
+//! assert_eq!(summary.tvoc.mean, 20.0);
+//! ```
+
+use heapless::HistoryBuffer;
+
+use types::Measurement;
+
+/// Statistics of a single signal.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelSummary {
+    /// Smallest value in the window
+    pub min: u16,
+    /// Largest value in the window
+    pub max: u16,
+    /// Arithmetic mean of the window
+    pub mean: f32,
+    /// Population standard deviation of the window
+    pub stddev: f32,
+}
+
+/// Statistics of both air quality signals.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Summary {
+    /// CO₂eq statistics (ppm)
+    pub co2eq: ChannelSummary,
+    /// TVOC statistics (ppb)
+    pub tvoc: ChannelSummary,
+    /// Number of measurements the statistics are based on
+    pub count: usize,
+}
+
+/// An accumulator over the last `N` measurements.
+pub struct Stats<const N: usize> {
+    /// The measurements in the window.
+    window: HistoryBuffer<Measurement, N>,
+}
+
+impl<const N: usize> Stats<N> {
+    /// Create a new, empty accumulator.
+    pub const fn new() -> Self {
+        Stats {
+            window: HistoryBuffer::new(),
+        }
+    }
+
+    /// Add a measurement, dropping the oldest one if the window is full.
+    pub fn push(&mut self, measurement: Measurement) {
+        self.window.write(measurement);
+    }
+
+    /// Return the number of measurements in the window.
+    pub fn len(&self) -> usize {
+        self.window.len()
+    }
+
+    /// Whether the window is empty.
+    pub fn is_empty(&self) -> bool {
+        self.window.len() == 0
+    }
+
+    /// Whether the window contains `N` measurements.
+    pub fn is_full(&self) -> bool {
+        self.window.len() == N
+    }
+
+    /// Remove all measurements from the window.
+    pub fn clear(&mut self) {
+        self.window.clear();
+    }
+
+    /// Summarize the measurements in the window, or return `None` if the
+    /// window is empty.
+    pub fn summary(&self) -> Option<Summary> {
+        if self.is_empty() {
+            return None;
+        }
+        let values = self.window.as_slice();
+        Some(Summary {
+            co2eq: summarize(values.iter().map(|m| m.co2eq_ppm)),
+            tvoc: summarize(values.iter().map(|m| m.tvoc_ppb)),
+            count: values.len(),
+        })
+    }
+}
+
+impl<const N: usize> Default for Stats<N> {
+    fn default() -> Self {
+        Stats::new()
+    }
+}
+
+/// Summarize a non-empty series of values.
+fn summarize<I: Iterator<Item = u16> + Clone>(values: I) -> ChannelSummary {
+    let (mut min, mut max, mut sum, mut count) = (u16::MAX, u16::MIN, 0u64, 0u64);
+    for value in values.clone() {
+        min = min.min(value);
+        max = max.max(value);
+        sum += u64::from(value);
+        count += 1;
+    }
+    let mean = sum as f32 / count as f32;
+    let variance = values
+        .map(|value| {
+            let diff = f32::from(value) - mean;
+            diff * diff
+        })
+        .sum::<f32>()
+        / count as f32;
+    ChannelSummary {
+        min,
+        max,
+        mean,
+        stddev: libm::sqrtf(variance),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(co2eq_ppm: u16, tvoc_ppb: u16) -> Measurement {
+        Measurement { co2eq_ppm, tvoc_ppb }
+    }
+
+    #[test]
+    fn empty() {
+        let stats: Stats<4> = Stats::new();
+        assert!(stats.is_empty());
+        assert_eq!(stats.summary(), None);
+    }
+
+    #[test]
+    fn summary() {
+        let mut stats: Stats<8> = Stats::new();
+        for &tvoc in &[2, 4, 4, 4, 5, 5, 7, 9] {
+            stats.push(m(400 + tvoc, tvoc));
+        }
+        assert!(stats.is_full());
+        let summary = stats.summary().unwrap();
+        assert_eq!(summary.count, 8);
+        assert_eq!(summary.tvoc, ChannelSummary { min: 2, max: 9, mean: 5.0, stddev: 2.0 });
+        assert_eq!(summary.co2eq.min, 402);
+        assert_eq!(summary.co2eq.mean, 405.0);
+    }
+
+    #[test]
+    fn sliding_window() {
+        let mut stats: Stats<2> = Stats::new();
+        stats.push(m(1000, 1000));
+        stats.push(m(400, 10));
+        stats.push(m(600, 30));
+        let summary = stats.summary().unwrap();
+        assert_eq!(summary.count, 2);
+        assert_eq!(summary.co2eq.max, 600);
+        assert_eq!(summary.tvoc.mean, 20.0);
+        stats.clear();
+        assert!(stats.is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use super::record::{decode_sequenced, encode_sequenced, is_newer, SEQUENCED_LEN};
+use super::{BaselineRecord, BaselineStore, StorageError};
+
+/// Size of the buffer used to pad a record to the write and read sizes of
+/// the flash.
+const BUF_LEN: usize = 32;
+
+/// A power-loss-safe baseline store using two NOR flash pages (A/B).
+///
+/// Every save erases and writes the page holding the *older* record, with a
+/// sequence number one higher than the newer record. If power is lost during
+/// a save, only the page being written is affected and the other page still
+/// holds a restorable baseline. [`load()`](#method.load) returns the valid
+/// record with the newest sequence number.
+///
+/// This store is only available with the `embedded-storage` feature.
+#[derive(Debug)]
+pub struct DualFlashStore<F> {
+    /// The flash device.
+    flash: F,
+    /// Start addresses of the two pages.
+    pages: [u32; 2],
+    /// Page index and sequence number of the newest record (`None` if the
+    /// pages were not read yet).
+    newest: Option<Option<(usize, u16)>>,
+}
+
+impl<F: NorFlash> DualFlashStore<F> {
+    /// Create a new store using the erase pages at `offset_a` and `offset_b`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offsets are not aligned to the erase size of the flash or
+    /// are equal, or if the write or read size of the flash exceeds 32 bytes.
+    pub fn new(flash: F, offset_a: u32, offset_b: u32) -> Self {
+        assert!(
+            (offset_a as usize).is_multiple_of(F::ERASE_SIZE) && (offset_b as usize).is_multiple_of(F::ERASE_SIZE),
+            "offsets must be page-aligned"
+        );
+        assert!(offset_a != offset_b, "the two pages must differ");
+        assert!(Self::padded_len() <= BUF_LEN, "flash write/read size too large");
+        DualFlashStore {
+            flash,
+            pages: [offset_a, offset_b],
+            newest: None,
+        }
+    }
+
+    /// Destroy the store, return the flash device.
+    pub fn destroy(self) -> F {
+        self.flash
+    }
+
+    /// Return the record length rounded up to the write and read sizes.
+    fn padded_len() -> usize {
+        let align = F::WRITE_SIZE.max(F::READ_SIZE);
+        SEQUENCED_LEN.div_ceil(align) * align
+    }
+
+    /// Read a page and return its sequence number and record, if it contains
+    /// a valid record.
+    fn read_page(&mut self, page: usize) -> Result<Option<(u16, BaselineRecord)>, F::Error> {
+        let mut buf = [0; BUF_LEN];
+        self.flash.read(self.pages[page], &mut buf[..Self::padded_len()])?;
+        Ok(decode_sequenced(&buf))
+    }
+
+    /// Read both pages and return the newest valid record.
+    fn scan(&mut self) -> Result<Option<(usize, u16, BaselineRecord)>, F::Error> {
+        let newest = match (self.read_page(0)?, self.read_page(1)?) {
+            (Some((seq_a, a)), Some((seq_b, b))) => {
+                if is_newer(seq_b, seq_a) {
+                    Some((1, seq_b, b))
+                } else {
+                    Some((0, seq_a, a))
+                }
+            }
+            (Some((seq, a)), None) => Some((0, seq, a)),
+            (None, Some((seq, b))) => Some((1, seq, b)),
+            (None, None) => None,
+        };
+        self.newest = Some(newest.map(|(page, sequence, _)| (page, sequence)));
+        Ok(newest)
+    }
+}
+
+impl<F: NorFlash> BaselineStore for DualFlashStore<F> {
+    type Error = StorageError<F::Error>;
+
+    fn save(&mut self, record: &BaselineRecord) -> Result<(), Self::Error> {
+        if self.newest.is_none() {
+            self.scan().map_err(StorageError::Storage)?;
+        }
+        let (page, sequence) = match self.newest.flatten() {
+            Some((page, sequence)) => (1 - page, sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+
+        let mut buf = [0xFF; BUF_LEN];
+        buf[..SEQUENCED_LEN].copy_from_slice(&encode_sequenced(sequence, record));
+        let offset = self.pages[page];
+        // Until the write completes, the other page holds the newest record
+        self.newest = None;
+        self.flash
+            .erase(offset, offset + F::ERASE_SIZE as u32)
+            .map_err(StorageError::Storage)?;
+        self.flash
+            .write(offset, &buf[..Self::padded_len()])
+            .map_err(StorageError::Storage)?;
+        self.newest = Some(Some((page, sequence)));
+        Ok(())
+    }
+
+    fn load(&mut self) -> Result<Option<BaselineRecord>, Self::Error> {
+        self.scan()
+            .map(|newest| newest.map(|(_, _, record)| record))
+            .map_err(StorageError::Storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::flash::tests::{record, RamFlash};
+    use super::*;
+
+    #[test]
+    fn alternating_pages() {
+        let mut store = DualFlashStore::new(RamFlash::new(), 0, 64);
+        assert_eq!(store.load(), Ok(None));
+        for i in 0..3 {
+            store.save(&record(i, i)).unwrap();
+            assert_eq!(store.load(), Ok(Some(record(i, i))));
+        }
+        let flash = store.destroy();
+        // Page A holds sequence numbers 0 and 2, page B number 1
+        assert_eq!(&flash.data[0..2], &[0, 2]);
+        assert_eq!(&flash.data[64..64 + 2], &[0, 1]);
+        assert_eq!(flash.erase_count, 3);
+    }
+
+    /// A save interrupted after the erase leaves the previous baseline.
+    #[test]
+    fn power_loss_during_save() {
+        let mut store = DualFlashStore::new(RamFlash::new(), 0, 64);
+        store.save(&record(1, 1)).unwrap();
+        store.save(&record(2, 2)).unwrap();
+        let mut flash = store.destroy();
+        // Page A (to be written next) was erased, then power was lost
+        for byte in &mut flash.data[..64] {
+            *byte = 0xFF;
+        }
+        let mut store = DualFlashStore::new(flash, 0, 64);
+        assert_eq!(store.load(), Ok(Some(record(2, 2))));
+        // Half-written record
+        let mut flash = store.destroy();
+        flash.data[64 + 12] ^= 0xFF;
+        let mut store = DualFlashStore::new(flash, 0, 64);
+        assert_eq!(store.load(), Ok(None));
+    }
+
+    #[test]
+    fn sequence_wraparound() {
+        let mut store = DualFlashStore::new(RamFlash::new(), 0, 64);
+        store.newest = Some(Some((1, 0xFFFE)));
+        store.save(&record(1, 1)).unwrap();
+        store.save(&record(2, 2)).unwrap();
+        store.save(&record(3, 3)).unwrap();
+        let mut store = DualFlashStore::new(store.destroy(), 0, 64);
+        assert_eq!(store.load(), Ok(Some(record(3, 3))));
+    }
+}
@@ -0,0 +1,298 @@
+use hal::blocking::delay::DelayMs;
+use hal::blocking::i2c::{Write, WriteRead};
+
+use super::record::{decode_sequenced, encode_sequenced, is_newer, SEQUENCED_LEN};
+use super::{BaselineRecord, BaselineStore, StorageError};
+
+/// Size of a slot. A sequence-numbered record fits into 32 bytes, which is
+/// also the page size of most 24xx EEPROMs with two byte addressing.
+pub const SLOT_SIZE: u16 = 32;
+
+/// Maximum write cycle time of 24xx EEPROMs in milliseconds.
+const WRITE_CYCLE_MS: u16 = 5;
+
+/// Default I²C address of a 24xx EEPROM with all address pins pulled low.
+pub const DEFAULT_EEPROM_ADDRESS: u8 = 0x50;
+
+/// The width of the memory address sent to the EEPROM.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressWidth {
+    /// One byte memory addresses (24C01 - 24C16).
+    OneByte,
+    /// Two byte memory addresses (24C32 and larger).
+    TwoBytes,
+}
+
+impl AddressWidth {
+    /// The smallest page size of the EEPROMs using this address width. Page
+    /// writes must not cross a page boundary.
+    fn page_size(self) -> usize {
+        match self {
+            AddressWidth::OneByte => 8,
+            AddressWidth::TwoBytes => 32,
+        }
+    }
+}
+
+/// A baseline store in a 24xx-series I²C EEPROM.
+///
+/// Since the EEPROM usually shares the bus with the sensor, the store needs
+/// its own bus handle, e.g. a proxy from the
+/// [`shared-bus`](https://docs.rs/shared-bus) crate.
+///
+/// To spread the writes across the EEPROM cells, baselines are written
+/// round-robin into a number of slots of 32 bytes each. Every record contains
+/// a sequence number, and [`load()`](#method.load) returns the valid record
+/// with the newest sequence number. Corrupt slots (e.g. after a power loss
+/// during a write) are skipped.
+#[derive(Debug)]
+pub struct EepromStore<I2C, D> {
+    i2c: I2C,
+    delay: D,
+    /// The I²C address of the EEPROM.
+    address: u8,
+    /// The memory address width of the EEPROM.
+    width: AddressWidth,
+    /// Memory address of the first slot.
+    start: u16,
+    /// Number of slots.
+    slots: u16,
+    /// Slot index and sequence number of the newest record (`None` if the
+    /// slots were not scanned yet).
+    newest: Option<Option<(u16, u16)>>,
+}
+
+impl<I2C, D, E> EepromStore<I2C, D>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+    D: DelayMs<u16>,
+{
+    /// Create a new store using `slots` slots of 32 bytes, starting at memory
+    /// address `start`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` is not a multiple of 32 or `slots` is zero.
+    pub fn new(i2c: I2C, delay: D, address: u8, width: AddressWidth, start: u16, slots: u16) -> Self {
+        assert!(start.is_multiple_of(SLOT_SIZE), "start address must be aligned to 32 bytes");
+        assert!(slots > 0, "at least one slot is required");
+        EepromStore {
+            i2c,
+            delay,
+            address,
+            width,
+            start,
+            slots,
+            newest: None,
+        }
+    }
+
+    /// Destroy the store, return the I²C bus instance.
+    pub fn destroy(self) -> I2C {
+        self.i2c
+    }
+
+    /// Return the I²C address and the memory address bytes of a slot.
+    ///
+    /// With one byte addressing, the upper bits of the memory address select
+    /// the block via the I²C address.
+    fn slot_address(&self, slot: u16, offset: u16) -> (u8, [u8; 2], usize) {
+        let mem = self.start + slot * SLOT_SIZE + offset;
+        match self.width {
+            AddressWidth::OneByte => (self.address | ((mem >> 8) as u8 & 0x07), [mem as u8, 0], 1),
+            AddressWidth::TwoBytes => (self.address, [(mem >> 8) as u8, mem as u8], 2),
+        }
+    }
+
+    /// Read a slot and return the sequence number and record, if it contains
+    /// a valid record.
+    fn read_slot(&mut self, slot: u16) -> Result<Option<(u16, BaselineRecord)>, E> {
+        let (address, mem, mem_len) = self.slot_address(slot, 0);
+        let mut buf = [0; SEQUENCED_LEN];
+        self.i2c.write_read(address, &mem[..mem_len], &mut buf)?;
+        Ok(decode_sequenced(&buf))
+    }
+
+    /// Scan all slots for the newest valid record.
+    fn scan(&mut self) -> Result<Option<(u16, u16, BaselineRecord)>, E> {
+        let mut newest: Option<(u16, u16, BaselineRecord)> = None;
+        for slot in 0..self.slots {
+            if let Some((sequence, record)) = self.read_slot(slot)? {
+                if newest.is_none_or(|(_, best, _)| is_newer(sequence, best)) {
+                    newest = Some((slot, sequence, record));
+                }
+            }
+        }
+        self.newest = Some(newest.map(|(slot, sequence, _)| (slot, sequence)));
+        Ok(newest)
+    }
+}
+
+impl<I2C, D, E> BaselineStore for EepromStore<I2C, D>
+where
+    I2C: Write<Error = E> + WriteRead<Error = E>,
+    D: DelayMs<u16>,
+{
+    type Error = StorageError<E>;
+
+    fn save(&mut self, record: &BaselineRecord) -> Result<(), Self::Error> {
+        if self.newest.is_none() {
+            self.scan().map_err(StorageError::Storage)?;
+        }
+        let (slot, sequence) = match self.newest.flatten() {
+            Some((slot, sequence)) => ((slot + 1) % self.slots, sequence.wrapping_add(1)),
+            None => (0, 0),
+        };
+
+        // Write the record page by page, waiting for the write cycle after
+        // every page
+        let data = encode_sequenced(sequence, record);
+        let page_size = self.width.page_size();
+        for (i, chunk) in data.chunks(page_size).enumerate() {
+            let (address, mem, mem_len) = self.slot_address(slot, (i * page_size) as u16);
+            let mut buf = [0; 2 + 32];
+            buf[..mem_len].copy_from_slice(&mem[..mem_len]);
+            buf[mem_len..mem_len + chunk.len()].copy_from_slice(chunk);
+            self.i2c
+                .write(address, &buf[..mem_len + chunk.len()])
+                .map_err(StorageError::Storage)?;
+            self.delay.delay_ms(WRITE_CYCLE_MS);
+        }
+        self.newest = Some(Some((slot, sequence)));
+        Ok(())
+    }
+
+    fn load(&mut self) -> Result<Option<BaselineRecord>, Self::Error> {
+        self.scan()
+            .map(|newest| newest.map(|(_, _, record)| record))
+            .map_err(StorageError::Storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as mock;
+
+    use super::*;
+    use types::Baseline;
+
+    /// A 24C02-style EEPROM with one byte addressing (or 24C32-style with
+    /// two byte addressing) of 512 bytes.
+    struct Eeprom {
+        width: AddressWidth,
+        data: [u8; 512],
+        writes: usize,
+    }
+
+    impl Eeprom {
+        fn new(width: AddressWidth) -> Self {
+            Eeprom { width, data: [0xFF; 512], writes: 0 }
+        }
+
+        fn offset(&self, address: u8, bytes: &[u8]) -> (usize, usize) {
+            match self.width {
+                AddressWidth::OneByte => (usize::from(address & 0x07) << 8 | usize::from(bytes[0]), 1),
+                AddressWidth::TwoBytes => (usize::from(bytes[0]) << 8 | usize::from(bytes[1]), 2),
+            }
+        }
+    }
+
+    impl Write for Eeprom {
+        type Error = ();
+        fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), ()> {
+            let (offset, len) = self.offset(address, bytes);
+            let data = &bytes[len..];
+            self.data[offset..offset + data.len()].copy_from_slice(data);
+            self.writes += 1;
+            Ok(())
+        }
+    }
+
+    impl WriteRead for Eeprom {
+        type Error = ();
+        fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), ()> {
+            let (offset, _) = self.offset(address, bytes);
+            buffer.copy_from_slice(&self.data[offset..offset + buffer.len()]);
+            Ok(())
+        }
+    }
+
+    fn new_store(eeprom: Eeprom, start: u16, slots: u16) -> EepromStore<Eeprom, mock::DelayMockNoop> {
+        let width = eeprom.width;
+        EepromStore::new(eeprom, mock::DelayMockNoop, DEFAULT_EEPROM_ADDRESS, width, start, slots)
+    }
+
+    fn record(co2eq: u16, tvoc: u16) -> BaselineRecord {
+        BaselineRecord {
+            serial: [0, 0, 1, 2, 3, 4],
+            baseline: Baseline { co2eq, tvoc },
+            timestamp: 1_600_000_000,
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let mut store = new_store(Eeprom::new(AddressWidth::TwoBytes), 0, 4);
+        assert_eq!(store.load(), Ok(None));
+    }
+
+    #[test]
+    fn slot_rotation() {
+        let mut store = new_store(Eeprom::new(AddressWidth::TwoBytes), 64, 3);
+        for i in 0..5 {
+            store.save(&record(i, 100 + i)).unwrap();
+        }
+        assert_eq!(store.load(), Ok(Some(record(4, 104))));
+        let eeprom = store.destroy();
+        assert_eq!(eeprom.writes, 5);
+        // Slot 0 holds sequence number 3, slot 1 number 4, slot 2 number 2
+        assert_eq!(&eeprom.data[64..64 + 2], &[0, 3]);
+        assert_eq!(&eeprom.data[96..96 + 2], &[0, 4]);
+        assert_eq!(&eeprom.data[128..128 + 2], &[0, 2]);
+        // Nothing outside of the slots was written
+        assert!(eeprom.data[..64].iter().all(|&b| b == 0xFF));
+        assert!(eeprom.data[160..].iter().all(|&b| b == 0xFF));
+
+        // A new store instance continues with the next slot
+        let mut store = new_store(eeprom, 64, 3);
+        store.save(&record(5, 105)).unwrap();
+        let eeprom = store.destroy();
+        assert_eq!(&eeprom.data[128..128 + 2], &[0, 5]);
+    }
+
+    #[test]
+    fn page_writes() {
+        let mut store = new_store(Eeprom::new(AddressWidth::OneByte), 224, 2);
+        store.save(&record(1, 1)).unwrap();
+        store.save(&record(2, 2)).unwrap();
+        let eeprom = store.destroy();
+        // Records are written in pages of 8 bytes
+        assert_eq!(eeprom.writes, 8);
+        // The second slot lies in the next 256 byte block
+        assert_eq!(&eeprom.data[256..258], &[0, 1]);
+        let mut store = new_store(eeprom, 224, 2);
+        assert_eq!(store.load(), Ok(Some(record(2, 2))));
+    }
+
+    #[test]
+    fn corrupt_slot_skipped() {
+        let mut store = new_store(Eeprom::new(AddressWidth::TwoBytes), 0, 2);
+        store.save(&record(1, 1)).unwrap();
+        store.save(&record(2, 2)).unwrap();
+        let mut eeprom = store.destroy();
+        eeprom.data[32 + 10] ^= 0xFF;
+        let mut store = new_store(eeprom, 0, 2);
+        assert_eq!(store.load(), Ok(Some(record(1, 1))));
+    }
+
+    #[test]
+    fn sequence_wraparound() {
+        let mut store = new_store(Eeprom::new(AddressWidth::TwoBytes), 0, 2);
+        store.newest = Some(Some((0, 0xFFFE)));
+        store.save(&record(6, 6)).unwrap();
+        store.save(&record(7, 7)).unwrap();
+        let mut store = new_store(store.destroy(), 0, 2);
+        assert_eq!(store.load(), Ok(Some(record(7, 7))));
+    }
+}
@@ -0,0 +1,129 @@
+use std::fs::{self, File};
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::{decode_stored, BaselineRecord, BaselineStore, RecordError, StorageError};
+
+/// A file-backed baseline store, e.g. for Linux gateways.
+///
+/// The baseline is stored as encoded
+/// [`BaselineRecord`](struct.BaselineRecord.html) in a small binary file.
+/// To never leave a partially
+/// written file behind, it is first written to a temporary file next to the
+/// target, synced and then atomically renamed.
+///
+/// This store is only available with the `std` feature.
+///
+/// ```no_run
+/// use sgp30::storage::{BaselineRecord, BaselineStore, FileStore};
+///
+/// let mut store = FileStore::new("/var/lib/sgp30/baseline");
+/// let stored: Option<BaselineRecord> = store.load().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileStore {
+    /// Path of the baseline file.
+    path: PathBuf,
+}
+
+impl FileStore {
+    /// Create a new store using the file at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        FileStore {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Return the path of the baseline file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Return the path of the temporary file.
+    fn tmp_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+        name.push(".tmp");
+        self.path.with_file_name(name)
+    }
+}
+
+impl BaselineStore for FileStore {
+    type Error = StorageError<io::Error>;
+
+    fn save(&mut self, record: &BaselineRecord) -> Result<(), Self::Error> {
+        let tmp_path = self.tmp_path();
+        let write = || -> io::Result<()> {
+            let mut file = File::create(&tmp_path)?;
+            file.write_all(&record.encode())?;
+            file.sync_all()?;
+            fs::rename(&tmp_path, &self.path)
+        };
+        write().map_err(StorageError::Storage)
+    }
+
+    fn load(&mut self) -> Result<Option<BaselineRecord>, Self::Error> {
+        let mut buf = [0; BaselineRecord::ENCODED_LEN];
+        match File::open(&self.path).and_then(|mut file| file.read_exact(&mut buf)) {
+            Ok(()) => decode_stored(&buf),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(ref e) if e.kind() == ErrorKind::UnexpectedEof => {
+                Err(StorageError::Record(RecordError::TooShort))
+            }
+            Err(e) => Err(StorageError::Storage(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::format;
+    use std::process;
+
+    use super::*;
+    use types::Baseline;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("sgp30-{}-{}", process::id(), name))
+    }
+
+    #[test]
+    fn roundtrip() {
+        let path = temp_path("roundtrip");
+        let mut store = FileStore::new(&path);
+        assert!(store.load().unwrap().is_none());
+        let record = BaselineRecord {
+            serial: [0, 0, 1, 2, 3, 4],
+            baseline: Baseline { co2eq: 0x8F3A, tvoc: 0x9012 },
+            timestamp: 1_600_000_000,
+        };
+        store.save(&record).unwrap();
+        assert_eq!(store.load().unwrap(), Some(record));
+        assert!(!store.tmp_path().exists());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_file() {
+        let path = temp_path("corrupt");
+        let mut store = FileStore::new(&path);
+        store.save(&BaselineRecord {
+            serial: [0; 6],
+            baseline: Baseline { co2eq: 1, tvoc: 2 },
+            timestamp: 0,
+        }).unwrap();
+        let mut data = fs::read(&path).unwrap();
+        data[10] ^= 0x01;
+        fs::write(&path, &data).unwrap();
+        match store.load() {
+            Err(StorageError::Record(RecordError::Crc)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        fs::write(&path, &data[..10]).unwrap();
+        match store.load() {
+            Err(StorageError::Record(RecordError::TooShort)) => {}
+            other => panic!("unexpected result: {:?}", other),
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
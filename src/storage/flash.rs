@@ -0,0 +1,179 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use super::{decode_stored, BaselineRecord, BaselineStore, StorageError};
+
+/// Size of the buffer used to pad a record to the write and read sizes of
+/// the flash.
+const BUF_LEN: usize = 32;
+
+/// A baseline store in NOR flash, using the `embedded-storage` traits.
+///
+/// The store occupies one erase page starting at `offset`. Every save erases
+/// the page and writes an encoded [`BaselineRecord`](struct.BaselineRecord.html).
+/// An erased page is reported as "no baseline stored".
+///
+/// This store is only available with the `embedded-storage` feature.
+#[derive(Debug)]
+pub struct FlashStore<F> {
+    /// The flash device.
+    flash: F,
+    /// Start address of the page.
+    offset: u32,
+}
+
+impl<F: NorFlash> FlashStore<F> {
+    /// Create a new store using the erase page at `offset`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `offset` is not aligned to the erase size of the flash, or if
+    /// the write or read size of the flash exceeds 32 bytes.
+    pub fn new(flash: F, offset: u32) -> Self {
+        assert!((offset as usize).is_multiple_of(F::ERASE_SIZE), "offset must be page-aligned");
+        assert!(Self::padded_len() <= BUF_LEN, "flash write/read size too large");
+        FlashStore { flash, offset }
+    }
+
+    /// Destroy the store, return the flash device.
+    pub fn destroy(self) -> F {
+        self.flash
+    }
+
+    /// Return the record length rounded up to the write and read sizes.
+    fn padded_len() -> usize {
+        let align = F::WRITE_SIZE.max(F::READ_SIZE);
+        BaselineRecord::ENCODED_LEN.div_ceil(align) * align
+    }
+}
+
+impl<F: NorFlash> BaselineStore for FlashStore<F> {
+    type Error = StorageError<F::Error>;
+
+    fn save(&mut self, record: &BaselineRecord) -> Result<(), Self::Error> {
+        let mut buf = [0xFF; BUF_LEN];
+        buf[..BaselineRecord::ENCODED_LEN].copy_from_slice(&record.encode());
+        self.flash
+            .erase(self.offset, self.offset + F::ERASE_SIZE as u32)
+            .map_err(StorageError::Storage)?;
+        self.flash
+            .write(self.offset, &buf[..Self::padded_len()])
+            .map_err(StorageError::Storage)
+    }
+
+    fn load(&mut self) -> Result<Option<BaselineRecord>, Self::Error> {
+        let mut buf = [0; BUF_LEN];
+        self.flash
+            .read(self.offset, &mut buf[..Self::padded_len()])
+            .map_err(StorageError::Storage)?;
+        decode_stored(&buf)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use embedded_storage::nor_flash::{ErrorType, NorFlashErrorKind, ReadNorFlash};
+
+    use super::*;
+    use storage::RecordError;
+    use types::Baseline;
+
+    /// A RAM-backed flash with 2 pages of 64 bytes.
+    pub struct RamFlash {
+        pub data: [u8; 128],
+        pub erase_count: usize,
+    }
+
+    impl RamFlash {
+        pub fn new() -> Self {
+            RamFlash { data: [0xFF; 128], erase_count: 0 }
+        }
+    }
+
+    impl ErrorType for RamFlash {
+        type Error = NorFlashErrorKind;
+    }
+
+    impl ReadNorFlash for RamFlash {
+        const READ_SIZE: usize = 1;
+
+        fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            let offset = offset as usize;
+            let end = offset + bytes.len();
+            if end > self.data.len() {
+                return Err(NorFlashErrorKind::OutOfBounds);
+            }
+            bytes.copy_from_slice(&self.data[offset..end]);
+            Ok(())
+        }
+
+        fn capacity(&self) -> usize {
+            self.data.len()
+        }
+    }
+
+    impl NorFlash for RamFlash {
+        const WRITE_SIZE: usize = 4;
+        const ERASE_SIZE: usize = 64;
+
+        fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            for byte in &mut self.data[from as usize..to as usize] {
+                *byte = 0xFF;
+            }
+            self.erase_count += 1;
+            Ok(())
+        }
+
+        fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+            if !(offset as usize).is_multiple_of(Self::WRITE_SIZE) || !bytes.len().is_multiple_of(Self::WRITE_SIZE) {
+                return Err(NorFlashErrorKind::NotAligned);
+            }
+            for (i, byte) in bytes.iter().enumerate() {
+                // NOR flash can only clear bits
+                self.data[offset as usize + i] &= byte;
+            }
+            Ok(())
+        }
+    }
+
+    pub fn record(co2eq: u16, tvoc: u16) -> BaselineRecord {
+        BaselineRecord {
+            serial: [0, 0, 1, 2, 3, 4],
+            baseline: Baseline { co2eq, tvoc },
+            timestamp: 1_600_000_000,
+        }
+    }
+
+    #[test]
+    fn roundtrip() {
+        let mut store = FlashStore::new(RamFlash::new(), 64);
+        assert_eq!(store.load(), Ok(None));
+        store.save(&record(0x8F3A, 0x9012)).unwrap();
+        assert_eq!(store.load(), Ok(Some(record(0x8F3A, 0x9012))));
+        store.save(&record(0x1234, 0x5678)).unwrap();
+        assert_eq!(store.load(), Ok(Some(record(0x1234, 0x5678))));
+        let flash = store.destroy();
+        assert_eq!(flash.erase_count, 2);
+        // The first page was not touched
+        assert!(flash.data[..64].iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn corrupt_records() {
+        let mut store = FlashStore::new(RamFlash::new(), 0);
+        store.save(&record(1, 2)).unwrap();
+        let mut flash = store.destroy();
+        flash.data[10] ^= 0x01;
+        let mut store = FlashStore::new(flash, 0);
+        assert_eq!(store.load(), Err(StorageError::Record(RecordError::Crc)));
+        let mut flash = store.destroy();
+        flash.data[2] = 99;
+        let mut store = FlashStore::new(flash, 0);
+        assert_eq!(store.load(), Err(StorageError::Record(RecordError::UnsupportedVersion(99))));
+    }
+
+    #[test]
+    #[should_panic]
+    fn unaligned_offset() {
+        FlashStore::new(RamFlash::new(), 10);
+    }
+}
@@ -0,0 +1,216 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use super::record::{decode_sequenced, encode_sequenced, is_newer, SEQUENCED_LEN};
+use super::{BaselineRecord, BaselineStore, StorageError};
+
+/// Size of the buffer used to pad an entry to the write and read sizes of
+/// the flash.
+const BUF_LEN: usize = 32;
+
+/// A wear-aware, append-only baseline journal in NOR flash.
+///
+/// Erasing a page for every save, as [`FlashStore`](struct.FlashStore.html)
+/// does, quickly adds up with the recommended hourly saves. The journal
+/// instead appends sequence-numbered records to a region of several erase
+/// pages and only erases a page once the journal wraps around to it. With
+/// `n` records per page, this reduces the number of erase cycles by a factor
+/// of `n`.
+///
+/// On [`load()`](#method.load), the journal is replayed and the valid record
+/// with the newest sequence number is returned. Since at least one other page
+/// is left untouched while a page is erased, a power loss never loses all
+/// records.
+///
+/// This store is only available with the `embedded-storage` feature.
+#[derive(Debug)]
+pub struct JournalStore<F> {
+    /// The flash device.
+    flash: F,
+    /// Start address of the region.
+    start: u32,
+    /// Number of erase pages in the region.
+    pages: u32,
+    /// Slot index and sequence number of the newest record (`None` if the
+    /// journal was not replayed yet).
+    newest: Option<Option<(u32, u16)>>,
+}
+
+impl<F: NorFlash> JournalStore<F> {
+    /// Create a new journal using `pages` erase pages starting at `start`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start` is not aligned to the erase size of the flash, if
+    /// less than two pages are used, if the write or read size of the flash
+    /// exceeds 32 bytes, or if the region holds 32768 records or more.
+    pub fn new(flash: F, start: u32, pages: u32) -> Self {
+        assert!((start as usize).is_multiple_of(F::ERASE_SIZE), "start must be page-aligned");
+        assert!(pages >= 2, "the journal requires at least two pages");
+        assert!(Self::entry_len() <= BUF_LEN, "flash write/read size too large");
+        let store = JournalStore {
+            flash,
+            start,
+            pages,
+            newest: None,
+        };
+        assert!(store.capacity() < 0x8000, "too many records in the journal region");
+        store
+    }
+
+    /// Destroy the store, return the flash device.
+    pub fn destroy(self) -> F {
+        self.flash
+    }
+
+    /// Return the number of records that fit into one erase page.
+    pub fn entries_per_page(&self) -> u32 {
+        (F::ERASE_SIZE / Self::entry_len()) as u32
+    }
+
+    /// Return the number of records that fit into the journal region.
+    pub fn capacity(&self) -> u32 {
+        self.entries_per_page() * self.pages
+    }
+
+    /// Return the length of an entry, rounded up to the write and read sizes.
+    fn entry_len() -> usize {
+        let align = F::WRITE_SIZE.max(F::READ_SIZE);
+        SEQUENCED_LEN.div_ceil(align) * align
+    }
+
+    /// Return the flash address of a slot.
+    fn address(&self, slot: u32) -> u32 {
+        let per_page = self.entries_per_page();
+        self.start + (slot / per_page) * F::ERASE_SIZE as u32 + (slot % per_page) * Self::entry_len() as u32
+    }
+
+    /// Read the entry in a slot.
+    fn read_slot(&mut self, slot: u32, buf: &mut [u8; BUF_LEN]) -> Result<(), F::Error> {
+        let address = self.address(slot);
+        self.flash.read(address, &mut buf[..Self::entry_len()])
+    }
+
+    /// Replay the journal and return the newest valid record.
+    fn scan(&mut self) -> Result<Option<(u32, u16, BaselineRecord)>, F::Error> {
+        let mut newest: Option<(u32, u16, BaselineRecord)> = None;
+        let mut buf = [0; BUF_LEN];
+        for slot in 0..self.capacity() {
+            self.read_slot(slot, &mut buf)?;
+            if let Some((sequence, record)) = decode_sequenced(&buf) {
+                if newest.is_none_or(|(_, best, _)| is_newer(sequence, best)) {
+                    newest = Some((slot, sequence, record));
+                }
+            }
+        }
+        self.newest = Some(newest.map(|(slot, sequence, _)| (slot, sequence)));
+        Ok(newest)
+    }
+
+    /// Find the slot for the next record, erasing its page if the journal
+    /// wrapped around to the beginning of a page.
+    fn next_slot(&mut self, newest: Option<(u32, u16)>) -> Result<u32, F::Error> {
+        let mut slot = newest.map_or(0, |(slot, _)| (slot + 1) % self.capacity());
+        let mut buf = [0; BUF_LEN];
+        loop {
+            if slot.is_multiple_of(self.entries_per_page()) {
+                let address = self.address(slot);
+                self.flash.erase(address, address + F::ERASE_SIZE as u32)?;
+                return Ok(slot);
+            }
+            // Skip slots that are not erased, e.g. after a power loss during
+            // a write
+            self.read_slot(slot, &mut buf)?;
+            if buf[..Self::entry_len()].iter().all(|&b| b == 0xFF) {
+                return Ok(slot);
+            }
+            slot = (slot + 1) % self.capacity();
+        }
+    }
+}
+
+impl<F: NorFlash> BaselineStore for JournalStore<F> {
+    type Error = StorageError<F::Error>;
+
+    fn save(&mut self, record: &BaselineRecord) -> Result<(), Self::Error> {
+        if self.newest.is_none() {
+            self.scan().map_err(StorageError::Storage)?;
+        }
+        let newest = self.newest.flatten();
+        let sequence = newest.map_or(0, |(_, sequence)| sequence.wrapping_add(1));
+        let slot = self.next_slot(newest).map_err(StorageError::Storage)?;
+
+        let mut buf = [0xFF; BUF_LEN];
+        buf[..SEQUENCED_LEN].copy_from_slice(&encode_sequenced(sequence, record));
+        let address = self.address(slot);
+        self.flash
+            .write(address, &buf[..Self::entry_len()])
+            .map_err(StorageError::Storage)?;
+        self.newest = Some(Some((slot, sequence)));
+        Ok(())
+    }
+
+    fn load(&mut self) -> Result<Option<BaselineRecord>, Self::Error> {
+        self.scan()
+            .map(|newest| newest.map(|(_, _, record)| record))
+            .map_err(StorageError::Storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::flash::tests::{record, RamFlash};
+    use super::*;
+
+    #[test]
+    fn layout() {
+        let store = JournalStore::new(RamFlash::new(), 0, 2);
+        // Entries of 28 bytes in pages of 64 bytes
+        assert_eq!(store.entries_per_page(), 2);
+        assert_eq!(store.capacity(), 4);
+        assert_eq!(store.address(3), 64 + 28);
+    }
+
+    #[test]
+    fn append_and_wrap() {
+        let mut store = JournalStore::new(RamFlash::new(), 0, 2);
+        assert_eq!(store.load(), Ok(None));
+        for i in 0..6 {
+            store.save(&record(i, i)).unwrap();
+            assert_eq!(store.load(), Ok(Some(record(i, i))));
+        }
+        let flash = store.destroy();
+        // Page 0 was erased at the first and the fifth save, page 1 once
+        assert_eq!(flash.erase_count, 3);
+        // A new instance replays the journal and continues
+        let mut store = JournalStore::new(flash, 0, 2);
+        assert_eq!(store.load(), Ok(Some(record(5, 5))));
+        store.save(&record(6, 6)).unwrap();
+        let flash = store.destroy();
+        assert_eq!(flash.erase_count, 4);
+        assert_eq!(&flash.data[64..66], &[0, 6]);
+    }
+
+    /// A half-written entry is ignored and skipped.
+    #[test]
+    fn power_loss_during_write() {
+        let mut store = JournalStore::new(RamFlash::new(), 0, 2);
+        store.save(&record(1, 1)).unwrap();
+        let mut flash = store.destroy();
+        // Partially written second entry
+        flash.data[28] = 0x00;
+        flash.data[29] = 0x01;
+        let mut store = JournalStore::new(flash, 0, 2);
+        assert_eq!(store.load(), Ok(Some(record(1, 1))));
+        store.save(&record(2, 2)).unwrap();
+        assert_eq!(store.load(), Ok(Some(record(2, 2))));
+        let flash = store.destroy();
+        // Written to the next page instead
+        assert_eq!(&flash.data[64..66], &[0, 1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn single_page() {
+        JournalStore::new(RamFlash::new(), 0, 1);
+    }
+}
@@ -0,0 +1,158 @@
+//! Baseline persistence.
+//!
+//! The SGP30 loses its baseline on every power cycle. Sensirion recommends
+//! storing the baseline periodically and restoring it after
+//! [`init()`](../struct.Sgp30.html#method.init), so that the sensor does not
+//! need another 12 hours of burn-in to reach full accuracy.
+//!
+//! This module defines a common on-storage format,
+//! [`BaselineRecord`](struct.BaselineRecord.html), and the
+//! [`BaselineStore`](trait.BaselineStore.html) trait together with
+//! implementations for common storage backends:
+//!
+//! - [`EepromStore`](struct.EepromStore.html): 24xx-series I²C EEPROMs
+//! - [`FlashStore`](struct.FlashStore.html): NOR flash via the
+//!   `embedded-storage` traits (requires the `embedded-storage` feature)
+//! - [`DualFlashStore`](struct.DualFlashStore.html): Power-loss-safe variant
+//!   of `FlashStore` using two pages (requires the `embedded-storage` feature)
+//! - [`JournalStore`](struct.JournalStore.html): Wear-aware append-only
+//!   journal over several NOR flash pages (requires the `embedded-storage`
+//!   feature)
+//! - [`FileStore`](struct.FileStore.html): A file on a regular filesystem
+//!   (requires the `std` feature)
+
+use core::fmt;
+
+use Error;
+
+#[cfg(feature = "embedded-storage")]
+mod dual;
+mod eeprom;
+#[cfg(feature = "std")]
+mod file;
+#[cfg(feature = "embedded-storage")]
+mod flash;
+#[cfg(feature = "embedded-storage")]
+mod journal;
+mod record;
+
+pub use self::record::{BaselineRecord, RecordError};
+pub use self::eeprom::{AddressWidth, EepromStore, DEFAULT_EEPROM_ADDRESS, SLOT_SIZE};
+
+#[cfg(feature = "embedded-storage")]
+pub use self::dual::DualFlashStore;
+#[cfg(feature = "std")]
+pub use self::file::FileStore;
+#[cfg(feature = "embedded-storage")]
+pub use self::flash::FlashStore;
+#[cfg(feature = "embedded-storage")]
+pub use self::journal::JournalStore;
+
+/// A storage backend for baseline records.
+///
+/// Usually, the records are not saved and loaded directly, but using
+/// [`Sgp30::save_baseline()`](../struct.Sgp30.html#method.save_baseline) and
+/// [`Sgp30::restore_baseline_from()`](../struct.Sgp30.html#method.restore_baseline_from).
+pub trait BaselineStore {
+    /// The error type of the storage backend.
+    type Error;
+
+    /// Persist a record, replacing the previously stored one.
+    fn save(&mut self, record: &BaselineRecord) -> Result<(), Self::Error>;
+
+    /// Load the stored record, or `None` if no record was stored yet.
+    fn load(&mut self) -> Result<Option<BaselineRecord>, Self::Error>;
+}
+
+/// Errors of the storage backends.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StorageError<E> {
+    /// The underlying storage reported an error.
+    Storage(E),
+    /// The stored record is invalid.
+    Record(RecordError),
+}
+
+impl<E: fmt::Debug> fmt::Display for StorageError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            StorageError::Storage(ref e) => write!(f, "Storage error: {:?}", e),
+            StorageError::Record(ref e) => write!(f, "Invalid baseline record: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug> ::std::error::Error for StorageError<E> {}
+
+/// Decode a record read from storage. Erased storage (no magic bytes) is
+/// reported as `None`.
+#[cfg(any(feature = "embedded-storage", feature = "std"))]
+fn decode_stored<E>(buf: &[u8]) -> Result<Option<BaselineRecord>, StorageError<E>> {
+    match BaselineRecord::decode(buf) {
+        Ok(record) => Ok(Some(record)),
+        Err(RecordError::InvalidMagic) => Ok(None),
+        Err(e) => Err(StorageError::Record(e)),
+    }
+}
+
+/// Errors when saving or restoring a baseline with the driver.
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PersistError<E, S> {
+    /// Communication with the sensor failed.
+    Sensor(Error<E>),
+    /// The storage backend failed.
+    Storage(S),
+}
+
+impl<E: fmt::Debug, S: fmt::Debug> fmt::Display for PersistError<E, S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            PersistError::Sensor(ref e) => write!(f, "Sensor error: {}", e),
+            PersistError::Storage(ref e) => write!(f, "Baseline storage error: {:?}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug, S: fmt::Debug> ::std::error::Error for PersistError<E, S> {}
+
+/// The result of
+/// [`Sgp30::restore_baseline_from()`](../struct.Sgp30.html#method.restore_baseline_from).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RestoreOutcome {
+    /// The stored baseline was written to the sensor.
+    Restored,
+    /// No baseline was stored.
+    NotFound,
+    /// The stored baseline is older than one week and was ignored.
+    Expired,
+    /// The stored baseline was captured from a different sensor and was
+    /// ignored.
+    SerialMismatch {
+        /// The serial number of the sensor the baseline was captured from
+        stored: [u8; 6],
+    },
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A store keeping a single record in memory.
+    pub struct MemoryStore(pub Option<BaselineRecord>);
+
+    impl BaselineStore for MemoryStore {
+        type Error = ();
+        fn save(&mut self, record: &BaselineRecord) -> Result<(), ()> {
+            self.0 = Some(*record);
+            Ok(())
+        }
+        fn load(&mut self) -> Result<Option<BaselineRecord>, ()> {
+            Ok(self.0)
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use byteorder::{BigEndian, ByteOrder};
+use core::fmt;
+
+use protocol::crc8;
+use snapshot::BaselineSnapshot;
+use types::Baseline;
+
+/// Magic bytes identifying a baseline record.
+const MAGIC: [u8; 2] = *b"SB";
+
+/// A self-describing baseline record for non-volatile storage.
+///
+/// The encoded record has a fixed length of
+/// [`ENCODED_LEN`](#associatedconstant.ENCODED_LEN) bytes, all multi-byte
+/// values are big-endian:
+///
+/// | Offset | Length | Content                              |
+/// |--------|--------|--------------------------------------|
+/// | 0      | 2      | Magic bytes `SB`                     |
+/// | 2      | 1      | Format version                       |
+/// | 3      | 6      | Sensor serial number                 |
+/// | 9      | 4      | Baseline (CO₂eq, TVOC)               |
+/// | 13     | 8      | Timestamp in seconds                 |
+/// | 21     | 1      | CRC8 (as used by the SGP30) of 0..21 |
+///
+/// With the `serde` feature, the record can alternatively be serialized with
+/// any serde format, e.g. `postcard`.
+///
+/// ```
+/// use sgp30::Baseline;
+/// use sgp30::storage::BaselineRecord;
+///
+/// let record = BaselineRecord {
+///     serial: [0, 0, 1, 2, 3, 4],
+///     baseline: Baseline { co2eq: 0x8F3A, tvoc: 0x9012 },
+///     timestamp: 1_600_000_000,
+/// };
+/// let bytes = record.encode();
+/// assert_eq!(BaselineRecord::decode(&bytes), Ok(record));
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BaselineRecord {
+    /// The 48 bit serial number of the sensor the baseline was read from
+    pub serial: [u8; 6],
+    /// The baseline values
+    pub baseline: Baseline,
+    /// The time at which the baseline was read, in seconds
+    pub timestamp: u64,
+}
+
+impl BaselineRecord {
+    /// Whether the record was captured from the sensor with the given serial
+    /// number.
+    pub fn matches_serial(&self, serial: &[u8; 6]) -> bool {
+        &self.serial == serial
+    }
+
+    /// The current format version.
+    pub const VERSION: u8 = 1;
+
+    /// The length of an encoded record in bytes.
+    pub const ENCODED_LEN: usize = 22;
+
+    /// Create a record from a baseline snapshot.
+    pub fn from_snapshot(serial: [u8; 6], snapshot: &BaselineSnapshot<u64>) -> Self {
+        BaselineRecord {
+            serial,
+            baseline: snapshot.baseline,
+            timestamp: snapshot.stored_at,
+        }
+    }
+
+    /// Return the baseline and timestamp as snapshot, e.g. for
+    /// [`Sgp30::restore_baseline()`](../struct.Sgp30.html#method.restore_baseline).
+    pub fn to_snapshot(&self) -> BaselineSnapshot<u64> {
+        BaselineSnapshot::new(self.baseline, self.timestamp)
+    }
+
+    /// Encode the record.
+    pub fn encode(&self) -> [u8; BaselineRecord::ENCODED_LEN] {
+        let mut buf = [0; BaselineRecord::ENCODED_LEN];
+        buf[0..2].copy_from_slice(&MAGIC);
+        buf[2] = BaselineRecord::VERSION;
+        buf[3..9].copy_from_slice(&self.serial);
+        buf[9..13].copy_from_slice(&self.baseline.to_bytes());
+        BigEndian::write_u64(&mut buf[13..21], self.timestamp);
+        buf[21] = crc8(&buf[0..21]);
+        buf
+    }
+
+    /// Decode a record from the first
+    /// [`ENCODED_LEN`](#associatedconstant.ENCODED_LEN) bytes of `buf`.
+    pub fn decode(buf: &[u8]) -> Result<Self, RecordError> {
+        if buf.len() < BaselineRecord::ENCODED_LEN {
+            return Err(RecordError::TooShort);
+        }
+        if buf[0..2] != MAGIC {
+            return Err(RecordError::InvalidMagic);
+        }
+        if buf[2] != BaselineRecord::VERSION {
+            return Err(RecordError::UnsupportedVersion(buf[2]));
+        }
+        if crc8(&buf[0..21]) != buf[21] {
+            return Err(RecordError::Crc);
+        }
+        let mut serial = [0; 6];
+        serial.copy_from_slice(&buf[3..9]);
+        Ok(BaselineRecord {
+            serial,
+            baseline: Baseline::from_bytes([buf[9], buf[10], buf[11], buf[12]]),
+            timestamp: BigEndian::read_u64(&buf[13..21]),
+        })
+    }
+}
+
+/// Length of a sequence-numbered record, as used by the stores that rotate
+/// between several locations: A sequence number (big-endian) and its CRC,
+/// followed by the encoded record.
+pub(crate) const SEQUENCED_LEN: usize = 3 + BaselineRecord::ENCODED_LEN;
+
+/// Encode a sequence-numbered record.
+pub(crate) fn encode_sequenced(sequence: u16, record: &BaselineRecord) -> [u8; SEQUENCED_LEN] {
+    let mut buf = [0; SEQUENCED_LEN];
+    BigEndian::write_u16(&mut buf[0..2], sequence);
+    buf[2] = crc8(&buf[0..2]);
+    buf[3..].copy_from_slice(&record.encode());
+    buf
+}
+
+/// Decode a sequence-numbered record, return `None` if it is invalid.
+pub(crate) fn decode_sequenced(buf: &[u8]) -> Option<(u16, BaselineRecord)> {
+    if buf.len() < SEQUENCED_LEN || crc8(&buf[0..2]) != buf[2] {
+        return None;
+    }
+    let record = BaselineRecord::decode(&buf[3..]).ok()?;
+    Some((BigEndian::read_u16(&buf[0..2]), record))
+}
+
+/// Whether sequence number `a` is newer than `b`, taking wrap-around into
+/// account.
+pub(crate) fn is_newer(a: u16, b: u16) -> bool {
+    a != b && a.wrapping_sub(b) < 0x8000
+}
+
+/// Errors when decoding a [`BaselineRecord`](struct.BaselineRecord.html).
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecordError {
+    /// The buffer is shorter than a record.
+    TooShort,
+    /// The buffer does not start with the magic bytes, e.g. because the
+    /// storage is erased.
+    InvalidMagic,
+    /// The record uses an unknown format version.
+    UnsupportedVersion(u8),
+    /// The record has an invalid checksum.
+    Crc,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RecordError::TooShort => f.write_str("Buffer too short for a baseline record"),
+            RecordError::InvalidMagic => f.write_str("No baseline record found"),
+            RecordError::UnsupportedVersion(v) => {
+                write!(f, "Unsupported baseline record format version {}", v)
+            }
+            RecordError::Crc => f.write_str("Baseline record has an invalid checksum"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for RecordError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record() -> BaselineRecord {
+        BaselineRecord {
+            serial: [0x00, 0x00, 0x01, 0x8F, 0x3A, 0x12],
+            baseline: Baseline { co2eq: 0x8F3A, tvoc: 0x9012 },
+            timestamp: 0x0102_0304_0506_0708,
+        }
+    }
+
+    #[test]
+    fn encode_layout() {
+        let bytes = record().encode();
+        assert_eq!(&bytes[0..3], b"SB\x01");
+        assert_eq!(&bytes[3..9], &[0x00, 0x00, 0x01, 0x8F, 0x3A, 0x12]);
+        assert_eq!(&bytes[9..13], &[0x8F, 0x3A, 0x90, 0x12]);
+        assert_eq!(&bytes[13..21], &[1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(bytes[21], crc8(&bytes[0..21]));
+    }
+
+    #[test]
+    fn decode_errors() {
+        let mut bytes = record().encode();
+        assert_eq!(BaselineRecord::decode(&bytes[..21]), Err(RecordError::TooShort));
+        assert_eq!(BaselineRecord::decode(&[0xFF; 32]), Err(RecordError::InvalidMagic));
+        bytes[10] ^= 0x01;
+        assert_eq!(BaselineRecord::decode(&bytes), Err(RecordError::Crc));
+        bytes[2] = 2;
+        assert_eq!(BaselineRecord::decode(&bytes), Err(RecordError::UnsupportedVersion(2)));
+    }
+
+    #[test]
+    fn sequenced() {
+        let bytes = encode_sequenced(0x1234, &record());
+        assert_eq!(decode_sequenced(&bytes), Some((0x1234, record())));
+        let mut corrupt = bytes;
+        corrupt[1] ^= 0x01;
+        assert_eq!(decode_sequenced(&corrupt), None);
+        assert!(is_newer(1, 0));
+        assert!(is_newer(0, 0xFFFF));
+        assert!(!is_newer(0xFFFF, 0));
+        assert!(!is_newer(5, 5));
+    }
+
+    #[test]
+    fn snapshot_conversion() {
+        let snapshot = record().to_snapshot();
+        assert_eq!(snapshot.stored_at, 0x0102_0304_0506_0708);
+        assert_eq!(BaselineRecord::from_snapshot(record().serial, &snapshot), record());
+    }
+}
@@ -0,0 +1,274 @@
+//! A stream of trustworthy measurements.
+//!
+//! With the `stream` feature, [`Samples`](struct.Samples.html) turns the
+//! driver into a `futures_core::Stream` that owns the whole measurement
+//! cycle: A ticker stream, such as `embassy_time::Ticker` or a Tokio
+//! interval, sets the 1 Hz cadence; the air quality measurement is
+//! initialized on the first tick; the humidity compensation is refreshed
+//! periodically; and only measurements that can be trusted are yielded.
+//! Samples taken during the initialization phase and samples failing a
+//! [`PlausibilityCheck`](../plausibility/struct.PlausibilityCheck.html)
+//! are measured (to keep the baseline compensation running) but skipped.
+//!
+//! ```ignore
+//! let ticker = Ticker::every(Duration::from_secs(1));
+//! let mut samples = Samples::new(Sgp30::new(i2c, I2cAddress::DEFAULT, delay), ticker)
+//!     .with_humidity(|| sht.read().ok().and_then(|(t, rh)| absolute_humidity(t, rh).ok()), 60);
+//! while let Some(sample) = samples.next().await {
+//!     match sample {
+//!         Ok(measurement) => publish(measurement),
+//!         Err(e) => warn!("SGP30 error: {:?}", e),
+//!     }
+//! }
+//! ```
+//!
+//! The stream drives any [`Sgp30Api`](../middleware/trait.Sgp30Api.html),
+//! so the driver can be wrapped in the decorators of the
+//! [`middleware`](../middleware/index.html) module, e.g. to retry a failed
+//! initialization or humidity update; failed measurements are not
+//! repeated, so the cadence is kept. The I²C transfers themselves are blocking; a measurement
+//! takes about 12 ms.
+
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use middleware::Sgp30Api;
+use plausibility::{Limits, PlausibilityCheck};
+use types::{Humidity, Measurement};
+
+/// The humidity compensation of a [`Samples`](struct.Samples.html) stream.
+#[derive(Debug, Copy, Clone)]
+struct HumidityRefresh<H> {
+    /// Returns the current absolute humidity, or `None` to disable the
+    /// compensation.
+    source: H,
+    /// Number of ticks between two refreshes.
+    interval: u32,
+}
+
+/// A stream of plausible measurements, taken at the cadence of a ticker.
+///
+/// Errors are yielded as items; the stream continues with the next tick.
+/// It ends when the ticker ends.
+pub struct Samples<A, T, H = fn() -> Option<Humidity>> {
+    sgp: A,
+    ticker: T,
+    check: PlausibilityCheck,
+    humidity: Option<HumidityRefresh<H>>,
+    /// Ticks since the air quality measurement was initialized.
+    ticks: u32,
+    /// Number of implausible samples skipped.
+    skipped: u32,
+}
+
+impl<A: Sgp30Api, T> Samples<A, T> {
+    /// Create a stream measuring on every tick of `ticker`, which should
+    /// tick every second.
+    ///
+    /// Samples are checked against the datasheet
+    /// [`Limits`](../plausibility/struct.Limits.html).
+    pub fn new(sgp: A, ticker: T) -> Self {
+        Samples {
+            sgp,
+            ticker,
+            check: PlausibilityCheck::new(Limits::DATASHEET),
+            humidity: None,
+            ticks: 0,
+            skipped: 0,
+        }
+    }
+
+}
+
+impl<A: Sgp30Api, T, H: FnMut() -> Option<Humidity>> Samples<A, T, H> {
+    /// Check samples against different limits.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.check = PlausibilityCheck::new(limits);
+        self
+    }
+
+    /// Set the humidity compensation to the value returned by `source`
+    /// after the initialization and then every `interval` ticks.
+    ///
+    /// The source is typically a closure reading another sensor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `interval` is 0.
+    pub fn with_humidity<H2>(self, source: H2, interval: u32) -> Samples<A, T, H2>
+    where
+        H2: FnMut() -> Option<Humidity>,
+    {
+        assert!(interval > 0, "the humidity refresh interval must not be 0");
+        Samples {
+            sgp: self.sgp,
+            ticker: self.ticker,
+            check: self.check,
+            humidity: Some(HumidityRefresh { source, interval }),
+            ticks: self.ticks,
+            skipped: self.skipped,
+        }
+    }
+
+    /// Return the number of implausible samples skipped.
+    pub fn skipped(&self) -> u32 {
+        self.skipped
+    }
+
+    /// Access the driver, e.g. to save the baseline between samples.
+    ///
+    /// Re-initializing the air quality measurement through the driver is
+    /// noticed on the next tick, and restarts the initialization phase.
+    pub fn sgp(&mut self) -> &mut A {
+        &mut self.sgp
+    }
+
+    /// Destroy the stream and return the driver and the ticker.
+    pub fn destroy(self) -> (A, T) {
+        (self.sgp, self.ticker)
+    }
+
+    /// Run one measurement cycle. Return `None` if the sample is skipped.
+    fn tick(&mut self) -> Option<Result<Measurement, A::Error>> {
+        if !self.sgp.is_initialized() {
+            if let Err(e) = self.sgp.init() {
+                return Some(Err(e));
+            }
+            self.check.reset();
+            self.ticks = 0;
+        }
+        if let Some(ref mut refresh) = self.humidity {
+            if self.ticks.is_multiple_of(refresh.interval) {
+                let humidity = (refresh.source)();
+                if let Err(e) = self.sgp.set_humidity(humidity.as_ref()) {
+                    return Some(Err(e));
+                }
+            }
+        }
+        self.ticks = self.ticks.wrapping_add(1);
+        let measurement = match self.sgp.measure() {
+            Ok(measurement) => measurement,
+            Err(e) => return Some(Err(e)),
+        };
+        let warming_up = self.check.is_warming_up();
+        let issues = self.check.check(&measurement);
+        if warming_up {
+            return None;
+        }
+        if !issues.is_empty() {
+            log_event!(warn, "Skipping implausible {:?}: {:?}", measurement, issues);
+            self.skipped = self.skipped.wrapping_add(1);
+            return None;
+        }
+        Some(Ok(measurement))
+    }
+}
+
+impl<A, T, H> Stream for Samples<A, T, H>
+where
+    A: Sgp30Api + Unpin,
+    T: Stream + Unpin,
+    H: FnMut() -> Option<Humidity> + Unpin,
+{
+    type Item = Result<Measurement, A::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match Pin::new(&mut this.ticker).poll_next(cx) {
+                Poll::Ready(Some(_)) => {}
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+            // Skipped samples wait for the next tick
+            if let Some(item) = this.tick() {
+                return Poll::Ready(Some(item));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::task::Waker;
+
+    use super::*;
+    use middleware::Retrying;
+    use plausibility::WARMUP_SAMPLES;
+    use simulator::{Fault, SimulatedSgp30, Trajectory};
+    use {Error, Sgp30};
+
+    /// A ticker ticking `n` times, then ending.
+    struct Ticks(u32);
+
+    impl Stream for Ticks {
+        type Item = ();
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<()>> {
+            if self.0 == 0 {
+                return Poll::Ready(None);
+            }
+            self.0 -= 1;
+            Poll::Ready(Some(()))
+        }
+    }
+
+    fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        match Pin::new(stream).poll_next(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(item) => item,
+            Poll::Pending => panic!("stream is pending"),
+        }
+    }
+
+    #[test]
+    fn samples() {
+        // A spike at 20 s, which is a jump up and back down
+        let trajectory = Trajectory::Custom(|t| Measurement {
+            co2eq_ppm: if t == 20 { 5000 } else { 1000 },
+            tvoc_ppb: 200,
+        });
+        // The first measurement is not acknowledged
+        let sensor = SimulatedSgp30::new(trajectory)
+            .with_reference_baseline(::simulator::INITIAL_BASELINE)
+            .with_fault(2, Fault::Nack);
+        let mut readings = 0;
+        let mut samples = Samples::new(Sgp30::simulated(sensor), Ticks(WARMUP_SAMPLES + 10))
+            .with_limits(Limits::DATASHEET.with_max_step(100, 100))
+            .with_humidity(
+                || {
+                    readings += 1;
+                    Humidity::new(8, 0).ok()
+                },
+                5,
+            );
+
+        assert!(matches!(next(&mut samples), Some(Err(Error::I2c { .. }))));
+        // The initialization phase and the spike are skipped
+        for _ in 0..7 {
+            assert_eq!(next(&mut samples).unwrap().unwrap(), Measurement { co2eq_ppm: 1000, tvoc_ppb: 200 });
+        }
+        assert!(next(&mut samples).is_none());
+        assert_eq!(samples.skipped(), 2);
+
+        let (sgp, _) = samples.destroy();
+        let sensor = sgp.destroy();
+        assert_eq!(sensor.time_secs(), WARMUP_SAMPLES + 9);
+        assert_eq!(sensor.humidity(), Humidity::new(8, 0).ok());
+        assert_eq!(readings, 5);
+    }
+
+    #[test]
+    fn samples_retrying() {
+        // The initialization is not acknowledged, and repeated
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement { co2eq_ppm: 1000, tvoc_ppb: 200 }))
+            .with_reference_baseline(::simulator::INITIAL_BASELINE)
+            .with_fault(0, Fault::Nack);
+        let sgp = Retrying::new(Sgp30::simulated(sensor), 2);
+        let mut samples = Samples::new(sgp, Ticks(WARMUP_SAMPLES + 1));
+        assert_eq!(next(&mut samples).unwrap().unwrap(), Measurement { co2eq_ppm: 1000, tvoc_ppb: 200 });
+        assert!(next(&mut samples).is_none());
+        assert_eq!(samples.sgp().retries(), 1);
+    }
+}
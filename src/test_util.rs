@@ -0,0 +1,281 @@
+//! Expectations for testing code that uses the driver.
+//!
+//! With the `test-util` feature, applications can unit-test their SGP30
+//! integration against a mock I²C bus without calculating checksums by
+//! hand. Every driver method has a function of the same name returning the
+//! [`Transaction`](struct.Transaction.html) it performs: the bytes the
+//! driver writes and a valid response for the given result.
+//!
+//! ```
+//! # extern crate embedded_hal_mock;
+//! # extern crate sgp30;
+//! use embedded_hal_mock::{DelayMockNoop, I2cMock};
+//! use sgp30::{I2cAddress, Measurement, Sgp30};
+//! use sgp30::test_util;
+//!
+//! # fn main() {
+//! let measurement = Measurement { co2eq_ppm: 450, tvoc_ppb: 12 };
+//! let expected = test_util::measure(&measurement);
+//!
+//! let mut i2c = I2cMock::new();
+//! i2c.set_read_data(&expected.read);
+//! let mut sgp = Sgp30::new(i2c, I2cAddress::DEFAULT, DelayMockNoop);
+//! sgp.init().unwrap();
+//! assert_eq!(sgp.measure().unwrap(), measurement);
+//! assert_eq!(sgp.destroy().get_write_data(), &expected.write[..]);
+//! # }
+//! ```
+
+use byteorder::{BigEndian, ByteOrder};
+use heapless::Vec;
+
+use protocol::{self, Command, MAX_FRAME_LEN};
+use types::{Baseline, FeatureSet, Humidity, Measurement, RawSignals};
+
+/// The I²C address of the SGP30.
+pub const ADDRESS: u8 = 0x58;
+
+/// The I²C general call address, used by
+/// [`soft_reset()`](fn.soft_reset.html).
+pub const GENERAL_CALL_ADDRESS: u8 = 0x00;
+
+/// Maximum length of a response: The three words of the serial number,
+/// each followed by a CRC byte.
+pub const MAX_RESPONSE_LEN: usize = 9;
+
+/// The bytes written in a transaction.
+pub type Request = Vec<u8, MAX_FRAME_LEN>;
+
+/// The bytes read in a transaction.
+pub type Response = Vec<u8, MAX_RESPONSE_LEN>;
+
+/// A transaction of the driver with the sensor: A write, followed by a read
+/// after the command's execution time unless the response is empty.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Transaction {
+    /// The command
+    pub command: Command,
+    /// The I²C address written to
+    pub address: u8,
+    /// The bytes written
+    pub write: Request,
+    /// The bytes read
+    pub read: Response,
+}
+
+impl Transaction {
+    /// Create a transaction sending `command` with the data `words` to the
+    /// SGP30 and reading the `response` words.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are more than two data words or more than three
+    /// response words.
+    pub fn new(command: Command, words: &[u16], response_words: &[u16]) -> Self {
+        Transaction {
+            command,
+            address: ADDRESS,
+            write: request(command, words),
+            read: response(response_words),
+        }
+    }
+}
+
+/// Return the bytes written to send `command` with the data `words`.
+///
+/// # Panics
+///
+/// Panics if there are more than two data words.
+pub fn request(command: Command, words: &[u16]) -> Request {
+    let mut buf = [0; MAX_FRAME_LEN];
+    let len = protocol::encode_frame(command, words, &mut buf);
+    Vec::from_slice(&buf[..len]).unwrap()
+}
+
+/// Return a valid response consisting of `words`, each followed by its
+/// checksum.
+///
+/// # Panics
+///
+/// Panics if there are more than three words.
+pub fn response(words: &[u16]) -> Response {
+    let mut buf = Response::new();
+    for &word in words {
+        let mut bytes = [0; 3];
+        BigEndian::write_u16(&mut bytes[0..2], word);
+        bytes[2] = protocol::crc8(&bytes[0..2]);
+        buf.extend_from_slice(&bytes).expect("a response has at most three words");
+    }
+    buf
+}
+
+/// The transaction of [`Sgp30::serial()`](../struct.Sgp30.html#method.serial).
+pub fn serial(serial: &[u8; 6]) -> Transaction {
+    let words = [
+        BigEndian::read_u16(&serial[0..2]),
+        BigEndian::read_u16(&serial[2..4]),
+        BigEndian::read_u16(&serial[4..6]),
+    ];
+    Transaction::new(Command::GetSerial, &[], &words)
+}
+
+/// The transaction of [`Sgp30::selftest()`](../struct.Sgp30.html#method.selftest),
+/// returning `success`.
+pub fn selftest(success: bool) -> Transaction {
+    // Any pattern other than 0xD400 indicates a failure
+    Transaction::new(Command::SelfTest, &[], &[if success { 0xD400 } else { 0x4B00 }])
+}
+
+/// The transaction of [`Sgp30::init()`](../struct.Sgp30.html#method.init).
+pub fn init() -> Transaction {
+    Transaction::new(Command::InitAirQuality, &[], &[])
+}
+
+/// The transaction of [`Sgp30::measure()`](../struct.Sgp30.html#method.measure),
+/// without autosave or calibration.
+pub fn measure(measurement: &Measurement) -> Transaction {
+    Transaction::new(Command::MeasureAirQuality, &[], &[measurement.co2eq_ppm, measurement.tvoc_ppb])
+}
+
+/// The transaction of
+/// [`Sgp30::measure_raw_signals()`](../struct.Sgp30.html#method.measure_raw_signals).
+pub fn measure_raw_signals(signals: &RawSignals) -> Transaction {
+    Transaction::new(Command::MeasureRawSignals, &[], &[signals.h2, signals.ethanol])
+}
+
+/// The transaction of [`Sgp30::get_baseline()`](../struct.Sgp30.html#method.get_baseline).
+pub fn get_baseline(baseline: &Baseline) -> Transaction {
+    Transaction::new(Command::GetBaseline, &[], &[baseline.co2eq, baseline.tvoc])
+}
+
+/// The transaction of [`Sgp30::set_baseline()`](../struct.Sgp30.html#method.set_baseline),
+/// without read-back verification.
+pub fn set_baseline(baseline: &Baseline) -> Transaction {
+    Transaction::new(Command::SetBaseline, &[baseline.co2eq, baseline.tvoc], &[])
+}
+
+/// The transaction of [`Sgp30::set_humidity()`](../struct.Sgp30.html#method.set_humidity).
+pub fn set_humidity(humidity: Option<&Humidity>) -> Transaction {
+    let word = humidity.map_or(0, |humidity| BigEndian::read_u16(&humidity.as_bytes()));
+    Transaction::new(Command::SetHumidity, &[word], &[])
+}
+
+/// The transaction of
+/// [`Sgp30::get_feature_set()`](../struct.Sgp30.html#method.get_feature_set).
+pub fn get_feature_set(feature_set: &FeatureSet) -> Transaction {
+    let msb = feature_set.product_type.as_u8() << 4 | feature_set.reserved & 0x0F;
+    let word = u16::from(msb) << 8 | u16::from(feature_set.product_version);
+    Transaction::new(Command::GetFeatureSet, &[], &[word])
+}
+
+/// The transaction of [`Sgp30::soft_reset()`](../struct.Sgp30.html#method.soft_reset):
+/// A single byte sent to the general call address.
+pub fn soft_reset() -> Transaction {
+    let [address, byte] = Command::SoftReset.as_bytes();
+    Transaction {
+        command: Command::SoftReset,
+        address,
+        write: Vec::from_slice(&[byte]).unwrap(),
+        read: Response::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate embedded_hal_mock as hal;
+
+    use super::*;
+    use types::ProductType;
+    use {I2cAddress, Sgp30};
+
+    fn mock_sgp(transaction: &Transaction) -> Sgp30<hal::I2cMock<'_>, hal::DelayMockNoop> {
+        let mut dev = hal::I2cMock::new();
+        dev.set_read_data(&transaction.read);
+        let mut sgp = Sgp30::new(dev, I2cAddress::DEFAULT, hal::DelayMockNoop);
+        sgp.init().unwrap();
+        sgp
+    }
+
+    fn check(sgp: Sgp30<hal::I2cMock, hal::DelayMockNoop>, transaction: &Transaction) {
+        let dev = sgp.destroy();
+        assert_eq!(dev.get_last_address(), Some(transaction.address));
+        assert_eq!(dev.get_write_data(), &transaction.write[..]);
+    }
+
+    #[test]
+    fn frames() {
+        assert_eq!(&request(Command::GetBaseline, &[])[..], &[0x20, 0x15]);
+        assert_eq!(
+            &request(Command::SetBaseline, &[0x1234, 0x5678])[..],
+            &[0x20, 0x1E, 0x12, 0x34, 0x37, 0x56, 0x78, 0x7D],
+        );
+        assert_eq!(&response(&[0x1234, 0xD402])[..], &[0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4]);
+        assert!(response(&[]).is_empty());
+        assert_eq!(&init().write[..], &[0x20, 0x03]);
+        assert_eq!(&soft_reset().write[..], &[0x06]);
+        assert_eq!(soft_reset().address, GENERAL_CALL_ADDRESS);
+    }
+
+    #[test]
+    fn driver() {
+        let expected = serial(&[0, 0, 0, 100, 204, 130]);
+        assert_eq!(&expected.read[..], &[0, 0, 129, 0, 100, 254, 204, 130, 135]);
+        let mut sgp = mock_sgp(&expected);
+        assert_eq!(sgp.serial().unwrap(), [0, 0, 0, 100, 204, 130]);
+        check(sgp, &expected);
+
+        for &success in &[true, false] {
+            let expected = selftest(success);
+            let mut sgp = mock_sgp(&expected);
+            assert_eq!(sgp.selftest().unwrap(), success);
+            check(sgp, &expected);
+        }
+
+        let measurement = Measurement { co2eq_ppm: 4_660, tvoc_ppb: 54_274 };
+        let expected = measure(&measurement);
+        let mut sgp = mock_sgp(&expected);
+        assert_eq!(sgp.measure().unwrap(), measurement);
+        check(sgp, &expected);
+
+        let signals = RawSignals { h2: 13_600, ethanol: 18_500 };
+        let expected = measure_raw_signals(&signals);
+        let mut sgp = mock_sgp(&expected);
+        assert_eq!(sgp.measure_raw_signals().unwrap(), signals);
+        check(sgp, &expected);
+
+        let baseline = Baseline { co2eq: 0x1234, tvoc: 0x5678 };
+        let expected = get_baseline(&baseline);
+        let mut sgp = mock_sgp(&expected);
+        assert_eq!(sgp.get_baseline().unwrap(), baseline);
+        check(sgp, &expected);
+
+        let expected = set_baseline(&baseline);
+        let mut sgp = mock_sgp(&expected);
+        sgp.set_baseline(&baseline).unwrap();
+        check(sgp, &expected);
+
+        let humidity = Humidity::from_f32(15.5).unwrap();
+        for humidity in &[Some(&humidity), None] {
+            let expected = set_humidity(*humidity);
+            let mut sgp = mock_sgp(&expected);
+            sgp.set_humidity(*humidity).unwrap();
+            check(sgp, &expected);
+        }
+
+        let feature_set = FeatureSet {
+            product_type: ProductType::Sgp30,
+            product_version: 0x42,
+            reserved: 0,
+        };
+        let expected = get_feature_set(&feature_set);
+        assert_eq!(&expected.read[..], &[0x00, 0x42, 0xDE]);
+        let mut sgp = mock_sgp(&expected);
+        assert_eq!(sgp.get_feature_set().unwrap(), feature_set);
+        check(sgp, &expected);
+
+        let expected = soft_reset();
+        let mut sgp = mock_sgp(&expected);
+        sgp.soft_reset().unwrap();
+        check(sgp, &expected);
+    }
+}
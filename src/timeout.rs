@@ -0,0 +1,47 @@
+/// Time limit for transactions.
+///
+/// With a timeout set via
+/// [`Sgp30::set_timeout()`](struct.Sgp30.html#method.set_timeout), the
+/// driver measures the time spent on each transaction: the command write,
+/// the wait for command execution and the response read, including retries
+/// and their backoff. The clock is checked after every transfer, successful
+/// or not, and after the wait. Once the limit is exceeded, `Error::Timeout`
+/// is returned and no further transfers are made, so a slow or misbehaving
+/// bus cannot keep the driver busy indefinitely. The limit must therefore
+/// be longer than the execution time of the commands used, e.g. 12 ms for
+/// a measurement. In the split-phase API, the write and the read are
+/// limited separately.
+///
+/// The clock is a function returning a free-running microsecond counter,
+/// e.g. derived from a cycle counter or a hardware timer. It may wrap
+/// around.
+///
+/// Note that the driver cannot interrupt a blocking I²C call that never
+/// returns, e.g. when the HAL waits forever for a slave that keeps SCL
+/// low. Use the timeout of the I²C peripheral, if it has one, and a
+/// hardware watchdog (see the [`watchdog`](watchdog/index.html) module) for
+/// that case.
+#[derive(Debug, Copy, Clone)]
+pub struct Timeout {
+    /// Function returning the current time in µs
+    pub clock: fn() -> u32,
+    /// Maximum time per transaction in µs, including retries
+    pub limit_us: u32,
+}
+
+impl Timeout {
+    /// Create a new timeout.
+    pub fn new(clock: fn() -> u32, limit_us: u32) -> Self {
+        Timeout { clock, limit_us }
+    }
+
+    /// Return the current time.
+    pub(crate) fn now(&self) -> u32 {
+        (self.clock)()
+    }
+
+    /// Whether the limit was exceeded since `start`.
+    pub(crate) fn is_expired(&self, start: u32) -> bool {
+        self.now().wrapping_sub(start) >= self.limit_us
+    }
+}
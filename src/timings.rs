@@ -0,0 +1,185 @@
+use hal::blocking::delay::DelayUs;
+
+use protocol::Command;
+
+/// Time the driver waits for the sensor to execute each command, in µs.
+///
+/// By default, the driver waits for the maximum durations from the
+/// datasheet (Table 10). Most sensors finish faster, so battery powered
+/// devices can reclaim a few milliseconds of awake time per measurement by
+/// using the typical durations, at the risk of reading a response too early
+/// (which the sensor answers with a NACK, see
+/// [`RetryPolicy`](struct.RetryPolicy.html)). Single commands can be
+/// overridden:
+///
+/// ```
+/// use sgp30::Timings;
+///
+/// let timings = Timings {
+///     measure_air_quality_us: 11_000,
+///     ..Timings::TYPICAL
+/// };
+/// ```
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timings {
+    /// Wait time of `get_serial`
+    pub get_serial_us: u32,
+    /// Wait time of `measure_test`
+    pub selftest_us: u32,
+    /// Wait time of `init_air_quality`
+    pub init_air_quality_us: u32,
+    /// Wait time of `measure_air_quality`
+    pub measure_air_quality_us: u32,
+    /// Wait time of `measure_raw_signals`
+    pub measure_raw_signals_us: u32,
+    /// Wait time of `get_baseline`
+    pub get_baseline_us: u32,
+    /// Wait time of `set_baseline`
+    pub set_baseline_us: u32,
+    /// Wait time of `set_humidity`
+    pub set_humidity_us: u32,
+    /// Wait time of `get_feature_set`
+    pub get_feature_set_us: u32,
+    /// Wait time after a soft reset
+    pub soft_reset_us: u32,
+}
+
+impl Timings {
+    /// Maximum durations according to the datasheet.
+    pub const MAX: Timings = Timings {
+        get_serial_us: 500,
+        selftest_us: 220_000,
+        init_air_quality_us: 10_000,
+        measure_air_quality_us: 12_000,
+        measure_raw_signals_us: 25_000,
+        get_baseline_us: 10_000,
+        set_baseline_us: 10_000,
+        set_humidity_us: 10_000,
+        get_feature_set_us: 2_000,
+        soft_reset_us: 1_000,
+    };
+
+    /// Typical durations according to the datasheet.
+    pub const TYPICAL: Timings = Timings {
+        get_serial_us: 500,
+        selftest_us: 200_000,
+        init_air_quality_us: 2_000,
+        measure_air_quality_us: 10_000,
+        measure_raw_signals_us: 20_000,
+        get_baseline_us: 2_000,
+        set_baseline_us: 2_000,
+        set_humidity_us: 1_000,
+        get_feature_set_us: 1_000,
+        soft_reset_us: 600,
+    };
+
+    /// Return the wait time of a command.
+    pub fn duration_us(&self, command: Command) -> u32 {
+        match command {
+            Command::GetSerial => self.get_serial_us,
+            Command::SelfTest => self.selftest_us,
+            Command::InitAirQuality => self.init_air_quality_us,
+            Command::MeasureAirQuality => self.measure_air_quality_us,
+            Command::MeasureRawSignals => self.measure_raw_signals_us,
+            Command::GetBaseline => self.get_baseline_us,
+            Command::SetBaseline => self.set_baseline_us,
+            Command::SetHumidity => self.set_humidity_us,
+            Command::GetFeatureSet => self.get_feature_set_us,
+            Command::SoftReset => self.soft_reset_us,
+        }
+    }
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Timings::MAX
+    }
+}
+
+/// How the driver waits for the sensor to execute a command.
+///
+/// After writing a command, the driver calls the strategy with the wait time
+/// from its [`Timings`](struct.Timings.html) before reading the response (or
+/// returning, for commands without a response). The default strategy,
+/// [`Timed`](struct.Timed.html), blocks on the delay provider. Whether the
+/// maximum or typical durations are waited for is chosen with
+/// [`Sgp30::set_timings()`](struct.Sgp30.html#method.set_timings).
+///
+/// Other strategies can e.g. power-gate the MCU until a timer fires, or
+/// wait for an event and let the [`RetryPolicy`](struct.RetryPolicy.html)
+/// handle a response that is not ready yet:
+///
+/// ```
+/// # extern crate embedded_hal;
+/// # extern crate sgp30;
+/// use embedded_hal::blocking::delay::DelayUs;
+/// use sgp30::{Command, DelayStrategy, Timed};
+///
+/// /// Sleeps in a low power mode during measurements
+/// struct LowPower;
+///
+/// impl<D: DelayUs<u16>> DelayStrategy<D> for LowPower {
+///     fn wait(&mut self, command: Command, duration_us: u32, delay: &mut D) {
+///         match command {
+///             Command::MeasureAirQuality => { /* sleep until a timer wakes us */ }
+///             // Waits longer than `u16::MAX` µs, e.g. the self-test, are
+///             // split by `Timed`
+///             _ => Timed.wait(command, duration_us, delay),
+///         }
+///     }
+/// }
+/// # fn main() {}
+/// ```
+pub trait DelayStrategy<D> {
+    /// Wait for the sensor to execute `command`, which takes up to
+    /// `duration_us` according to the driver's timings.
+    fn wait(&mut self, command: Command, duration_us: u32, delay: &mut D);
+}
+
+/// Wait for the command execution time using the delay provider (default).
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct Timed;
+
+impl<D: DelayUs<u16>> DelayStrategy<D> for Timed {
+    fn wait(&mut self, _command: Command, duration_us: u32, delay: &mut D) {
+        delay_us(delay, duration_us);
+    }
+}
+
+/// Do not wait at all.
+///
+/// For sensors that are polled until they respond, e.g. combined with a
+/// [`RetryPolicy`](struct.RetryPolicy.html), or transports that wait on
+/// their own.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct NoWait;
+
+impl<D> DelayStrategy<D> for NoWait {
+    fn wait(&mut self, _command: Command, _duration_us: u32, _delay: &mut D) {}
+}
+
+/// Wait for `duration_us` with a delay provider that only provides
+/// `DelayUs<u16>`, splitting waits longer than 65 ms into several delays.
+pub(crate) fn delay_us<D: DelayUs<u16>>(delay: &mut D, duration_us: u32) {
+    let mut remaining = duration_us;
+    while remaining > 0 {
+        let step = remaining.min(u32::from(u16::MAX));
+        delay.delay_us(step as u16);
+        remaining -= step;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duration_us() {
+        assert_eq!(Timings::MAX.duration_us(Command::MeasureAirQuality), 12_000);
+        assert_eq!(Timings::TYPICAL.duration_us(Command::MeasureAirQuality), 10_000);
+        let timings = Timings { set_humidity_us: 5_000, ..Timings::TYPICAL };
+        assert_eq!(timings.duration_us(Command::SetHumidity), 5_000);
+    }
+}
@@ -0,0 +1,485 @@
+//! Recording and replaying I²C traffic.
+//!
+//! A [`TranscriptRecorder`](struct.TranscriptRecorder.html) wraps a bus and
+//! captures every transfer, including failed ones, into a compact binary
+//! transcript. [`Replay`](struct.Replay.html) is a bus that plays a transcript
+//! back and checks that the driver sends the same requests. Together they
+//! turn traffic captured on misbehaving hardware into regression tests:
+//!
+//! ```
+//! use sgp30::{Error, I2cAddress, Measurement, Sgp30};
+//! use sgp30::simulator::{Fault, SimulatedSgp30, Trajectory};
+//! use sgp30::transcript::{Replay, TranscriptRecorder};
+//!
+//! // Capture, e.g. on the real sensor
+//! let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement::default()))
+//!     .with_fault(2, Fault::CorruptCrc);
+//! let bus = TranscriptRecorder::new(sensor, heapless::Vec::<u8, 64>::new());
+//! let mut sgp = Sgp30::new(bus, I2cAddress::DEFAULT, sgp30::NoDelay);
+//! sgp.init().unwrap();
+//! assert!(matches!(sgp.measure(), Err(Error::Crc { .. })));
+//! let (_, transcript) = sgp.destroy().destroy();
+//!
+//! // Replay, e.g. in a test
+//! let mut sgp = Sgp30::new(Replay::new(&transcript), I2cAddress::DEFAULT, sgp30::NoDelay);
+//! sgp.init().unwrap();
+//! assert!(matches!(sgp.measure(), Err(Error::Crc { .. })));
+//! assert!(sgp.destroy().is_finished());
+//! ```
+//!
+//! # Format
+//!
+//! A transcript is a sequence of entries, one per transfer. Every entry
+//! starts with a four byte header, followed by the written and the read
+//! bytes:
+//!
+//! | Byte | Content                                                       |
+//! |------|---------------------------------------------------------------|
+//! | 0    | Bits 0-1: kind (0 = write, 1 = read, 2 = write-read); bit 7: failed |
+//! | 1    | I²C address                                                   |
+//! | 2    | Number of written bytes                                       |
+//! | 3    | Number of read bytes                                          |
+//!
+//! The read bytes are omitted for failed transfers, since the content of the
+//! buffer is undefined. The error itself is not recorded; on replay, a
+//! failed transfer returns
+//! [`ReplayError::Recorded`](enum.ReplayError.html#variant.Recorded).
+
+use core::convert::TryFrom;
+use core::fmt;
+
+use hal::blocking::i2c::{Read, Write, WriteRead};
+
+/// Length of the entry header.
+const HEADER_LEN: usize = 4;
+
+/// Flag for failed transfers in the first header byte.
+const FLAG_FAILED: u8 = 0x80;
+
+/// Mask for the kind in the first header byte.
+const KIND_MASK: u8 = 0x03;
+
+/// The kind of a transfer.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Kind {
+    /// `Write::write()`
+    Write,
+    /// `Read::read()`
+    Read,
+    /// `WriteRead::write_read()`
+    WriteRead,
+}
+
+impl Kind {
+    /// Return the encoded kind.
+    fn as_u8(self) -> u8 {
+        match self {
+            Kind::Write => 0,
+            Kind::Read => 1,
+            Kind::WriteRead => 2,
+        }
+    }
+
+    /// Decode a kind.
+    fn from_u8(val: u8) -> Option<Kind> {
+        match val {
+            0 => Some(Kind::Write),
+            1 => Some(Kind::Read),
+            2 => Some(Kind::WriteRead),
+            _ => None,
+        }
+    }
+}
+
+/// A recorded transfer.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub struct Entry<'a> {
+    /// The kind of transfer
+    pub kind: Kind,
+    /// The I²C address
+    pub address: u8,
+    /// The written bytes
+    pub written: &'a [u8],
+    /// The number of bytes read
+    pub read_len: usize,
+    /// The read bytes, or `None` if the transfer failed
+    pub read: Option<&'a [u8]>,
+}
+
+impl<'a> Entry<'a> {
+    /// Whether the transfer failed.
+    pub fn failed(&self) -> bool {
+        self.read.is_none()
+    }
+}
+
+/// An error decoding a transcript.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MalformedError {
+    /// Offset of the malformed entry
+    pub offset: usize,
+}
+
+impl fmt::Display for MalformedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Malformed transcript entry at offset {}", self.offset)
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for MalformedError {}
+
+/// An iterator over the entries of a transcript.
+#[derive(Debug, Clone)]
+pub struct Entries<'a> {
+    /// The transcript.
+    transcript: &'a [u8],
+    /// Offset of the next entry.
+    offset: usize,
+}
+
+/// Iterate over the entries of a transcript.
+pub fn entries(transcript: &[u8]) -> Entries<'_> {
+    Entries { transcript, offset: 0 }
+}
+
+impl<'a> Entries<'a> {
+    /// Return the offset of the next entry.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<Entry<'a>, MalformedError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = &self.transcript[self.offset..];
+        if rest.is_empty() {
+            return None;
+        }
+        let error = MalformedError { offset: self.offset };
+        let entry = (|| {
+            let header = rest.get(..HEADER_LEN)?;
+            let kind = Kind::from_u8(header[0] & KIND_MASK)?;
+            let failed = header[0] & FLAG_FAILED != 0;
+            let written_len = usize::from(header[2]);
+            let read_len = usize::from(header[3]);
+            let data_len = written_len + if failed { 0 } else { read_len };
+            let data = rest.get(HEADER_LEN..HEADER_LEN + data_len)?;
+            Some(Entry {
+                kind,
+                address: header[1],
+                written: &data[..written_len],
+                read_len,
+                read: if failed { None } else { Some(&data[written_len..]) },
+            })
+        })();
+        match entry {
+            Some(entry) => {
+                self.offset += HEADER_LEN + entry.written.len() + entry.read.map_or(0, <[u8]>::len);
+                Some(Ok(entry))
+            }
+            None => {
+                // Stop after the first malformed entry
+                self.offset = self.transcript.len();
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Storage for a transcript.
+pub trait Sink {
+    /// Append the bytes of an entry. Either all or none of the bytes must be
+    /// stored; return whether they were.
+    fn append(&mut self, bytes: &[&[u8]]) -> bool;
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> Sink for heapless::Vec<u8, N> {
+    fn append(&mut self, bytes: &[&[u8]]) -> bool {
+        let len: usize = bytes.iter().map(|b| b.len()).sum();
+        if self.len() + len > N {
+            return false;
+        }
+        for b in bytes {
+            // Cannot fail, the capacity was checked
+            let _ = self.extend_from_slice(b);
+        }
+        true
+    }
+}
+
+#[cfg(feature = "std")]
+impl Sink for ::std::vec::Vec<u8> {
+    fn append(&mut self, bytes: &[&[u8]]) -> bool {
+        for b in bytes {
+            self.extend_from_slice(b);
+        }
+        true
+    }
+}
+
+/// An I²C bus wrapper recording every transfer into a transcript.
+///
+/// Transfers longer than 255 bytes, which the SGP30 never uses, and
+/// transfers that do not fit into the sink are not recorded, see
+/// [`dropped()`](#method.dropped).
+#[derive(Debug)]
+pub struct TranscriptRecorder<I2C, S> {
+    /// The wrapped bus.
+    i2c: I2C,
+    /// The transcript.
+    sink: S,
+    /// Number of transfers that were not recorded.
+    dropped: u32,
+}
+
+impl<I2C, S: Sink> TranscriptRecorder<I2C, S> {
+    /// Wrap a bus, recording into `sink`.
+    pub fn new(i2c: I2C, sink: S) -> Self {
+        TranscriptRecorder { i2c, sink, dropped: 0 }
+    }
+
+    /// Return the transcript recorded so far.
+    pub fn sink(&self) -> &S {
+        &self.sink
+    }
+
+    /// Return the number of transfers that were not recorded.
+    pub fn dropped(&self) -> u32 {
+        self.dropped
+    }
+
+    /// Destroy the wrapper, return the bus and the transcript.
+    pub fn destroy(self) -> (I2C, S) {
+        (self.i2c, self.sink)
+    }
+
+    /// Record a transfer.
+    fn record<E>(&mut self, kind: Kind, address: u8, written: &[u8], read: &[u8], result: &Result<(), E>) {
+        let (written_len, read_len) = match (u8::try_from(written.len()), u8::try_from(read.len())) {
+            (Ok(w), Ok(r)) => (w, r),
+            _ => {
+                self.dropped = self.dropped.saturating_add(1);
+                return;
+            }
+        };
+        let flags = if result.is_err() { FLAG_FAILED } else { 0 };
+        let header = [kind.as_u8() | flags, address, written_len, read_len];
+        let read = if result.is_err() { &[][..] } else { read };
+        if !self.sink.append(&[&header, written, read]) {
+            self.dropped = self.dropped.saturating_add(1);
+        }
+    }
+}
+
+impl<I2C, S, E> Write for TranscriptRecorder<I2C, S>
+where
+    I2C: Write<Error = E>,
+    S: Sink,
+{
+    type Error = E;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), E> {
+        let result = self.i2c.write(address, bytes);
+        self.record(Kind::Write, address, bytes, &[], &result);
+        result
+    }
+}
+
+impl<I2C, S, E> Read for TranscriptRecorder<I2C, S>
+where
+    I2C: Read<Error = E>,
+    S: Sink,
+{
+    type Error = E;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), E> {
+        let result = self.i2c.read(address, buffer);
+        self.record(Kind::Read, address, &[], buffer, &result);
+        result
+    }
+}
+
+impl<I2C, S, E> WriteRead for TranscriptRecorder<I2C, S>
+where
+    I2C: WriteRead<Error = E>,
+    S: Sink,
+{
+    type Error = E;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), E> {
+        let result = self.i2c.write_read(address, bytes, buffer);
+        self.record(Kind::WriteRead, address, bytes, buffer, &result);
+        result
+    }
+}
+
+/// Errors of the replay bus.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReplayError {
+    /// The transfer failed when it was recorded.
+    Recorded,
+    /// The transfer differs from the recorded one in kind, address, written
+    /// data or read length.
+    Mismatch {
+        /// Offset of the recorded entry
+        offset: usize,
+    },
+    /// All recorded transfers have been replayed.
+    Exhausted,
+    /// The transcript is malformed.
+    Malformed(MalformedError),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReplayError::Recorded => write!(f, "Recorded transfer failed"),
+            ReplayError::Mismatch { offset } => {
+                write!(f, "Transfer does not match the transcript entry at offset {}", offset)
+            }
+            ReplayError::Exhausted => write!(f, "Transcript exhausted"),
+            ReplayError::Malformed(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for ReplayError {}
+
+/// An I²C bus replaying a transcript.
+///
+/// Every transfer must match the next recorded one. Its recorded outcome is
+/// returned: the read bytes, or
+/// [`ReplayError::Recorded`](enum.ReplayError.html#variant.Recorded) if it
+/// failed.
+#[derive(Debug, Clone)]
+pub struct Replay<'a> {
+    /// The remaining entries.
+    entries: Entries<'a>,
+}
+
+impl<'a> Replay<'a> {
+    /// Create a bus replaying `transcript`.
+    pub fn new(transcript: &'a [u8]) -> Self {
+        Replay { entries: entries(transcript) }
+    }
+
+    /// Whether all recorded transfers have been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.entries.clone().next().is_none()
+    }
+
+    /// Replay the next transfer.
+    fn replay(&mut self, kind: Kind, address: u8, written: &[u8], buffer: &mut [u8]) -> Result<(), ReplayError> {
+        let offset = self.entries.offset();
+        let entry = match self.entries.next() {
+            Some(entry) => entry.map_err(ReplayError::Malformed)?,
+            None => return Err(ReplayError::Exhausted),
+        };
+        if entry.kind != kind || entry.address != address || entry.written != written || entry.read_len != buffer.len() {
+            return Err(ReplayError::Mismatch { offset });
+        }
+        let read = entry.read.ok_or(ReplayError::Recorded)?;
+        buffer.copy_from_slice(read);
+        Ok(())
+    }
+}
+
+impl<'a> Write for Replay<'a> {
+    type Error = ReplayError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), ReplayError> {
+        self.replay(Kind::Write, address, bytes, &mut [])
+    }
+}
+
+impl<'a> Read for Replay<'a> {
+    type Error = ReplayError;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), ReplayError> {
+        self.replay(Kind::Read, address, &[], buffer)
+    }
+}
+
+impl<'a> WriteRead for Replay<'a> {
+    type Error = ReplayError;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), ReplayError> {
+        self.replay(Kind::WriteRead, address, bytes, buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use simulator::{Fault, SimulatedSgp30, Trajectory};
+    use types::{Baseline, Measurement};
+    use {Error, I2cAddress, NoDelay, Sgp30};
+
+    #[test]
+    fn record_and_replay() {
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement::default()))
+            .with_fault(1, Fault::Nack);
+        let bus = TranscriptRecorder::new(sensor, heapless::Vec::<u8, 64>::new());
+        let mut sgp = Sgp30::new(bus, I2cAddress::DEFAULT, NoDelay);
+        sgp.init().unwrap();
+        assert!(sgp.get_baseline().is_err());
+        let baseline = sgp.get_baseline().unwrap();
+        let (_, transcript) = sgp.destroy().destroy();
+        assert_eq!(&transcript[..6], &[0x00, 0x58, 2, 0, 0x20, 0x03]);
+        assert_eq!(&transcript[6..12], &[0x80, 0x58, 2, 0, 0x20, 0x15]);
+
+        let decoded: heapless::Vec<_, 8> = entries(&transcript).map(Result::unwrap).collect();
+        assert_eq!(decoded.len(), 4);
+        assert!(decoded[1].failed());
+        assert_eq!(decoded[3].kind, Kind::Read);
+        assert_eq!(decoded[3].read.unwrap().len(), 6);
+
+        let mut sgp = Sgp30::new(Replay::new(&transcript), I2cAddress::DEFAULT, NoDelay);
+        sgp.init().unwrap();
+        assert!(matches!(sgp.get_baseline(), Err(Error::I2c { source: ReplayError::Recorded, .. })));
+        assert_eq!(sgp.get_baseline().unwrap(), baseline);
+        assert!(matches!(sgp.measure(), Err(Error::I2c { source: ReplayError::Exhausted, .. })));
+        assert!(sgp.destroy().is_finished());
+    }
+
+    #[test]
+    fn mismatch() {
+        let transcript = [0x00, 0x58, 2, 0, 0x20, 0x03];
+        let mut sgp = Sgp30::new(Replay::new(&transcript), I2cAddress::DEFAULT, NoDelay);
+        assert!(matches!(
+            sgp.set_baseline(&Baseline::default()),
+            Err(Error::NotInitialized)
+        ));
+        assert!(matches!(
+            sgp.get_baseline(),
+            Err(Error::I2c { source: ReplayError::Mismatch { offset: 0 }, .. })
+        ));
+    }
+
+    #[test]
+    fn malformed() {
+        let mut iter = entries(&[0x00, 0x58, 2, 0, 0x20]);
+        assert_eq!(iter.next(), Some(Err(MalformedError { offset: 0 })));
+        assert_eq!(iter.next(), None);
+        assert_eq!(entries(&[0x03, 0x58, 0, 0]).next(), Some(Err(MalformedError { offset: 0 })));
+    }
+
+    #[test]
+    fn full_sink() {
+        let sensor = SimulatedSgp30::new(Trajectory::Constant(Measurement::default()));
+        let mut sgp = Sgp30::new(TranscriptRecorder::new(sensor, heapless::Vec::<u8, 8>::new()), I2cAddress::DEFAULT, NoDelay);
+        sgp.init().unwrap();
+        sgp.force_init().unwrap();
+        let bus = sgp.destroy();
+        assert_eq!(bus.dropped(), 1);
+        assert_eq!(bus.sink().len(), 6);
+    }
+}
@@ -0,0 +1,193 @@
+//! Trend detection over measurements.
+//!
+//! Ventilation controllers often need to know whether the air quality is
+//! getting better or worse, not just the current level.
+//! [`TrendDetector`](struct.TrendDetector.html) fits a least-squares line
+//! through the last `N` measurements and classifies its slope.
+//!
+//! ```
+//! use sgp30::Measurement;
+//! use sgp30::trend::{Trend, TrendDetector};
+//!
+//! // Window of 30 samples, trend when changing by more than 1 ppm / 0.5 ppb per sample
+//! let mut detector: TrendDetector<30> = TrendDetector::new(1.0, 0.5);
+//! for i in 0..30 {
+//!     detector.push(Measurement { co2eq_ppm: 400 + 5 * i, tvoc_ppb: 20 });
+//! }
+//! let trends = detector.trends().unwrap();
+//! assert_eq!(trends.co2eq, Trend::Rising);
+//! assert_eq!(trends.tvoc, Trend::Stable);
+//! ```
+
+use heapless::HistoryBuffer;
+
+use types::Measurement;
+
+/// The direction of a signal.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Trend {
+    /// The signal increases faster than the threshold.
+    Rising,
+    /// The signal decreases faster than the threshold.
+    Falling,
+    /// The signal changes less than the threshold.
+    Stable,
+}
+
+impl Trend {
+    fn classify(slope: f32, threshold: f32) -> Self {
+        if slope > threshold {
+            Trend::Rising
+        } else if slope < -threshold {
+            Trend::Falling
+        } else {
+            Trend::Stable
+        }
+    }
+}
+
+/// The trends of both air quality signals.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Trends {
+    /// CO₂eq trend
+    pub co2eq: Trend,
+    /// TVOC trend
+    pub tvoc: Trend,
+}
+
+/// The least-squares slopes of both air quality signals, in units per sample.
+#[derive(Debug, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Slopes {
+    /// CO₂eq slope (ppm per sample)
+    pub co2eq: f32,
+    /// TVOC slope (ppb per sample)
+    pub tvoc: f32,
+}
+
+/// A trend classifier over the last `N` measurements.
+pub struct TrendDetector<const N: usize> {
+    /// Minimum CO₂eq slope (ppm per sample) considered a trend.
+    co2eq_threshold: f32,
+    /// Minimum TVOC slope (ppb per sample) considered a trend.
+    tvoc_threshold: f32,
+    /// The measurements in the window.
+    window: HistoryBuffer<Measurement, N>,
+}
+
+impl<const N: usize> TrendDetector<N> {
+    /// Create a new detector with the given slope thresholds, in ppm (CO₂eq)
+    /// and ppb (TVOC) per sample.
+    pub fn new(co2eq_threshold: f32, tvoc_threshold: f32) -> Self {
+        TrendDetector {
+            co2eq_threshold,
+            tvoc_threshold,
+            window: HistoryBuffer::new(),
+        }
+    }
+
+    /// Add a measurement, dropping the oldest one if the window is full.
+    pub fn push(&mut self, measurement: Measurement) {
+        self.window.write(measurement);
+    }
+
+    /// Whether the window contains `N` measurements.
+    pub fn is_full(&self) -> bool {
+        self.window.len() == N
+    }
+
+    /// Remove all measurements.
+    pub fn clear(&mut self) {
+        self.window.clear();
+    }
+
+    /// Return the least-squares slopes over the window, or `None` if it
+    /// contains less than two measurements.
+    pub fn slopes(&self) -> Option<Slopes> {
+        let n = self.window.len();
+        if n < 2 {
+            return None;
+        }
+        // Sample indices are 0..n, so their mean is (n - 1) / 2
+        let x_mean = (n - 1) as f32 / 2.0;
+        let (mut sum_co2eq, mut sum_tvoc) = (0.0, 0.0);
+        for m in self.window.oldest_ordered() {
+            sum_co2eq += f32::from(m.co2eq_ppm);
+            sum_tvoc += f32::from(m.tvoc_ppb);
+        }
+        let (co2eq_mean, tvoc_mean) = (sum_co2eq / n as f32, sum_tvoc / n as f32);
+        let (mut cov_co2eq, mut cov_tvoc, mut var_x) = (0.0, 0.0, 0.0);
+        for (i, m) in self.window.oldest_ordered().enumerate() {
+            let dx = i as f32 - x_mean;
+            cov_co2eq += dx * (f32::from(m.co2eq_ppm) - co2eq_mean);
+            cov_tvoc += dx * (f32::from(m.tvoc_ppb) - tvoc_mean);
+            var_x += dx * dx;
+        }
+        Some(Slopes {
+            co2eq: cov_co2eq / var_x,
+            tvoc: cov_tvoc / var_x,
+        })
+    }
+
+    /// Classify the trends over the window, or return `None` if it contains
+    /// less than two measurements.
+    pub fn trends(&self) -> Option<Trends> {
+        self.slopes().map(|slopes| Trends {
+            co2eq: Trend::classify(slopes.co2eq, self.co2eq_threshold),
+            tvoc: Trend::classify(slopes.tvoc, self.tvoc_threshold),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(co2eq_ppm: u16, tvoc_ppb: u16) -> Measurement {
+        Measurement { co2eq_ppm, tvoc_ppb }
+    }
+
+    #[test]
+    fn not_enough_data() {
+        let mut detector: TrendDetector<10> = TrendDetector::new(1.0, 1.0);
+        assert_eq!(detector.trends(), None);
+        detector.push(m(400, 0));
+        assert_eq!(detector.slopes(), None);
+        detector.push(m(402, 0));
+        assert_eq!(detector.slopes(), Some(Slopes { co2eq: 2.0, tvoc: 0.0 }));
+    }
+
+    #[test]
+    fn linear_slopes() {
+        let mut detector: TrendDetector<5> = TrendDetector::new(1.0, 1.0);
+        for i in 0..8 {
+            detector.push(m(1000 - 10 * i, 3 * i));
+        }
+        assert!(detector.is_full());
+        assert_eq!(detector.slopes(), Some(Slopes { co2eq: -10.0, tvoc: 3.0 }));
+        assert_eq!(
+            detector.trends(),
+            Some(Trends { co2eq: Trend::Falling, tvoc: Trend::Rising }),
+        );
+    }
+
+    #[test]
+    fn noise_is_stable() {
+        let mut detector: TrendDetector<20> = TrendDetector::new(0.5, 0.5);
+        for i in 0..20 {
+            let noise = if i % 2 == 0 { 5 } else { 0 };
+            detector.push(m(600 + noise, 50 + noise));
+        }
+        assert_eq!(
+            detector.trends(),
+            Some(Trends { co2eq: Trend::Stable, tvoc: Trend::Stable }),
+        );
+        detector.clear();
+        assert_eq!(detector.trends(), None);
+    }
+}
@@ -1,8 +1,20 @@
+use core::fmt;
+use core::ops::Sub;
+
+use byteorder::{BigEndian, ByteOrder};
+use humidity::{self, RelativeHumidity, Temperature};
 #[allow(unused_imports)] // Required for no_std
 use num_traits::float::FloatCore;
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer};
+#[cfg(feature = "wasm")]
+use wasm_bindgen::prelude::*;
 
 /// A measurement result from the sensor.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Measurement {
     /// CO₂ equivalent (parts per million, ppm)
 	pub co2eq_ppm: u16,
@@ -10,8 +22,99 @@ pub struct Measurement {
 	pub tvoc_ppb: u16,
 }
 
+impl Measurement {
+    /// The highest CO₂eq value reported by the sensor. Higher concentrations
+    /// are clamped to this value.
+    pub const MAX_CO2EQ_PPM: u16 = 57_330;
+
+    /// The highest TVOC value reported by the sensor. Higher concentrations
+    /// are clamped to this value.
+    pub const MAX_TVOC_PPB: u16 = 60_000;
+
+    /// Whether the CO₂eq value is pegged at the upper limit of the sensor.
+    pub fn is_co2eq_saturated(&self) -> bool {
+        self.co2eq_ppm >= Measurement::MAX_CO2EQ_PPM
+    }
+
+    /// Whether the TVOC value is pegged at the upper limit of the sensor.
+    pub fn is_tvoc_saturated(&self) -> bool {
+        self.tvoc_ppb >= Measurement::MAX_TVOC_PPB
+    }
+
+    /// Return the mean of a series of measurements, rounded to the nearest
+    /// integer, or `None` if the slice is empty.
+    pub fn average(measurements: &[Measurement]) -> Option<Measurement> {
+        if measurements.is_empty() {
+            return None;
+        }
+        let (co2eq, tvoc) = measurements.iter().fold((0u64, 0u64), |(co2eq, tvoc), m| {
+            (co2eq + u64::from(m.co2eq_ppm), tvoc + u64::from(m.tvoc_ppb))
+        });
+        let count = measurements.len() as u64;
+        Some(Measurement {
+            co2eq_ppm: ((co2eq + count / 2) / count) as u16,
+            tvoc_ppb: ((tvoc + count / 2) / count) as u16,
+        })
+    }
+
+    /// Whether any of the two signals is pegged at the upper limit of the
+    /// sensor.
+    ///
+    /// A saturated value means that the actual concentration is at least as
+    /// high as the reported one, but could be arbitrarily higher.
+    pub fn is_saturated(&self) -> bool {
+        self.is_co2eq_saturated() || self.is_tvoc_saturated()
+    }
+}
+
+#[cfg(feature = "serde-json-core")]
+impl Measurement {
+    /// Serialize the measurement as JSON into `buf`, e.g.
+    /// `{"co2eq_ppm":400,"tvoc_ppb":12}`, and return the number of bytes
+    /// written.
+    ///
+    /// This does not allocate, so it can be used to publish readings from
+    /// `no_std` firmware, e.g. via MQTT.
+    pub fn to_json(&self, buf: &mut [u8]) -> Result<usize, serde_json_core::ser::Error> {
+        serde_json_core::to_slice(self, buf)
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CO₂eq {} ppm, TVOC {} ppb", self.co2eq_ppm, self.tvoc_ppb)
+    }
+}
+
+impl Sub for Measurement {
+    type Output = MeasurementDelta;
+
+    /// Return the change from `other` to `self`, e.g. between two
+    /// consecutive readings.
+    fn sub(self, other: Measurement) -> MeasurementDelta {
+        MeasurementDelta {
+            co2eq_ppm: i32::from(self.co2eq_ppm) - i32::from(other.co2eq_ppm),
+            tvoc_ppb: i32::from(self.tvoc_ppb) - i32::from(other.tvoc_ppb),
+        }
+    }
+}
+
+/// The difference between two measurements.
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MeasurementDelta {
+    /// CO₂ equivalent difference (parts per million, ppm)
+    pub co2eq_ppm: i32,
+    /// Total Volatile Organic Compounds difference (parts per billion, ppb)
+    pub tvoc_ppb: i32,
+}
+
 /// A raw signals result from the sensor.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct RawSignals {
     /// H2 signal
 	pub h2: u16,
@@ -19,8 +122,61 @@ pub struct RawSignals {
 	pub ethanol: u16,
 }
 
+/// Concentration (ppm) of H2 at the reference signal, according to the
+/// datasheet.
+#[cfg(feature = "libm")]
+const H2_REFERENCE_PPM: f32 = 0.5;
+
+/// Concentration (ppm) of ethanol at the reference signal, according to the
+/// datasheet.
+#[cfg(feature = "libm")]
+const ETHANOL_REFERENCE_PPM: f32 = 0.4;
+
+/// Sensitivity of the raw signals (ticks per e-fold change of concentration).
+#[cfg(feature = "libm")]
+const RAW_SIGNAL_SENSITIVITY: f32 = 512.0;
+
+#[cfg(feature = "libm")]
+impl RawSignals {
+    /// Calculate the H2 concentration (in ppm) relative to a reference
+    /// measurement.
+    ///
+    /// The datasheet defines the relation between the raw signal and the
+    /// gas concentration as `ln(c / c_ref) = (s_ref - s_out) / a`, with
+    /// `a = 512` and `c_ref = 0.5 ppm` for H2. The reference signals `s_ref`
+    /// must be measured in clean air (e.g. during part verification).
+    ///
+    /// The concentration methods are only available with the `libm` feature
+    /// (enabled by default).
+    pub fn h2_ppm(&self, reference: &RawSignals) -> f32 {
+        RawSignals::concentration(self.h2, reference.h2, H2_REFERENCE_PPM)
+    }
+
+    /// Calculate the ethanol concentration (in ppm) relative to a reference
+    /// measurement.
+    ///
+    /// See [`h2_ppm()`](#method.h2_ppm), with `c_ref = 0.4 ppm` for ethanol.
+    pub fn ethanol_ppm(&self, reference: &RawSignals) -> f32 {
+        RawSignals::concentration(self.ethanol, reference.ethanol, ETHANOL_REFERENCE_PPM)
+    }
+
+    fn concentration(signal: u16, reference: u16, reference_ppm: f32) -> f32 {
+        let exponent = (f32::from(reference) - f32::from(signal)) / RAW_SIGNAL_SENSITIVITY;
+        reference_ppm * libm::expf(exponent)
+    }
+}
+
+impl fmt::Display for RawSignals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "H2 {}, Ethanol {}", self.h2, self.ethanol)
+    }
+}
+
 /// The baseline values..
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Baseline {
     /// CO₂eq baseline
 	pub co2eq: u16,
@@ -28,20 +184,61 @@ pub struct Baseline {
 	pub tvoc: u16,
 }
 
+impl Baseline {
+    /// Return the baseline in the big-endian byte order used on the wire
+    /// (CO₂eq first, then TVOC), e.g. for storing it in an EEPROM.
+    pub fn to_bytes(&self) -> [u8; 4] {
+        let mut buf = [0; 4];
+        BigEndian::write_u16(&mut buf[0..2], self.co2eq);
+        BigEndian::write_u16(&mut buf[2..4], self.tvoc);
+        buf
+    }
+
+    /// Create a baseline from the byte representation returned by
+    /// [`to_bytes()`](#method.to_bytes).
+    pub fn from_bytes(bytes: [u8; 4]) -> Self {
+        Baseline {
+            co2eq: BigEndian::read_u16(&bytes[0..2]),
+            tvoc: BigEndian::read_u16(&bytes[2..4]),
+        }
+    }
+}
+
+#[cfg(feature = "serde-json-core")]
+impl Baseline {
+    /// Serialize the baseline as JSON into `buf`, e.g.
+    /// `{"co2eq":36608,"tvoc":36864}`, and return the number of bytes
+    /// written.
+    pub fn to_json(&self, buf: &mut [u8]) -> Result<usize, serde_json_core::ser::Error> {
+        serde_json_core::to_slice(self, buf)
+    }
+}
+
+impl fmt::Display for Baseline {
+    /// The baseline values are opaque to the user, so they are printed as
+    /// hex values (e.g. for manually restoring them later on).
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "CO₂eq 0x{:04X}, TVOC 0x{:04X}", self.co2eq, self.tvoc)
+    }
+}
+
 /// Absolute humidity in g/m³.
 ///
 /// Internally this is represented as a 8.8bit fixed-point number.
 ///
 /// To construct a `Humidity` instance, either use the lossless `new()`
 /// constructor, or the lossy `from_f32()` method.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Humidity {
 	integer: u8, // 0-255
 	fractional: u8, // 0/256-255/256
 }
 
 /// Errors that can occur when constructing a `Humidity` value.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum HumidityError {
     /// A zero value is not allowed in a `Humidity` struct since that will turn
     /// off the temperature compensation.
@@ -50,7 +247,37 @@ pub enum HumidityError {
     OutOfRange,
 }
 
+impl fmt::Display for HumidityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HumidityError::ZeroValue => f.write_str("humidity must not be zero"),
+            HumidityError::OutOfRange => f.write_str("humidity out of range"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl ::std::error::Error for HumidityError {}
+
 impl Humidity {
+    /// The default humidity used by the sensor for compensation after a
+    /// power-up or soft reset (11.57 g/m³).
+    pub const DEFAULT: Humidity = Humidity { integer: 0x0B, fractional: 0x92 };
+
+    /// Zero humidity. Sending it to the sensor turns off the humidity
+    /// compensation, just like passing `None` to
+    /// [`Sgp30::set_humidity()`](struct.Sgp30.html#method.set_humidity).
+    ///
+    /// This is the only way to get a zero value: the constructors reject it
+    /// with `HumidityError::ZeroValue`.
+    pub const ZERO: Humidity = Humidity { integer: 0x00, fractional: 0x00 };
+
+    /// The smallest non-zero humidity (1/256 g/m³).
+    pub const MIN: Humidity = Humidity { integer: 0x00, fractional: 0x01 };
+
+    /// The largest representable humidity (255 + 255/256 g/m³).
+    pub const MAX: Humidity = Humidity { integer: 0xFF, fractional: 0xFF };
+
 	/// Create a new `Humidity` instance.
     ///
     /// The humidity should be passed in as a 8.8bit fixed-point number.
@@ -75,7 +302,7 @@ impl Humidity {
             return Err(HumidityError::OutOfRange);
         }
 
-        let integer = if val >= 256.0 || val < 0.0 {
+        let integer = if !(0.0..256.0).contains(&val) {
             return Err(HumidityError::OutOfRange);
         } else {
             val.trunc() as u8
@@ -93,6 +320,83 @@ impl Humidity {
         Humidity::new(integer, fractional)
     }
 
+    /// Create a new `Humidity` instance from a f32, clamping values that are
+    /// out of range.
+    ///
+    /// Values below [`MIN`](#associatedconstant.MIN) (including zero and
+    /// NaN) result in `MIN`, values above [`MAX`](#associatedconstant.MAX)
+    /// result in `MAX`. As with `from_f32()`, the fractional part will always
+    /// be rounded down.
+    pub fn from_f32_saturating(val: f32) -> Self {
+        match Humidity::from_f32(val) {
+            Ok(humidity) => humidity,
+            Err(_) if val >= 256.0 => Humidity::MAX,
+            Err(_) => Humidity::MIN,
+        }
+    }
+
+    /// Create a new `Humidity` instance from a temperature (in °C) and a
+    /// relative humidity (in %RH), e.g. as measured by an SHT3x sensor.
+    ///
+    /// See [`humidity::absolute_humidity()`](humidity/fn.absolute_humidity.html)
+    /// for details about the conversion.
+    ///
+    /// Relative humidity values outside the range 0-100 %RH and non-finite
+    /// inputs result in a `HumidityError::OutOfRange` error.
+    ///
+    /// Only available with the `libm` feature (enabled by default).
+    #[cfg(feature = "libm")]
+    pub fn from_relative(temp_celsius: f32, rh_percent: f32) -> Result<Self, HumidityError> {
+        if !temp_celsius.is_finite() || !(0.0..=100.0).contains(&rh_percent) {
+            return Err(HumidityError::OutOfRange);
+        }
+        humidity::absolute_humidity(
+            Temperature::from_celsius(temp_celsius),
+            RelativeHumidity::from_percent(rh_percent),
+        )
+    }
+
+    /// Create a new `Humidity` instance from a temperature (in m°C) and a
+    /// relative humidity (in m%RH) without using floating point math.
+    ///
+    /// This is the integer counterpart of
+    /// [`from_relative()`](#method.from_relative), intended for targets
+    /// without an FPU. See
+    /// [`humidity::absolute_humidity_fixed()`](humidity/fn.absolute_humidity_fixed.html)
+    /// for details about the conversion.
+    ///
+    /// Temperatures outside the SGP30 operating range of -40 °C to 85 °C,
+    /// relative humidity values above 100 %RH and results that exceed the
+    /// representable range return `HumidityError::OutOfRange`.
+    pub fn from_relative_milli(temp_milli_celsius: i32, rh_milli_percent: u32) -> Result<Self, HumidityError> {
+        humidity::absolute_humidity_fixed(
+            Temperature::from_milli_celsius(temp_milli_celsius),
+            RelativeHumidity::from_milli_percent(rh_milli_percent),
+        )
+    }
+
+    /// Create a new `Humidity` instance from an absolute humidity in mg/m³.
+    ///
+    /// The value is rounded to the nearest representable 1/256 g/m³ step.
+    pub fn from_mg_per_m3(val: u32) -> Result<Self, HumidityError> {
+        let raw = (u64::from(val) * 256 + 500) / 1000;
+        if raw > 0xFFFF {
+            return Err(HumidityError::OutOfRange);
+        }
+        Humidity::new((raw >> 8) as u8, raw as u8)
+    }
+
+    /// Return the absolute humidity in mg/m³, rounded to the nearest integer.
+    pub fn to_mg_per_m3(&self) -> u32 {
+        let raw = u32::from(self.integer) << 8 | u32::from(self.fractional);
+        (raw * 1000 + 128) / 256
+    }
+
+    /// Return the absolute humidity in g/m³ as a f32.
+    pub fn to_f32(&self) -> f32 {
+        f32::from(self.integer) + (f32::from(self.fractional) / 256.0)
+    }
+
 	/// Convert this to the binary fixed-point representation expected by the
 	/// SGP30 sensor.
     pub fn as_bytes(&self) -> [u8; 2] {
@@ -100,18 +404,57 @@ impl Humidity {
 	}
 }
 
-impl Into<f32> for Humidity {
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for Humidity {
+    /// Deserialize a `Humidity` instance, rejecting the zero value just like
+    /// [`new()`](#method.new) does.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(rename = "Humidity")]
+        struct Raw {
+            integer: u8,
+            fractional: u8,
+        }
+        let raw = Raw::deserialize(deserializer)?;
+        Humidity::new(raw.integer, raw.fractional)
+            .map_err(|_| de::Error::custom("humidity must not be zero"))
+    }
+}
+
+impl Default for Humidity {
+    /// Return [`Humidity::DEFAULT`](#associatedconstant.DEFAULT), the value
+    /// used by the sensor after a power-up or soft reset.
+    fn default() -> Self {
+        Humidity::DEFAULT
+    }
+}
+
+impl fmt::Display for Humidity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:.2} g/m³", self.to_f32())
+    }
+}
+
+impl From<Humidity> for f32 {
     /// Convert a `Humidity` instance to a f32.
-    fn into(self) -> f32 {
-        f32::from(self.integer) + (f32::from(self.fractional) / 256.0)
+    fn from(humidity: Humidity) -> f32 {
+        humidity.to_f32()
     }
 }
 
-/// The product types compatible with this driver.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+/// The product type reported in the feature set.
+///
+/// Product types that are not known to this driver are preserved in the
+/// `Unknown` variant, so that they can at least be identified in logs.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum ProductType {
     /// SGP30
     Sgp30,
+    /// SGPC3 (low-power sibling of the SGP30 with a different command set)
+    Sgpc3,
     /// Unknown product type
     Unknown(u8),
 }
@@ -121,18 +464,43 @@ impl ProductType {
     pub fn parse(val: u8) -> Self {
         match val {
             0 => ProductType::Sgp30,
+            1 => ProductType::Sgpc3,
             _ => ProductType::Unknown(val),
-        } 
+        }
+    }
+
+    /// Return the 4 bit product type value as reported by the sensor.
+    pub fn as_u8(self) -> u8 {
+        match self {
+            ProductType::Sgp30 => 0,
+            ProductType::Sgpc3 => 1,
+            ProductType::Unknown(val) => val,
+        }
+    }
+}
+
+impl fmt::Display for ProductType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ProductType::Sgp30 => f.write_str("SGP30"),
+            ProductType::Sgpc3 => f.write_str("SGPC3"),
+            ProductType::Unknown(val) => write!(f, "unknown (0x{:X})", val),
+        }
     }
 }
 
 /// The feature set returned by the sensor.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct FeatureSet {
     /// The product type (see [`ProductType`](enum.ProductType.html))
     pub product_type: ProductType,
     /// The product version
     pub product_version: u8,
+    /// The reserved bits 11:8 of the feature set, which are zero on current
+    /// silicon but kept to identify future revisions
+    pub reserved: u8,
 }
 
 impl FeatureSet {
@@ -141,16 +509,34 @@ impl FeatureSet {
         FeatureSet {
             product_type: ProductType::parse(msb >> 4),
             product_version: lsb,
+            reserved: msb & 0x0F,
         }
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::excessive_precision)]
 mod tests {
+    extern crate std;
+
     use core::f32;
 
+    use self::std::string::ToString;
     use super::*;
 
+    #[test]
+    #[cfg(feature = "serde-json-core")]
+    fn to_json() {
+        let mut buf = [0; 64];
+        let measurement = Measurement { co2eq_ppm: 400, tvoc_ppb: 12 };
+        let len = measurement.to_json(&mut buf).unwrap();
+        assert_eq!(&buf[..len], br#"{"co2eq_ppm":400,"tvoc_ppb":12}"#);
+        let baseline = Baseline { co2eq: 0x8F00, tvoc: 0x9000 };
+        let len = baseline.to_json(&mut buf).unwrap();
+        assert_eq!(&buf[..len], br#"{"co2eq":36608,"tvoc":36864}"#);
+        assert!(measurement.to_json(&mut buf[..8]).is_err());
+    }
+
     #[test]
     fn humidity_as_bytes() {
         assert_eq!(Humidity::new(0x00, 0x01).unwrap().as_bytes(), [0x00, 0x01]);
@@ -174,6 +560,96 @@ mod tests {
         assert_eq!(Humidity::from_f32(f32::NAN), Err(HumidityError::OutOfRange));
     }
 
+    #[cfg(feature = "libm")]
+    #[test]
+    fn humidity_from_relative() {
+        // Reference values calculated with the Sensirion application note formula
+        let humidity: f32 = Humidity::from_relative(25.0, 50.0).unwrap().into();
+        assert!((humidity - 11.48).abs() < 0.01);
+        let humidity: f32 = Humidity::from_relative(0.0, 100.0).unwrap().into();
+        assert!((humidity - 4.85).abs() < 0.01);
+        let humidity: f32 = Humidity::from_relative(-10.0, 80.0).unwrap().into();
+        assert!((humidity - 1.89).abs() < 0.01);
+    }
+
+    #[cfg(feature = "libm")]
+    #[test]
+    fn humidity_from_relative_err() {
+        assert_eq!(Humidity::from_relative(25.0, 0.0), Err(HumidityError::ZeroValue));
+        assert_eq!(Humidity::from_relative(25.0, 100.5), Err(HumidityError::OutOfRange));
+        assert_eq!(Humidity::from_relative(25.0, -1.0), Err(HumidityError::OutOfRange));
+        assert_eq!(Humidity::from_relative(f32::NAN, 50.0), Err(HumidityError::OutOfRange));
+    }
+
+    #[cfg(feature = "libm")]
+    #[test]
+    fn humidity_from_relative_milli() {
+        // Must match the floating point implementation within 1/256 g/m³
+        for &temp in &[-40_000, -12_345, 0, 21_500, 25_000, 60_010, 85_000] {
+            for &rh in &[1_000, 33_300, 50_000, 87_650, 100_000] {
+                let float = Humidity::from_relative(temp as f32 / 1000.0, rh as f32 / 1000.0);
+                let fixed = Humidity::from_relative_milli(temp, rh);
+                match (float, fixed) {
+                    (Ok(float), Ok(fixed)) => {
+                        let float = u16::from(float.integer) << 8 | u16::from(float.fractional);
+                        let fixed = u16::from(fixed.integer) << 8 | u16::from(fixed.fractional);
+                        assert!((i32::from(float) - i32::from(fixed)).abs() <= 1, "{} {}", temp, rh);
+                    }
+                    (float, fixed) => assert_eq!(float, fixed),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn humidity_from_relative_milli_err() {
+        assert_eq!(Humidity::from_relative_milli(25_000, 0), Err(HumidityError::ZeroValue));
+        assert_eq!(Humidity::from_relative_milli(25_000, 100_001), Err(HumidityError::OutOfRange));
+        assert_eq!(Humidity::from_relative_milli(-40_001, 50_000), Err(HumidityError::OutOfRange));
+        assert_eq!(Humidity::from_relative_milli(85_001, 50_000), Err(HumidityError::OutOfRange));
+    }
+
+    #[test]
+    fn humidity_from_f32_saturating() {
+        assert_eq!(Humidity::from_f32_saturating(16.5), Humidity::new(0x10, 0x80).unwrap());
+        assert_eq!(Humidity::from_f32_saturating(0.0), Humidity::MIN);
+        assert_eq!(Humidity::from_f32_saturating(-3.0), Humidity::MIN);
+        assert_eq!(Humidity::from_f32_saturating(f32::NAN), Humidity::MIN);
+        assert_eq!(Humidity::from_f32_saturating(300.0), Humidity::MAX);
+        assert_eq!(Humidity::from_f32_saturating(f32::INFINITY), Humidity::MAX);
+    }
+
+    #[test]
+    fn humidity_ordering() {
+        assert!(Humidity::ZERO < Humidity::MIN);
+        assert!(Humidity::MIN < Humidity::DEFAULT);
+        assert!(Humidity::DEFAULT < Humidity::MAX);
+        assert!(Humidity::new(0x01, 0x00).unwrap() > Humidity::new(0x00, 0xFF).unwrap());
+        assert_eq!(Humidity::DEFAULT.to_string(), "11.57 g/m³");
+    }
+
+    #[test]
+    fn humidity_mg_per_m3() {
+        assert_eq!(Humidity::from_mg_per_m3(16_500), Ok(Humidity::new(0x10, 0x80).unwrap()));
+        assert_eq!(Humidity::from_mg_per_m3(4), Ok(Humidity::MIN));
+        assert_eq!(Humidity::from_mg_per_m3(1), Err(HumidityError::ZeroValue));
+        assert_eq!(Humidity::from_mg_per_m3(256_000), Err(HumidityError::OutOfRange));
+        assert_eq!(Humidity::new(0x10, 0x80).unwrap().to_mg_per_m3(), 16_500);
+        assert_eq!(Humidity::MAX.to_mg_per_m3(), 255_996);
+
+        // Round trip for every representable value
+        for raw in 1..=0xFFFFu16 {
+            let humidity = Humidity::new((raw >> 8) as u8, raw as u8).unwrap();
+            assert_eq!(Humidity::from_mg_per_m3(humidity.to_mg_per_m3()), Ok(humidity));
+        }
+    }
+
+    #[test]
+    fn humidity_to_f32() {
+        assert_eq!(Humidity::new(0x10, 0x80).unwrap().to_f32(), 16.5);
+        assert_eq!(Humidity::MIN.to_f32(), 0.00390625);
+    }
+
     #[test]
     fn humidity_into_f32() {
         let float: f32 = Humidity::new(0x00, 0x01).unwrap().into();
@@ -183,4 +659,94 @@ mod tests {
         let float: f32 = Humidity::new(0x10, 0x80).unwrap().into();
         assert_eq!(float, 16.5);
     }
+
+    #[test]
+    fn measurement_saturation() {
+        let measurement = Measurement { co2eq_ppm: 612, tvoc_ppb: 87 };
+        assert!(!measurement.is_saturated());
+        let measurement = Measurement { co2eq_ppm: 57_330, tvoc_ppb: 87 };
+        assert!(measurement.is_co2eq_saturated());
+        assert!(!measurement.is_tvoc_saturated());
+        assert!(measurement.is_saturated());
+        let measurement = Measurement { co2eq_ppm: 612, tvoc_ppb: 60_000 };
+        assert!(!measurement.is_co2eq_saturated());
+        assert!(measurement.is_tvoc_saturated());
+        assert!(measurement.is_saturated());
+    }
+
+    #[test]
+    fn baseline_bytes() {
+        let baseline = Baseline { co2eq: 0x1234, tvoc: 0x5678 };
+        assert_eq!(baseline.to_bytes(), [0x12, 0x34, 0x56, 0x78]);
+        assert_eq!(Baseline::from_bytes([0x12, 0x34, 0x56, 0x78]), baseline);
+    }
+
+    #[test]
+    fn defaults() {
+        assert_eq!(Humidity::default(), Humidity::DEFAULT);
+        assert_eq!(Measurement::default(), Measurement { co2eq_ppm: 0, tvoc_ppb: 0 });
+        assert_eq!(Baseline::default(), Baseline { co2eq: 0, tvoc: 0 });
+    }
+
+    #[test]
+    fn feature_set_parse() {
+        let feature_set = FeatureSet::parse(0x00, 0x22);
+        assert_eq!(feature_set.product_type, ProductType::Sgp30);
+        assert_eq!(feature_set.product_version, 0x22);
+        assert_eq!(feature_set.reserved, 0);
+        let feature_set = FeatureSet::parse(0x10, 0x06);
+        assert_eq!(feature_set.product_type, ProductType::Sgpc3);
+        let feature_set = FeatureSet::parse(0x73, 0x01);
+        assert_eq!(feature_set.product_type, ProductType::Unknown(7));
+        assert_eq!(feature_set.product_type.as_u8(), 7);
+        assert_eq!(feature_set.reserved, 3);
+        assert_eq!(feature_set.product_type.to_string(), "unknown (0x7)");
+    }
+
+    #[test]
+    fn measurement_sub() {
+        let a = Measurement { co2eq_ppm: 400, tvoc_ppb: 87 };
+        let b = Measurement { co2eq_ppm: 612, tvoc_ppb: 12 };
+        assert_eq!(b - a, MeasurementDelta { co2eq_ppm: 212, tvoc_ppb: -75 });
+        assert_eq!(a - b, MeasurementDelta { co2eq_ppm: -212, tvoc_ppb: 75 });
+    }
+
+    #[test]
+    fn measurement_average() {
+        assert_eq!(Measurement::average(&[]), None);
+        let measurements = [
+            Measurement { co2eq_ppm: 400, tvoc_ppb: 0 },
+            Measurement { co2eq_ppm: 401, tvoc_ppb: 1 },
+            Measurement { co2eq_ppm: 60_000, tvoc_ppb: 60_000 },
+            Measurement { co2eq_ppm: 60_000, tvoc_ppb: 60_000 },
+        ];
+        assert_eq!(
+            Measurement::average(&measurements),
+            Some(Measurement { co2eq_ppm: 30_200, tvoc_ppb: 30_000 }),
+        );
+    }
+
+    #[test]
+    fn display() {
+        let measurement = Measurement { co2eq_ppm: 612, tvoc_ppb: 87 };
+        assert_eq!(measurement.to_string(), "CO₂eq 612 ppm, TVOC 87 ppb");
+        let signals = RawSignals { h2: 13545, ethanol: 18523 };
+        assert_eq!(signals.to_string(), "H2 13545, Ethanol 18523");
+        let baseline = Baseline { co2eq: 0x8f3a, tvoc: 0x12 };
+        assert_eq!(baseline.to_string(), "CO₂eq 0x8F3A, TVOC 0x0012");
+        let humidity = Humidity::new(0x0B, 0x92).unwrap();
+        assert_eq!(humidity.to_string(), "11.57 g/m³");
+    }
+
+    #[cfg(feature = "libm")]
+    #[test]
+    fn raw_signal_concentrations() {
+        let reference = RawSignals { h2: 13_500, ethanol: 18_500 };
+        assert_eq!(reference.h2_ppm(&reference), 0.5);
+        assert_eq!(reference.ethanol_ppm(&reference), 0.4);
+        // A signal drop by `a` ticks corresponds to a factor e
+        let signals = RawSignals { h2: 13_500 - 512, ethanol: 18_500 + 512 };
+        assert!((signals.h2_ppm(&reference) - 0.5 * core::f32::consts::E).abs() < 1e-5);
+        assert!((signals.ethanol_ppm(&reference) - 0.4 / core::f32::consts::E).abs() < 1e-5);
+    }
 }
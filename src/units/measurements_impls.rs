@@ -0,0 +1,63 @@
+//! Conversions from and into [`measurements`](https://docs.rs/measurements)
+//! types.
+
+use core::convert::TryFrom;
+
+use measurements::{Density, Humidity as MeasurementsHumidity, Temperature as MeasurementsTemperature};
+
+use humidity::{RelativeHumidity, Temperature};
+use types::{Humidity, HumidityError};
+
+impl From<Humidity> for Density {
+    /// Convert a `Humidity` instance to a `measurements` density.
+    fn from(humidity: Humidity) -> Density {
+        Density::from_kilograms_per_cubic_meter(f64::from(humidity.to_f32()) / 1000.0)
+    }
+}
+
+impl TryFrom<Density> for Humidity {
+    type Error = HumidityError;
+
+    /// Convert a `measurements` density to a `Humidity` instance.
+    ///
+    /// Like [`Humidity::from_f32()`](../struct.Humidity.html#method.from_f32),
+    /// the fractional part will always be rounded down.
+    fn try_from(val: Density) -> Result<Self, HumidityError> {
+        Humidity::from_f32((val.as_kilograms_per_cubic_meter() * 1000.0) as f32)
+    }
+}
+
+impl From<MeasurementsTemperature> for Temperature {
+    /// Convert a `measurements` temperature, rounded to m°C.
+    fn from(val: MeasurementsTemperature) -> Temperature {
+        Temperature::from_celsius(val.as_celsius() as f32)
+    }
+}
+
+impl From<MeasurementsHumidity> for RelativeHumidity {
+    /// Convert a `measurements` relative humidity, rounded to m%RH.
+    fn from(val: MeasurementsHumidity) -> RelativeHumidity {
+        RelativeHumidity::from_percent(val.as_percent() as f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn humidity_density() {
+        let humidity = Humidity::new(0x10, 0x80).unwrap();
+        let density = Density::from(humidity);
+        assert_eq!(density.as_kilograms_per_cubic_meter(), 0.0165);
+        assert_eq!(Humidity::try_from(density), Ok(humidity));
+    }
+
+    #[test]
+    fn temperature_and_relative_humidity() {
+        let temperature = Temperature::from(MeasurementsTemperature::from_fahrenheit(212.0));
+        assert_eq!(temperature.as_milli_celsius(), 100_000);
+        let rh = RelativeHumidity::from(MeasurementsHumidity::from_percent(45.5));
+        assert_eq!(rh.as_milli_percent(), 45_500);
+    }
+}
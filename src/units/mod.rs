@@ -0,0 +1,9 @@
+//! Conversions from and into the types of third party unit crates.
+//!
+//! Each integration is only available with the corresponding feature
+//! enabled.
+
+#[cfg(feature = "measurements")]
+mod measurements_impls;
+#[cfg(feature = "uom")]
+mod uom_impls;
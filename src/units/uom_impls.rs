@@ -0,0 +1,69 @@
+//! Conversions into [`uom`](https://docs.rs/uom) quantities.
+
+use core::convert::TryFrom;
+
+#[allow(unused_imports)] // Required for no_std
+use num_traits::float::FloatCore;
+use uom::si::f32::{MassConcentration, Ratio};
+use uom::si::mass_concentration::gram_per_cubic_meter;
+use uom::si::ratio::{part_per_billion, part_per_million};
+
+use types::{Humidity, HumidityError, Measurement};
+
+impl Measurement {
+    /// Return the CO₂ equivalent as a `uom` ratio.
+    pub fn co2eq(&self) -> Ratio {
+        Ratio::new::<part_per_million>(f32::from(self.co2eq_ppm))
+    }
+
+    /// Return the Total Volatile Organic Compounds as a `uom` ratio.
+    pub fn tvoc(&self) -> Ratio {
+        Ratio::new::<part_per_billion>(f32::from(self.tvoc_ppb))
+    }
+}
+
+impl From<Humidity> for MassConcentration {
+    /// Convert a `Humidity` instance to a `uom` mass concentration.
+    fn from(humidity: Humidity) -> MassConcentration {
+        MassConcentration::new::<gram_per_cubic_meter>(humidity.to_f32())
+    }
+}
+
+impl TryFrom<MassConcentration> for Humidity {
+    type Error = HumidityError;
+
+    /// Convert a `uom` mass concentration to a `Humidity` instance.
+    ///
+    /// Since `uom` stores the value in the SI base unit (kg/m³), converting
+    /// back and forth is not exact. Therefore, unlike
+    /// [`Humidity::from_f32()`](struct.Humidity.html#method.from_f32), the
+    /// value is rounded to the nearest representable 1/256 g/m³ step.
+    fn try_from(val: MassConcentration) -> Result<Self, HumidityError> {
+        let raw = (val.get::<gram_per_cubic_meter>() * 256.0).round();
+        if !(0.0..65536.0).contains(&raw) {
+            return Err(HumidityError::OutOfRange);
+        }
+        let raw = raw as u16;
+        Humidity::new((raw >> 8) as u8, raw as u8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn measurement_ratios() {
+        let measurement = Measurement { co2eq_ppm: 612, tvoc_ppb: 87 };
+        assert!((measurement.co2eq().get::<part_per_billion>() - 612_000.0).abs() < 1.0);
+        assert!((measurement.tvoc().get::<part_per_billion>() - 87.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn humidity_mass_concentration() {
+        let humidity = Humidity::new(0x10, 0x80).unwrap();
+        let concentration = MassConcentration::from(humidity);
+        assert!((concentration.get::<gram_per_cubic_meter>() - 16.5).abs() < 0.0001);
+        assert_eq!(Humidity::try_from(concentration), Ok(humidity));
+    }
+}
@@ -0,0 +1,228 @@
+//! JavaScript bindings for WebAssembly.
+//!
+//! With the `wasm` feature, the protocol core is exported via `wasm-bindgen`,
+//! so web dashboards and documentation demos can encode commands and parse
+//! sensor responses with the same code that runs on the device:
+//!
+//! ```text
+//! cargo build --target wasm32-unknown-unknown --features wasm
+//! wasm-bindgen --target web target/wasm32-unknown-unknown/debug/sgp30.wasm --out-dir pkg
+//! ```
+//!
+//! ```js
+//! import init, { encodeCommand, decodeMeasurement } from "./pkg/sgp30.js";
+//!
+//! await init();
+//! const frame = encodeCommand(0x2008, []);
+//! const measurement = decodeMeasurement(response);  // undefined if invalid
+//! console.log(measurement.co2eq_ppm, measurement.tvoc_ppb);
+//! ```
+//!
+//! The [simulator](../simulator/index.html) is exported as `Simulator`,
+//! which runs the driver on a simulated sensor:
+//!
+//! ```js
+//! import { Simulator } from "./pkg/sgp30.js";
+//!
+//! const sim = Simulator.ramp(400, 0, 1400, 500, 600);
+//! sim.init();
+//! setInterval(() => console.log(sim.measure()), 1000);
+//! ```
+//!
+//! `Measurement`, `RawSignals` and `Baseline` are exported as classes with
+//! their fields as properties.
+
+use std::vec::Vec;
+
+use wasm_bindgen::prelude::*;
+
+use protocol::{self, Command, MAX_FRAME_LEN};
+use simulator::{SimulatedSgp30, Trajectory};
+use types::{Baseline, Humidity, Measurement, RawSignals};
+use {NoDelay, Sgp30};
+
+/// Calculate the CRC8 checksum of `data`.
+#[wasm_bindgen]
+pub fn crc8(data: &[u8]) -> u8 {
+    protocol::crc8(data)
+}
+
+/// Encode the frame for the command with the 16 bit command code `code`
+/// (e.g. `0x2008` for "measure air quality") followed by up to two data
+/// words, with the CRC bytes added.
+///
+/// Return `undefined` for an unknown command code or more than two words.
+#[wasm_bindgen(js_name = encodeCommand)]
+pub fn encode_command(code: u16, words: &[u16]) -> Option<Vec<u8>> {
+    let command = Command::from_bytes(code.to_be_bytes())?;
+    if words.len() > 2 {
+        return None;
+    }
+    let mut buf = [0; MAX_FRAME_LEN];
+    let len = protocol::encode_frame(command, words, &mut buf);
+    Some(buf[..len].to_vec())
+}
+
+/// Validate a response (data words, each followed by a CRC byte) and return
+/// its data words.
+///
+/// Return `undefined` if the response is invalid or a checksum is wrong, see
+/// [`protocol::decode_response()`](../protocol/fn.decode_response.html).
+#[wasm_bindgen(js_name = decodeWords)]
+pub fn decode_words(response: &[u8]) -> Option<Vec<u16>> {
+    let mut words = std::vec![0; response.len() / 3];
+    protocol::decode_response(response, &mut words).ok()?;
+    Some(words)
+}
+
+/// Decode the two words of a response, or `None` if it is invalid.
+fn decode_pair(response: &[u8]) -> Option<(u16, u16)> {
+    let mut words = [0; 2];
+    protocol::decode_response(response, &mut words).ok()?;
+    Some((words[0], words[1]))
+}
+
+/// Decode the 6 byte response of "measure air quality".
+#[wasm_bindgen(js_name = decodeMeasurement)]
+pub fn decode_measurement(response: &[u8]) -> Option<Measurement> {
+    decode_pair(response).map(|(co2eq_ppm, tvoc_ppb)| Measurement { co2eq_ppm, tvoc_ppb })
+}
+
+/// Decode the 6 byte response of "measure raw signals".
+#[wasm_bindgen(js_name = decodeRawSignals)]
+pub fn decode_raw_signals(response: &[u8]) -> Option<RawSignals> {
+    decode_pair(response).map(|(h2, ethanol)| RawSignals { h2, ethanol })
+}
+
+/// Decode the 6 byte response of "get baseline".
+#[wasm_bindgen(js_name = decodeBaseline)]
+pub fn decode_baseline(response: &[u8]) -> Option<Baseline> {
+    decode_pair(response).map(|(co2eq, tvoc)| Baseline { co2eq, tvoc })
+}
+
+/// The driver running on a simulated sensor.
+///
+/// Methods return `undefined` or `false` if the driver returns an error,
+/// e.g. when measuring before `init()`.
+#[wasm_bindgen]
+pub struct Simulator {
+    /// The driver.
+    sgp: Sgp30<SimulatedSgp30, NoDelay>,
+}
+
+#[wasm_bindgen]
+impl Simulator {
+    /// Simulate constant concentrations.
+    pub fn constant(co2eq_ppm: u16, tvoc_ppb: u16) -> Simulator {
+        Simulator::with_trajectory(Trajectory::Constant(Measurement { co2eq_ppm, tvoc_ppb }))
+    }
+
+    /// Simulate a linear transition between two concentrations.
+    pub fn ramp(
+        from_co2eq_ppm: u16,
+        from_tvoc_ppb: u16,
+        to_co2eq_ppm: u16,
+        to_tvoc_ppb: u16,
+        duration_secs: u32,
+    ) -> Simulator {
+        Simulator::with_trajectory(Trajectory::Ramp {
+            from: Measurement { co2eq_ppm: from_co2eq_ppm, tvoc_ppb: from_tvoc_ppb },
+            to: Measurement { co2eq_ppm: to_co2eq_ppm, tvoc_ppb: to_tvoc_ppb },
+            duration_secs,
+        })
+    }
+
+    /// Initialize the air quality measurement.
+    pub fn init(&mut self) -> bool {
+        self.sgp.init().is_ok()
+    }
+
+    /// Take a measurement, advancing the simulated time by one second.
+    pub fn measure(&mut self) -> Option<Measurement> {
+        self.sgp.measure().ok()
+    }
+
+    /// Measure the raw signals.
+    #[wasm_bindgen(js_name = measureRawSignals)]
+    pub fn measure_raw_signals(&mut self) -> Option<RawSignals> {
+        self.sgp.measure_raw_signals().ok()
+    }
+
+    /// Return the baseline.
+    #[wasm_bindgen(js_name = getBaseline)]
+    pub fn get_baseline(&mut self) -> Option<Baseline> {
+        self.sgp.get_baseline().ok()
+    }
+
+    /// Restore a baseline.
+    #[wasm_bindgen(js_name = setBaseline)]
+    pub fn set_baseline(&mut self, co2eq: u16, tvoc: u16) -> bool {
+        self.sgp.set_baseline(&Baseline { co2eq, tvoc }).is_ok()
+    }
+
+    /// Set the absolute humidity (in g/m³) for the compensation.
+    #[wasm_bindgen(js_name = setHumidity)]
+    pub fn set_humidity(&mut self, humidity: f32) -> bool {
+        match Humidity::from_f32(humidity) {
+            Ok(humidity) => self.sgp.set_humidity(Some(&humidity)).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    /// Return the simulated time in seconds.
+    #[wasm_bindgen(js_name = timeSecs)]
+    pub fn time_secs(&self) -> u32 {
+        self.sgp.i2c.time_secs()
+    }
+}
+
+impl Simulator {
+    /// Create a simulator for `trajectory`.
+    fn with_trajectory(trajectory: Trajectory) -> Simulator {
+        Simulator {
+            sgp: Sgp30::simulated(SimulatedSgp30::new(trajectory)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encode() {
+        assert_eq!(encode_command(0x2008, &[]).unwrap(), [0x20, 0x08]);
+        assert_eq!(encode_command(0x2061, &[0x0100]).unwrap(), [0x20, 0x61, 0x01, 0x00, 0x75]);
+        assert!(encode_command(0x1234, &[]).is_none());
+        assert!(encode_command(0x201E, &[1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn decode() {
+        let response = [0x12, 0x34, 0x37, 0xD4, 0x02, 0xA4];
+        assert_eq!(decode_words(&response).unwrap(), [0x1234, 0xD402]);
+        assert_eq!(
+            decode_measurement(&response).unwrap(),
+            Measurement { co2eq_ppm: 0x1234, tvoc_ppb: 0xD402 }
+        );
+        assert_eq!(decode_baseline(&response).unwrap(), Baseline { co2eq: 0x1234, tvoc: 0xD402 });
+        assert!(decode_measurement(&response[..3]).is_none());
+        assert!(decode_measurement(&[0x12, 0x34, 0x00, 0xD4, 0x02, 0xA4]).is_none());
+        assert!(decode_words(&[0xFF; 6]).is_none());
+    }
+
+    #[test]
+    fn simulator() {
+        let mut sim = Simulator::constant(800, 100);
+        assert!(sim.measure().is_none());
+        assert!(sim.init());
+        assert!(sim.set_baseline(0x8F00, 0x9000));
+        assert!(sim.set_humidity(8.5));
+        assert!(!sim.set_humidity(300.0));
+        for _ in 0..16 {
+            sim.measure().unwrap();
+        }
+        assert_eq!(sim.measure().unwrap(), Measurement { co2eq_ppm: 800, tvoc_ppb: 100 });
+        assert_eq!(sim.time_secs(), 17);
+    }
+}
@@ -0,0 +1,49 @@
+//! Watchdog feeding.
+//!
+//! Blocking operations like the 220 ms self-test or the 1 s measurement
+//! cadence of [`Sgp30::run_with_watchdog()`](../struct.Sgp30.html#method.run_with_watchdog)
+//! can exceed the timeout of a hardware watchdog. The measurement loop,
+//! [`Sgp30::selftest_with_watchdog()`](../struct.Sgp30.html#method.selftest_with_watchdog)
+//! and the [`Sgp30Manager`](../manager/struct.Sgp30Manager.html) accept a
+//! [`Watchdog`](trait.Watchdog.html) trait object which they feed regularly.
+//!
+//! Closures implement the trait, so an `embedded-hal` watchdog can be passed
+//! as `&mut || wdt.feed()`.
+
+/// Something that must be fed regularly to prevent a reset.
+pub trait Watchdog {
+    /// Feed the watchdog.
+    fn feed(&mut self);
+}
+
+/// A watchdog that does nothing.
+impl Watchdog for () {
+    fn feed(&mut self) {}
+}
+
+impl<F: FnMut()> Watchdog for F {
+    fn feed(&mut self) {
+        self()
+    }
+}
+
+/// Maximum delay between two feeds during the measurement loop and the
+/// self-test.
+pub(crate) const FEED_INTERVAL_MS: u16 = 100;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closure() {
+        let mut fed = 0;
+        {
+            let mut watchdog = || fed += 1;
+            let watchdog: &mut dyn Watchdog = &mut watchdog;
+            watchdog.feed();
+            watchdog.feed();
+        }
+        assert_eq!(fed, 2);
+    }
+}